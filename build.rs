@@ -0,0 +1,26 @@
+//! Build script: writes the C header for the `capi` feature
+
+fn main() {
+    #[cfg(feature = "capi")]
+    generate_header();
+}
+
+/// Write the declarations of the C API to `twine.h` in `OUT_DIR`
+///
+/// The copy checked in as `include/twine.h` is compared against this one by
+/// the C API integration test.
+#[cfg(feature = "capi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/capi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("set by cargo");
+    let out_dir = std::env::var("OUT_DIR").expect("set by cargo");
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("the C API can be described in C")
+        .write_to_file(format!("{out_dir}/twine.h"));
+}
@@ -1,36 +1,62 @@
 ;; Error Handling Demonstration for Twine Scheme Interpreter
-;; This file shows why unbound identifier errors are essential
+;; This file shows how errors are reported, with the offending source shown
+;;
+;; Running the file stops at the first error:
+;;   twine-scheme examples/error_demo.scm
+;; Piping it into the REPL reports every error in turn:
+;;   twine-scheme < examples/error_demo.scm
+
+(import (scheme base) (scheme write))
 
 ;; Example 1: Typo in identifier name
 (define my-counter 42)
 (display my-count)  ; Typo! Should be "my-counter"
-;; Without error handling: undefined behavior or crash
-;; With error handling: "Unbound identifier: 'my-count'. Did you mean: 'my-counter'?"
+;; error[E0006]: Unbound identifier: 'my-count'
+;;   --> examples/error_demo.scm:13:10
+;;    |
+;; 13 | (display my-count)  ; Typo! Should be "my-counter"
+;;    |          ^^^^^^^^
+;;    |
+;;    = help: did you mean 'my-counter'?
 
 ;; Example 2: Forgetting to define an identifier
 (define (calculate-area width)
   (* width height))  ; Forgot to define or pass 'height'
-;; Without error handling: silent failure or crash
-;; With error handling: "Unbound identifier: 'height'. Make sure the identifier is defined before use"
+(calculate-area 3)
+;; The error points inside the procedure, and the backtrace shows the call:
+;; error[E0006]: Unbound identifier: 'height'
+;;   --> examples/error_demo.scm:24:12
+;;    |
+;; 24 |   (* width height))  ; Forgot to define or pass 'height'
+;;    |            ^^^^^^
+;;    |
+;; Backtrace (most recent call first):
+;;   0: calculate-area at examples/error_demo.scm:25:1
 
 ;; Example 3: Normal binding shadowing (no warning - this is expected behavior)
 (define global-binding 100)
 (let ((global-binding 50))  ; This binding shadows the outer binding
   (display global-binding)) ; Uses inner binding (50) - shadowing is normal
 
-;; Example 4: Mathematical operations with undefined identifiers
-(define result (+ x y z))  ; None of these are defined
-;; Without error handling: arithmetic on undefined values
-;; With error handling: Clear error message for first undefined identifier
+;; Example 4: Duplicate parameters point at both declarations
+(define (area width width) (* width width))
+;; error[E0009]: define: duplicate parameter 'width'
+;;   --> examples/error_demo.scm:42:21
+;;    |
+;; 42 | (define (area width width) (* width width))
+;;    |               ----- parameter first declared here
+;;    |                     ^^^^^
+;;    |
 
-;; Example 5: Function calls with undefined identifiers
-(unknown-function 1 2 3)
-;; Without error handling: attempt to call undefined value
-;; With error handling: "Unbound identifier: 'unknown-function'"
+;; Example 5: Calling something that is not a procedure
+(my-counter 1 2 3)
+;; error[E0010]: '42' is not a procedure, got number
+;;   --> examples/error_demo.scm:52:1
+;;    |
+;; 52 | (my-counter 1 2 3)
+;;    | ^^^^^^^^^^^^^^^^^^
+;;    |
 
-;; These examples show how unbound identifier errors:
-;; 1. Prevent silent bugs
-;; 2. Help catch typos quickly
-;; 3. Provide educational feedback
-;; 4. Ensure language specification compliance
-;; 5. Make debugging much easier
+;; Every error has a stable code, listed with `ErrorCode` in src/error.rs, so
+;; that it can be looked up, and is rendered with colour when written to a
+;; terminal (set NO_COLOR to turn colour off).
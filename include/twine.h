@@ -0,0 +1,154 @@
+#ifndef TWINE_H
+#define TWINE_H
+
+/* Generated from src/capi.rs by the build script with the `capi` feature; do not edit. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+// The outcome of a C API call
+typedef enum TwineStatus {
+  // The call succeeded
+  TWINE_STATUS_OK = 0,
+  // Evaluation failed; the output is the error message
+  TWINE_STATUS_ERROR = 1,
+  // A pointer was null or a string was not valid UTF-8
+  TWINE_STATUS_INVALID_ARGUMENT = 2,
+} TwineStatus;
+
+// An interpreter and its global environment
+typedef struct TwineInterpreter TwineInterpreter;
+
+// A Scheme value owned by the host, or borrowed by a callback
+typedef struct TwineValue TwineValue;
+
+// A C function called as a native procedure
+//
+// It is given the `user_data` pointer it was registered with and
+// `arg_count` borrowed arguments, and stores a new value in `*result`. It
+// returns [`TwineStatus::Ok`] on success; on failure, a string value left
+// in `*result` becomes the error message.
+typedef enum TwineStatus (*TwineNativeFn)(void *user_data,
+                                          const struct TwineValue *const *args,
+                                          size_t arg_count,
+                                          struct TwineValue **result);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create an interpreter with a fresh global environment
+struct TwineInterpreter *twine_interpreter_new(void);
+
+// Destroy an interpreter
+//
+// # Safety
+//
+// `interpreter` must be null or come from [`twine_interpreter_new`], and
+// must not be used afterwards.
+void twine_interpreter_free(struct TwineInterpreter *interpreter);
+
+// Evaluate every expression in `source`, in order
+//
+// On success `*output` is the printed value of the last expression, as
+// the REPL prints it; on failure it is the error's diagnostic without
+// colour, as the command-line interpreter prints it, or the message of a
+// panic in the interpreter. Either way the host frees it with
+// [`twine_string_free`]. `output` may be null to discard it.
+//
+// # Safety
+//
+// `interpreter` must come from [`twine_interpreter_new`], `source` must be
+// a NUL-terminated string, and `output` must be null or valid for writes.
+enum TwineStatus twine_eval(struct TwineInterpreter *interpreter,
+                            const char *source,
+                            char **output);
+
+// Free a string returned by the C API
+//
+// # Safety
+//
+// `string` must be null or a string returned by this API that has not
+// been freed.
+void twine_string_free(char *string);
+
+// Bind `name` to a native procedure that calls `function`
+//
+// The procedure accepts at least `min_args` arguments, and at most
+// `max_args`, or any number more if `max_args` is negative. `user_data` is
+// passed to every call; the host keeps it alive while the interpreter is.
+//
+// # Safety
+//
+// `interpreter` must come from [`twine_interpreter_new`] and `name` must be
+// a NUL-terminated string. `function` may be called from any thread the
+// interpreter is used on, with `user_data`.
+enum TwineStatus twine_define_native(struct TwineInterpreter *interpreter,
+                                     const char *name,
+                                     size_t min_args,
+                                     ptrdiff_t max_args,
+                                     TwineNativeFn function,
+                                     void *user_data);
+
+// Create the empty list
+struct TwineValue *twine_value_nil(void);
+
+// Create a number
+struct TwineValue *twine_value_number(double number);
+
+// Create a boolean
+struct TwineValue *twine_value_boolean(bool boolean);
+
+// Create a string, or return null if `string` is not valid UTF-8
+//
+// # Safety
+//
+// `string` must be null or point to a NUL-terminated string.
+struct TwineValue *twine_value_string(const char *string);
+
+// Free a value the host owns
+//
+// # Safety
+//
+// `value` must be null or come from one of the `twine_value_*`
+// constructors, and must not have been given to the interpreter.
+void twine_value_free(struct TwineValue *value);
+
+// Store the number in `*number` and return true if `value` is a number
+//
+// # Safety
+//
+// `value` must be a valid value and `number` valid for writes.
+bool twine_value_as_number(const struct TwineValue *value, double *number);
+
+// Store the boolean in `*boolean` and return true if `value` is a boolean
+//
+// # Safety
+//
+// `value` must be a valid value and `boolean` valid for writes.
+bool twine_value_as_boolean(const struct TwineValue *value, bool *boolean);
+
+// A copy of the contents of a string value, or null for other values
+//
+// The host frees it with [`twine_string_free`].
+//
+// # Safety
+//
+// `value` must be a valid value.
+char *twine_value_as_string(const struct TwineValue *value);
+
+// The value as the REPL prints it
+//
+// The host frees it with [`twine_string_free`].
+//
+// # Safety
+//
+// `value` must be a valid value.
+char *twine_value_print(const struct TwineValue *value);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TWINE_H */
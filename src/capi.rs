@@ -0,0 +1,500 @@
+//! C ABI for embedding the interpreter, behind the `capi` feature
+//!
+//! These `extern "C"` functions wrap [`Interpreter`] for hosts written in C
+//! or C++. Their declarations are in `include/twine.h`, which the build
+//! regenerates in `OUT_DIR` and the tests check is current; build a library
+//! to link against with
+//! `cargo rustc --lib --release --features capi --crate-type staticlib` (or
+//! `cdylib` for a shared library):
+//!
+//! ```c
+//! TwineInterpreter *interpreter = twine_interpreter_new();
+//! char *output = NULL;
+//! if (twine_eval(interpreter, "(+ 1 2)", &output) == TWINE_STATUS_OK) {
+//!     printf("%s\n", output); /* 3 */
+//! }
+//! twine_string_free(output);
+//! twine_interpreter_free(interpreter);
+//! ```
+//!
+//! Strings cross the boundary as NUL-terminated UTF-8. Strings and values
+//! returned to the host are owned by it and freed with
+//! [`twine_string_free`] and [`twine_value_free`]; pointers passed in are
+//! only borrowed for the duration of the call.
+//!
+//! C callbacks become native procedures with [`twine_define_native`]. They
+//! receive their arguments as borrowed [`TwineValue`]s and return a new
+//! one, which the interpreter takes ownership of.
+//!
+//! No panic unwinds into the host: each function catches any panic and
+//! reports it as it reports other failures, with [`TwineStatus::Error`], a
+//! null pointer or `false`.
+
+use std::any::Any;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::diagnostics::Renderer;
+use crate::error::Error;
+use crate::interpreter::Interpreter;
+use crate::types::{Arity, Value};
+
+/// The outcome of a C API call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwineStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// Evaluation failed; the output is the error message
+    Error = 1,
+    /// A pointer was null or a string was not valid UTF-8
+    InvalidArgument = 2,
+}
+
+/// An interpreter and its global environment
+pub struct TwineInterpreter(Interpreter);
+
+/// A Scheme value owned by the host, or borrowed by a callback
+pub struct TwineValue(Value);
+
+/// A C function called as a native procedure
+///
+/// It is given the `user_data` pointer it was registered with and
+/// `arg_count` borrowed arguments, and stores a new value in `*result`. It
+/// returns [`TwineStatus::Ok`] on success; on failure, a string value left
+/// in `*result` becomes the error message.
+pub type TwineNativeFn = extern "C" fn(
+    user_data: *mut c_void,
+    args: *const *const TwineValue,
+    arg_count: usize,
+    result: *mut *mut TwineValue,
+) -> TwineStatus;
+
+/// A registered callback and the data it is called with
+struct Callback {
+    function: TwineNativeFn,
+    user_data: *mut c_void,
+}
+
+// The host promises, by registering the callback, that it may be called from
+// any thread the interpreter is used on.
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+impl Callback {
+    fn call(&self, name: &str, args: &[Value]) -> crate::Result<Value> {
+        panic::catch_unwind(AssertUnwindSafe(|| self.call_function(name, args))).unwrap_or_else(
+            |payload| {
+                Err(Error::runtime_error(&format!(
+                    "{name}: native procedure panicked: {}",
+                    panic_message(&*payload)
+                )))
+            },
+        )
+    }
+
+    fn call_function(&self, name: &str, args: &[Value]) -> crate::Result<Value> {
+        let args: Vec<TwineValue> = args.iter().cloned().map(TwineValue).collect();
+        let pointers: Vec<*const TwineValue> = args.iter().map(ptr::from_ref).collect();
+        let mut result: *mut TwineValue = ptr::null_mut();
+        let status = (self.function)(self.user_data, pointers.as_ptr(), args.len(), &mut result);
+
+        // SAFETY: callbacks return values made by `twine_value_*`, which
+        // the interpreter now owns
+        let result = (!result.is_null()).then(|| unsafe { Box::from_raw(result) }.0);
+        match (status, result) {
+            (TwineStatus::Ok, Some(value)) => Ok(value),
+            (TwineStatus::Ok, None) => Err(Error::runtime_error(&format!(
+                "{name}: native procedure returned no value"
+            ))),
+            (_, Some(Value::String(message))) => Err(Error::runtime_error(&format!(
+                "{name}: {}",
+                message.as_str()
+            ))),
+            (_, _) => Err(Error::runtime_error(&format!(
+                "{name}: native procedure failed"
+            ))),
+        }
+    }
+}
+
+/// Run the body of an `extern "C"` function, returning `on_panic` if it
+/// panics, as unwinding into the host would abort it
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic", String::as_str),
+    }
+}
+
+/// Borrow a C string as UTF-8
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+unsafe fn borrow_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(string) }.to_str().ok()
+}
+
+/// Give the host a copy of a string, replacing interior NULs
+fn host_string(string: &str) -> *mut c_char {
+    let string = CString::new(string.replace('\0', "\u{fffd}")).expect("NULs were replaced");
+    string.into_raw()
+}
+
+/// Create an interpreter with a fresh global environment
+#[unsafe(no_mangle)]
+pub extern "C" fn twine_interpreter_new() -> *mut TwineInterpreter {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(TwineInterpreter(Interpreter::new())))
+    })
+}
+
+/// Destroy an interpreter
+///
+/// # Safety
+///
+/// `interpreter` must be null or come from [`twine_interpreter_new`], and
+/// must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_interpreter_free(interpreter: *mut TwineInterpreter) {
+    guard((), || {
+        if !interpreter.is_null() {
+            drop(unsafe { Box::from_raw(interpreter) });
+        }
+    })
+}
+
+/// Evaluate every expression in `source`, in order
+///
+/// On success `*output` is the printed value of the last expression, as
+/// the REPL prints it; on failure it is the error's diagnostic without
+/// colour, as the command-line interpreter prints it, or the message of a
+/// panic in the interpreter. Either way the host frees it with
+/// [`twine_string_free`]. `output` may be null to discard it.
+///
+/// # Safety
+///
+/// `interpreter` must come from [`twine_interpreter_new`], `source` must be
+/// a NUL-terminated string, and `output` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_eval(
+    interpreter: *mut TwineInterpreter,
+    source: *const c_char,
+    output: *mut *mut c_char,
+) -> TwineStatus {
+    guard(TwineStatus::Error, || {
+        let Some(TwineInterpreter(interpreter)) = (unsafe { interpreter.as_mut() }) else {
+            return TwineStatus::InvalidArgument;
+        };
+        let Some(source) = (unsafe { borrow_str(source) }) else {
+            return TwineStatus::InvalidArgument;
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| interpreter.eval_str(source)));
+        let (status, text) = match result {
+            Ok(Ok(value)) => (TwineStatus::Ok, value.to_string()),
+            Ok(Err(error)) => (
+                TwineStatus::Error,
+                error.render(&Renderer::with_color(false)),
+            ),
+            Err(payload) => (
+                TwineStatus::Error,
+                format!("internal error: {}", panic_message(&*payload)),
+            ),
+        };
+        if let Some(output) = unsafe { output.as_mut() } {
+            *output = host_string(&text);
+        }
+        status
+    })
+}
+
+/// Free a string returned by the C API
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this API that has not
+/// been freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_string_free(string: *mut c_char) {
+    guard((), || {
+        if !string.is_null() {
+            drop(unsafe { CString::from_raw(string) });
+        }
+    })
+}
+
+/// Bind `name` to a native procedure that calls `function`
+///
+/// The procedure accepts at least `min_args` arguments, and at most
+/// `max_args`, or any number more if `max_args` is negative. `user_data` is
+/// passed to every call; the host keeps it alive while the interpreter is.
+///
+/// # Safety
+///
+/// `interpreter` must come from [`twine_interpreter_new`] and `name` must be
+/// a NUL-terminated string. `function` may be called from any thread the
+/// interpreter is used on, with `user_data`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_define_native(
+    interpreter: *mut TwineInterpreter,
+    name: *const c_char,
+    min_args: usize,
+    max_args: isize,
+    function: TwineNativeFn,
+    user_data: *mut c_void,
+) -> TwineStatus {
+    guard(TwineStatus::Error, || {
+        let Some(TwineInterpreter(interpreter)) = (unsafe { interpreter.as_mut() }) else {
+            return TwineStatus::InvalidArgument;
+        };
+        let Some(name) = (unsafe { borrow_str(name) }) else {
+            return TwineStatus::InvalidArgument;
+        };
+        let arity = match usize::try_from(max_args) {
+            Ok(max_args) if max_args < min_args => return TwineStatus::InvalidArgument,
+            Ok(max_args) => Arity::between(min_args, max_args),
+            Err(_) => Arity::at_least(min_args),
+        };
+
+        let callback = Callback {
+            function,
+            user_data,
+        };
+        let procedure_name = name.to_string();
+        interpreter.define_native(name, arity, move |args| {
+            callback.call(&procedure_name, args)
+        });
+        TwineStatus::Ok
+    })
+}
+
+fn host_value(value: Value) -> *mut TwineValue {
+    Box::into_raw(Box::new(TwineValue(value)))
+}
+
+/// Create the empty list
+#[unsafe(no_mangle)]
+pub extern "C" fn twine_value_nil() -> *mut TwineValue {
+    guard(ptr::null_mut(), || host_value(Value::nil()))
+}
+
+/// Create a number
+#[unsafe(no_mangle)]
+pub extern "C" fn twine_value_number(number: f64) -> *mut TwineValue {
+    guard(ptr::null_mut(), || host_value(Value::number(number)))
+}
+
+/// Create a boolean
+#[unsafe(no_mangle)]
+pub extern "C" fn twine_value_boolean(boolean: bool) -> *mut TwineValue {
+    guard(ptr::null_mut(), || host_value(Value::boolean(boolean)))
+}
+
+/// Create a string, or return null if `string` is not valid UTF-8
+///
+/// # Safety
+///
+/// `string` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_string(string: *const c_char) -> *mut TwineValue {
+    guard(ptr::null_mut(), || match unsafe { borrow_str(string) } {
+        Some(string) => host_value(Value::string(string)),
+        None => ptr::null_mut(),
+    })
+}
+
+/// Free a value the host owns
+///
+/// # Safety
+///
+/// `value` must be null or come from one of the `twine_value_*`
+/// constructors, and must not have been given to the interpreter.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_free(value: *mut TwineValue) {
+    guard((), || {
+        if !value.is_null() {
+            drop(unsafe { Box::from_raw(value) });
+        }
+    })
+}
+
+/// Store the number in `*number` and return true if `value` is a number
+///
+/// # Safety
+///
+/// `value` must be a valid value and `number` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_as_number(value: *const TwineValue, number: *mut f64) -> bool {
+    guard(false, || {
+        let Some(TwineValue(value)) = (unsafe { value.as_ref() }) else {
+            return false;
+        };
+        match (value.as_number(), unsafe { number.as_mut() }) {
+            (Some(value), Some(number)) => {
+                *number = value;
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+/// Store the boolean in `*boolean` and return true if `value` is a boolean
+///
+/// # Safety
+///
+/// `value` must be a valid value and `boolean` valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_as_boolean(
+    value: *const TwineValue,
+    boolean: *mut bool,
+) -> bool {
+    guard(false, || {
+        let Some(TwineValue(value)) = (unsafe { value.as_ref() }) else {
+            return false;
+        };
+        match (value.as_boolean(), unsafe { boolean.as_mut() }) {
+            (Some(value), Some(boolean)) => {
+                *boolean = value;
+                true
+            }
+            _ => false,
+        }
+    })
+}
+
+/// A copy of the contents of a string value, or null for other values
+///
+/// The host frees it with [`twine_string_free`].
+///
+/// # Safety
+///
+/// `value` must be a valid value.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_as_string(value: *const TwineValue) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        match unsafe { value.as_ref() }.and_then(|TwineValue(value)| value.as_string()) {
+            Some(string) => host_string(string),
+            None => ptr::null_mut(),
+        }
+    })
+}
+
+/// The value as the REPL prints it
+///
+/// The host frees it with [`twine_string_free`].
+///
+/// # Safety
+///
+/// `value` must be a valid value.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn twine_value_print(value: *const TwineValue) -> *mut c_char {
+    guard(ptr::null_mut(), || match unsafe { value.as_ref() } {
+        Some(TwineValue(value)) => host_string(&value.to_string()),
+        None => ptr::null_mut(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn negate(
+        _user_data: *mut c_void,
+        args: *const *const TwineValue,
+        arg_count: usize,
+        result: *mut *mut TwineValue,
+    ) -> TwineStatus {
+        let mut number = 0.0;
+        let status = match unsafe { twine_value_as_number(*args, &mut number) } {
+            true => TwineStatus::Ok,
+            false => TwineStatus::Error,
+        };
+        assert_eq!(arg_count, 1);
+        unsafe { *result = twine_value_number(-number) };
+        status
+    }
+
+    fn eval(interpreter: *mut TwineInterpreter, source: &str) -> (TwineStatus, String) {
+        let source = CString::new(source).unwrap();
+        let mut output = ptr::null_mut();
+        let status = unsafe { twine_eval(interpreter, source.as_ptr(), &mut output) };
+        let text = unsafe { CStr::from_ptr(output) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { twine_string_free(output) };
+        (status, text)
+    }
+
+    #[test]
+    fn test_eval_and_native_callback() {
+        let interpreter = twine_interpreter_new();
+        assert_eq!(
+            eval(interpreter, "(define x 4) (* x 10)"),
+            (TwineStatus::Ok, "40".to_string())
+        );
+
+        let name = CString::new("negate").unwrap();
+        let status = unsafe {
+            twine_define_native(interpreter, name.as_ptr(), 1, 1, negate, ptr::null_mut())
+        };
+        assert_eq!(status, TwineStatus::Ok);
+        assert_eq!(
+            eval(interpreter, "(negate x)"),
+            (TwineStatus::Ok, "-4".to_string())
+        );
+
+        // A failing callback's result is freed, and is not a message
+        assert_eq!(
+            eval(interpreter, "(negate 'x)"),
+            (
+                TwineStatus::Error,
+                "error[E0003]: negate: native procedure failed\n \
+                 --> <string>:1:1\n  \
+                 |\n\
+                 1 | (negate 'x)\n  \
+                 | ^^^^^^^^^^^\n  \
+                 |\n\
+                 Backtrace (most recent call first):\n  \
+                 0: negate at <string>:1:1\n"
+                    .to_string()
+            )
+        );
+        let (status, message) = eval(interpreter, "(negate)");
+        assert_eq!(status, TwineStatus::Error);
+        assert!(message.contains("negate"), "{message}");
+
+        unsafe { twine_interpreter_free(interpreter) };
+    }
+
+    #[test]
+    fn test_panics_do_not_unwind_into_the_host() {
+        let interpreter = twine_interpreter_new();
+        let TwineInterpreter(inner) = unsafe { &mut *interpreter };
+        inner.define_native("explode", Arity::exactly(0), |_| panic!("exploded"));
+        assert_eq!(
+            eval(interpreter, "(explode)"),
+            (TwineStatus::Error, "internal error: exploded".to_string())
+        );
+        // The interpreter is still usable
+        assert_eq!(
+            eval(interpreter, "(+ 1 2)"),
+            (TwineStatus::Ok, "3".to_string())
+        );
+        unsafe { twine_interpreter_free(interpreter) };
+    }
+}
@@ -0,0 +1,403 @@
+//! Diagnostic rendering for the Twine Scheme interpreter.
+//!
+//! Renders an [`Error`] in the style of rustc: a header with the stable error
+//! code and message, the offending source line with the failing span
+//! underlined, secondary labels, help notes and the Scheme backtrace.
+//!
+//! ```text
+//! error[E0005]: car: expected list, got number
+//!  --> main.scm:3:5
+//!   |
+//! 3 |     (car xs)))
+//!   |     ^^^^^^^^
+//!   |
+//! ```
+//!
+//! Source text is looked up in a [`SourceMap`], falling back to reading the
+//! file named by the span (such as a library file) from disk. Colour is used
+//! only when the [`Renderer`] is configured for it, normally when writing to
+//! a terminal.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use crate::Error;
+use crate::lexer::Span;
+
+/// ANSI escape sequences used for coloured output
+mod style {
+    pub const ERROR: &str = "\x1b[1;31m";
+    pub const GUTTER: &str = "\x1b[1;34m";
+    pub const HELP: &str = "\x1b[1;36m";
+    pub const BOLD: &str = "\x1b[1m";
+    pub const RESET: &str = "\x1b[0m";
+}
+
+/// Source texts available for rendering snippets, by source name
+///
+/// Spans without a source name are taken to refer to the most recently added
+/// source.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    sources: Vec<(Arc<str>, Arc<str>)>,
+}
+
+impl SourceMap {
+    /// Create an empty source map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add (or replace) the text of a named source
+    pub fn add(&mut self, name: &str, text: &str) {
+        self.sources
+            .retain(|(existing, _)| existing.as_ref() != name);
+        self.sources.push((Arc::from(name), Arc::from(text)));
+    }
+
+    /// Get the text of a source by name, or of the latest source for None
+    ///
+    /// Sources that were never added are read from disk if the name is the
+    /// path of a readable file.
+    pub fn get(&self, name: Option<&str>) -> Option<Cow<'_, str>> {
+        let found = match name {
+            Some(name) => self
+                .sources
+                .iter()
+                .rev()
+                .find(|(existing, _)| existing.as_ref() == name),
+            None => self.sources.last(),
+        };
+        match (found, name) {
+            (Some((_, text)), _) => Some(Cow::Borrowed(text)),
+            (None, Some(name)) => std::fs::read_to_string(name).ok().map(Cow::Owned),
+            (None, None) => None,
+        }
+    }
+
+    /// Get the name of the most recently added source
+    fn latest_name(&self) -> Option<&str> {
+        self.sources.last().map(|(name, _)| name.as_ref())
+    }
+}
+
+/// Renders errors as multi-line diagnostics
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    /// Create a renderer producing plain text
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a renderer that uses colour if `color` is true
+    pub fn with_color(color: bool) -> Self {
+        Self { color }
+    }
+
+    /// Create a renderer suited to standard error
+    ///
+    /// Colour is used when standard error is a terminal, unless the
+    /// `NO_COLOR` environment variable is set.
+    pub fn for_stderr() -> Self {
+        let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        Self::with_color(color)
+    }
+
+    /// Render an error, with source snippets looked up in `sources`
+    ///
+    /// The result ends with a newline.
+    pub fn render(&self, error: &Error, sources: &SourceMap) -> String {
+        let mut out = String::new();
+        let (error_style, gutter_style, help_style, bold, reset) = if self.color {
+            (
+                style::ERROR,
+                style::GUTTER,
+                style::HELP,
+                style::BOLD,
+                style::RESET,
+            )
+        } else {
+            ("", "", "", "", "")
+        };
+
+        let _ = writeln!(
+            out,
+            "{error_style}error[{}]{reset}{bold}: {}{reset}",
+            error.code(),
+            error.message()
+        );
+
+        // Annotations: the primary span first, then secondary labels
+        let mut annotations: Vec<Annotation> = Vec::new();
+        if let Some(span) = error.span() {
+            annotations.push(Annotation {
+                span,
+                message: None,
+                primary: true,
+            });
+        }
+        for label in error.labels() {
+            annotations.push(Annotation {
+                span: &label.span,
+                message: Some(&label.message),
+                primary: false,
+            });
+        }
+
+        let width = annotations
+            .iter()
+            .map(|annotation| digits(annotation.span.start.line))
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+
+        // One snippet per source, in order of first appearance
+        let mut rendered_sources: Vec<Option<&str>> = Vec::new();
+        for annotation in &annotations {
+            let source = annotation.span.source.as_deref();
+            if rendered_sources.contains(&source) {
+                continue;
+            }
+            rendered_sources.push(source);
+
+            let arrow = if rendered_sources.len() == 1 {
+                "-->"
+            } else {
+                ":::"
+            };
+            let name = source.or(sources.latest_name());
+            let start = &annotation.span.start;
+            let location = match name {
+                Some(name) => format!("{name}:{}:{}", start.line, start.column),
+                None => format!("{}:{}", start.line, start.column),
+            };
+            let _ = writeln!(out, "{pad}{gutter_style}{arrow}{reset} {location}");
+
+            let Some(text) = sources.get(source) else {
+                continue;
+            };
+            let lines: Vec<&str> = text.lines().collect();
+            let _ = writeln!(out, "{pad} {gutter_style}|{reset}");
+
+            let mut in_source: Vec<&Annotation> = annotations
+                .iter()
+                .filter(|other| other.span.source.as_deref() == source)
+                .collect();
+            in_source.sort_by_key(|other| (other.span.start.line, other.span.start.column));
+
+            let mut previous_line = None;
+            for annotation in in_source {
+                let line_number = annotation.span.start.line;
+                let Some(line) = lines.get(line_number.wrapping_sub(1)) else {
+                    continue;
+                };
+                if previous_line != Some(line_number) {
+                    let _ = writeln!(out, "{gutter_style}{line_number:>width$} |{reset} {line}");
+                    previous_line = Some(line_number);
+                }
+
+                let (marker, marker_style) = if annotation.primary {
+                    ('^', error_style)
+                } else {
+                    ('-', gutter_style)
+                };
+                let (indent, length) = underline(line, annotation.span);
+                let underline: String = std::iter::repeat_n(marker, length).collect();
+                let message = annotation
+                    .message
+                    .map(|message| format!(" {message}"))
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "{pad} {gutter_style}|{reset} {indent}{marker_style}{underline}{message}{reset}"
+                );
+            }
+            let _ = writeln!(out, "{pad} {gutter_style}|{reset}");
+        }
+
+        for help in error.help() {
+            let _ = writeln!(
+                out,
+                "{pad} {gutter_style}={reset} {help_style}help{reset}: {help}"
+            );
+        }
+
+        if let Some(backtrace) = error.backtrace() {
+            let _ = writeln!(out, "{backtrace}");
+        }
+
+        out
+    }
+}
+
+/// A span to underline, with an optional message
+struct Annotation<'a> {
+    span: &'a Span,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+/// Number of decimal digits in a line number
+fn digits(line: usize) -> usize {
+    line.to_string().len()
+}
+
+/// Compute the indentation and underline length for a span on its first line
+///
+/// The indentation copies tabs from the source line so that the underline
+/// stays aligned. Spans running past the end of the line are underlined to
+/// the end of the line; every span gets at least one marker.
+fn underline(line: &str, span: &Span) -> (String, usize) {
+    let start = span.start.column.saturating_sub(1);
+    let indent: String = line
+        .chars()
+        .take(start)
+        .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let line_length = line.chars().count();
+    let end = if span.end.line == span.start.line {
+        span.end.column.saturating_sub(1)
+    } else {
+        line_length
+    };
+    let length = end.min(line_length).saturating_sub(start).max(1);
+    (indent, length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Position;
+
+    fn span(source: &str, start: (usize, usize), end: (usize, usize)) -> Span {
+        Span::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+            .with_source(Some(Arc::from(source)))
+    }
+
+    #[test]
+    fn test_render_primary_span() {
+        let mut sources = SourceMap::new();
+        sources.add("main.scm", "(define x 1)\n(display (car x))\n");
+
+        let error = Error::type_error("car", "list", "number", None).with_span(&span(
+            "main.scm",
+            (2, 10),
+            (2, 17),
+        ));
+        assert_eq!(
+            Renderer::new().render(&error, &sources),
+            "\
+error[E0005]: car: expected list, got number
+ --> main.scm:2:10
+  |
+2 | (display (car x))
+  |          ^^^^^^^
+  |
+"
+        );
+    }
+
+    #[test]
+    fn test_render_labels_and_help() {
+        let mut sources = SourceMap::new();
+        sources.add(
+            "let.scm",
+            "(letrec ((x 1)\n         (y 2)\n         (x 3))\n  x)",
+        );
+
+        let error = Error::duplicate_identifier_error("letrec", "x")
+            .with_span(&span("let.scm", (3, 11), (3, 12)))
+            .with_label(
+                &span("let.scm", (1, 10), (1, 11)),
+                "binding first defined here",
+            )
+            .with_help("rename one of the bindings");
+        assert_eq!(
+            Renderer::new().render(&error, &sources),
+            "\
+error[E0008]: letrec: duplicate identifier 'x'
+ --> let.scm:3:11
+  |
+1 | (letrec ((x 1)
+  |          - binding first defined here
+3 |          (x 3))
+  |           ^
+  |
+  = help: rename one of the bindings
+"
+        );
+    }
+
+    #[test]
+    fn test_render_without_source() {
+        // Errors without spans render as a header only
+        let error = Error::runtime_error("cannot read file");
+        assert_eq!(
+            Renderer::new().render(&error, &SourceMap::new()),
+            "error[E0003]: cannot read file\n"
+        );
+
+        // Unknown sources still show the location
+        let error =
+            Error::runtime_error("oops").with_span(&span("/nonexistent/file.scm", (4, 2), (4, 3)));
+        assert_eq!(
+            Renderer::new().render(&error, &SourceMap::new()),
+            "error[E0003]: oops\n --> /nonexistent/file.scm:4:2\n"
+        );
+    }
+
+    #[test]
+    fn test_render_syntax_error_and_multiline_span() {
+        let mut sources = SourceMap::new();
+        sources.add("bad.scm", "(display\n  \"oops)");
+
+        let error = Error::syntax_error("unterminated string", 2, 3).in_source("bad.scm");
+        let rendered = Renderer::new().render(&error, &sources);
+        assert!(rendered.starts_with("error[E0001]: unterminated string\n --> bad.scm:2:3\n"));
+        assert!(rendered.contains("2 |   \"oops)\n  |   ^\n"));
+
+        // Spans covering several lines are underlined to the end of the first
+        let error = Error::runtime_error("multi").with_span(&span("bad.scm", (1, 1), (2, 9)));
+        let rendered = Renderer::new().render(&error, &sources);
+        assert!(rendered.contains("1 | (display\n  | ^^^^^^^^\n"));
+    }
+
+    #[test]
+    fn test_render_color() {
+        let mut sources = SourceMap::new();
+        sources.add("main.scm", "(car 1)");
+        let error = Error::type_error("car", "list", "number", None).with_span(&span(
+            "main.scm",
+            (1, 1),
+            (1, 8),
+        ));
+
+        let colored = Renderer::with_color(true).render(&error, &sources);
+        assert!(colored.starts_with("\x1b[1;31merror[E0005]\x1b[0m"));
+        assert!(colored.contains("\x1b[1;31m^^^^^^^"));
+        assert!(!Renderer::new().render(&error, &sources).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_source_map_lookup() {
+        let mut sources = SourceMap::new();
+        assert!(sources.get(None).is_none());
+
+        sources.add("a.scm", "first");
+        sources.add("b.scm", "second");
+        assert_eq!(sources.get(Some("a.scm")).unwrap(), "first");
+        assert_eq!(sources.get(None).unwrap(), "second");
+
+        sources.add("a.scm", "replaced");
+        assert_eq!(sources.get(Some("a.scm")).unwrap(), "replaced");
+        assert_eq!(sources.get(None).unwrap(), "replaced");
+        assert!(sources.get(Some("/nonexistent/source.scm")).is_none());
+    }
+}
@@ -2,8 +2,20 @@
 //!
 //! This module defines the error types used throughout the interpreter,
 //! including syntax errors with position information and general parse errors.
+//!
+//! Every error can carry the source [`Span`] of the expression that failed.
+//! Errors are created without one; the evaluator attaches the span of the
+//! innermost failing form as the error propagates (see [`Error::with_span`]).
+//! Errors escaping from procedure calls likewise collect a [`Backtrace`] of
+//! the Scheme call stack (see [`Error::push_frame`]).
+//!
+//! For diagnostics, every error also has a stable [`ErrorCode`] and may carry
+//! secondary [`Label`]s and help notes. `crate::diagnostics` renders all of
+//! this with source snippets.
 
+use crate::lexer::{Position, Span};
 use std::fmt;
+use std::sync::Arc;
 
 /// Error types for the Twine interpreter
 #[derive(Debug, Clone)]
@@ -13,19 +25,27 @@ pub enum Error {
         message: String,
         line: usize,
         column: usize,
+        details: Option<Box<ErrorDetails>>,
     },
 
     /// General parsing errors
-    ParseError(String),
+    ParseError {
+        message: String,
+        details: Option<Box<ErrorDetails>>,
+    },
 
     /// Runtime evaluation errors
-    RuntimeError(String),
+    RuntimeError {
+        message: String,
+        details: Option<Box<ErrorDetails>>,
+    },
 
     /// Arity errors for incorrect number of arguments
     ArityError {
         procedure: String,
         expected: usize,
         actual: usize,
+        details: Option<Box<ErrorDetails>>,
     },
 
     /// Type errors for incorrect argument types
@@ -34,6 +54,7 @@ pub enum Error {
         expected: String,
         actual: String,
         position: Option<usize>,
+        details: Option<Box<ErrorDetails>>,
     },
 
     /// Environment-related errors
@@ -41,7 +62,29 @@ pub enum Error {
         kind: EnvironmentErrorKind,
         identifier: String,
         context: Option<String>,
+        details: Option<Box<ErrorDetails>>,
+    },
+
+    /// Evaluation took more steps than its fuel
+    FuelExhausted {
+        fuel: u64,
+        details: Option<Box<ErrorDetails>>,
+    },
+
+    /// Procedure calls were nested more deeply than the depth limit
+    DepthExceeded {
+        max_depth: usize,
+        details: Option<Box<ErrorDetails>>,
+    },
+
+    /// A list or string was longer than the allocation limit
+    AllocationExceeded {
+        max_allocation: usize,
+        details: Option<Box<ErrorDetails>>,
     },
+
+    /// Evaluation ran past its deadline
+    DeadlineExceeded { details: Option<Box<ErrorDetails>> },
 }
 
 /// Specific kinds of environment errors
@@ -53,6 +96,191 @@ pub enum EnvironmentErrorKind {
     InvalidIdentifier,
 }
 
+/// Location, diagnostic and call stack information attached to an error
+///
+/// Boxed inside each error variant so that errors without any details stay
+/// small.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorDetails {
+    /// Source span of the innermost expression that failed
+    pub span: Option<Span>,
+    /// Code overriding the default for the error's variant
+    pub code: Option<ErrorCode>,
+    /// Secondary source locations relevant to the error
+    pub labels: Vec<Label>,
+    /// Suggestions on how to fix the error
+    pub help: Vec<String>,
+    /// Procedure calls the error escaped from
+    pub backtrace: Backtrace,
+}
+
+/// Stable codes identifying each kind of error in diagnostics
+///
+/// Codes never change meaning once assigned, so they can be searched for and
+/// documented independently of the message wording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// E0001: Malformed source text, such as an unterminated string
+    Syntax,
+    /// E0002: A malformed expression or special form
+    Parse,
+    /// E0003: A general failure during evaluation
+    Runtime,
+    /// E0004: A procedure called with the wrong number of arguments
+    Arity,
+    /// E0005: An argument of the wrong type
+    Type,
+    /// E0006: A reference to an identifier with no binding
+    UnboundIdentifier,
+    /// E0007: An identifier that is not valid in its position
+    InvalidIdentifier,
+    /// E0008: The same identifier bound twice in one binding form
+    DuplicateIdentifier,
+    /// E0009: The same parameter declared twice in one parameter list
+    DuplicateParameter,
+    /// E0010: A call whose operator is not a procedure
+    NotAProcedure,
+    /// E0011: A use of a builtin or special form that a sandbox denies
+    NotPermitted,
+    /// E0012: An evaluation that ran out of fuel
+    FuelExhausted,
+    /// E0013: Procedure calls nested beyond the depth limit
+    DepthExceeded,
+    /// E0014: A list or string larger than the allocation limit
+    AllocationExceeded,
+    /// E0015: An evaluation that ran past its deadline
+    DeadlineExceeded,
+    /// E0016: An evaluation stopped by an interrupt, such as Ctrl+C
+    Interrupted,
+}
+
+impl ErrorCode {
+    /// Get the code as written in diagnostics, e.g. `E0004`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Syntax => "E0001",
+            ErrorCode::Parse => "E0002",
+            ErrorCode::Runtime => "E0003",
+            ErrorCode::Arity => "E0004",
+            ErrorCode::Type => "E0005",
+            ErrorCode::UnboundIdentifier => "E0006",
+            ErrorCode::InvalidIdentifier => "E0007",
+            ErrorCode::DuplicateIdentifier => "E0008",
+            ErrorCode::DuplicateParameter => "E0009",
+            ErrorCode::NotAProcedure => "E0010",
+            ErrorCode::NotPermitted => "E0011",
+            ErrorCode::FuelExhausted => "E0012",
+            ErrorCode::DepthExceeded => "E0013",
+            ErrorCode::AllocationExceeded => "E0014",
+            ErrorCode::DeadlineExceeded => "E0015",
+            ErrorCode::Interrupted => "E0016",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A secondary source location shown alongside an error
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    /// The labelled source text
+    pub span: Span,
+    /// Explanation of the location's relevance, e.g. "first defined here"
+    pub message: String,
+}
+
+/// Maximum number of frames recorded in a backtrace
+///
+/// Deep non-tail recursion can leave thousands of calls on the stack; only the
+/// innermost ones are kept and the rest are counted.
+pub const MAX_BACKTRACE_FRAMES: usize = 64;
+
+/// An active procedure call recorded in a backtrace
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Name of the called procedure, or as written at the call site if it has none
+    pub procedure: String,
+    /// Span of the call expression, if known
+    pub call_site: Option<Span>,
+    /// Number of calling frames replaced by tail calls before this one
+    pub tail_calls_elided: usize,
+}
+
+impl Frame {
+    /// Create a new frame
+    pub fn new(procedure: &str, call_site: Option<&Span>, tail_calls_elided: usize) -> Self {
+        Self {
+            procedure: procedure.to_string(),
+            call_site: call_site.cloned(),
+            tail_calls_elided,
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.procedure)?;
+        if let Some(span) = &self.call_site {
+            write!(f, " at {span}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The chain of procedure calls an error escaped from, most recent first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Backtrace {
+    frames: Vec<Frame>,
+    omitted: usize,
+}
+
+impl Backtrace {
+    /// Get the recorded frames, most recent call first
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Get the number of outer frames dropped beyond [`MAX_BACKTRACE_FRAMES`]
+    pub fn omitted(&self) -> usize {
+        self.omitted
+    }
+
+    /// Check whether no calls were recorded
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty() && self.omitted == 0
+    }
+
+    fn push(&mut self, frame: Frame) {
+        if self.frames.len() < MAX_BACKTRACE_FRAMES {
+            self.frames.push(frame);
+        } else {
+            self.omitted += 1;
+        }
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Backtrace (most recent call first):")?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            write!(f, "\n  {index}: {frame}")?;
+            match frame.tail_calls_elided {
+                0 => {}
+                1 => write!(f, "\n     ... 1 tail call elided")?,
+                count => write!(f, "\n     ... {count} tail calls elided")?,
+            }
+        }
+        if self.omitted > 0 {
+            write!(f, "\n  ... {} more frames", self.omitted)?;
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -60,15 +288,17 @@ impl fmt::Display for Error {
                 message,
                 line,
                 column,
+                ..
             } => {
                 write!(f, "Syntax error at line {line}, column {column}: {message}")
             }
-            Error::ParseError(msg) => write!(f, "Parse error: {msg}"),
-            Error::RuntimeError(msg) => write!(f, "Runtime error: {msg}"),
+            Error::ParseError { message, .. } => write!(f, "Parse error: {message}"),
+            Error::RuntimeError { message, .. } => write!(f, "Runtime error: {message}"),
             Error::ArityError {
                 procedure,
                 expected,
                 actual,
+                ..
             } => {
                 write!(
                     f,
@@ -84,6 +314,7 @@ impl fmt::Display for Error {
                 expected,
                 actual,
                 position,
+                ..
             } => {
                 if let Some(pos) = position {
                     write!(
@@ -98,6 +329,7 @@ impl fmt::Display for Error {
                 kind,
                 identifier,
                 context,
+                ..
             } => {
                 let base_msg = match kind {
                     EnvironmentErrorKind::UnboundIdentifier => {
@@ -114,6 +346,20 @@ impl fmt::Display for Error {
                     write!(f, "{base_msg}")
                 }
             }
+            Error::FuelExhausted { fuel, .. } => {
+                write!(f, "Fuel exhausted: more than {fuel} evaluation steps")
+            }
+            Error::DepthExceeded { max_depth, .. } => {
+                write!(
+                    f,
+                    "Depth limit exceeded: more than {max_depth} nested calls"
+                )
+            }
+            Error::AllocationExceeded { max_allocation, .. } => write!(
+                f,
+                "Allocation limit exceeded: list or string longer than {max_allocation}"
+            ),
+            Error::DeadlineExceeded { .. } => write!(f, "Deadline exceeded"),
         }
     }
 }
@@ -130,17 +376,228 @@ impl Error {
             message: message.to_string(),
             line,
             column,
+            details: None,
         }
     }
 
     /// Create a general parse error
     pub fn parse_error(message: &str) -> Self {
-        Self::ParseError(message.to_string())
+        Self::parse(message.to_string())
     }
 
     /// Create a runtime error
     pub fn runtime_error(message: &str) -> Self {
-        Self::RuntimeError(message.to_string())
+        Self::runtime(message.to_string())
+    }
+
+    fn parse(message: String) -> Self {
+        Self::ParseError {
+            message,
+            details: None,
+        }
+    }
+
+    fn runtime(message: String) -> Self {
+        Self::RuntimeError {
+            message,
+            details: None,
+        }
+    }
+
+    /// Get the source span of the expression that caused this error, if known
+    pub fn span(&self) -> Option<&Span> {
+        self.details().and_then(|details| details.span.as_ref())
+    }
+
+    /// Attach a source span, unless the error already has one
+    ///
+    /// Errors propagate outwards through nested forms, so the first span
+    /// attached is the innermost (most precise) one and is kept.
+    pub fn with_span(mut self, new_span: &Span) -> Self {
+        let details = self.details_mut();
+        if details.span.is_none() {
+            details.span = Some(new_span.clone());
+        }
+        self
+    }
+
+    /// Get the Scheme call stack that was active when this error was raised
+    ///
+    /// Returns None if the error did not escape from any procedure call.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.details()
+            .map(|details| &details.backtrace)
+            .filter(|backtrace| !backtrace.is_empty())
+    }
+
+    /// Record a procedure call that this error escaped from
+    ///
+    /// Frames are pushed as the error unwinds, so the first frame pushed is
+    /// the innermost (most recent) call.
+    pub fn push_frame(mut self, frame: Frame) -> Self {
+        self.details_mut().backtrace.push(frame);
+        self
+    }
+
+    /// Get the stable code identifying this kind of error
+    pub fn code(&self) -> ErrorCode {
+        if let Some(code) = self.details().and_then(|details| details.code) {
+            return code;
+        }
+        match self {
+            Error::SyntaxError { .. } => ErrorCode::Syntax,
+            Error::ParseError { .. } => ErrorCode::Parse,
+            Error::RuntimeError { .. } => ErrorCode::Runtime,
+            Error::ArityError { .. } => ErrorCode::Arity,
+            Error::TypeError { .. } => ErrorCode::Type,
+            Error::EnvironmentError {
+                kind: EnvironmentErrorKind::UnboundIdentifier,
+                ..
+            } => ErrorCode::UnboundIdentifier,
+            Error::EnvironmentError {
+                kind: EnvironmentErrorKind::InvalidIdentifier,
+                ..
+            } => ErrorCode::InvalidIdentifier,
+            Error::FuelExhausted { .. } => ErrorCode::FuelExhausted,
+            Error::DepthExceeded { .. } => ErrorCode::DepthExceeded,
+            Error::AllocationExceeded { .. } => ErrorCode::AllocationExceeded,
+            Error::DeadlineExceeded { .. } => ErrorCode::DeadlineExceeded,
+        }
+    }
+
+    /// Give this error a more specific code than its variant's default
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.details_mut().code = Some(code);
+        self
+    }
+
+    /// Get the secondary source locations attached to this error
+    pub fn labels(&self) -> &[Label] {
+        self.details().map_or(&[], |details| &details.labels)
+    }
+
+    /// Attach a secondary source location with an explanation
+    pub fn with_label(mut self, span: &Span, message: &str) -> Self {
+        self.details_mut().labels.push(Label {
+            span: span.clone(),
+            message: message.to_string(),
+        });
+        self
+    }
+
+    /// Get the help notes attached to this error
+    pub fn help(&self) -> &[String] {
+        self.details().map_or(&[], |details| &details.help)
+    }
+
+    /// Attach a help note suggesting how to fix the error
+    pub fn with_help(mut self, message: &str) -> Self {
+        self.details_mut().help.push(message.to_string());
+        self
+    }
+
+    /// Locate a syntax error in a named source
+    ///
+    /// Syntax errors are raised by the lexer and parser with a line and column
+    /// but no span. This gives such an error a one-character span in `source`
+    /// so that it can be reported like any other located error. Other errors
+    /// are returned unchanged.
+    pub fn in_source(self, source: &str) -> Self {
+        match &self {
+            Error::SyntaxError { line, column, .. } if self.span().is_none() => {
+                let span = Span::new(
+                    Position::new(*line, *column),
+                    Position::new(*line, column + 1),
+                )
+                .with_source(Some(Arc::from(source)));
+                self.with_span(&span)
+            }
+            _ => self,
+        }
+    }
+
+    /// Get the error message without its kind prefix or location
+    ///
+    /// Diagnostics show the kind and location separately, so they use this
+    /// rather than the `Display` output.
+    pub fn message(&self) -> String {
+        match self {
+            Error::SyntaxError { message, .. }
+            | Error::ParseError { message, .. }
+            | Error::RuntimeError { message, .. } => message.clone(),
+            _ => self.to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            Error::SyntaxError { details, .. }
+            | Error::ParseError { details, .. }
+            | Error::RuntimeError { details, .. }
+            | Error::ArityError { details, .. }
+            | Error::TypeError { details, .. }
+            | Error::EnvironmentError { details, .. }
+            | Error::FuelExhausted { details, .. }
+            | Error::DepthExceeded { details, .. }
+            | Error::AllocationExceeded { details, .. }
+            | Error::DeadlineExceeded { details } => details.as_deref(),
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut ErrorDetails {
+        match self {
+            Error::SyntaxError { details, .. }
+            | Error::ParseError { details, .. }
+            | Error::RuntimeError { details, .. }
+            | Error::ArityError { details, .. }
+            | Error::TypeError { details, .. }
+            | Error::EnvironmentError { details, .. }
+            | Error::FuelExhausted { details, .. }
+            | Error::DepthExceeded { details, .. }
+            | Error::AllocationExceeded { details, .. }
+            | Error::DeadlineExceeded { details } => details.get_or_insert_default(),
+        }
+    }
+
+    /// Create an error for a builtin procedure or special form that a
+    /// sandbox denies
+    pub fn not_permitted(identifier: &str) -> Self {
+        Self::runtime(format!("'{identifier}' is not permitted in this sandbox"))
+            .with_code(ErrorCode::NotPermitted)
+    }
+
+    /// Create an error for an evaluation stopped by an interrupt
+    pub fn interrupted() -> Self {
+        Self::runtime("interrupted".to_string()).with_code(ErrorCode::Interrupted)
+    }
+
+    /// Create an error for an evaluation that used up its fuel
+    pub fn fuel_exhausted(fuel: u64) -> Self {
+        Self::FuelExhausted {
+            fuel,
+            details: None,
+        }
+    }
+
+    /// Create an error for calls nested more deeply than `max_depth`
+    pub fn depth_exceeded(max_depth: usize) -> Self {
+        Self::DepthExceeded {
+            max_depth,
+            details: None,
+        }
+    }
+
+    /// Create an error for a list or string longer than `max_allocation`
+    pub fn allocation_exceeded(max_allocation: usize) -> Self {
+        Self::AllocationExceeded {
+            max_allocation,
+            details: None,
+        }
+    }
+
+    /// Create an error for an evaluation that ran past its deadline
+    pub fn deadline_exceeded() -> Self {
+        Self::DeadlineExceeded { details: None }
     }
 
     /// Create an unbound identifier error with optional context
@@ -149,6 +606,7 @@ impl Error {
             kind: EnvironmentErrorKind::UnboundIdentifier,
             identifier: identifier.to_string(),
             context: context.map(|c| c.to_string()),
+            details: None,
         }
     }
 
@@ -158,6 +616,7 @@ impl Error {
             kind: EnvironmentErrorKind::InvalidIdentifier,
             identifier: identifier.to_string(),
             context: context.map(|c| c.to_string()),
+            details: None,
         }
     }
 
@@ -167,6 +626,7 @@ impl Error {
             procedure: procedure.to_string(),
             expected,
             actual,
+            details: None,
         }
     }
 
@@ -182,40 +642,41 @@ impl Error {
             expected: expected.to_string(),
             actual: actual.to_string(),
             position,
+            details: None,
         }
     }
 
     /// Create an error for when an identifier must be a symbol but isn't
     pub fn identifier_must_be_symbol_error(form_name: &str, actual_type: &str) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: identifier must be a symbol, got {actual_type}"
         ))
     }
 
     /// Create an error for when a binding must be a list but isn't
     pub fn binding_must_be_list_error(form_name: &str, actual_type: &str) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: binding must be a list, got {actual_type}"
         ))
     }
 
     /// Create an error for when a binding list must be a list but isn't
     pub fn binding_list_must_be_list_error(form_name: &str, actual_type: &str) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: binding list must be a list, got {actual_type}"
         ))
     }
 
     /// Create an error for when a parameter list must be a list but isn't
     pub fn parameter_list_must_be_list_error(form_name: &str, actual_type: &str) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: parameter list must be a list, got {actual_type}"
         ))
     }
 
     /// Create an error for when a procedure name must be a symbol but isn't
     pub fn procedure_name_must_be_symbol_error(form_name: &str, actual_type: &str) -> Self {
-        Self::RuntimeError(format!(
+        Self::runtime(format!(
             "{form_name}: procedure name must be a symbol, got {actual_type}"
         ))
     }
@@ -225,43 +686,67 @@ impl Error {
         form_name: &str,
         actual_type: &str,
     ) -> Self {
-        Self::RuntimeError(format!(
+        Self::runtime(format!(
             "{form_name}: first argument must be a list of bindings, got {actual_type}"
         ))
     }
 
     /// Create an error for when each binding must be a list but isn't
     pub fn each_binding_must_be_list_error(form_name: &str) -> Self {
-        Self::RuntimeError(format!("{form_name}: each binding must be a list"))
+        Self::runtime(format!("{form_name}: each binding must be a list"))
     }
 
     /// Create an error for when a parameter must be a symbol but isn't
     pub fn parameter_must_be_symbol_error(form_name: &str, actual_type: &str) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: parameter must be a symbol, got {actual_type}"
         ))
     }
 
     /// Create an error for when a duplicate parameter is found
     pub fn duplicate_parameter_error(form_name: &str, param_name: &str) -> Self {
-        Self::ParseError(format!("{form_name}: duplicate parameter '{param_name}'"))
+        Self::parse(format!("{form_name}: duplicate parameter '{param_name}'"))
+            .with_code(ErrorCode::DuplicateParameter)
     }
 
     /// Create an error for when a binding has wrong number of elements
     pub fn binding_wrong_arity_error(form_name: &str, expected: usize, actual: usize) -> Self {
-        Self::ParseError(format!(
+        Self::parse(format!(
             "{form_name}: binding must have exactly {expected} elements (identifier and expression), got {actual}"
         ))
     }
 
     /// Create an error for when a duplicate identifier is found in bindings
     pub fn duplicate_identifier_error(form_name: &str, identifier: &str) -> Self {
-        Self::ParseError(format!("{form_name}: duplicate identifier '{identifier}'"))
+        Self::parse(format!("{form_name}: duplicate identifier '{identifier}'"))
+            .with_code(ErrorCode::DuplicateIdentifier)
+    }
+
+    /// Create an error for when a record field specification has the wrong
+    /// number of elements
+    pub fn field_spec_wrong_arity_error(form_name: &str, actual: usize) -> Self {
+        Self::parse(format!(
+            "{form_name}: field specification must have 2 or 3 elements (field accessor [updater]), got {actual}"
+        ))
+    }
+
+    /// Create an error for when a record constructor names an unknown field
+    pub fn unknown_constructor_field_error(form_name: &str, field: &str, record: &str) -> Self {
+        Self::parse(format!(
+            "{form_name}: constructor field '{field}' is not a field of {record}"
+        ))
+    }
+
+    /// Create an error for when a record constructor specification is malformed
+    pub fn constructor_spec_error(form_name: &str, actual_type: &str) -> Self {
+        Self::parse(format!(
+            "{form_name}: constructor must be a list, identifier or #f, got {actual_type}"
+        ))
     }
 
     /// Create an error for when each binding must have exactly 2 elements
     pub fn binding_elements_wrong_arity_error(form_name: &str) -> Self {
-        Self::RuntimeError(format!(
+        Self::runtime(format!(
             "{form_name}: each binding must be a list of exactly 2 elements (identifier expression)"
         ))
     }
@@ -277,6 +762,7 @@ mod tests {
             message: "unexpected token".to_string(),
             line: 5,
             column: 10,
+            details: None,
         };
 
         assert_eq!(
@@ -287,7 +773,7 @@ mod tests {
 
     #[test]
     fn test_parse_error_creation() {
-        let error = Error::ParseError("invalid expression".to_string());
+        let error = Error::parse_error("invalid expression");
 
         assert_eq!(error.to_string(), "Parse error: invalid expression");
     }
@@ -296,7 +782,7 @@ mod tests {
     fn test_result_type_usage() {
         fn parse_number(s: &str) -> Result<i32> {
             s.parse()
-                .map_err(|_| Error::ParseError("not a valid number".to_string()))
+                .map_err(|_| Error::parse_error("not a valid number"))
         }
 
         assert!(parse_number("42").is_ok());
@@ -305,7 +791,7 @@ mod tests {
         assert!(parse_number("abc").is_err());
         assert!(matches!(
             parse_number("abc").unwrap_err(),
-            Error::ParseError(_)
+            Error::ParseError { .. }
         ));
     }
 
@@ -313,7 +799,7 @@ mod tests {
     fn test_runtime_error() {
         let error = Error::runtime_error("Division by zero");
 
-        assert!(matches!(error, Error::RuntimeError(_)));
+        assert!(matches!(error, Error::RuntimeError { .. }));
         assert_eq!(error.to_string(), "Runtime error: Division by zero");
     }
 
@@ -323,6 +809,7 @@ mod tests {
             message: "test error".to_string(),
             line: 1,
             column: 1,
+            details: None,
         };
 
         let debug_output = format!("{syntax_error:?}");
@@ -334,7 +821,7 @@ mod tests {
 
     #[test]
     fn test_error_cloning() {
-        let original = Error::ParseError("original error".to_string());
+        let original = Error::parse_error("original error");
         let cloned = original.clone();
 
         assert_eq!(original.to_string(), cloned.to_string());
@@ -355,7 +842,7 @@ mod tests {
     fn test_parse_error_helper() {
         let error = Error::parse_error("invalid expression");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(error.to_string(), "Parse error: invalid expression");
     }
 
@@ -446,7 +933,7 @@ mod tests {
     fn test_identifier_must_be_symbol_error() {
         let error = Error::identifier_must_be_symbol_error("let", "number");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: let: identifier must be a symbol, got number"
@@ -457,7 +944,7 @@ mod tests {
     fn test_binding_must_be_list_error() {
         let error = Error::binding_must_be_list_error("letrec", "symbol");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: letrec: binding must be a list, got symbol"
@@ -468,7 +955,7 @@ mod tests {
     fn test_binding_list_must_be_list_error() {
         let error = Error::binding_list_must_be_list_error("let*", "string");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: let*: binding list must be a list, got string"
@@ -479,7 +966,7 @@ mod tests {
     fn test_parameter_list_must_be_list_error() {
         let error = Error::parameter_list_must_be_list_error("lambda", "number");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: lambda: parameter list must be a list, got number"
@@ -490,7 +977,7 @@ mod tests {
     fn test_procedure_name_must_be_symbol_error() {
         let error = Error::procedure_name_must_be_symbol_error("define", "number");
 
-        assert!(matches!(error, Error::RuntimeError(_)));
+        assert!(matches!(error, Error::RuntimeError { .. }));
         assert_eq!(
             error.to_string(),
             "Runtime error: define: procedure name must be a symbol, got number"
@@ -501,7 +988,7 @@ mod tests {
     fn test_first_argument_must_be_list_of_bindings_error() {
         let error = Error::first_argument_must_be_list_of_bindings_error("let", "number");
 
-        assert!(matches!(error, Error::RuntimeError(_)));
+        assert!(matches!(error, Error::RuntimeError { .. }));
         assert_eq!(
             error.to_string(),
             "Runtime error: let: first argument must be a list of bindings, got number"
@@ -512,7 +999,7 @@ mod tests {
     fn test_each_binding_must_be_list_error() {
         let error = Error::each_binding_must_be_list_error("let");
 
-        assert!(matches!(error, Error::RuntimeError(_)));
+        assert!(matches!(error, Error::RuntimeError { .. }));
         assert_eq!(
             error.to_string(),
             "Runtime error: let: each binding must be a list"
@@ -523,7 +1010,7 @@ mod tests {
     fn test_parameter_must_be_symbol_error() {
         let error = Error::parameter_must_be_symbol_error("lambda", "number");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: lambda: parameter must be a symbol, got number"
@@ -534,7 +1021,7 @@ mod tests {
     fn test_duplicate_parameter_error() {
         let error = Error::duplicate_parameter_error("lambda", "x");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: lambda: duplicate parameter 'x'"
@@ -545,7 +1032,7 @@ mod tests {
     fn test_binding_wrong_arity_error() {
         let error = Error::binding_wrong_arity_error("letrec", 2, 3);
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: letrec: binding must have exactly 2 elements (identifier and expression), got 3"
@@ -556,7 +1043,7 @@ mod tests {
     fn test_duplicate_identifier_error() {
         let error = Error::duplicate_identifier_error("letrec", "x");
 
-        assert!(matches!(error, Error::ParseError(_)));
+        assert!(matches!(error, Error::ParseError { .. }));
         assert_eq!(
             error.to_string(),
             "Parse error: letrec: duplicate identifier 'x'"
@@ -567,10 +1054,117 @@ mod tests {
     fn test_binding_elements_wrong_arity_error() {
         let error = Error::binding_elements_wrong_arity_error("let");
 
-        assert!(matches!(error, Error::RuntimeError(_)));
+        assert!(matches!(error, Error::RuntimeError { .. }));
         assert_eq!(
             error.to_string(),
             "Runtime error: let: each binding must be a list of exactly 2 elements (identifier expression)"
         );
     }
+
+    #[test]
+    fn test_error_span() {
+        use crate::lexer::Position;
+
+        let inner = Span::new(Position::new(3, 5), Position::new(3, 12));
+        let outer = Span::new(Position::new(1, 1), Position::new(4, 2));
+
+        let error = Error::type_error("car", "list", "number", Some(1));
+        assert!(error.span().is_none());
+
+        // The first (innermost) span is kept
+        let error = error.with_span(&inner).with_span(&outer);
+        assert_eq!(error.span(), Some(&inner));
+
+        // Spans do not change the message
+        assert_eq!(
+            error.to_string(),
+            "car: expected list for argument 1, got number"
+        );
+        assert!(matches!(error, Error::TypeError { .. }));
+    }
+
+    #[test]
+    fn test_error_backtrace() {
+        use crate::lexer::Position;
+
+        let site = Span::new(Position::new(2, 3), Position::new(2, 10))
+            .with_source(Some("main.scm".into()));
+        let error = Error::type_error("car", "list", "number", None);
+        assert!(error.backtrace().is_none());
+
+        let error = error
+            .push_frame(Frame::new("car", Some(&site), 2))
+            .push_frame(Frame::new("<lambda>", None, 0));
+        let backtrace = error.backtrace().unwrap();
+        assert_eq!(backtrace.frames().len(), 2);
+        assert_eq!(backtrace.frames()[0].procedure, "car");
+        assert_eq!(
+            backtrace.to_string(),
+            "Backtrace (most recent call first):
+  0: car at main.scm:2:3
+     ... 2 tail calls elided
+  1: <lambda>"
+        );
+
+        // Frames do not affect the span or the message
+        assert!(error.span().is_none());
+        assert_eq!(error.to_string(), "car: expected list, got number");
+    }
+
+    #[test]
+    fn test_error_backtrace_limit() {
+        let mut error = Error::runtime_error("deep");
+        for _ in 0..MAX_BACKTRACE_FRAMES + 5 {
+            error = error.push_frame(Frame::new("f", None, 0));
+        }
+
+        let backtrace = error.backtrace().unwrap();
+        assert_eq!(backtrace.frames().len(), MAX_BACKTRACE_FRAMES);
+        assert_eq!(backtrace.omitted(), 5);
+        assert!(backtrace.to_string().ends_with("  ... 5 more frames"));
+    }
+
+    #[test]
+    fn test_error_codes_labels_and_help() {
+        use crate::lexer::Position;
+
+        assert_eq!(
+            Error::type_error("car", "list", "number", None).code(),
+            ErrorCode::Type
+        );
+        assert_eq!(
+            Error::duplicate_parameter_error("lambda", "x").code(),
+            ErrorCode::DuplicateParameter
+        );
+        assert_eq!(ErrorCode::UnboundIdentifier.to_string(), "E0006");
+
+        let first = Span::new(Position::new(1, 2), Position::new(1, 3));
+        let error = Error::runtime_error("not callable")
+            .with_code(ErrorCode::NotAProcedure)
+            .with_label(&first, "defined here")
+            .with_help("check the operator");
+        assert_eq!(error.code(), ErrorCode::NotAProcedure);
+        assert_eq!(error.labels()[0].span, first);
+        assert_eq!(error.labels()[0].message, "defined here");
+        assert_eq!(error.help(), ["check the operator"]);
+
+        // Diagnostics details do not change the message
+        assert_eq!(error.to_string(), "Runtime error: not callable");
+        assert_eq!(error.message(), "not callable");
+    }
+
+    #[test]
+    fn test_error_in_source() {
+        use crate::lexer::Position;
+
+        let error = Error::syntax_error("unexpected ')'", 2, 7).in_source("main.scm");
+        let span = error.span().unwrap();
+        assert_eq!(span.source.as_deref(), Some("main.scm"));
+        assert_eq!(span.start, Position::new(2, 7));
+        assert_eq!(span.end, Position::new(2, 8));
+
+        // Other errors are left alone
+        let error = Error::runtime_error("oops").in_source("main.scm");
+        assert!(error.span().is_none());
+    }
 }
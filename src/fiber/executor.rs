@@ -153,10 +153,10 @@ impl FiberExecutor {
     /// Complete a fiber with the given result
     pub fn complete_fiber(&self, fiber_id: FiberId, result: Result<Value>) -> Result<()> {
         // Send completion notification if there's a waiting task handle
-        if let Ok(mut senders) = self.completion_senders.lock() {
-            if let Some(sender) = senders.remove(&fiber_id) {
-                let _ = sender.try_send(result.clone());
-            }
+        if let Ok(mut senders) = self.completion_senders.lock()
+            && let Some(sender) = senders.remove(&fiber_id)
+        {
+            let _ = sender.try_send(result.clone());
         }
 
         let mut scheduler = self.scheduler.lock().unwrap();
@@ -8,9 +8,10 @@
 
 use super::types::{Fiber, FiberId, FiberState, SuspendReason};
 use crate::Result;
+use crate::runtime::interrupt::InterruptHandle;
 use crate::types::Value;
 use smol::future::poll_once;
-use smol::{Executor, Timer, block_on};
+use smol::{Executor, Timer};
 use std::collections::{HashMap, VecDeque};
 use std::fmt::{self, Debug, Formatter};
 use std::future::Future;
@@ -38,6 +39,8 @@ pub struct FiberScheduler {
     shutdown: Arc<AtomicBool>,
     /// Number of threads in the pool
     thread_count: usize,
+    /// The interrupt flag checked between fiber steps, if any
+    interrupt: Option<InterruptHandle>,
 }
 
 impl FiberScheduler {
@@ -49,7 +52,6 @@ impl FiberScheduler {
 
     /// Create a new fiber scheduler with specific thread count
     pub fn with_threads(thread_count: usize) -> Self {
-        
         // Don't initialize thread pool by default to avoid hanging in tests
         Self {
             ready_queue: VecDeque::new(),
@@ -60,9 +62,17 @@ impl FiberScheduler {
             next_fiber_id: 1,
             shutdown: Arc::new(AtomicBool::new(false)),
             thread_count,
+            interrupt: None,
         }
     }
 
+    /// Stop running fibers when `interrupt` is set, as the evaluations
+    /// they run do
+    pub fn with_interrupt(mut self, interrupt: InterruptHandle) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
     /// Create a test scheduler without thread pool
     pub fn new_for_test() -> Self {
         Self {
@@ -74,6 +84,7 @@ impl FiberScheduler {
             next_fiber_id: 1,
             shutdown: Arc::new(AtomicBool::new(false)),
             thread_count: 0,
+            interrupt: None,
         }
     }
 
@@ -90,12 +101,11 @@ impl FiberScheduler {
                 let handle = thread::Builder::new()
                     .name(format!("fiber-worker-{i}"))
                     .spawn(move || {
-                        block_on(async {
-                            while !shutdown_flag.load(Ordering::Relaxed) {
-                                exec.tick().await;
-                                sleep(Duration::from_millis(1));
-                            }
-                        });
+                        // Poll without waiting for a task, so shutdown is noticed
+                        while !shutdown_flag.load(Ordering::Relaxed) {
+                            exec.try_tick();
+                            sleep(Duration::from_millis(1));
+                        }
                     })
                     .expect("Failed to spawn fiber worker thread");
 
@@ -156,10 +166,10 @@ impl FiberScheduler {
         let fiber = Fiber::new(fiber_id, future, parent);
 
         // Add to parent's children if parent exists
-        if let Some(parent_id) = parent {
-            if let Some(parent_fiber) = self.fibers.get_mut(&parent_id) {
-                parent_fiber.add_child(fiber_id);
-            }
+        if let Some(parent_id) = parent
+            && let Some(parent_fiber) = self.fibers.get_mut(&parent_id)
+        {
+            parent_fiber.add_child(fiber_id);
         }
 
         self.fibers.insert(fiber_id, fiber);
@@ -201,10 +211,10 @@ impl FiberScheduler {
     pub fn cleanup_fiber(&mut self, fiber_id: FiberId) -> Result<()> {
         if let Some(fiber) = self.fibers.remove(&fiber_id) {
             // Remove from parent's children
-            if let Some(parent_id) = fiber.parent {
-                if let Some(parent) = self.fibers.get_mut(&parent_id) {
-                    parent.remove_child(fiber_id);
-                }
+            if let Some(parent_id) = fiber.parent
+                && let Some(parent) = self.fibers.get_mut(&parent_id)
+            {
+                parent.remove_child(fiber_id);
             }
 
             // Clean up children (mark them as orphaned)
@@ -270,6 +280,10 @@ impl FiberScheduler {
         self.init_thread_pool();
 
         while !self.shutdown.load(Ordering::Relaxed) {
+            if let Some(interrupt) = &self.interrupt {
+                interrupt.check()?;
+            }
+
             // Execute ready fibers
             if let Some(fiber_id) = self.next_ready_fiber() {
                 self.execute_fiber(fiber_id).await?;
@@ -299,19 +313,24 @@ impl FiberScheduler {
         self.set_current_fiber(Some(fiber_id))?;
 
         if let Some(fiber) = self.fibers.get_mut(&fiber_id) {
-            // Try to poll the fiber's future
-            match poll_once(&mut fiber.continuation).await {
+            // Run the fiber with its own parameter bindings installed
+            let previous_dynamic_env = fiber.enter_dynamic_env();
+            let poll_result = poll_once(&mut fiber.continuation).await;
+            fiber.leave_dynamic_env(previous_dynamic_env);
+
+            // Check the result of polling the fiber's future
+            match poll_result {
                 Some(result) => {
                     // Fiber completed
                     self.complete_fiber(fiber_id, result)?;
                 }
                 None => {
                     // Fiber yielded, put it back in ready queue
-                    if let Some(fiber) = self.fibers.get_mut(&fiber_id) {
-                        if fiber.is_running() {
-                            fiber.set_ready();
-                            self.ready_queue.push_back(fiber_id);
-                        }
+                    if let Some(fiber) = self.fibers.get_mut(&fiber_id)
+                        && fiber.is_running()
+                    {
+                        fiber.set_ready();
+                        self.ready_queue.push_back(fiber_id);
                     }
                 }
             }
@@ -439,6 +458,7 @@ impl Drop for FiberScheduler {
 mod tests {
     use super::*;
     use crate::types::Value;
+    use smol::block_on;
     use std::future;
 
     fn create_test_scheduler() -> FiberScheduler {
@@ -766,4 +786,40 @@ mod tests {
 
         assert_eq!(scheduler.fiber_count(), 0);
     }
+
+    #[test]
+    fn test_execute_fiber_with_dynamic_env() {
+        use crate::types::{DynamicEnvironment, Parameter};
+
+        let mut scheduler = create_test_scheduler();
+        let parameter = Parameter::new(Value::number(1.0), None);
+
+        // Fiber spawned inside a parameterize-style binding
+        let inner = {
+            let _scope = DynamicEnvironment::current()
+                .extend([(Arc::clone(&parameter), Value::number(2.0))])
+                .enter();
+            let parameter = Arc::clone(&parameter);
+            scheduler.spawn_fiber(Box::pin(async move { Ok(parameter.value()) }), None)
+        };
+
+        // Sibling fiber spawned outside the binding
+        let sibling = {
+            let parameter = Arc::clone(&parameter);
+            scheduler.spawn_fiber(Box::pin(async move { Ok(parameter.value()) }), None)
+        };
+
+        block_on(scheduler.execute_fiber(inner)).unwrap();
+        block_on(scheduler.execute_fiber(sibling)).unwrap();
+
+        let result_of = |fiber_id| match &scheduler.get_fiber(fiber_id).unwrap().state {
+            FiberState::Completed(Ok(value)) => value.clone(),
+            other => panic!("Expected completed fiber, got {other:?}"),
+        };
+        assert_eq!(result_of(inner), Value::number(2.0));
+        assert_eq!(result_of(sibling), Value::number(1.0));
+
+        // The scheduler's own dynamic environment is left untouched
+        assert_eq!(parameter.value(), Value::number(1.0));
+    }
 }
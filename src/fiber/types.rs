@@ -7,7 +7,7 @@
 //! - Common type aliases and utilities
 
 use crate::Result;
-use crate::types::Value;
+use crate::types::{DynamicEnvironment, Value};
 use std::collections::HashSet;
 use std::fmt::{self, Debug, Formatter};
 
@@ -63,10 +63,18 @@ pub struct Fiber {
     pub parent: Option<FiberId>,
     /// Child fibers spawned by this fiber
     pub children: HashSet<FiberId>,
+    /// Parameter bindings (from `parameterize`) visible to this fiber
+    ///
+    /// Captured from the spawning context and installed by the scheduler
+    /// whenever the fiber runs, so fibers inherit their spawner's dynamic
+    /// bindings without seeing those of sibling fibers.
+    pub dynamic_env: DynamicEnvironment,
 }
 
 impl Fiber {
     /// Create a new fiber with the given id and continuation
+    ///
+    /// The fiber inherits the current thread's dynamic environment.
     pub fn new(
         id: FiberId,
         continuation: std::pin::Pin<Box<dyn std::future::Future<Output = Result<Value>> + Send>>,
@@ -78,6 +86,7 @@ impl Fiber {
             continuation,
             parent,
             children: HashSet::new(),
+            dynamic_env: DynamicEnvironment::current(),
         }
     }
 
@@ -130,6 +139,22 @@ impl Fiber {
     pub fn remove_child(&mut self, child_id: FiberId) {
         self.children.remove(&child_id);
     }
+
+    /// Install this fiber's dynamic environment on the current thread
+    ///
+    /// Returns the environment that was previously installed.
+    pub fn enter_dynamic_env(&mut self) -> DynamicEnvironment {
+        std::mem::take(&mut self.dynamic_env).install()
+    }
+
+    /// Save the current thread's dynamic environment back into this fiber
+    ///
+    /// Restores `previous` as the thread's dynamic environment, so bindings
+    /// established by the fiber (e.g. a `parameterize` body suspended
+    /// mid-way) are preserved until the fiber next runs.
+    pub fn leave_dynamic_env(&mut self, previous: DynamicEnvironment) {
+        self.dynamic_env = previous.install();
+    }
 }
 
 impl Debug for Fiber {
@@ -225,6 +250,41 @@ mod tests {
         assert_eq!(fiber.parent, Some(parent_id));
     }
 
+    #[test]
+    fn test_fiber_inherits_dynamic_env() {
+        use crate::types::Parameter;
+        use std::sync::Arc;
+
+        let parameter = Parameter::new(Value::number(1.0), None);
+
+        // Fibers created outside any binding see the default
+        let outside = create_test_fiber();
+        assert!(outside.dynamic_env.is_empty());
+
+        // Fibers created inside a binding capture it
+        let mut inside = {
+            let _scope = DynamicEnvironment::current()
+                .extend([(Arc::clone(&parameter), Value::number(2.0))])
+                .enter();
+            create_test_fiber()
+        };
+        assert_eq!(parameter.value(), Value::number(1.0));
+        assert_eq!(
+            inside.dynamic_env.lookup(&parameter),
+            Some(Value::number(2.0))
+        );
+
+        // Entering the fiber installs its bindings until it is left again
+        let previous = inside.enter_dynamic_env();
+        assert_eq!(parameter.value(), Value::number(2.0));
+        inside.leave_dynamic_env(previous);
+        assert_eq!(parameter.value(), Value::number(1.0));
+        assert_eq!(
+            inside.dynamic_env.lookup(&parameter),
+            Some(Value::number(2.0))
+        );
+    }
+
     #[test]
     fn test_fiber_id_api() {
         let id = FiberId::new(42);
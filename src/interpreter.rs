@@ -0,0 +1,467 @@
+//! High-level embedding API for the Twine Scheme interpreter.
+//!
+//! An [`Interpreter`] owns a global environment and a fiber scheduler, and
+//! wraps parsing and evaluation behind a few methods, so Rust programs can
+//! run Scheme code without assembling the parser and evaluator themselves:
+//!
+//! ```
+//! use twine_scheme::Interpreter;
+//! use twine_scheme::types::Value;
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.define("base", Value::number(40.0));
+//! interpreter.eval_str("(define (offset x) (+ base x))").unwrap();
+//!
+//! let result = interpreter.call("offset", &[Value::number(2.0)]).unwrap();
+//! assert_eq!(result, Value::number(42.0));
+//! ```
+//!
+//! [`Interpreter::eval_async`] evaluates source as a future instead, for
+//! running scripts as tasks of an async application.
+//!
+//! Source evaluation fails with a [`ScriptError`], which locates the failing
+//! form and can render it as a diagnostic. Calls and lookups fail with the
+//! underlying [`Error`], which carries the Scheme backtrace.
+
+use smol::channel::bounded;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::fiber::{FiberScheduler, FiberTask};
+use crate::runtime::eval::{Evaluator, apply_procedure};
+use crate::runtime::interrupt::InterruptHandle;
+use crate::runtime::{Environment, Limits, Sandbox};
+use crate::script::{self, ScriptError};
+use crate::types::{Arity, Native, NativeFunction, Procedure, Symbol, Value};
+
+/// Source name used for code evaluated with [`Interpreter::eval_str`]
+pub const STRING_SOURCE_NAME: &str = "<string>";
+
+/// An embedded Scheme interpreter
+///
+/// Every method works in the interpreter's global environment, so
+/// definitions made by one call are visible to the next. Builtin procedures
+/// are available from the start.
+///
+/// Dropping the interpreter frees its global environment. Procedures taken
+/// out of the interpreter refer to that environment weakly, so calling one
+/// afterwards is an error if it looks up a global binding.
+#[derive(Debug)]
+pub struct Interpreter {
+    env: Environment,
+    scheduler: Arc<Mutex<FiberScheduler>>,
+    /// Resource limits applied afresh to each evaluation
+    limits: Option<Limits>,
+}
+
+impl Interpreter {
+    /// Create an interpreter with a fresh global environment
+    pub fn new() -> Self {
+        Self::with_environment(Environment::new())
+    }
+
+    /// Create an interpreter for untrusted code, restricted to the builtin
+    /// procedures and special forms `sandbox` permits
+    ///
+    /// Procedures defined with [`Interpreter::define_native`] and the other
+    /// definition methods are available regardless, so an embedder can
+    /// grant narrower capabilities of its own.
+    pub fn sandboxed(sandbox: Sandbox) -> Self {
+        Self::with_environment(Environment::new_sandboxed(sandbox))
+    }
+
+    /// Create an interpreter for `env`, with a scheduler that stops when
+    /// its interrupt flag is set
+    fn with_environment(env: Environment) -> Self {
+        let scheduler = FiberScheduler::new().with_interrupt(env.interrupt_handle());
+        Self {
+            env,
+            scheduler: Arc::new(Mutex::new(scheduler)),
+            limits: None,
+        }
+    }
+
+    /// Run code with `evaluator` instead of the default bytecode evaluator
+    ///
+    /// Each interpreter has its own evaluator, so interpreters using
+    /// either can run side by side:
+    ///
+    /// ```
+    /// # use twine_scheme::{Interpreter, runtime::eval::Evaluator, types::Value};
+    /// let mut interpreter = Interpreter::new().with_evaluator(Evaluator::TreeWalker);
+    /// assert_eq!(interpreter.eval_str("(* 6 7)").unwrap(), Value::number(42.0));
+    /// ```
+    pub fn with_evaluator(self, evaluator: Evaluator) -> Self {
+        let Self {
+            env,
+            scheduler,
+            limits,
+        } = self;
+        Self {
+            env: env.with_evaluator(evaluator),
+            scheduler,
+            limits,
+        }
+    }
+
+    /// Evaluate every expression in `source`, in order
+    ///
+    /// Returns the value of the last expression, or Nil for an empty source.
+    /// Errors are located in the pseudo-file [`STRING_SOURCE_NAME`].
+    pub fn eval_str(&mut self, source: &str) -> std::result::Result<Value, ScriptError> {
+        self.start_budget();
+        script::run_source(STRING_SOURCE_NAME, source, &mut self.env)
+    }
+
+    /// Evaluate every expression in `source`, in order, as a future
+    ///
+    /// The future yields to its executor as the evaluation runs, so scripts
+    /// can run as tasks of an async application alongside other work, on a
+    /// [`smol::Executor`] or any other executor:
+    ///
+    /// ```
+    /// # use twine_scheme::{Interpreter, types::Value};
+    /// let interpreter = Interpreter::new();
+    /// let executor = smol::Executor::new();
+    /// let task = executor.spawn(interpreter.eval_async("(define x 20) (+ x 22)"));
+    /// assert_eq!(smol::block_on(executor.run(task)).unwrap(), Value::number(42.0));
+    /// assert_eq!(interpreter.get("x").unwrap(), Value::number(20.0));
+    /// ```
+    ///
+    /// Results and errors are those of [`Interpreter::eval_str`]; the
+    /// evaluation gets a fresh budget when this is called. The future does
+    /// not borrow the interpreter, but evaluates in its global environment,
+    /// so it should finish before the interpreter is dropped.
+    pub fn eval_async(
+        &self,
+        source: &str,
+    ) -> impl Future<Output = std::result::Result<Value, ScriptError>> + Send + use<> {
+        let mut env = self.env.share();
+        if let Some(limits) = self.limits {
+            env.set_limits(limits);
+        }
+        script::run_source_async(STRING_SOURCE_NAME, source, &mut env)
+    }
+
+    /// Evaluate `source` in a fiber of the interpreter's scheduler
+    ///
+    /// The fiber runs, interleaved with the others, when the scheduler is
+    /// run; the returned task then yields its result:
+    ///
+    /// ```
+    /// # use twine_scheme::{Interpreter, types::Value};
+    /// let interpreter = Interpreter::new();
+    /// let task = interpreter.spawn_fiber("(* 6 7)");
+    /// smol::block_on(interpreter.scheduler().lock().unwrap().run_scheduler()).unwrap();
+    /// assert_eq!(smol::block_on(task.wait()).unwrap(), Value::number(42.0));
+    /// ```
+    pub fn spawn_fiber(&self, source: &str) -> FiberTask {
+        let (sender, receiver) = bounded(1);
+        let script = self.eval_async(source);
+        let fiber = async move {
+            let result = script.await.map_err(|error| *error.error);
+            // The task may have been dropped without waiting
+            let _ = sender.try_send(result.clone());
+            result
+        };
+        let fiber_id = self
+            .scheduler
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .spawn_fiber(Box::pin(fiber), None);
+        FiberTask::new(fiber_id, receiver)
+    }
+
+    /// Read and evaluate a source file
+    ///
+    /// The file's path, as given, is used as the source name in errors.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> std::result::Result<Value, ScriptError> {
+        self.start_budget();
+        script::run_file(path.as_ref(), &mut self.env)
+    }
+
+    /// Bind `name` to `value` in the global environment
+    ///
+    /// Like a top-level `define`, this replaces any existing binding, and
+    /// procedures that refer to `name` see the new value.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.env.define(Symbol::new(name), value);
+    }
+
+    /// Bind `name` to a native procedure that calls `function`
+    ///
+    /// The procedure checks that it is given a number of arguments `arity`
+    /// accepts, and is otherwise called like a builtin procedure.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        function: impl Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    ) {
+        let procedure = Procedure::native(name, arity, function);
+        self.define(name, Value::Procedure(procedure));
+    }
+
+    /// Bind `name` to a native procedure that calls a typed Rust closure
+    ///
+    /// Arguments are converted to the closure's parameter types, and its
+    /// result back to a Scheme value:
+    ///
+    /// ```
+    /// # use twine_scheme::{Interpreter, types::Value};
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.define_fn("hypot", |a: f64, b: f64| a.hypot(b));
+    /// assert_eq!(interpreter.eval_str("(hypot 3 4)").unwrap(), Value::number(5.0));
+    /// ```
+    pub fn define_fn<Args, F: NativeFunction<Args>>(&mut self, name: &str, function: F) {
+        let procedure = Procedure::Native(Native::from_fn(name, function));
+        self.define(name, Value::Procedure(procedure));
+    }
+
+    /// Look up the value of a global binding, or a builtin procedure
+    pub fn get(&self, name: &str) -> Result<Value> {
+        self.env.lookup(&Symbol::new(name))
+    }
+
+    /// Call the procedure bound to `name` with the given arguments
+    pub fn call(&mut self, name: &str, args: &[Value]) -> Result<Value> {
+        match self.get(name)? {
+            Value::Procedure(procedure) => {
+                self.start_budget();
+                apply_procedure(procedure, args.to_vec())
+            }
+            value => {
+                let error_msg = format!("'{name}' is not a procedure, got {}", value.type_name());
+                Err(Error::runtime_error(&error_msg).with_code(ErrorCode::NotAProcedure))
+            }
+        }
+    }
+
+    /// Limit the resources each later evaluation may use
+    ///
+    /// Every call of [`Interpreter::eval_str`], [`Interpreter::eval_file`]
+    /// and [`Interpreter::call`] gets the full budget of fuel and time. An
+    /// evaluation that exceeds a limit fails with that limit's error, such
+    /// as [`Error::FuelExhausted`], and the interpreter remains usable:
+    ///
+    /// ```
+    /// # use twine_scheme::{Interpreter, Error, runtime::Limits};
+    /// let mut interpreter = Interpreter::new();
+    /// interpreter.set_limits(Limits::new().with_fuel(10_000));
+    /// let error = interpreter.eval_str("(define (f) (f)) (f)").unwrap_err();
+    /// assert!(matches!(*error.error, Error::FuelExhausted { fuel: 10_000, .. }));
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = Some(limits);
+    }
+
+    /// Remove the resource limits
+    pub fn clear_limits(&mut self) {
+        self.limits = None;
+        self.env.clear_limits();
+    }
+
+    /// The resource limits of each evaluation, if any
+    pub fn limits(&self) -> Option<Limits> {
+        self.limits
+    }
+
+    /// Give the next evaluation a fresh budget
+    fn start_budget(&mut self) {
+        if let Some(limits) = self.limits {
+            self.env.set_limits(limits);
+        }
+    }
+
+    /// The global environment
+    pub fn environment(&self) -> &Environment {
+        &self.env
+    }
+
+    /// The global environment, for evaluating with the lower-level API
+    pub fn environment_mut(&mut self) -> &mut Environment {
+        &mut self.env
+    }
+
+    /// A handle to the interrupt flag of this interpreter
+    ///
+    /// Setting it, from any thread, stops the evaluations running in this
+    /// interpreter and its fibers; other interpreters keep running.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.env.interrupt_handle()
+    }
+
+    /// The scheduler for fibers spawned by this interpreter
+    pub fn scheduler(&self) -> &Arc<Mutex<FiberScheduler>> {
+        &self.scheduler
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_str_keeps_definitions() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str("(define x 20)").unwrap();
+        assert_eq!(
+            interpreter.eval_str("(+ x 22)").unwrap(),
+            Value::number(42.0)
+        );
+        assert_eq!(interpreter.get("x").unwrap(), Value::number(20.0));
+        assert_eq!(interpreter.eval_str("").unwrap(), Value::nil());
+
+        let error = interpreter.eval_str("(car x)").unwrap_err();
+        assert_eq!(error.source_name, STRING_SOURCE_NAME);
+        assert!(matches!(*error.error, Error::TypeError { .. }));
+    }
+
+    #[test]
+    fn test_interpreters_choose_their_evaluators() {
+        let mut bytecode = Interpreter::new();
+        let mut tree_walker = Interpreter::new().with_evaluator(Evaluator::TreeWalker);
+        assert_eq!(bytecode.environment().evaluator(), Evaluator::Bytecode);
+        assert_eq!(tree_walker.environment().evaluator(), Evaluator::TreeWalker);
+
+        // Procedures and libraries defined in each keep its evaluator
+        let source = "(define-library (evaluator test) (export twice) (import (scheme base))
+                        (begin (define (twice x) (* 2 x))))
+                      (import (evaluator test))
+                      (define (f) (lambda (x) (twice x)))";
+        for interpreter in [&mut bytecode, &mut tree_walker] {
+            interpreter.eval_str(source).unwrap();
+            let evaluator = interpreter.environment().evaluator();
+            for procedure in ["(f)", "twice"] {
+                let value = interpreter.eval_str(procedure).unwrap();
+                let Value::Procedure(Procedure::Lambda(lambda)) = value else {
+                    panic!("Expected a lambda, got {value:?}");
+                };
+                assert_eq!(lambda.env().evaluator(), evaluator, "{procedure}");
+            }
+            assert_eq!(
+                interpreter.eval_str("((f) 21)").unwrap(),
+                Value::number(42.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_define_and_call() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define("scale", Value::number(3.0));
+        interpreter
+            .eval_str("(define (scaled x) (* scale x))")
+            .unwrap();
+        assert_eq!(
+            interpreter.call("scaled", &[Value::number(2.0)]).unwrap(),
+            Value::number(6.0)
+        );
+
+        // Redefinitions are seen by procedures defined earlier
+        interpreter.define("scale", Value::number(10.0));
+        assert_eq!(
+            interpreter.call("scaled", &[Value::number(2.0)]).unwrap(),
+            Value::number(20.0)
+        );
+
+        // Builtins can be called by name
+        assert_eq!(
+            interpreter
+                .call("+", &[Value::number(1.0), Value::number(2.0)])
+                .unwrap(),
+            Value::number(3.0)
+        );
+    }
+
+    #[test]
+    fn test_define_native() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_native("twice", Arity::exactly(1), |args| {
+            let x = args[0].as_number().unwrap_or_default();
+            Ok(Value::number(x * 2.0))
+        });
+
+        assert_eq!(
+            interpreter.eval_str("(twice 21)").unwrap(),
+            Value::number(42.0)
+        );
+        assert_eq!(
+            interpreter.eval_str("twice").unwrap().to_string(),
+            "#<builtin:twice>"
+        );
+        let error = interpreter.eval_str("(twice 1 2)").unwrap_err();
+        assert_eq!(error.error.code(), ErrorCode::Arity);
+    }
+
+    #[test]
+    fn test_define_fn() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define_fn("join", |parts: Vec<String>, separator: String| {
+            parts.join(&separator)
+        });
+
+        assert_eq!(
+            interpreter
+                .eval_str("(join (list \"a\" \"b\") \"-\")")
+                .unwrap(),
+            Value::string("a-b")
+        );
+        let error = interpreter
+            .eval_str("(join (list \"a\" 1) \"-\")")
+            .unwrap_err();
+        assert_eq!(
+            error.error.to_string(),
+            "join: expected list of string for argument 1, got number at index 1"
+        );
+        let error = interpreter.eval_str("(join (list))").unwrap_err();
+        assert_eq!(error.error.code(), ErrorCode::Arity);
+    }
+
+    #[test]
+    fn test_sandboxed() {
+        let mut interpreter = Interpreter::sandboxed(Sandbox::safe());
+        interpreter.define_native("log", Arity::exactly(1), |_| Ok(Value::nil()));
+
+        assert_eq!(
+            interpreter
+                .eval_str("(define (f x) (* x 2)) (f 21)")
+                .unwrap(),
+            Value::number(42.0)
+        );
+        assert_eq!(interpreter.eval_str("(log 1)").unwrap(), Value::nil());
+
+        let error = interpreter.eval_str("(display 1)").unwrap_err();
+        assert_eq!(error.error.code(), ErrorCode::NotPermitted);
+        assert_eq!(
+            error.error.to_string(),
+            "Runtime error: 'display' is not permitted in this sandbox"
+        );
+        let error = interpreter.eval_str("(async (+ 1 2))").unwrap_err();
+        assert_eq!(error.error.code(), ErrorCode::NotPermitted);
+        let error = interpreter.call("newline", &[]).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotPermitted);
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let mut interpreter = Interpreter::new();
+        interpreter.define("x", Value::number(1.0));
+
+        let error = interpreter.call("x", &[]).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::NotAProcedure);
+
+        let error = interpreter.call("missing", &[]).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::UnboundIdentifier);
+
+        interpreter.eval_str("(define (f x) x)").unwrap();
+        assert!(interpreter.call("f", &[]).is_err());
+    }
+}
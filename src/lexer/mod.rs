@@ -6,7 +6,7 @@
 use crate::Error;
 use crate::Result;
 
-pub use token::{Position, PositionedToken, Token};
+pub use token::{Position, PositionedToken, Span, Token};
 
 mod token;
 
@@ -287,6 +287,7 @@ impl Lexer {
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
@@ -724,6 +725,7 @@ mod tests {
             message,
             line,
             column,
+            ..
         }) = result
         {
             assert_eq!(message, "Unterminated string literal");
@@ -784,6 +786,7 @@ mod tests {
                 message,
                 line,
                 column,
+                ..
             }) = result
             {
                 assert!(message.contains("Unexpected character"));
@@ -3,6 +3,9 @@
 //! Defines the core token types and position tracking used throughout
 //! the lexical analysis phase.
 
+use std::fmt;
+use std::sync::Arc;
+
 /// Position information for tracking token locations in source code.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Position {
@@ -22,6 +25,44 @@ impl Position {
     }
 }
 
+/// A range of source text, used to locate expressions and errors.
+///
+/// The range runs from `start` (inclusive) to `end` (exclusive). The source
+/// name is usually a file path, or a pseudo-name such as `<stdin>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub source: Option<Arc<str>>,
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// Create a span between two positions with no source name.
+    pub fn new(start: Position, end: Position) -> Self {
+        Self {
+            source: None,
+            start,
+            end,
+        }
+    }
+
+    /// Attach the name of the source this span belongs to.
+    pub fn with_source(mut self, source: Option<Arc<str>>) -> Self {
+        self.source = source;
+        self
+    }
+}
+
+/// Formats as `source:line:column`, or `line:column` without a source name.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(source) = &self.source {
+            write!(f, "{source}:")?;
+        }
+        write!(f, "{}:{}", self.start.line, self.start.column)
+    }
+}
+
 /// A token with its position in the source code.
 #[derive(Debug, Clone, PartialEq)]
 pub struct PositionedToken {
@@ -3,13 +3,22 @@
 //! A minimalist Scheme interpreter written in Rust that implements a functional
 //! subset of R7RS-small Scheme with fiber-based concurrency and strict immutability.
 
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod diagnostics;
 pub mod error;
 pub mod fiber;
+pub mod interpreter;
 pub mod lexer;
 pub mod parser;
 pub mod repl;
 pub mod runtime;
+pub mod script;
 pub mod types;
 
 // Re-export error types for convenience
-pub use error::{Error, Result};
+pub use error::{Backtrace, Error, Frame, Result};
+
+// Re-export the embedding API for convenience
+pub use interpreter::Interpreter;
+pub use script::ScriptError;
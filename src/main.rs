@@ -1,9 +1,233 @@
+//! Command-line entry point for the Twine Scheme interpreter.
+//!
+//! ```text
+//! twine-scheme                     Start the interactive REPL
+//! twine-scheme FILE [ARGS...]      Run a script file
+//! twine-scheme -e EXPR [ARGS...]   Evaluate EXPR and print its value
+//! twine-scheme - [ARGS...]         Run a script read from standard input
+//! ```
+//!
+//! Any of these may be preceded by `--tree-walker` to evaluate with the
+//! tree-walking evaluator instead of compiling to bytecode.
+//!
+//! Programs import the builtin procedures they use from libraries such as
+//! `(scheme base)`, as library bodies do. `--implicit-builtins` binds every
+//! builtin without an import instead, for scripts written before libraries.
+//!
+//! Exits with status 0 on success, 1 if the program fails and 2 on invalid
+//! usage. Script arguments are available through `(command-line)`.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use twine_scheme::diagnostics::Renderer;
 use twine_scheme::repl::Repl;
+use twine_scheme::runtime::Environment;
+use twine_scheme::runtime::builtins::process::set_command_line;
+use twine_scheme::runtime::eval::Evaluator;
+use twine_scheme::runtime::library::add_search_path;
+use twine_scheme::script::{self, EXPRESSION_SOURCE_NAME};
+use twine_scheme::types::Value;
+
+/// Exit status for a program that raised an error
+const EXIT_FAILURE: u8 = 1;
+
+/// Exit status for invalid command-line usage
+const EXIT_USAGE: u8 = 2;
+
+const USAGE: &str = "\
+Usage:
+  twine-scheme                     Start the interactive REPL
+  twine-scheme FILE [ARGS...]      Run a script file
+  twine-scheme -e EXPR [ARGS...]   Evaluate EXPR and print its value
+  twine-scheme - [ARGS...]         Run a script read from standard input
+  twine-scheme -h | --help         Show this help
+
+Options:
+  --tree-walker                    Evaluate with the tree-walking evaluator
+  --implicit-builtins              Bind every builtin procedure without an import";
+
+/// What the interpreter was asked to do
+#[derive(Debug, PartialEq)]
+enum Command {
+    Repl,
+    Help,
+    File(PathBuf),
+    Expression(String),
+    Stdin,
+}
+
+/// Options given before the command
+#[derive(Debug, PartialEq)]
+struct Options {
+    evaluator: Evaluator,
+    /// Whether every builtin is bound without an import
+    implicit_builtins: bool,
+}
+
+/// Split the options off the front of the command line
+fn parse_options(mut args: &[String]) -> (Options, &[String]) {
+    let mut options = Options {
+        evaluator: Evaluator::Bytecode,
+        implicit_builtins: false,
+    };
+    loop {
+        match args.first().map(String::as_str) {
+            Some("--tree-walker") => options.evaluator = Evaluator::TreeWalker,
+            Some("--implicit-builtins") => options.implicit_builtins = true,
+            _ => return (options, args),
+        }
+        args = &args[1..];
+    }
+}
+
+/// Parse the command line into a command and the script's `(command-line)`
+fn parse_args(args: &[String]) -> Result<(Command, Vec<String>), String> {
+    let Some(first) = args.first() else {
+        return Ok((Command::Repl, Vec::new()));
+    };
+
+    let (command, name, rest) = match first.as_str() {
+        "-h" | "--help" => return Ok((Command::Help, Vec::new())),
+        // A one-liner is named as it is in errors
+        "-e" => match args.get(1) {
+            Some(expr) => (
+                Command::Expression(expr.clone()),
+                EXPRESSION_SOURCE_NAME,
+                &args[2..],
+            ),
+            None => return Err("-e requires an expression".to_string()),
+        },
+        "-" => (Command::Stdin, "-", &args[1..]),
+        option if option.starts_with('-') => {
+            return Err(format!("unknown option '{option}'"));
+        }
+        path => (Command::File(PathBuf::from(path)), path, &args[1..]),
+    };
+
+    // The script name comes first, as R7RS `command-line` expects
+    let mut command_line = vec![name.to_string()];
+    command_line.extend_from_slice(rest);
+    Ok((command, command_line))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (options, args) = parse_options(&args);
+    let (command, command_line) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("twine-scheme: {message}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    set_command_line(command_line);
+
+    let mut env = match options.implicit_builtins {
+        true => Environment::new(),
+        false => Environment::new_without_builtins(),
+    }
+    .with_evaluator(options.evaluator);
+    let result = match command {
+        Command::Help => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Command::Repl => {
+            let mut repl = Repl::with_environment(env);
+            if let Err(error) = repl.run() {
+                eprintln!("REPL error: {error}");
+                return ExitCode::from(EXIT_FAILURE);
+            }
+            return ExitCode::SUCCESS;
+        }
+        Command::File(path) => {
+            // Libraries next to the script can be imported without configuration
+            if let Some(directory) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                add_search_path(directory);
+            }
+            script::run_file(&path, &mut env)
+        }
+        Command::Stdin => script::run_stdin(&mut env),
+        Command::Expression(expr) => script::run_source(EXPRESSION_SOURCE_NAME, &expr, &mut env)
+            .inspect(|value| {
+                if *value != Value::Nil {
+                    println!("{value}");
+                }
+            }),
+    };
+
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprint!("{}", error.render(&Renderer::for_stderr()));
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]).unwrap(), (Command::Repl, vec![]));
+        assert_eq!(parse_args(&args(&["--help"])).unwrap().0, Command::Help);
+
+        assert_eq!(
+            parse_args(&args(&["build.scm", "--release", "x"])).unwrap(),
+            (
+                Command::File(PathBuf::from("build.scm")),
+                args(&["build.scm", "--release", "x"])
+            )
+        );
+        assert_eq!(
+            parse_args(&args(&["-e", "(+ 1 2)", "a"])).unwrap(),
+            (
+                Command::Expression("(+ 1 2)".to_string()),
+                args(&["<expression>", "a"])
+            )
+        );
+        assert_eq!(
+            parse_args(&args(&["-", "a"])).unwrap(),
+            (Command::Stdin, args(&["-", "a"]))
+        );
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let command_line = args(&["--tree-walker", "-e", "1"]);
+        let (options, rest) = parse_options(&command_line);
+        assert_eq!(options.evaluator, Evaluator::TreeWalker);
+        assert!(!options.implicit_builtins);
+        assert_eq!(rest, args(&["-e", "1"]));
+
+        let command_line = args(&["--implicit-builtins", "--tree-walker", "-"]);
+        let (options, rest) = parse_options(&command_line);
+        assert_eq!(
+            options,
+            Options {
+                evaluator: Evaluator::TreeWalker,
+                implicit_builtins: true,
+            }
+        );
+        assert_eq!(rest, args(&["-"]));
+
+        // Options after the command belong to the script
+        let command_line = args(&["run.scm", "--tree-walker"]);
+        let (options, rest) = parse_options(&command_line);
+        assert_eq!(options.evaluator, Evaluator::Bytecode);
+        assert_eq!(rest, args(&["run.scm", "--tree-walker"]));
+    }
 
-fn main() {
-    let mut repl = Repl::new();
-    if let Err(error) = repl.run() {
-        eprintln!("REPL error: {error}");
-        std::process::exit(1);
+    #[test]
+    fn test_parse_args_errors() {
+        assert!(parse_args(&args(&["-e"])).is_err());
+        assert!(parse_args(&args(&["--frobnicate"])).is_err());
     }
 }
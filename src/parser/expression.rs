@@ -3,7 +3,7 @@
 //! Defines the Abstract Syntax Tree nodes and positioned expressions
 //! used throughout the parsing phase.
 
-use crate::lexer::Position;
+use crate::lexer::{Position, Span};
 use crate::types::Value;
 use std::sync::Arc;
 
@@ -24,13 +24,18 @@ use std::sync::Arc;
 /// | `(+ 1 2)` | `Expression::List([Atom(+), Atom(1), Atom(2)])` |
 /// | `'x` | `Expression::Quote(Box::new(Atom(Symbol("x"))))` |
 /// | `'(a b)` | `Expression::Quote(Box::new(List([Atom(a), Atom(b)])))` |
-#[derive(Debug, PartialEq)]
+///
+/// Every variant ends with the node's source [`Span`], shared behind an Arc
+/// to keep nodes small. Expressions built by the parser always have one;
+/// expressions built in code have `None`. Spans are ignored when comparing
+/// expressions for equality.
+#[derive(Debug)]
 pub enum Expression {
     /// Atomic expressions (primitive values)
-    Atom(Value),
+    Atom(Value, Option<Arc<Span>>),
 
     /// List expressions (compound structures)
-    List(Vec<Arc<Expression>>),
+    List(Vec<Arc<Expression>>, Option<Arc<Span>>),
 
     /// Quoted expressions (prevent evaluation)
     ///
@@ -38,61 +43,72 @@ pub enum Expression {
     /// Arc provides heap allocation to break the recursion and enables
     /// arbitrarily deep nesting without stack overflow while allowing
     /// efficient sharing of expression trees.
-    Quote(Arc<Expression>),
+    Quote(Arc<Expression>, Option<Arc<Span>>),
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Atom(a, _), Expression::Atom(b, _)) => a == b,
+            (Expression::List(a, _), Expression::List(b, _)) => a == b,
+            (Expression::Quote(a, _), Expression::Quote(b, _)) => a == b,
+            _ => false,
+        }
+    }
 }
 
 impl Expression {
     /// Create an atomic expression from a Value.
     pub fn atom(value: Value) -> Self {
-        Expression::Atom(value)
+        Expression::Atom(value, None)
     }
 
     /// Create an atomic expression from a Value wrapped in Arc.
     pub fn arc_atom(value: Value) -> Arc<Self> {
-        Arc::new(Expression::Atom(value))
+        Arc::new(Expression::Atom(value, None))
     }
 
     /// Create a list expression from a vector of expressions.
     pub fn list(exprs: Vec<Arc<Expression>>) -> Self {
-        Expression::List(exprs)
+        Expression::List(exprs, None)
     }
 
     /// Create a list expression from a vector of expressions wrapped in Arc.
     pub fn arc_list(exprs: Vec<Arc<Expression>>) -> Arc<Self> {
-        Arc::new(Expression::List(exprs))
+        Arc::new(Expression::List(exprs, None))
     }
 
     /// Create a quoted expression.
     ///
     /// Handles the Arc allocation required for the recursive structure.
     pub fn quote(expr: Arc<Expression>) -> Self {
-        Expression::Quote(expr)
+        Expression::Quote(expr, None)
     }
 
     /// Create a quoted expression wrapped in Arc.
     pub fn arc_quote(expr: Arc<Expression>) -> Arc<Self> {
-        Arc::new(Expression::Quote(expr))
+        Arc::new(Expression::Quote(expr, None))
     }
 
     /// Check if this expression is an atom.
     pub fn is_atom(&self) -> bool {
-        matches!(self, Expression::Atom(_))
+        matches!(self, Expression::Atom(..))
     }
 
     /// Check if this expression is a list.
     pub fn is_list(&self) -> bool {
-        matches!(self, Expression::List(_))
+        matches!(self, Expression::List(..))
     }
 
     /// Check if this expression is quoted.
     pub fn is_quoted(&self) -> bool {
-        matches!(self, Expression::Quote(_))
+        matches!(self, Expression::Quote(..))
     }
 
     /// Get the value if this expression is an atom.
     pub fn as_atom(&self) -> Option<&Value> {
         match self {
-            Expression::Atom(value) => Some(value),
+            Expression::Atom(value, _) => Some(value),
             _ => None,
         }
     }
@@ -100,7 +116,7 @@ impl Expression {
     /// Get the list of expressions if this expression is a list.
     pub fn as_list(&self) -> Option<&Vec<Arc<Expression>>> {
         match self {
-            Expression::List(exprs) => Some(exprs),
+            Expression::List(exprs, _) => Some(exprs),
             _ => None,
         }
     }
@@ -108,7 +124,7 @@ impl Expression {
     /// Get the quoted expression if this expression is quoted.
     pub fn as_quoted(&self) -> Option<&Arc<Expression>> {
         match self {
-            Expression::Quote(expr) => Some(expr),
+            Expression::Quote(expr, _) => Some(expr),
             _ => None,
         }
     }
@@ -118,9 +134,28 @@ impl Expression {
     /// Useful for error messages and debugging output.
     pub fn type_name(&self) -> &'static str {
         match self {
-            Expression::Atom(_) => "atom",
-            Expression::List(_) => "list",
-            Expression::Quote(_) => "quote",
+            Expression::Atom(..) => "atom",
+            Expression::List(..) => "list",
+            Expression::Quote(..) => "quote",
+        }
+    }
+
+    /// Get the source span of this expression, if known.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            Expression::Atom(_, span) | Expression::List(_, span) | Expression::Quote(_, span) => {
+                span.as_deref()
+            }
+        }
+    }
+
+    /// Replace the source span of this expression.
+    pub fn with_span(self, span: Span) -> Self {
+        let span = Arc::new(span);
+        match self {
+            Expression::Atom(value, _) => Expression::Atom(value, Some(span)),
+            Expression::List(exprs, _) => Expression::List(exprs, Some(span)),
+            Expression::Quote(expr, _) => Expression::Quote(expr, Some(span)),
         }
     }
 }
@@ -25,7 +25,8 @@
 //! ]));
 //! ```
 
-use crate::lexer::Lexer;
+use crate::lexer::{Lexer, Position, Span};
+use std::sync::Arc;
 
 pub use expression::{Expression, PositionedExpression};
 
@@ -34,7 +35,8 @@ mod expression;
 /// Parser for converting tokens into Abstract Syntax Tree.
 ///
 /// Implements recursive descent parsing for Scheme S-expressions.
-/// Maintains current position in token stream for error reporting.
+/// Maintains current position in token stream for error reporting, and
+/// records the source [`Span`] of every expression it builds.
 ///
 /// # Example
 /// ```rust
@@ -47,7 +49,11 @@ mod expression;
 /// ```
 pub struct Parser {
     tokens: Vec<crate::lexer::PositionedToken>,
+    /// Position just past the end of each token, parallel to `tokens`
+    token_ends: Vec<Position>,
     current: usize,
+    /// Source name recorded in spans
+    source: Option<Arc<str>>,
 }
 
 impl Parser {
@@ -57,19 +63,44 @@ impl Parser {
     pub fn new(input: String) -> crate::Result<Self> {
         let mut lexer = Lexer::new(input);
         let mut tokens = Vec::new();
+        let mut token_ends = Vec::new();
 
         // Collect all tokens from the lexer
         loop {
             let positioned_token = lexer.next_token()?;
             let is_eof = positioned_token.token.is_eof();
             tokens.push(positioned_token);
+            token_ends.push(lexer.current_position());
 
             if is_eof {
                 break;
             }
         }
 
-        Ok(Self { tokens, current: 0 })
+        Ok(Self {
+            tokens,
+            token_ends,
+            current: 0,
+            source: None,
+        })
+    }
+
+    /// Set the source name (usually a file path) recorded in spans.
+    pub fn with_source_name(mut self, name: &str) -> Self {
+        self.source = Some(Arc::from(name));
+        self
+    }
+
+    /// Span from the given start position to the end of the last consumed token.
+    fn span_from(&self, start: Position) -> Span {
+        let end = self.token_ends[self.current.saturating_sub(1)].clone();
+        Span::new(start, end).with_source(self.source.clone())
+    }
+
+    /// Wrap a just-parsed expression with its span and start position.
+    fn positioned(&self, expr: Expression, start: Position) -> PositionedExpression {
+        let span = self.span_from(start.clone());
+        PositionedExpression::new(Arc::new(expr.with_span(span)), start)
     }
 
     /// Get the current token without advancing the parser.
@@ -112,10 +143,7 @@ impl Parser {
             crate::lexer::Token::Quote => {
                 self.advance(); // consume quote
                 let quoted_expr = self.parse_expression()?;
-                Ok(PositionedExpression::new(
-                    Expression::arc_quote(quoted_expr.expr),
-                    position,
-                ))
+                Ok(self.positioned(Expression::quote(quoted_expr.expr), position))
             }
             crate::lexer::Token::LeftParen => {
                 self.advance(); // consume left paren
@@ -136,10 +164,7 @@ impl Parser {
                 }
 
                 self.advance(); // consume right paren
-                Ok(PositionedExpression::new(
-                    Expression::arc_list(expressions),
-                    position,
-                ))
+                Ok(self.positioned(Expression::list(expressions), position))
             }
             crate::lexer::Token::RightParen => Err(crate::Error::syntax_error(
                 "Unexpected ')'",
@@ -149,32 +174,32 @@ impl Parser {
             crate::lexer::Token::Number(n) => {
                 let value = *n;
                 self.advance();
-                Ok(PositionedExpression::new(
-                    Expression::arc_atom(crate::types::Value::number(value)),
+                Ok(self.positioned(
+                    Expression::atom(crate::types::Value::number(value)),
                     position,
                 ))
             }
             crate::lexer::Token::String(s) => {
                 let value = s.clone();
                 self.advance();
-                Ok(PositionedExpression::new(
-                    Expression::arc_atom(crate::types::Value::string(&value)),
+                Ok(self.positioned(
+                    Expression::atom(crate::types::Value::string(&value)),
                     position,
                 ))
             }
             crate::lexer::Token::Symbol(s) => {
                 let value = s.clone();
                 self.advance();
-                Ok(PositionedExpression::new(
-                    Expression::arc_atom(crate::types::Value::symbol(&value)),
+                Ok(self.positioned(
+                    Expression::atom(crate::types::Value::symbol(&value)),
                     position,
                 ))
             }
             crate::lexer::Token::Boolean(b) => {
                 let value = *b;
                 self.advance();
-                Ok(PositionedExpression::new(
-                    Expression::arc_atom(crate::types::Value::boolean(value)),
+                Ok(self.positioned(
+                    Expression::atom(crate::types::Value::boolean(value)),
                     position,
                 ))
             }
@@ -206,8 +231,8 @@ impl Parser {
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expression::Atom(value) => write!(f, "{value}"),
-            Expression::List(exprs) => {
+            Expression::Atom(value, _) => write!(f, "{value}"),
+            Expression::List(exprs, _) => {
                 write!(f, "(")?;
                 for (i, expr) in exprs.iter().enumerate() {
                     if i > 0 {
@@ -217,7 +242,7 @@ impl std::fmt::Display for Expression {
                 }
                 write!(f, ")")
             }
-            Expression::Quote(expr) => write!(f, "'{expr}"),
+            Expression::Quote(expr, _) => write!(f, "'{expr}"),
         }
     }
 }
@@ -868,4 +893,54 @@ mod tests {
         let expr = parser.parse_expression().unwrap();
         assert_eq!(format!("{}", expr.expr), "'(the quick brown fox)");
     }
+
+    #[test]
+    fn test_spans_on_every_expression() {
+        let mut parser = Parser::new("(foo\n  (bar 42)\n  'x)".to_string())
+            .unwrap()
+            .with_source_name("test.scm");
+        let expr = parser.parse_expression().unwrap().expr;
+
+        let span = expr.span().unwrap();
+        assert_eq!(span.source.as_deref(), Some("test.scm"));
+        assert_eq!(span.start, Position::new(1, 1));
+        assert_eq!(span.end, Position::new(3, 6));
+        assert_eq!(span.to_string(), "test.scm:1:1");
+
+        let elements = expr.as_list().unwrap();
+        let foo = elements[0].span().unwrap();
+        assert_eq!(
+            (foo.start.clone(), foo.end.clone()),
+            (Position::new(1, 2), Position::new(1, 5))
+        );
+
+        let inner = elements[1].span().unwrap();
+        assert_eq!(
+            (inner.start.clone(), inner.end.clone()),
+            (Position::new(2, 3), Position::new(2, 11))
+        );
+        let number = elements[1].as_list().unwrap()[1].span().unwrap();
+        assert_eq!(number.start, Position::new(2, 8));
+
+        let quote = elements[2].span().unwrap();
+        assert_eq!(
+            (quote.start.clone(), quote.end.clone()),
+            (Position::new(3, 3), Position::new(3, 5))
+        );
+    }
+
+    #[test]
+    fn test_spans_ignored_by_equality() {
+        let mut parser = Parser::new("(+ 1 2)".to_string()).unwrap();
+        let parsed = parser.parse_expression().unwrap().expr;
+        let built = Expression::arc_list(vec![
+            Expression::arc_atom(Value::symbol("+")),
+            Expression::arc_atom(Value::number(1.0)),
+            Expression::arc_atom(Value::number(2.0)),
+        ]);
+
+        assert!(parsed.span().is_some());
+        assert!(built.span().is_none());
+        assert_eq!(parsed, built);
+    }
 }
@@ -1,44 +1,81 @@
 //! Interactive REPL (Read-Eval-Print Loop) for the Twine Scheme interpreter.
 //!
 //! Provides an enhanced command-line interface with multi-line input support
-//! using standard I/O without external dependencies.
+//! using standard I/O.
+//!
+//! Ctrl+C interrupts the expression being evaluated and returns to the
+//! prompt, keeping every definition made so far. Pressing it again before
+//! the evaluator notices, as when a builtin is blocked, exits the REPL.
 
 use std::io::{self, Write};
+use std::sync::Arc;
+
+use signal_hook::consts::SIGINT;
 
 use crate::{
     Error,
+    diagnostics::{Renderer, SourceMap},
     lexer::{Lexer, Token},
     parser::Parser,
-    runtime::{Environment, eval},
+    runtime::{Environment, eval, interrupt::InterruptHandle},
     types::Value,
 };
 
 /// REPL configuration and state management
 pub struct Repl {
-    env: Environment<'static>,
+    env: Environment,
+    /// Every input so far, so errors can show lines from earlier inputs
+    sources: SourceMap,
+    inputs: usize,
 }
 
 impl Repl {
     /// Create a new REPL instance
+    ///
+    /// As in a program, builtin procedures are imported from libraries such
+    /// as `(scheme base)` before they are used.
     pub fn new() -> Self {
+        Self::with_environment(Environment::new_without_builtins())
+    }
+
+    /// Create a REPL evaluating in `env`, such as one with every builtin
+    /// bound by [`Environment::new`]
+    pub fn with_environment(env: Environment) -> Self {
         Self {
-            env: Environment::new(),
+            env,
+            sources: SourceMap::new(),
+            inputs: 0,
         }
     }
 
     /// Run the interactive REPL loop
     pub fn run(&mut self) -> io::Result<()> {
+        install_interrupt_handler(&self.env.interrupt_handle())?;
+
         println!("Twine Scheme Interpreter");
-        println!("Type expressions to evaluate, or Ctrl+C to exit.");
+        println!("Type expressions to evaluate, Ctrl+C to interrupt, or Ctrl+D to exit.");
+        if !self.env.builtins_visible() {
+            println!("Import the builtins you need, e.g. (import (scheme base) (scheme write)).");
+        }
         println!();
 
         loop {
             match self.read_complete_expression() {
                 Ok(Some(input)) => {
-                    // Evaluate and print
-                    match eval_source(&input, &mut self.env) {
+                    // Each input is a separate source, named for error locations
+                    self.inputs += 1;
+                    let source_name = format!("<repl-{}>", self.inputs);
+                    self.sources.add(&source_name, &input);
+
+                    // Evaluate and print; Ctrl+C pressed at the prompt is
+                    // not meant for this evaluation
+                    self.env.interrupt_handle().clear();
+                    match eval_source(&source_name, &input, &mut self.env) {
                         Ok(value) => println!("{value}"),
-                        Err(error) => eprintln!("Error: {error}"),
+                        Err(error) => {
+                            let error = error.in_source(&source_name);
+                            eprint!("{}", Renderer::for_stderr().render(&error, &self.sources));
+                        }
                     }
                 }
                 Ok(None) => {
@@ -116,6 +153,18 @@ impl Default for Repl {
     }
 }
 
+/// Make SIGINT interrupt the evaluation running with `interrupt`'s flag
+///
+/// A second SIGINT arriving before the first has been delivered terminates
+/// the process, as the evaluation is not checking for interrupts.
+fn install_interrupt_handler(interrupt: &InterruptHandle) -> io::Result<()> {
+    let flag = interrupt.flag();
+    // Registered first, so it sees the flag as it was before this signal
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 130, Arc::clone(&flag))?;
+    signal_hook::flag::register(SIGINT, flag)?;
+    Ok(())
+}
+
 /// Determines if an expression is complete by tokenizing and checking bracket balance
 fn is_expression_complete(input: &str) -> Result<bool, Error> {
     let mut lexer = Lexer::new(input.to_string());
@@ -156,12 +205,12 @@ fn is_expression_complete(input: &str) -> Result<bool, Error> {
 }
 
 /// Helper function to evaluate source code strings in the REPL context
-fn eval_source(source: &str, env: &mut Environment) -> Result<Value, Error> {
+fn eval_source(source_name: &str, source: &str, env: &mut Environment) -> Result<Value, Error> {
     if source.trim().is_empty() {
         return Ok(Value::nil());
     }
 
-    let mut parser = Parser::new(source.to_string())?;
+    let mut parser = Parser::new(source.to_string())?.with_source_name(source_name);
     let mut last_value = Value::nil();
 
     // Parse and evaluate all expressions in the input
@@ -23,7 +23,7 @@ pub fn add(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "+",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -52,7 +52,7 @@ pub fn subtract(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "-",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -88,7 +88,7 @@ pub fn multiply(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "*",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -117,7 +117,7 @@ pub fn divide(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "/",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -240,9 +240,9 @@ mod tests {
         let symbol_arg = Value::symbol("x");
 
         assert!(add(&[Value::number(1.0), string_arg.clone()]).is_err());
-        assert!(subtract(&[bool_arg.clone()]).is_err());
+        assert!(subtract(std::slice::from_ref(&bool_arg)).is_err());
         assert!(multiply(&[Value::number(1.0), symbol_arg.clone()]).is_err());
-        assert!(divide(&[string_arg.clone()]).is_err());
+        assert!(divide(std::slice::from_ref(&string_arg)).is_err());
     }
 
     #[test]
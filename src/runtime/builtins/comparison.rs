@@ -24,7 +24,7 @@ pub fn equal(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "=",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -55,7 +55,7 @@ pub fn less_than(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "<",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -87,7 +87,7 @@ pub fn greater_than(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 ">",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -119,7 +119,7 @@ pub fn less_than_or_equal(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 "<=",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
@@ -151,7 +151,7 @@ pub fn greater_than_or_equal(args: &[Value]) -> Result<Value> {
             return Err(Error::type_error(
                 ">=",
                 "number",
-                arg.type_name(),
+                &arg.type_description(),
                 Some(i + 1),
             ));
         }
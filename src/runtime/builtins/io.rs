@@ -61,7 +61,9 @@ pub fn display(args: &[Value]) -> Result<Value> {
         | Value::Boolean(_)
         | Value::List(_)
         | Value::Nil
-        | Value::Procedure(_) => {
+        | Value::Procedure(_)
+        | Value::Record(_)
+        | Value::Promise(_) => {
             // Use standard formatting for all other types
             format!("{value}")
         }
@@ -98,7 +100,9 @@ pub fn display_to_writer<W: Write>(args: &[Value], writer: &mut W) -> Result<Val
         | Value::Boolean(_)
         | Value::List(_)
         | Value::Nil
-        | Value::Procedure(_) => {
+        | Value::Procedure(_)
+        | Value::Record(_)
+        | Value::Promise(_) => {
             // Use standard formatting for all other types
             format!("{value}")
         }
@@ -159,6 +163,7 @@ pub fn newline_to_writer<W: Write>(args: &[Value], writer: &mut W) -> Result<Val
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
@@ -179,6 +184,7 @@ mod tests {
             procedure,
             expected,
             actual,
+            ..
         }) = result
         {
             assert_eq!(procedure, "display");
@@ -196,6 +202,7 @@ mod tests {
             procedure,
             expected,
             actual,
+            ..
         }) = result
         {
             assert_eq!(procedure, "display");
@@ -298,6 +305,7 @@ mod tests {
             procedure,
             expected,
             actual,
+            ..
         }) = result
         {
             assert_eq!(procedure, "newline");
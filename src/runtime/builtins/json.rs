@@ -0,0 +1,543 @@
+//! JSON procedures for the Twine Scheme runtime
+//!
+//! This module converts between JSON text and Scheme data:
+//! - `json->scheme`: Parse a JSON string into a Scheme value
+//! - `scheme->json`: Write a Scheme value as a JSON string
+//!
+//! JSON values map to Scheme data as follows:
+//!
+//! | JSON | Scheme |
+//! |------|--------|
+//! | non-empty object | association list of `(key value)` lists with symbol keys |
+//! | `{}` | the symbol `empty-object` |
+//! | array | list |
+//! | string | string |
+//! | number | number (all numbers are inexact); too large to represent is an error |
+//! | `true`, `false` | `#t`, `#f` |
+//! | `null` | the symbol `null` |
+//!
+//! This is not SRFI 180, which maps arrays to vectors and objects to
+//! association lists of pairs: the runtime has neither vectors nor pairs.
+//! Because the empty association list is the empty list, the empty object
+//! has a symbol of its own, as `null` does, so that it round-trips.
+//!
+//! When writing, a non-empty list whose elements are all two-element lists
+//! starting with a symbol other than `null` or `empty-object` is an object;
+//! any other list is an array, so the empty list is written as `[]` and
+//! `[[null, 1]]` round-trips. Other symbols are written as strings.
+//! Parse errors report the line and column of the offending character.
+//! Only strings are supported until the runtime has ports.
+
+use crate::error::{Error, Result};
+use crate::types::{ArcString, Symbol, Value};
+use std::fmt::Write;
+
+/// Arrays and objects nested deeper than this are rejected, so that
+/// malicious input cannot overflow the stack
+const MAX_DEPTH: usize = 512;
+
+/// The symbol standing for JSON `null`
+const NULL: &str = "null";
+
+/// The symbol standing for the empty JSON object `{}`
+const EMPTY_OBJECT: &str = "empty-object";
+
+/// Parse a JSON string into a Scheme value (json->scheme)
+///
+/// # Examples
+/// ```scheme
+/// (json->scheme "{\"id\": 7, \"tags\": [\"a\", null]}")
+/// ; => ((id 7) (tags ("a" null)))
+/// ```
+pub fn json_to_scheme(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("json->scheme", 1, args.len()));
+    }
+
+    match &args[0] {
+        Value::String(text) => parse(text.as_str()),
+        other => Err(Error::type_error(
+            "json->scheme",
+            "string",
+            &other.type_description(),
+            Some(1),
+        )),
+    }
+}
+
+/// Write a Scheme value as a JSON string (scheme->json)
+///
+/// # Examples
+/// ```scheme
+/// (scheme->json '((id 7) (tags ("a" null))))
+/// ; => "{\"id\":7,\"tags\":[\"a\",null]}"
+/// ```
+pub fn scheme_to_json(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("scheme->json", 1, args.len()));
+    }
+
+    let mut json = String::new();
+    write_value(&args[0], &mut json)?;
+    Ok(Value::String(ArcString::from_string(json)))
+}
+
+/// Parse a complete JSON text
+fn parse(text: &str) -> Result<Value> {
+    let mut parser = JsonParser {
+        text,
+        offset: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.offset < text.len() {
+        return Err(parser.error("unexpected data after the JSON value"));
+    }
+    Ok(value)
+}
+
+/// A recursive descent parser over JSON text
+struct JsonParser<'a> {
+    text: &'a str,
+    /// Byte offset of the next character
+    offset: usize,
+    /// Number of arrays and objects being parsed
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    /// An error located at the next character
+    fn error(&self, message: &str) -> Error {
+        let before = &self.text[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rfind('\n')
+            .map_or(before, |newline| &before[newline + 1..])
+            .chars()
+            .count()
+            + 1;
+        Error::runtime_error(&format!(
+            "json->scheme: {message} at line {line}, column {column}"
+        ))
+    }
+
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(c) => self.error(&format!("unexpected character '{c}'")),
+            None => self.error("unexpected end of input"),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        if self.peek() == Some(expected) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Value::String(ArcString::from_string(self.string()?))),
+            Some('-' | '0'..='9') => self.number(),
+            Some('t') => self.literal("true", Value::boolean(true)),
+            Some('f') => self.literal("false", Value::boolean(false)),
+            Some('n') => self.literal("null", Value::symbol(NULL)),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Parse an array or object, limiting how deeply they nest
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value>) -> Result<Value> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("arrays and objects nested too deeply"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value> {
+        if self.text[self.offset..].starts_with(word) {
+            self.offset += word.len();
+            Ok(value)
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn object(&mut self) -> Result<Value> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.offset += 1;
+            return Ok(Value::symbol(EMPTY_OBJECT));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.unexpected());
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            entries.push(Value::list(vec![
+                Value::Symbol(Symbol::from_string(key)),
+                value,
+            ]));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Value::list(entries)),
+                _ => {
+                    self.offset -= 1;
+                    return Err(self.unexpected());
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.offset += 1;
+            return Ok(Value::list(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.offset += 1,
+                Some(']') => {
+                    self.offset += 1;
+                    return Ok(Value::list(elements));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+        loop {
+            let start = self.offset;
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) if c < ' ' => {
+                    self.offset = start;
+                    return Err(self.error("control character in string"));
+                }
+                Some(c) => string.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    /// Parse the escape sequence after a backslash
+    fn escape(&mut self) -> Result<char> {
+        let c = match self.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => return self.unicode_escape(),
+            Some(_) => {
+                self.offset -= 1;
+                return Err(self.error("invalid escape sequence"));
+            }
+            None => return Err(self.error("unterminated string")),
+        };
+        Ok(c)
+    }
+
+    /// Parse the code point of a `\u` escape, which may be a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.offset..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate in escape sequence"));
+            }
+            self.offset += 2;
+            let low = self.hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate in escape sequence"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("unpaired surrogate in escape sequence"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let digits = self.text[self.offset..].get(..4).unwrap_or_default();
+        if digits.len() < 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        self.offset += 4;
+        Ok(u32::from_str_radix(digits, 16).expect("checked hex digits"))
+    }
+
+    fn number(&mut self) -> Result<Value> {
+        let start = self.offset;
+        let digits = |parser: &mut Self| {
+            let from = parser.offset;
+            while let Some('0'..='9') = parser.peek() {
+                parser.offset += 1;
+            }
+            parser.offset > from
+        };
+
+        if self.peek() == Some('-') {
+            self.offset += 1;
+        }
+        // No leading zeros, except a lone zero before the fraction
+        if self.peek() == Some('0') {
+            self.offset += 1;
+        } else if !digits(self) {
+            return Err(self.unexpected());
+        }
+        if self.peek() == Some('.') {
+            self.offset += 1;
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+        if let Some('e' | 'E') = self.peek() {
+            self.offset += 1;
+            if let Some('+' | '-') = self.peek() {
+                self.offset += 1;
+            }
+            if !digits(self) {
+                return Err(self.unexpected());
+            }
+        }
+
+        let n = self.text[start..self.offset]
+            .parse::<f64>()
+            .expect("checked JSON number syntax");
+        // Infinities could not be written back as JSON
+        if n.is_infinite() {
+            self.offset = start;
+            return Err(self.error("number out of range"));
+        }
+        Ok(Value::number(n))
+    }
+}
+
+/// Append the JSON for `value` to `json`
+fn write_value(value: &Value, json: &mut String) -> Result<()> {
+    match value {
+        Value::Number(n) if n.value().is_finite() => write!(json, "{}", n.value()).unwrap(),
+        Value::Boolean(b) => json.push_str(if *b { "true" } else { "false" }),
+        Value::String(s) => write_string(s.as_str(), json),
+        Value::Symbol(s) if s.as_str() == NULL => json.push_str(NULL),
+        Value::Symbol(s) if s.as_str() == EMPTY_OBJECT => json.push_str("{}"),
+        Value::Symbol(s) => write_string(s.as_str(), json),
+        Value::List(list) if is_object(list.as_slice()) => {
+            json.push('{');
+            for (i, entry) in list.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                let entry = entry.as_list().expect("checked object entry").as_slice();
+                write_string(entry[0].as_symbol().expect("checked object key"), json);
+                json.push(':');
+                write_value(&entry[1], json)?;
+            }
+            json.push('}');
+        }
+        Value::List(list) => {
+            json.push('[');
+            for (i, element) in list.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write_value(element, json)?;
+            }
+            json.push(']');
+        }
+        Value::Nil => json.push_str("[]"),
+        other => {
+            return Err(Error::type_error(
+                "scheme->json",
+                "JSON-compatible value",
+                &other.type_description(),
+                Some(1),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check if a list is an association list, written as a JSON object
+///
+/// Keys may not be `null` or `empty-object`, since those symbols are JSON
+/// values and a list of them is an array.
+fn is_object(elements: &[Value]) -> bool {
+    !elements.is_empty()
+        && elements.iter().all(|entry| match entry.as_list() {
+            Some(entry) => match entry.as_slice()[..] {
+                [Value::Symbol(ref key), _] => key.as_str() != NULL && key.as_str() != EMPTY_OBJECT,
+                _ => false,
+            },
+            None => false,
+        })
+}
+
+fn write_string(s: &str, json: &mut String) {
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c < ' ' => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(json: &str) -> Result<Value> {
+        json_to_scheme(&[Value::string(json)])
+    }
+
+    fn write(value: Value) -> Result<String> {
+        scheme_to_json(&[value]).map(|json| json.as_string().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_json_to_scheme() {
+        let value =
+            read(r#" {"id": 7, "ok": true, "tags": ["a", null], "ratio": -2.5e-1} "#).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"((id 7) (ok #t) (tags ("a" null)) (ratio -0.25))"#
+        );
+
+        assert_eq!(read("[]").unwrap(), Value::list(vec![]));
+        assert_eq!(read("{}").unwrap(), Value::symbol("empty-object"));
+        assert_eq!(
+            read(r#""tab\there \u00e9 \ud83d\ude00""#).unwrap(),
+            Value::string("tab\there é 😀")
+        );
+        assert!(read("[1, 2]").unwrap().is_list());
+    }
+
+    #[test]
+    fn test_json_parse_errors() {
+        let error = read("{\"a\": 1,\n  \"b\" 2}").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Runtime error: json->scheme: unexpected character '2' at line 2, column 7"
+        );
+
+        let cases = [
+            ("", "unexpected end of input at line 1, column 1"),
+            ("[1, 2", "unexpected end of input at line 1, column 6"),
+            (
+                "01",
+                "unexpected data after the JSON value at line 1, column 2",
+            ),
+            ("\"abc", "unterminated string at line 1, column 5"),
+            ("\"\\x\"", "invalid escape sequence at line 1, column 3"),
+            ("tru", "unexpected character 't' at line 1, column 1"),
+            ("[1,]", "unexpected character ']' at line 1, column 4"),
+            ("[1, -1e400]", "number out of range at line 1, column 5"),
+            ("1e400", "number out of range at line 1, column 1"),
+        ];
+        for (json, message) in cases {
+            let error = read(json).unwrap_err();
+            assert!(error.to_string().ends_with(message), "{json:?}: {error}");
+        }
+
+        let deep = "[".repeat(MAX_DEPTH + 1);
+        assert!(
+            read(&deep)
+                .unwrap_err()
+                .to_string()
+                .contains("nested too deeply")
+        );
+        assert!(json_to_scheme(&[Value::number(1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_scheme_to_json() {
+        let value = read(r#"{"id":7,"tags":["a",null],"nested":{"x":[]},"s":"q\"\n"}"#).unwrap();
+        assert_eq!(
+            write(value).unwrap(),
+            r#"{"id":7,"tags":["a",null],"nested":{"x":[]},"s":"q\"\n"}"#
+        );
+        // The empty object is distinct from the empty array
+        let value = read(r#"{"a":{},"b":[]}"#).unwrap();
+        assert_eq!(write(value).unwrap(), r#"{"a":{},"b":[]}"#);
+
+        assert_eq!(write(Value::number(2.5)).unwrap(), "2.5");
+        assert_eq!(write(Value::symbol("name")).unwrap(), r#""name""#);
+        assert_eq!(write(Value::Nil).unwrap(), "[]");
+        // Lists of pairs keyed by strings are arrays
+        let pairs = Value::list(vec![Value::list(vec![
+            Value::string("a"),
+            Value::number(1.0),
+        ])]);
+        assert_eq!(write(pairs).unwrap(), r#"[["a",1]]"#);
+
+        // Nested arrays starting with null or {} are not objects
+        for json in [
+            r#"[[null,1]]"#,
+            r#"[[{},"x"],[null,null]]"#,
+            r#"[[[1,2]],[]]"#,
+        ] {
+            assert_eq!(write(read(json).unwrap()).unwrap(), json);
+        }
+
+        assert!(write(Value::number(f64::NAN)).is_err());
+        let procedure = Value::Procedure(crate::types::Procedure::builtin(
+            crate::runtime::builtins::Builtin::Car,
+        ));
+        let error = write(procedure).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "scheme->json: expected JSON-compatible value for argument 1, got procedure 'car'"
+        );
+    }
+}
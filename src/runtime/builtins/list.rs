@@ -35,7 +35,7 @@ pub fn car(args: &[Value]) -> Result<Value> {
 
     let list = args[0]
         .as_list()
-        .ok_or_else(|| Error::type_error("car", "list", args[0].type_name(), None))?;
+        .ok_or_else(|| Error::type_error("car", "list", &args[0].type_description(), None))?;
 
     if list.is_empty() {
         return Err(Error::runtime_error("car: cannot take car of empty list"));
@@ -68,7 +68,7 @@ pub fn cdr(args: &[Value]) -> Result<Value> {
 
     let list = args[0]
         .as_list()
-        .ok_or_else(|| Error::type_error("cdr", "list", args[0].type_name(), None))?;
+        .ok_or_else(|| Error::type_error("cdr", "list", &args[0].type_description(), None))?;
 
     if list.is_empty() {
         return Err(Error::runtime_error("cdr: cannot take cdr of empty list"));
@@ -196,12 +196,13 @@ pub fn length(args: &[Value]) -> Result<Value> {
 
     let list = args[0]
         .as_list()
-        .ok_or_else(|| Error::type_error("length", "list", args[0].type_name(), None))?;
+        .ok_or_else(|| Error::type_error("length", "list", &args[0].type_description(), None))?;
 
     Ok(Value::number(list.len() as f64))
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
@@ -432,7 +433,7 @@ mod tests {
             Value::number(2.0),
             Value::number(3.0),
         ]);
-        let first = car(&[original.clone()]).unwrap();
+        let first = car(std::slice::from_ref(&original)).unwrap();
         let rest = cdr(&[original]).unwrap();
 
         assert_eq!(first, Value::number(1.0));
@@ -455,7 +456,7 @@ mod tests {
     fn test_edge_cases() {
         // Test single element list operations
         let single = Value::list(vec![Value::string("only")]);
-        let first = car(&[single.clone()]).unwrap();
+        let first = car(std::slice::from_ref(&single)).unwrap();
         let rest = cdr(&[single]).unwrap();
 
         assert_eq!(first, Value::string("only"));
@@ -520,10 +521,10 @@ mod tests {
         // Verify that type errors give meaningful messages
         let not_list = Value::number(42.0);
 
-        let car_err = car(&[not_list.clone()]).unwrap_err();
+        let car_err = car(std::slice::from_ref(&not_list)).unwrap_err();
         assert!(car_err.to_string().contains("expected list"));
 
-        let cdr_err = cdr(&[not_list.clone()]).unwrap_err();
+        let cdr_err = cdr(std::slice::from_ref(&not_list)).unwrap_err();
         assert!(cdr_err.to_string().contains("expected list"));
 
         // cons should now accept any second argument, so this should succeed
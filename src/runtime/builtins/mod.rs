@@ -46,9 +46,87 @@ pub enum Builtin {
     // I/O operations
     Display,
     Newline,
+
+    // Parameter objects
+    MakeParameter,
+
+    // Symbols
+    SymbolToString,
+    StringToSymbol,
+    Gensym,
+    GenerateUninternedSymbol,
+
+    // JSON
+    JsonToScheme,
+    SchemeToJson,
+
+    // Process context
+    CommandLine,
+
+    // Procedure introspection
+    ProcedureName,
+
+    // Promises
+    Force,
+    MakePromise,
+    PromiseP,
+
+    // Streams
+    Stream,
+    StreamCar,
+    StreamCdr,
+    StreamNullP,
+    StreamPairP,
+    StreamToList,
 }
 
 impl Builtin {
+    /// Every builtin procedure, in declaration order
+    pub const ALL: &'static [Builtin] = &[
+        Builtin::Add,
+        Builtin::Subtract,
+        Builtin::Multiply,
+        Builtin::Divide,
+        Builtin::Equal,
+        Builtin::LessThan,
+        Builtin::GreaterThan,
+        Builtin::LessThanOrEqual,
+        Builtin::GreaterThanOrEqual,
+        Builtin::Car,
+        Builtin::Cdr,
+        Builtin::Cons,
+        Builtin::List,
+        Builtin::NullP,
+        Builtin::Length,
+        Builtin::NumberP,
+        Builtin::StringP,
+        Builtin::BooleanP,
+        Builtin::SymbolP,
+        Builtin::ListP,
+        Builtin::ProcedureP,
+        Builtin::EqP,
+        Builtin::Display,
+        Builtin::Newline,
+        Builtin::MakeParameter,
+        Builtin::SymbolToString,
+        Builtin::StringToSymbol,
+        Builtin::Gensym,
+        Builtin::GenerateUninternedSymbol,
+        Builtin::JsonToScheme,
+        Builtin::SchemeToJson,
+        Builtin::CommandLine,
+        Builtin::ProcedureName,
+        Builtin::Force,
+        Builtin::MakePromise,
+        Builtin::PromiseP,
+        Builtin::Stream,
+        Builtin::StreamCar,
+        Builtin::StreamCdr,
+        Builtin::StreamNullP,
+        Builtin::StreamPairP,
+        Builtin::StreamToList,
+    ];
+
     /// Get the display name for this builtin procedure
     pub fn name(self) -> &'static str {
         match self {
@@ -76,6 +154,24 @@ impl Builtin {
             Builtin::EqP => "eq?",
             Builtin::Display => "display",
             Builtin::Newline => "newline",
+            Builtin::MakeParameter => "make-parameter",
+            Builtin::SymbolToString => "symbol->string",
+            Builtin::StringToSymbol => "string->symbol",
+            Builtin::Gensym => "gensym",
+            Builtin::GenerateUninternedSymbol => "generate-uninterned-symbol",
+            Builtin::JsonToScheme => "json->scheme",
+            Builtin::SchemeToJson => "scheme->json",
+            Builtin::CommandLine => "command-line",
+            Builtin::ProcedureName => "procedure-name",
+            Builtin::Force => "force",
+            Builtin::MakePromise => "make-promise",
+            Builtin::PromiseP => "promise?",
+            Builtin::Stream => "stream",
+            Builtin::StreamCar => "stream-car",
+            Builtin::StreamCdr => "stream-cdr",
+            Builtin::StreamNullP => "stream-null?",
+            Builtin::StreamPairP => "stream-pair?",
+            Builtin::StreamToList => "stream->list",
         }
     }
 
@@ -106,6 +202,24 @@ impl Builtin {
             Builtin::EqP => predicates::eq_p(args),
             Builtin::Display => display(args),
             Builtin::Newline => newline(args),
+            Builtin::MakeParameter => make_parameter(args),
+            Builtin::SymbolToString => symbol_to_string(args),
+            Builtin::StringToSymbol => string_to_symbol(args),
+            Builtin::Gensym => gensym(args),
+            Builtin::GenerateUninternedSymbol => generate_uninterned_symbol(args),
+            Builtin::JsonToScheme => json_to_scheme(args),
+            Builtin::SchemeToJson => scheme_to_json(args),
+            Builtin::CommandLine => command_line(args),
+            Builtin::ProcedureName => procedure_name(args),
+            Builtin::Force => force(args),
+            Builtin::MakePromise => make_promise(args),
+            Builtin::PromiseP => promise_p(args),
+            Builtin::Stream => stream::stream(args),
+            Builtin::StreamCar => stream_car(args),
+            Builtin::StreamCdr => stream_cdr(args),
+            Builtin::StreamNullP => stream_null_p(args),
+            Builtin::StreamPairP => stream_pair_p(args),
+            Builtin::StreamToList => stream_to_list(args),
         }
     }
 
@@ -136,6 +250,24 @@ impl Builtin {
             "eq?" => Some(Builtin::EqP),
             "display" => Some(Builtin::Display),
             "newline" => Some(Builtin::Newline),
+            "make-parameter" => Some(Builtin::MakeParameter),
+            "symbol->string" => Some(Builtin::SymbolToString),
+            "string->symbol" => Some(Builtin::StringToSymbol),
+            "gensym" => Some(Builtin::Gensym),
+            "generate-uninterned-symbol" => Some(Builtin::GenerateUninternedSymbol),
+            "json->scheme" => Some(Builtin::JsonToScheme),
+            "scheme->json" => Some(Builtin::SchemeToJson),
+            "command-line" => Some(Builtin::CommandLine),
+            "procedure-name" => Some(Builtin::ProcedureName),
+            "force" => Some(Builtin::Force),
+            "make-promise" => Some(Builtin::MakePromise),
+            "promise?" => Some(Builtin::PromiseP),
+            "stream" => Some(Builtin::Stream),
+            "stream-car" => Some(Builtin::StreamCar),
+            "stream-cdr" => Some(Builtin::StreamCdr),
+            "stream-null?" => Some(Builtin::StreamNullP),
+            "stream-pair?" => Some(Builtin::StreamPairP),
+            "stream->list" => Some(Builtin::StreamToList),
             _ => None,
         }
     }
@@ -144,8 +276,15 @@ impl Builtin {
 pub mod arithmetic;
 pub mod comparison;
 pub mod io;
+pub mod json;
 pub mod list;
+pub mod parameter;
 pub mod predicates;
+pub mod procedure;
+pub mod process;
+pub mod promise;
+pub mod stream;
+pub mod symbol;
 
 // Re-export arithmetic functions for convenience
 pub use arithmetic::{add, divide, multiply, subtract};
@@ -162,6 +301,27 @@ pub use predicates::{boolean_p, eq_p, list_p, number_p, procedure_p, string_p, s
 // Re-export I/O functions for convenience
 pub use io::{display, newline};
 
+// Re-export parameter functions for convenience
+pub use parameter::make_parameter;
+
+// Re-export procedure introspection functions for convenience
+pub use procedure::procedure_name;
+
+// Re-export symbol functions for convenience
+pub use symbol::{generate_uninterned_symbol, gensym, string_to_symbol, symbol_to_string};
+
+// Re-export JSON functions for convenience
+pub use json::{json_to_scheme, scheme_to_json};
+
+// Re-export process context functions for convenience
+pub use process::command_line;
+
+// Re-export promise functions for convenience
+pub use promise::{force, make_promise, promise_p};
+
+// Re-export stream functions for convenience
+pub use stream::{stream_car, stream_cdr, stream_null_p, stream_pair_p, stream_to_list};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -341,6 +501,9 @@ mod tests {
         assert_eq!(Builtin::Length.name(), "length");
         assert_eq!(Builtin::Display.name(), "display");
         assert_eq!(Builtin::Newline.name(), "newline");
+        assert_eq!(Builtin::MakeParameter.name(), "make-parameter");
+        assert_eq!(Builtin::Force.name(), "force");
+        assert_eq!(Builtin::StreamToList.name(), "stream->list");
     }
 
     #[test]
@@ -362,6 +525,12 @@ mod tests {
         assert_eq!(Builtin::from_name("length"), Some(Builtin::Length));
         assert_eq!(Builtin::from_name("display"), Some(Builtin::Display));
         assert_eq!(Builtin::from_name("newline"), Some(Builtin::Newline));
+        assert_eq!(
+            Builtin::from_name("make-parameter"),
+            Some(Builtin::MakeParameter)
+        );
+        assert_eq!(Builtin::from_name("force"), Some(Builtin::Force));
+        assert_eq!(Builtin::from_name("stream-cdr"), Some(Builtin::StreamCdr));
 
         // Test unknown names
         assert_eq!(Builtin::from_name("unknown"), None);
@@ -369,6 +538,13 @@ mod tests {
         assert_eq!(Builtin::from_name("foo"), None);
     }
 
+    #[test]
+    fn test_builtin_all_round_trips() {
+        for builtin in Builtin::ALL {
+            assert_eq!(Builtin::from_name(builtin.name()), Some(*builtin));
+        }
+    }
+
     #[test]
     fn test_builtin_call() {
         // Test arithmetic operations
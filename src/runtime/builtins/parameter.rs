@@ -0,0 +1,113 @@
+//! Parameter object procedures for the Twine Scheme runtime
+//!
+//! This module implements R7RS `make-parameter`. Parameter objects provide
+//! dynamically scoped values that are rebound with the `parameterize`
+//! special form.
+
+use crate::error::{Error, Result};
+use crate::runtime::eval::apply_procedure;
+use crate::types::{Parameter, Procedure, Value};
+
+/// Create a new parameter object (make-parameter)
+///
+/// The optional converter procedure is applied to the initial value and to
+/// every value supplied by `parameterize`.
+///
+/// # Arguments
+/// * `args` - An initial value, optionally followed by a converter procedure
+///
+/// # Returns
+/// * `Ok(Value)` - A new parameter object
+/// * `Err(Error)` - If wrong number of arguments, the converter is not a
+///   procedure, or the converter fails on the initial value
+///
+/// # Examples
+/// ```scheme
+/// (define radix (make-parameter 10))
+/// (radix)                                   ; => 10
+/// (define level (make-parameter 1 (lambda (x) (* x 10))))
+/// (level)                                   ; => 10
+/// ```
+pub fn make_parameter(args: &[Value]) -> Result<Value> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(Error::arity_error("make-parameter", 1, args.len()));
+    }
+
+    let converter = match args.get(1) {
+        Some(Value::Procedure(procedure)) => Some(procedure.clone()),
+        Some(other) => {
+            return Err(Error::type_error(
+                "make-parameter",
+                "procedure",
+                &other.type_description(),
+                Some(2),
+            ));
+        }
+        None => None,
+    };
+
+    let initial = convert_parameter_value(converter.as_ref(), args[0].clone())?;
+    Ok(Value::Procedure(Procedure::Parameter(Parameter::new(
+        initial, converter,
+    ))))
+}
+
+/// Pass a value through a parameter's converter, if it has one
+pub fn convert_parameter_value(converter: Option<&Procedure>, value: Value) -> Result<Value> {
+    match converter {
+        Some(converter) => apply_procedure(converter.clone(), vec![value]),
+        None => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::builtins::Builtin;
+
+    #[test]
+    fn test_make_parameter() {
+        let parameter = make_parameter(&[Value::number(10.0)]).unwrap();
+        let procedure = parameter.as_procedure().unwrap();
+        assert_eq!(format!("{procedure}"), "#<parameter>");
+
+        match procedure {
+            Procedure::Parameter(parameter) => {
+                assert_eq!(parameter.value(), Value::number(10.0));
+            }
+            other => panic!("Expected parameter, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_make_parameter_with_converter() {
+        // Builtin converters are applied to the initial value
+        let converter = Value::Procedure(Procedure::builtin(Builtin::List));
+        let parameter = make_parameter(&[Value::number(1.0), converter]).unwrap();
+
+        match parameter.as_procedure().unwrap() {
+            Procedure::Parameter(parameter) => {
+                assert_eq!(parameter.value(), Value::list(vec![Value::number(1.0)]));
+                assert!(parameter.converter().is_some());
+            }
+            other => panic!("Expected parameter, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_make_parameter_errors() {
+        // Arity errors
+        assert!(make_parameter(&[]).is_err());
+        assert!(
+            make_parameter(&[Value::number(1.0), Value::number(2.0), Value::number(3.0)]).is_err()
+        );
+
+        // Converter must be a procedure
+        let err = make_parameter(&[Value::number(1.0), Value::number(2.0)]).unwrap_err();
+        assert!(matches!(err, Error::TypeError { .. }));
+
+        // Converter errors propagate
+        let converter = Value::Procedure(Procedure::builtin(Builtin::Car));
+        assert!(make_parameter(&[Value::number(1.0), converter]).is_err());
+    }
+}
@@ -202,12 +202,14 @@ pub fn eq_p(args: &[Value]) -> Result<Value> {
     }
 
     // For our implementation, eq? behaves the same as equality comparison
-    // since all our values are immutable and we use structural equality
+    // since all our values are immutable and we use structural equality.
+    // Symbols are interned, so comparing them only compares pointers
     let result = args[0] == args[1];
     Ok(Value::boolean(result))
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
     use crate::runtime::builtins::Builtin;
@@ -483,39 +485,59 @@ mod tests {
     #[test]
     fn test_type_checking_comprehensive() {
         // Test that each predicate returns false for all other types
-        let test_values = [Value::number(42.0),
+        let test_values = [
+            Value::number(42.0),
             Value::string("test"),
             Value::boolean(true),
             Value::symbol("test"),
             Value::list(vec![Value::number(1.0)]),
             Value::Nil,
-            Value::procedure(Procedure::builtin(Builtin::Add))];
+            Value::procedure(Procedure::builtin(Builtin::Add)),
+        ];
 
         for (i, value) in test_values.iter().enumerate() {
             // Each predicate should return true only for its own type
             assert_eq!(
-                number_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                number_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 0
             );
             assert_eq!(
-                string_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                string_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 1
             );
             assert_eq!(
-                boolean_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                boolean_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 2
             );
             assert_eq!(
-                symbol_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                symbol_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 3
             );
             // list? returns true for both lists and nil
             assert_eq!(
-                list_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                list_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 4 || i == 5
             );
             assert_eq!(
-                procedure_p(&[value.clone()]).unwrap().as_boolean().unwrap(),
+                procedure_p(std::slice::from_ref(value))
+                    .unwrap()
+                    .as_boolean()
+                    .unwrap(),
                 i == 6
             );
         }
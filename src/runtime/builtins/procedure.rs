@@ -0,0 +1,62 @@
+//! Procedure introspection for the Twine Scheme runtime
+//!
+//! This module implements procedures for inspecting procedure values:
+//! - `procedure-name`: The name a procedure was defined with
+
+use crate::error::{Error, Result};
+use crate::types::Value;
+
+/// Get the name a procedure was defined with (procedure-name)
+///
+/// Returns the name as a symbol, or `#f` for anonymous lambdas and parameter
+/// objects. Lambdas are named by the `define`, `letrec`, `letrec*` or named
+/// `let` that bound them.
+///
+/// # Examples
+/// ```scheme
+/// (define (square x) (* x x))
+/// (procedure-name square)              ; => square
+/// (procedure-name car)                 ; => car
+/// (procedure-name (lambda (x) x))      ; => #f
+/// ```
+pub fn procedure_name(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("procedure-name", 1, args.len()));
+    }
+
+    match &args[0] {
+        Value::Procedure(procedure) => Ok(procedure
+            .defined_name()
+            .map_or(Value::boolean(false), Value::Symbol)),
+        other => Err(Error::type_error(
+            "procedure-name",
+            "procedure",
+            other.type_name(),
+            Some(1),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Environment;
+    use crate::runtime::builtins::Builtin;
+    use crate::types::{Procedure, Symbol};
+
+    #[test]
+    fn test_procedure_name() {
+        let builtin = Value::Procedure(Procedure::builtin(Builtin::Car));
+        assert_eq!(procedure_name(&[builtin]).unwrap(), Value::symbol("car"));
+
+        let env = Environment::new();
+        let anonymous = Procedure::lambda(vec![Symbol::new("x")], vec![], env);
+        assert_eq!(
+            procedure_name(&[Value::Procedure(anonymous)]).unwrap(),
+            Value::boolean(false)
+        );
+
+        assert!(procedure_name(&[]).is_err());
+        assert!(procedure_name(&[Value::number(1.0)]).is_err());
+    }
+}
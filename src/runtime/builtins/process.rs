@@ -0,0 +1,61 @@
+//! Process context procedures for the Twine Scheme runtime
+//!
+//! This module implements the R7RS `(scheme process-context)` procedures:
+//! - `command-line`: The command line arguments as a list of strings
+//!
+//! The script runner records the command line with [`set_command_line`] so
+//! that scripts see their own name and arguments rather than the
+//! interpreter's flags.
+
+use crate::error::{Error, Result};
+use crate::types::Value;
+use std::sync::OnceLock;
+
+/// Command line recorded by the host, if any
+static COMMAND_LINE: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Record the command line returned by `command-line`
+///
+/// The first element should be the script name (R7RS leaves its exact form
+/// implementation-defined). Only the first call has an effect; returns false
+/// if the command line was already set.
+pub fn set_command_line(args: Vec<String>) -> bool {
+    COMMAND_LINE.set(args).is_ok()
+}
+
+/// Get the command line arguments (command-line)
+///
+/// Returns the command line recorded by the host, or the process arguments
+/// if none was recorded.
+///
+/// # Examples
+/// ```scheme
+/// ; twine-scheme build.scm --release
+/// (command-line)               ; => ("build.scm" "--release")
+/// ```
+pub fn command_line(args: &[Value]) -> Result<Value> {
+    if !args.is_empty() {
+        return Err(Error::arity_error("command-line", 0, args.len()));
+    }
+
+    let arguments = match COMMAND_LINE.get() {
+        Some(recorded) => recorded.iter().map(|arg| Value::string(arg)).collect(),
+        None => std::env::args().map(Value::string_from_owned).collect(),
+    };
+    Ok(Value::list(arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let result = command_line(&[]).unwrap();
+        let list = result.as_list().unwrap();
+        assert!(!list.is_empty());
+        assert!(list.iter().all(|arg| arg.is_string()));
+
+        assert!(command_line(&[Value::number(1.0)]).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Promise procedures for the Twine Scheme runtime
+//!
+//! This module implements the R7RS lazy evaluation procedures:
+//! - `force`: Force a promise, memoising its value
+//! - `make-promise`: Wrap a value in an already-forced promise
+//! - `promise?`: Check if a value is a promise
+//!
+//! The `delay` and `delay-force` special forms that create unforced promises
+//! live in `special_forms::lazy`.
+
+use crate::error::{Error, Result};
+use crate::runtime::Environment;
+use crate::runtime::eval::eval;
+use crate::types::{EvaluationId, ForceStep, Promise, PromiseKind, Value};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+/// Force a promise (force)
+///
+/// Values that are not promises are returned unchanged, as R7RS permits.
+///
+/// # Examples
+/// ```scheme
+/// (force (delay (+ 1 2)))      ; => 3
+/// (force (make-promise 5))     ; => 5
+/// (force 7)                    ; => 7
+/// ```
+pub fn force(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("force", 1, args.len()));
+    }
+
+    match &args[0] {
+        Value::Promise(promise) => force_promise(Arc::clone(promise)),
+        other => Ok(other.clone()),
+    }
+}
+
+/// Create an already-forced promise (make-promise)
+///
+/// If the argument is already a promise it is returned unchanged.
+///
+/// # Examples
+/// ```scheme
+/// (force (make-promise 42))    ; => 42
+/// ```
+pub fn make_promise(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("make-promise", 1, args.len()));
+    }
+
+    match &args[0] {
+        promise @ Value::Promise(_) => Ok(promise.clone()),
+        other => Ok(Value::promise(Promise::ready(other.clone()))),
+    }
+}
+
+/// Check if a value is a promise (promise?)
+///
+/// # Examples
+/// ```scheme
+/// (promise? (delay 1))         ; => #t
+/// (promise? 1)                 ; => #f
+/// ```
+pub fn promise_p(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("promise?", 1, args.len()));
+    }
+
+    Ok(Value::boolean(args[0].is_promise()))
+}
+
+/// Force a promise using R7RS iterative forcing
+///
+/// A `delay-force` body evaluates to another promise, which is forced in the
+/// same loop rather than by a recursive call, so arbitrarily long
+/// `delay-force` chains run in constant Rust stack space. Every promise
+/// along the chain is memoised with the final value.
+///
+/// If evaluation fails, every promise claimed along the chain is released so
+/// that it can be forced again. A body that forces its own promise is
+/// evaluated again, and the first evaluation to finish provides the value.
+///
+/// A promise another evaluation is forcing is waited for by blocking the
+/// thread, as a builtin cannot yield.
+///
+/// Both evaluators force promises applied to `force` on their own stacks
+/// instead, with the helpers below; this is for builtins forcing promises.
+pub fn force_promise(promise: Arc<Promise>) -> Result<Value> {
+    // Promises claimed by this call that are waiting for the final value
+    let mut pending: Vec<Arc<Promise>> = Vec::new();
+    let mut current = promise;
+    let evaluation = EvaluationId::current();
+
+    let result = loop {
+        let thunk = match current.claim(evaluation) {
+            ForceStep::Ready(value) => break Ok(value),
+            ForceStep::Evaluate(thunk) => thunk,
+            ForceStep::Busy => {
+                current.wait(evaluation);
+                continue;
+            }
+        };
+        pending.push(Arc::clone(&current));
+
+        let mut promise_env = Environment::new_scope(thunk.env());
+        let body = Arc::clone(thunk.body());
+        let value = match evaluation.run(|| eval(body, &mut promise_env)) {
+            Ok(value) => value,
+            Err(error) => break Err(error),
+        };
+
+        match next_in_chain(thunk.kind(), value) {
+            Ok(ControlFlow::Break(value)) => break Ok(value),
+            Ok(ControlFlow::Continue(next)) => current = next,
+            Err(error) => break Err(error),
+        }
+    };
+
+    match result {
+        Ok(value) => Ok(resolve_pending(&pending, value)),
+        Err(error) => {
+            abandon_pending(&pending);
+            Err(error)
+        }
+    }
+}
+
+/// What forcing a chain of promises does with the value of a promise body
+/// of the given kind: finish with it, or continue with the next promise
+pub(crate) fn next_in_chain(
+    kind: PromiseKind,
+    value: Value,
+) -> Result<ControlFlow<Value, Arc<Promise>>> {
+    match (kind, value) {
+        (PromiseKind::Delay, value) => Ok(ControlFlow::Break(value)),
+        (PromiseKind::DelayForce, Value::Promise(next)) => Ok(ControlFlow::Continue(next)),
+        (PromiseKind::DelayForce, other) => Err(Error::type_error(
+            "delay-force",
+            "promise",
+            &other.type_description(),
+            None,
+        )),
+    }
+}
+
+/// Memoise the final value of a chain in the promises claimed along it,
+/// returning the value they settle on
+pub(crate) fn resolve_pending(pending: &[Arc<Promise>], value: Value) -> Value {
+    // Innermost first, so that a value set by a nested force of a promise
+    // along the chain is passed on to the promises before it
+    pending
+        .iter()
+        .rev()
+        .fold(value, |value, promise| promise.resolve(value))
+}
+
+/// Release the promises claimed along a chain whose forcing failed
+pub(crate) fn abandon_pending(pending: &[Arc<Promise>]) {
+    pending.iter().for_each(|promise| promise.abandon());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Expression;
+
+    fn delayed(expr: Arc<Expression>, kind: PromiseKind) -> Arc<Promise> {
+        Promise::delayed(expr, Environment::new(), kind)
+    }
+
+    #[test]
+    fn test_force_non_promise() {
+        assert_eq!(force(&[Value::number(7.0)]).unwrap(), Value::number(7.0));
+        assert!(force(&[]).is_err());
+    }
+
+    #[test]
+    fn test_force_delay() {
+        let body = Expression::arc_list(vec![
+            Expression::arc_atom(Value::symbol("+")),
+            Expression::arc_atom(Value::number(1.0)),
+            Expression::arc_atom(Value::number(2.0)),
+        ]);
+        let promise = delayed(body, PromiseKind::Delay);
+
+        assert_eq!(
+            force(&[Value::promise(Arc::clone(&promise))]).unwrap(),
+            Value::number(3.0)
+        );
+        assert_eq!(promise.value(), Some(&Value::number(3.0)));
+    }
+
+    #[test]
+    fn test_force_delay_force_chain() {
+        // Build a chain of delay-force promises ending in a ready promise
+        let mut promise = Promise::ready(Value::number(42.0));
+        for _ in 0..10_000 {
+            promise = delayed(
+                Expression::arc_atom(Value::promise(promise)),
+                PromiseKind::DelayForce,
+            );
+        }
+
+        assert_eq!(
+            force_promise(Arc::clone(&promise)).unwrap(),
+            Value::number(42.0)
+        );
+        assert!(promise.is_forced());
+    }
+
+    #[test]
+    fn test_force_delay_force_requires_promise() {
+        let promise = delayed(
+            Expression::arc_atom(Value::number(1.0)),
+            PromiseKind::DelayForce,
+        );
+        let err = force_promise(Arc::clone(&promise)).unwrap_err();
+        assert!(matches!(err, Error::TypeError { .. }));
+
+        // A failed force leaves the promise unforced
+        assert!(!promise.is_forced());
+    }
+
+    #[test]
+    fn test_make_promise() {
+        let promise = make_promise(&[Value::number(5.0)]).unwrap();
+        assert!(promise.is_promise());
+        assert_eq!(
+            force(std::slice::from_ref(&promise)).unwrap(),
+            Value::number(5.0)
+        );
+
+        // Promises are returned unchanged
+        assert_eq!(
+            make_promise(std::slice::from_ref(&promise)).unwrap(),
+            promise
+        );
+        assert!(make_promise(&[]).is_err());
+    }
+
+    #[test]
+    fn test_promise_p() {
+        let promise = make_promise(&[Value::number(5.0)]).unwrap();
+        assert_eq!(promise_p(&[promise]).unwrap(), Value::boolean(true));
+        assert_eq!(
+            promise_p(&[Value::number(5.0)]).unwrap(),
+            Value::boolean(false)
+        );
+        assert!(promise_p(&[]).is_err());
+    }
+}
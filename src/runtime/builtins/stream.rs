@@ -0,0 +1,298 @@
+//! Lazy stream procedures for the Twine Scheme runtime
+//!
+//! This module implements a small SRFI-41-style stream library on top of
+//! promises. A stream is a promise whose value is either the empty list (the
+//! empty stream) or a *stream pair*: a record holding a promise for the head
+//! and a stream for the tail. Streams are built with the `stream-cons`
+//! special form or the `stream` procedure and taken apart with:
+//! - `stream-car`: Force the stream and its head
+//! - `stream-cdr`: Force the stream and return its tail stream
+//! - `stream-null?`: Check for the empty stream
+//! - `stream-pair?`: Check for a non-empty stream
+//! - `stream->list`: Collect (a prefix of) a stream into a list
+//!
+//! Because the tail of a stream pair is only evaluated on demand, streams can
+//! be infinite.
+
+use crate::error::{Error, Result};
+use crate::runtime::builtins::promise::force_promise;
+use crate::runtime::limits::builtin_step;
+use crate::types::{Promise, Record, RecordType, Symbol, Value};
+use std::sync::{Arc, OnceLock};
+
+/// Record type shared by every stream pair
+fn stream_pair_type() -> &'static Arc<RecordType> {
+    static STREAM_PAIR: OnceLock<Arc<RecordType>> = OnceLock::new();
+    STREAM_PAIR.get_or_init(|| {
+        RecordType::new(
+            Symbol::new("stream-pair"),
+            vec![Symbol::new("car"), Symbol::new("cdr")],
+        )
+    })
+}
+
+/// Create a stream whose first element and tail are the given promises
+///
+/// Used by the `stream-cons` special form.
+pub fn make_stream_pair(head: Arc<Promise>, tail: Arc<Promise>) -> Value {
+    let pair = Record::new(
+        Arc::clone(stream_pair_type()),
+        vec![Value::promise(head), Value::promise(tail)],
+    )
+    .expect("stream pair has exactly two fields");
+    Value::promise(Promise::ready(Value::record(pair)))
+}
+
+/// Force a stream and return its stream pair, or None for the empty stream
+fn force_stream(procedure: &str, stream: &Value) -> Result<Option<Record>> {
+    let promise = stream.as_promise().ok_or_else(|| {
+        Error::type_error(procedure, "stream", &stream.type_description(), Some(1))
+    })?;
+
+    match force_promise(Arc::clone(promise))? {
+        Value::Nil => Ok(None),
+        Value::List(list) if list.is_empty() => Ok(None),
+        Value::Record(record) if record.is_instance_of(stream_pair_type()) => Ok(Some(record)),
+        other => Err(Error::type_error(
+            procedure,
+            "stream",
+            &format!("promise of {}", other.type_description()),
+            Some(1),
+        )),
+    }
+}
+
+/// Force a non-empty stream and return its stream pair
+fn force_stream_pair(procedure: &str, stream: &Value) -> Result<Record> {
+    force_stream(procedure, stream)?
+        .ok_or_else(|| Error::runtime_error(&format!("{procedure}: stream is empty")))
+}
+
+/// Get a promise field of a stream pair
+fn stream_pair_field(pair: &Record, index: usize) -> Arc<Promise> {
+    match pair.get(index) {
+        Some(Value::Promise(promise)) => Arc::clone(promise),
+        _ => unreachable!("stream pair fields are always promises"),
+    }
+}
+
+/// Create a finite stream from its arguments (stream)
+///
+/// # Examples
+/// ```scheme
+/// (stream)                     ; => the empty stream
+/// (stream-car (stream 1 2 3))  ; => 1
+/// ```
+pub fn stream(args: &[Value]) -> Result<Value> {
+    let empty = Value::promise(Promise::ready(Value::Nil));
+    let stream = args.iter().rev().fold(empty, |tail, value| {
+        let tail = Arc::clone(tail.as_promise().expect("streams are promises"));
+        make_stream_pair(Promise::ready(value.clone()), tail)
+    });
+    Ok(stream)
+}
+
+/// Get the first element of a stream (stream-car)
+///
+/// # Examples
+/// ```scheme
+/// (stream-car (stream-cons 1 (stream)))  ; => 1
+/// ```
+pub fn stream_car(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("stream-car", 1, args.len()));
+    }
+
+    let pair = force_stream_pair("stream-car", &args[0])?;
+    force_promise(stream_pair_field(&pair, 0))
+}
+
+/// Get the rest of a stream (stream-cdr)
+///
+/// The tail is returned as a stream without being forced.
+///
+/// # Examples
+/// ```scheme
+/// (stream-car (stream-cdr (stream 1 2 3)))  ; => 2
+/// ```
+pub fn stream_cdr(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("stream-cdr", 1, args.len()));
+    }
+
+    let pair = force_stream_pair("stream-cdr", &args[0])?;
+    Ok(Value::promise(stream_pair_field(&pair, 1)))
+}
+
+/// Check if a stream is empty (stream-null?)
+///
+/// # Examples
+/// ```scheme
+/// (stream-null? (stream))      ; => #t
+/// (stream-null? (stream 1))    ; => #f
+/// ```
+pub fn stream_null_p(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("stream-null?", 1, args.len()));
+    }
+
+    if !args[0].is_promise() {
+        return Ok(Value::boolean(false));
+    }
+    Ok(Value::boolean(
+        force_stream("stream-null?", &args[0])?.is_none(),
+    ))
+}
+
+/// Check if a value is a non-empty stream (stream-pair?)
+///
+/// # Examples
+/// ```scheme
+/// (stream-pair? (stream 1))    ; => #t
+/// (stream-pair? (stream))      ; => #f
+/// (stream-pair? '(1))          ; => #f
+/// ```
+pub fn stream_pair_p(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("stream-pair?", 1, args.len()));
+    }
+
+    if !args[0].is_promise() {
+        return Ok(Value::boolean(false));
+    }
+    Ok(Value::boolean(
+        force_stream("stream-pair?", &args[0])?.is_some(),
+    ))
+}
+
+/// Collect the elements of a stream into a list (stream->list)
+///
+/// An optional count limits how many elements are taken, which is required
+/// for infinite streams. Under resource limits, each element taken is an
+/// evaluation step, and the list is held to the allocation limit as it grows.
+///
+/// # Examples
+/// ```scheme
+/// (stream->list (stream 1 2 3))    ; => (1 2 3)
+/// (stream->list (stream 1 2 3) 2)  ; => (1 2)
+/// ```
+pub fn stream_to_list(args: &[Value]) -> Result<Value> {
+    if args.is_empty() || args.len() > 2 {
+        return Err(Error::arity_error("stream->list", 1, args.len()));
+    }
+
+    let limit = match args.get(1) {
+        Some(count) => {
+            let count = count.as_number().ok_or_else(|| {
+                Error::type_error("stream->list", "number", &count.type_description(), Some(2))
+            })?;
+            if count < 0.0 || count.fract() != 0.0 {
+                return Err(Error::runtime_error(
+                    "stream->list: count must be a non-negative integer",
+                ));
+            }
+            Some(count as usize)
+        }
+        None => None,
+    };
+
+    let mut elements = Vec::new();
+    let mut current = args[0].clone();
+    while limit.is_none_or(|limit| elements.len() < limit) {
+        let Some(pair) = force_stream("stream->list", &current)? else {
+            break;
+        };
+        elements.push(force_promise(stream_pair_field(&pair, 0))?);
+        current = Value::promise(stream_pair_field(&pair, 1));
+        builtin_step(elements.len())?;
+    }
+
+    Ok(Value::list(elements))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_construction() {
+        let empty = stream(&[]).unwrap();
+        assert!(empty.is_promise());
+        assert_eq!(
+            stream_null_p(std::slice::from_ref(&empty)).unwrap(),
+            Value::boolean(true)
+        );
+        assert_eq!(stream_pair_p(&[empty]).unwrap(), Value::boolean(false));
+
+        let numbers = stream(&[Value::number(1.0), Value::number(2.0)]).unwrap();
+        assert_eq!(
+            stream_pair_p(std::slice::from_ref(&numbers)).unwrap(),
+            Value::boolean(true)
+        );
+        assert_eq!(stream_null_p(&[numbers]).unwrap(), Value::boolean(false));
+    }
+
+    #[test]
+    fn test_stream_car_cdr() {
+        let numbers = stream(&[Value::number(1.0), Value::number(2.0)]).unwrap();
+        assert_eq!(
+            stream_car(std::slice::from_ref(&numbers)).unwrap(),
+            Value::number(1.0)
+        );
+
+        let rest = stream_cdr(&[numbers]).unwrap();
+        assert_eq!(
+            stream_car(std::slice::from_ref(&rest)).unwrap(),
+            Value::number(2.0)
+        );
+
+        let end = stream_cdr(&[rest]).unwrap();
+        assert_eq!(
+            stream_null_p(std::slice::from_ref(&end)).unwrap(),
+            Value::boolean(true)
+        );
+        assert!(stream_car(&[end]).is_err());
+    }
+
+    #[test]
+    fn test_stream_to_list() {
+        let numbers =
+            stream(&[Value::number(1.0), Value::number(2.0), Value::number(3.0)]).unwrap();
+
+        assert_eq!(
+            stream_to_list(std::slice::from_ref(&numbers)).unwrap(),
+            Value::list(vec![
+                Value::number(1.0),
+                Value::number(2.0),
+                Value::number(3.0)
+            ])
+        );
+        assert_eq!(
+            stream_to_list(&[numbers.clone(), Value::number(2.0)]).unwrap(),
+            Value::list(vec![Value::number(1.0), Value::number(2.0)])
+        );
+        assert!(stream_to_list(&[numbers.clone(), Value::number(-1.0)]).is_err());
+        assert!(stream_to_list(&[numbers, Value::string("2")]).is_err());
+    }
+
+    #[test]
+    fn test_stream_type_errors() {
+        assert!(stream_car(&[Value::number(1.0)]).is_err());
+        assert!(stream_cdr(&[Value::list(vec![Value::number(1.0)])]).is_err());
+        assert_eq!(
+            stream_pair_p(&[Value::number(1.0)]).unwrap(),
+            Value::boolean(false)
+        );
+
+        // A promise of something other than a stream pair is not a stream
+        let promise = Value::promise(Promise::ready(Value::number(1.0)));
+        assert!(stream_car(&[promise]).is_err());
+
+        // Arity errors
+        assert!(stream_car(&[]).is_err());
+        assert!(stream_cdr(&[]).is_err());
+        assert!(stream_null_p(&[]).is_err());
+        assert!(stream_pair_p(&[]).is_err());
+        assert!(stream_to_list(&[]).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Symbol procedures for the Twine Scheme runtime
+//!
+//! This module implements conversions between symbols and strings, and the
+//! creation of uninterned symbols:
+//! - `symbol->string`: The name of a symbol
+//! - `string->symbol`: The interned symbol with a given name
+//! - `gensym` and `generate-uninterned-symbol`: A fresh uninterned symbol
+
+use crate::error::{Error, Result};
+use crate::types::{Symbol, Value};
+
+/// The name prefix of generated symbols when none is given
+const DEFAULT_PREFIX: &str = "g";
+
+/// Get the name of a symbol as a string (symbol->string)
+///
+/// # Examples
+/// ```scheme
+/// (symbol->string 'foo)                ; => "foo"
+/// ```
+pub fn symbol_to_string(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("symbol->string", 1, args.len()));
+    }
+
+    match &args[0] {
+        Value::Symbol(symbol) => Ok(Value::string(symbol.as_str())),
+        other => Err(Error::type_error(
+            "symbol->string",
+            "symbol",
+            &other.type_description(),
+            Some(1),
+        )),
+    }
+}
+
+/// Get the interned symbol with the given name (string->symbol)
+///
+/// Every call with the same name returns the same symbol, so the results
+/// are `eq?` to each other and to the symbol written in source code.
+///
+/// # Examples
+/// ```scheme
+/// (eq? (string->symbol "foo") 'foo)    ; => #t
+/// ```
+pub fn string_to_symbol(args: &[Value]) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error("string->symbol", 1, args.len()));
+    }
+
+    match &args[0] {
+        Value::String(name) => Ok(Value::Symbol(Symbol::new(name.as_str()))),
+        other => Err(Error::type_error(
+            "string->symbol",
+            "string",
+            &other.type_description(),
+            Some(1),
+        )),
+    }
+}
+
+/// Create a fresh uninterned symbol (gensym)
+///
+/// The optional argument, a string or symbol, is used as the prefix of the
+/// generated name. The result is not `eq?` to any other symbol, even one
+/// with the same name, so it cannot capture identifiers in user code.
+///
+/// # Examples
+/// ```scheme
+/// (gensym)                             ; => g1
+/// (gensym "tmp")                       ; => tmp2
+/// (eq? (gensym) (gensym))              ; => #f
+/// ```
+pub fn gensym(args: &[Value]) -> Result<Value> {
+    generate("gensym", args)
+}
+
+/// Create a fresh uninterned symbol (generate-uninterned-symbol)
+///
+/// The same as `gensym`, under the name MIT Scheme uses.
+pub fn generate_uninterned_symbol(args: &[Value]) -> Result<Value> {
+    generate("generate-uninterned-symbol", args)
+}
+
+fn generate(procedure: &str, args: &[Value]) -> Result<Value> {
+    if args.len() > 1 {
+        return Err(Error::arity_error(procedure, 0, args.len()));
+    }
+
+    let prefix = match args.first() {
+        Some(Value::String(prefix)) => prefix.as_str(),
+        Some(Value::Symbol(prefix)) => prefix.as_str(),
+        Some(other) => {
+            return Err(Error::type_error(
+                procedure,
+                "string or symbol",
+                &other.type_description(),
+                Some(1),
+            ));
+        }
+        None => DEFAULT_PREFIX,
+    };
+    Ok(Value::Symbol(Symbol::generate(prefix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_string_conversions() {
+        assert_eq!(
+            symbol_to_string(&[Value::symbol("foo")]).unwrap(),
+            Value::string("foo")
+        );
+        assert_eq!(
+            string_to_symbol(&[Value::string("foo")]).unwrap(),
+            Value::symbol("foo")
+        );
+
+        assert!(symbol_to_string(&[Value::string("foo")]).is_err());
+        assert!(string_to_symbol(&[Value::symbol("foo")]).is_err());
+        assert!(string_to_symbol(&[]).is_err());
+    }
+
+    #[test]
+    fn test_gensym() {
+        let first = gensym(&[]).unwrap();
+        let second = gensym(&[]).unwrap();
+        assert_ne!(first, second);
+        let Value::Symbol(symbol) = first else {
+            panic!("Expected symbol, got {first:?}");
+        };
+        assert!(!symbol.is_interned());
+
+        let prefixed = generate_uninterned_symbol(&[Value::string("tmp")]).unwrap();
+        let name = prefixed.as_symbol().unwrap();
+        assert!(name.starts_with("tmp"));
+        assert_ne!(prefixed, Value::symbol(name));
+
+        assert!(gensym(&[Value::number(1.0)]).is_err());
+        assert!(gensym(&[Value::string("a"), Value::string("b")]).is_err());
+    }
+}
@@ -4,61 +4,398 @@
 //! in the Scheme runtime. Environments support lexical scoping through
 //! parent environment chains and are designed to be thread-safe for use
 //! in the fiber-based concurrency system.
+//!
+//! The outermost scope of a chain is the *global* environment of a program
+//! or library. Closures copy the local bindings they refer to, which never
+//! change once made, but share the global environment: top-level
+//! definitions are looked up when the code runs, so a procedure sees
+//! helpers defined after it and redefinitions made later.
+//!
+//! Procedures are bound in the global environment they refer to, so a
+//! closure refers to it weakly, and the environment is freed when the last
+//! handle to it is dropped. Calling a procedure after that fails when it
+//! looks up a global binding.
 
 use crate::runtime::builtins::Builtin;
+use crate::runtime::eval::Evaluator;
+use crate::runtime::interrupt::InterruptHandle;
+use crate::runtime::library::library_exporting;
+use crate::runtime::limits::{Budget, Limits};
+use crate::runtime::sandbox::Sandbox;
+use crate::runtime::special_forms::SpecialForm;
 use crate::types::{Procedure, Symbol, Value};
 use crate::{Error, Result};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
 
 /// Environment for managing identifier bindings
 ///
-/// Supports lexical scoping through an optional parent environment reference.
-/// Uses lifetimes to ensure parent environments outlive their children.
+/// An environment is a handle to a scope of bindings. Scopes are reference
+/// counted and link to their parent scope, so an environment can be held by
+/// the evaluator's continuation frames without borrowing its parent.
 #[derive(Debug)]
-pub struct Environment<'a> {
+pub struct Environment {
+    scope: Arc<Scope>,
+}
+
+/// A single scope of bindings and a link to its enclosing scope
+#[derive(Debug)]
+struct Scope {
     /// Identifier bindings in this environment scope
-    bindings: HashMap<Symbol, Value>,
-    /// Optional parent environment for lexical scoping
-    parent: Option<&'a Environment<'a>>,
+    bindings: RwLock<Bindings>,
+    /// Optional parent scope for lexical scoping
+    parent: Option<Parent>,
+    /// Whether unbound identifiers fall back to builtin procedures
+    ///
+    /// True for top-level program environments, which implicitly import
+    /// every builtin. Library bodies start without builtins and must import
+    /// them from libraries such as `(scheme base)`.
+    builtins_visible: bool,
+    /// The builtin procedures and special forms code here may use, or None
+    /// if it may use all of them
+    sandbox: Option<Arc<Sandbox>>,
+    /// The resource limits of evaluations in this global scope, and what
+    /// they have used so far
+    budget: RwLock<Option<Arc<Budget>>>,
+    /// Whether an identifier naming a builtin procedure has been defined in
+    /// this global scope
+    builtins_rebound: AtomicBool,
+    /// Local names around where a closure was created, only kept to suggest
+    /// corrections for an identifier of the closure that is unbound
+    lexical: Option<Arc<[Symbol]>>,
+    /// The evaluator that runs code evaluated here
+    evaluator: Evaluator,
+    /// The interrupt flag of evaluations here, shared with the global scope
+    interrupt: InterruptHandle,
 }
 
-impl<'a> Environment<'a> {
-    /// Create a new empty environment with no parent
-    pub fn new() -> Self {
+/// The link from a scope to its parent
+#[derive(Debug)]
+enum Parent {
+    /// An enclosing scope, kept alive by this one
+    Scope(Arc<Scope>),
+    /// The global scope of a closure, which the closure's procedure may be
+    /// bound in, so is not kept alive by it
+    Global(Weak<Scope>),
+}
+
+/// A scope of a chain: borrowed, or upgraded from a closure's link to its
+/// global scope
+enum ScopeRef<'a> {
+    Borrowed(&'a Scope),
+    Upgraded(Arc<Scope>),
+}
+
+impl Deref for ScopeRef<'_> {
+    type Target = Scope;
+
+    fn deref(&self) -> &Scope {
+        match self {
+            ScopeRef::Borrowed(scope) => scope,
+            ScopeRef::Upgraded(scope) => scope,
+        }
+    }
+}
+
+/// The bindings of a scope, in the order they were first made
+///
+/// Keeping the order lets a binding be looked up by its index as well as by
+/// its identifier; redefining an identifier keeps its index.
+#[derive(Debug, Default)]
+struct Bindings {
+    entries: Vec<(Symbol, Value)>,
+    indices: HashMap<Symbol, usize>,
+}
+
+impl Bindings {
+    fn with_capacity(capacity: usize) -> Self {
         Self {
-            bindings: HashMap::new(),
-            parent: None,
+            entries: Vec::with_capacity(capacity),
+            indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    fn get(&self, identifier: &Symbol) -> Option<&Value> {
+        let index = *self.indices.get(identifier)?;
+        Some(&self.entries[index].1)
+    }
+
+    fn insert(&mut self, identifier: Symbol, value: Value) {
+        match self.indices.get(&identifier) {
+            Some(&index) => self.entries[index].1 = value,
+            None => {
+                self.indices.insert(identifier.clone(), self.entries.len());
+                self.entries.push((identifier, value));
+            }
         }
     }
 
-    /// Create a new environment with a parent environment reference
-    pub fn new_scope(parent: &'a Environment<'a>) -> Self {
+    fn contains_key(&self, identifier: &Symbol) -> bool {
+        self.indices.contains_key(identifier)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &Symbol> {
+        self.entries.iter().map(|(identifier, _)| identifier)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &(Symbol, Value)> {
+        self.entries.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Scope {
+    fn read(&self) -> RwLockReadGuard<'_, Bindings> {
+        self.bindings.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Bindings> {
+        self.bindings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Iterate over this scope and its ancestors, innermost first
+    ///
+    /// Stops early at a global scope that has been freed.
+    fn chain(&self) -> impl Iterator<Item = ScopeRef<'_>> {
+        std::iter::successors(Some(ScopeRef::Borrowed(self)), |scope| match scope {
+            ScopeRef::Borrowed(scope) => match scope.parent.as_ref()? {
+                Parent::Scope(parent) => Some(ScopeRef::Borrowed(parent)),
+                Parent::Global(global) => global.upgrade().map(ScopeRef::Upgraded),
+            },
+            ScopeRef::Upgraded(scope) => scope.parent_scope().map(ScopeRef::Upgraded),
+        })
+    }
+
+    /// The parent scope, if it has one that has not been freed
+    fn parent_scope(&self) -> Option<Arc<Scope>> {
+        match self.parent.as_ref()? {
+            Parent::Scope(parent) => Some(Arc::clone(parent)),
+            Parent::Global(global) => global.upgrade(),
+        }
+    }
+}
+
+impl Environment {
+    /// Create a scope linked to `parent`, sharing the evaluator and interrupt
+    /// flag of `enclosing`, the scope it is created in, if any
+    fn from_scope(
+        bindings: Bindings,
+        parent: Option<Parent>,
+        enclosing: Option<&Scope>,
+        builtins_visible: bool,
+        sandbox: Option<Arc<Sandbox>>,
+    ) -> Self {
+        let (evaluator, interrupt) = match enclosing {
+            Some(enclosing) => (enclosing.evaluator, enclosing.interrupt.clone()),
+            None => (Evaluator::default(), InterruptHandle::new()),
+        };
         Self {
-            bindings: HashMap::new(),
-            parent: Some(parent),
+            scope: Arc::new(Scope {
+                bindings: RwLock::new(bindings),
+                parent,
+                builtins_visible,
+                sandbox,
+                budget: RwLock::new(None),
+                builtins_rebound: AtomicBool::new(false),
+                lexical: None,
+                evaluator,
+                interrupt,
+            }),
         }
     }
 
+    /// Create a new empty environment with no parent
+    ///
+    /// Builtin procedures are implicitly visible, as in a program run with
+    /// `twine-scheme --implicit-builtins`.
+    pub fn new() -> Self {
+        Self::from_scope(Bindings::default(), None, None, true, None)
+    }
+
+    /// Create a new empty environment without implicit builtin procedures
+    ///
+    /// Used for library bodies and programs, which only see the bindings
+    /// they import.
+    pub fn new_without_builtins() -> Self {
+        Self::from_scope(Bindings::default(), None, None, false, None)
+    }
+
+    /// Create a new empty environment restricted to the capabilities of
+    /// `sandbox`
+    ///
+    /// Scopes and closures made inside it share the restrictions. Using a
+    /// denied builtin procedure or special form is an error with code
+    /// [`ErrorCode::NotPermitted`](crate::error::ErrorCode::NotPermitted).
+    pub fn new_sandboxed(sandbox: Sandbox) -> Self {
+        Self::from_scope(
+            Bindings::default(),
+            None,
+            None,
+            true,
+            Some(Arc::new(sandbox)),
+        )
+    }
+
+    /// Run code evaluated in this new global environment, and in the
+    /// procedures it defines, with `evaluator`
+    ///
+    /// Libraries defined with `define-library` here use it too; those loaded
+    /// from files use the default.
+    ///
+    /// # Panics
+    /// If another handle to the environment has been made.
+    pub fn with_evaluator(mut self, evaluator: Evaluator) -> Self {
+        Arc::get_mut(&mut self.scope)
+            .expect("the evaluator is chosen before the environment is shared")
+            .evaluator = evaluator;
+        self
+    }
+
+    /// Create a new environment whose parent is `parent`
+    ///
+    /// The new scope keeps its parent alive for as long as it exists.
+    pub fn new_scope(parent: &Environment) -> Self {
+        Self::from_scope(
+            Bindings::default(),
+            Some(Parent::Scope(Arc::clone(&parent.scope))),
+            Some(&parent.scope),
+            parent.scope.builtins_visible,
+            parent.scope.sandbox.clone(),
+        )
+    }
+
     /// Create a new environment for closures by capturing specific bindings
-    /// No parent reference needed - only specified bindings are copied
-    pub fn new_closure(env: &Environment<'a>, identifiers: &[Symbol]) -> Environment<'static> {
-        let mut bindings = HashMap::with_capacity(identifiers.len());
+    ///
+    /// The local bindings of `identifiers` are copied; the new environment's
+    /// parent is the global environment of `env`, so global bindings are
+    /// looked up when they are used. The closure does not keep the global
+    /// environment alive.
+    pub fn new_closure(env: &Environment, identifiers: &[Symbol]) -> Environment {
+        Self::new_closure_with(Vec::new(), env, identifiers, &[])
+    }
 
+    /// Create a new environment for closures from captured values, followed
+    /// by the local bindings of `identifiers` found in `env`
+    ///
+    /// The captured values come first and in order, so they can be looked up
+    /// with [`Environment::lookup_index`]. `lexical` are further local names
+    /// around the closure that are not in `env`, such as those in slots.
+    pub(crate) fn new_closure_with(
+        captured: Vec<(Symbol, Value)>,
+        env: &Environment,
+        identifiers: &[Symbol],
+        lexical: &[Symbol],
+    ) -> Environment {
+        let mut bindings = Bindings::with_capacity(captured.len() + identifiers.len());
+        for (identifier, value) in captured {
+            bindings.insert(identifier, strengthen(value));
+        }
+
+        // Captured values take precedence
+        let mut unbound = false;
         for identifier in identifiers {
+            if bindings.contains_key(identifier) {
+                continue;
+            }
             // Use direct hash lookup for each identifier
-            let mut current = Some(env);
-            while let Some(environment) = current {
-                if let Some(value) = environment.bindings.get(identifier) {
-                    bindings.insert(identifier.clone(), value.clone());
-                    break; // Found the identifier, stop traversing for this identifier
-                }
-                current = environment.parent;
+            let value = env
+                .scope
+                .chain()
+                .filter(|scope| scope.parent.is_some())
+                .find_map(|scope| scope.read().get(identifier).cloned());
+            match value {
+                Some(value) => bindings.insert(identifier.clone(), strengthen(value)),
+                None => unbound = unbound || !env.is_global_name(identifier),
             }
         }
 
-        Environment {
+        // The local names are only gathered for closures that refer to an
+        // identifier bound nowhere yet, which is rare
+        let lexical = unbound.then(|| {
+            let mut names = lexical.to_vec();
+            for scope in env.scope.chain().filter(|scope| scope.parent.is_some()) {
+                names.extend(scope.read().keys().cloned());
+                names.extend(scope.lexical.iter().flat_map(|names| names.iter().cloned()));
+            }
+            Arc::from(names)
+        });
+
+        let mut closure = Self::from_scope(
             bindings,
-            parent: None,
+            Some(Parent::Global(env.global_link())),
+            Some(&env.scope),
+            env.scope.builtins_visible,
+            env.scope.sandbox.clone(),
+        );
+        if let Some(scope) = Arc::get_mut(&mut closure.scope) {
+            scope.lexical = lexical;
+        }
+        closure
+    }
+
+    /// Check if an identifier is bound globally, as a builtin procedure or
+    /// as a special form
+    fn is_global_name(&self, identifier: &Symbol) -> bool {
+        self.global_scope()
+            .is_some_and(|global| global.read().contains_key(identifier))
+            || (self.scope.builtins_visible && Builtin::from_name(identifier.as_str()).is_some())
+            || SpecialForm::from_name(identifier.as_str()).is_some()
+    }
+
+    /// The global scope at the end of the chain, unless it has been freed
+    fn global_scope(&self) -> Option<ScopeRef<'_>> {
+        self.scope
+            .chain()
+            .last()
+            .filter(|scope| scope.parent.is_none())
+    }
+
+    /// A weak link to the global scope at the end of the chain
+    fn global_link(&self) -> Weak<Scope> {
+        let mut scope = &self.scope;
+        loop {
+            match &scope.parent {
+                None => return Arc::downgrade(scope),
+                Some(Parent::Scope(parent)) => scope = parent,
+                Some(Parent::Global(global)) => return Weak::clone(global),
+            }
+        }
+    }
+
+    /// Check if this is a global environment rather than a local scope
+    pub(crate) fn is_global(&self) -> bool {
+        self.scope.parent.is_none()
+    }
+
+    /// Check if an identifier naming a builtin procedure has been defined in
+    /// the global environment
+    ///
+    /// Code that calls builtins directly checks this to notice that they
+    /// have been redefined.
+    pub(crate) fn builtins_rebound(&self) -> bool {
+        self.global_scope()
+            .is_some_and(|global| global.builtins_rebound.load(Ordering::Relaxed))
+    }
+
+    /// Get another handle to this environment
+    ///
+    /// Both handles refer to the same scope, so definitions made through one
+    /// are visible through the other. Used by the evaluator to keep the
+    /// environment of a suspended computation.
+    pub(crate) fn share(&self) -> Environment {
+        Self {
+            scope: Arc::clone(&self.scope),
         }
     }
 
@@ -67,76 +404,167 @@ impl<'a> Environment<'a> {
     /// This creates a new binding in the current environment scope,
     /// potentially shadowing bindings in parent environments.
     pub fn define(&mut self, identifier: Symbol, value: Value) {
-        self.bindings.insert(identifier, value);
+        if self.is_global() && Builtin::from_name(identifier.as_str()).is_some() {
+            self.scope.builtins_rebound.store(true, Ordering::Relaxed);
+        }
+        self.scope.write().insert(identifier, value);
     }
 
     /// Define an identifier binding using a string key (convenience method)
     pub fn define_str(&mut self, identifier: &str, value: Value) {
-        self.bindings.insert(Symbol::new(identifier), value);
+        self.define(Symbol::new(identifier), value);
     }
 
     /// Look up a binding by identifier in this environment or parent environments
     pub fn lookup(&self, identifier: &Symbol) -> Result<Value> {
-        // First check this environment
-        if let Some(value) = self.bindings.get(identifier) {
-            return Ok(value.clone());
-        }
+        self.lookup_near(identifier, &[])
+    }
 
-        // Then check parent environment if it exists
-        if let Some(parent) = self.parent {
-            return parent.lookup(identifier);
+    /// Look up an identifier, also suggesting the names of `locals` bound
+    /// outside the environment if it is unbound
+    pub(crate) fn lookup_near(&self, identifier: &Symbol, locals: &[Symbol]) -> Result<Value> {
+        // Check this environment, then its parents
+        for scope in self.scope.chain() {
+            if let Some(value) = scope.read().get(identifier) {
+                return Ok(strengthen(value.clone()));
+            }
         }
 
         // Check for builtin procedures before failing
-        if let Some(builtin) = Builtin::from_name(identifier.as_str()) {
+        if self.scope.builtins_visible
+            && let Some(builtin) = Builtin::from_name(identifier.as_str())
+        {
+            if !self.permits_builtin(builtin) {
+                return Err(Error::not_permitted(identifier.as_str()));
+            }
             let procedure = Procedure::builtin(builtin);
             return Ok(Value::procedure(procedure));
         }
 
+        // The global environment is gone, rather than missing the binding
+        if self.global_scope().is_none() {
+            return Err(Error::runtime_error(&format!(
+                "cannot look up '{identifier}': the environment the procedure was defined in has been dropped"
+            )));
+        }
+
         // Identifier not found - provide detailed error
-        self.create_unbound_identifier_error(identifier)
+        self.create_unbound_identifier_error(identifier, locals)
+    }
+
+    /// Look up an identifier in this environment's own scope only
+    pub(crate) fn lookup_own(&self, identifier: &Symbol) -> Option<Value> {
+        self.scope.read().get(identifier).cloned()
+    }
+
+    /// Look up the binding made `index`th in this environment's own scope
+    ///
+    /// Used for bindings whose position is known ahead of time, such as
+    /// those captured by a closure.
+    pub(crate) fn lookup_index(&self, index: usize) -> Value {
+        strengthen(self.scope.read().entries[index].1.clone())
     }
 
     /// Create a detailed unbound identifier error with suggestions
-    fn create_unbound_identifier_error(&self, identifier: &Symbol) -> Result<Value> {
-        // Collect similar identifiers for suggestions
-        let suggestions = self.find_similar_identifiers(identifier);
-
-        if suggestions.is_empty() {
-            Err(Error::unbound_identifier(identifier.as_str(), None))
-        } else {
-            let formatted_suggestions: Vec<String> =
-                suggestions.iter().map(|s| format!("'{s}'")).collect();
-            let context = format!("Did you mean one of: {}?", formatted_suggestions.join(", "));
-            Err(Error::unbound_identifier(
-                identifier.as_str(),
-                Some(&context),
-            ))
-        }
+    ///
+    /// Similarly spelled identifiers are offered as a help note.
+    fn create_unbound_identifier_error(
+        &self,
+        identifier: &Symbol,
+        locals: &[Symbol],
+    ) -> Result<Value> {
+        let error = Error::unbound_identifier(identifier.as_str(), None);
+        let suggestions = self.find_similar_identifiers(identifier, locals);
+
+        // A builtin procedure that is not visible has to be imported
+        let error = match Builtin::from_name(identifier.as_str()) {
+            Some(builtin) if !self.scope.builtins_visible => match library_exporting(builtin) {
+                Some(library) => error.with_help(&format!(
+                    "'{identifier}' is exported by {library}: add (import {library})"
+                )),
+                None => error,
+            },
+            _ => error,
+        };
+
+        let formatted: Vec<String> = suggestions.iter().map(|s| format!("'{s}'")).collect();
+        Err(match formatted.as_slice() {
+            [] => error,
+            [single] => error.with_help(&format!("did you mean {single}?")),
+            _ => error.with_help(&format!("did you mean one of: {}?", formatted.join(", "))),
+        })
     }
 
-    /// Find similar identifiers in the environment chain for suggestions
-    fn find_similar_identifiers(&self, target: &Symbol) -> Vec<Symbol> {
-        let mut suggestions = Vec::new();
-        let mut seen = std::collections::HashSet::<Symbol>::new();
-
-        // Collect all identifiers from this environment and parents
-        let mut current = Some(self);
-        while let Some(env) = current {
-            for identifier in env.bindings.keys() {
-                if seen.insert(identifier.clone()) {
-                    // Simple similarity check: same length or edit distance of 1-2
-                    if is_similar_identifier(target, identifier) {
-                        suggestions.push(identifier.clone());
-                    }
-                }
+    /// Find similar identifiers for suggestions, closest first
+    ///
+    /// Candidates are `locals`, the bindings in the environment chain and
+    /// the local names around where its closures were created, the builtin
+    /// procedures (when visible) and the special forms that are permitted.
+    /// Enclosing local names are offered ahead of global ones, and operators
+    /// such as `+` are not offered for a one-character identifier, which is
+    /// within one edit of all of them.
+    fn find_similar_identifiers(&self, target: &Symbol, locals: &[Symbol]) -> Vec<String> {
+        let target = target.as_str();
+        let max_distance = target.chars().count().max(3) / 3;
+
+        // Candidates paired with whether they are global
+        let mut candidates: Vec<(bool, String)> = locals
+            .iter()
+            .map(|name| (false, name.as_str().to_string()))
+            .collect();
+        for scope in self.scope.chain() {
+            let global = scope.parent.is_none();
+            candidates.extend(
+                scope
+                    .read()
+                    .keys()
+                    .map(|key| (global, key.as_str().to_string())),
+            );
+            if let Some(lexical) = &scope.lexical {
+                candidates.extend(
+                    lexical
+                        .iter()
+                        .map(|name| (false, name.as_str().to_string())),
+                );
             }
-            current = env.parent;
         }
+        if self.scope.builtins_visible {
+            candidates.extend(
+                Builtin::ALL
+                    .iter()
+                    .filter(|builtin| self.permits_builtin(**builtin))
+                    .map(|builtin| (true, builtin.name().to_string())),
+            );
+        }
+        candidates.extend(
+            SpecialForm::ALL
+                .iter()
+                .filter(|form| self.permits_special_form(**form))
+                .map(|form| (true, form.name().to_string())),
+        );
+
+        let single_character = target.chars().count() == 1;
+        let mut suggestions: Vec<(bool, usize, String)> = candidates
+            .into_iter()
+            .filter(|(_, candidate)| candidate != target)
+            .filter(|(_, candidate)| {
+                !single_character || candidate.chars().any(char::is_alphanumeric)
+            })
+            .filter_map(|(global, candidate)| {
+                let distance = edit_distance(target, &candidate);
+                (distance <= max_distance).then_some((global, distance, candidate))
+            })
+            .collect();
+        suggestions.sort_unstable();
 
         // Limit suggestions to avoid overwhelming output
-        suggestions.truncate(3);
+        let mut seen = HashSet::new();
         suggestions
+            .into_iter()
+            .map(|(_, _, candidate)| candidate)
+            .filter(|candidate| seen.insert(candidate.clone()))
+            .take(3)
+            .collect()
     }
 
     /// Look up a binding by string key (convenience method)
@@ -147,10 +575,9 @@ impl<'a> Environment<'a> {
 
     /// Check if an identifier is defined in this environment or any parent
     pub fn contains(&self, identifier: &Symbol) -> bool {
-        self.bindings.contains_key(identifier)
-            || self
-                .parent
-                .is_some_and(|parent| parent.contains(identifier))
+        self.scope
+            .chain()
+            .any(|scope| scope.read().contains_key(identifier))
     }
 
     /// Check if an identifier is defined by string key (convenience method)
@@ -161,153 +588,195 @@ impl<'a> Environment<'a> {
 
     /// Get the number of bindings in this environment (not including parents)
     pub fn len(&self) -> usize {
-        self.bindings.len()
+        self.scope.read().len()
     }
 
     /// Check if this environment has no bindings
     pub fn is_empty(&self) -> bool {
-        self.bindings.is_empty()
+        self.scope.read().is_empty()
     }
 
     /// Get all identifier symbols defined in this environment (not including parents)
-    pub fn keys(&self) -> impl Iterator<Item = &Symbol> {
-        self.bindings.keys()
+    pub fn keys(&self) -> impl Iterator<Item = Symbol> {
+        let keys: Vec<Symbol> = self.scope.read().keys().cloned().collect();
+        keys.into_iter()
     }
 
-    /// Get the parent environment reference if it exists
-    pub fn parent(&self) -> Option<&Environment<'a>> {
-        self.parent
+    /// Get the parent environment if it exists
+    pub fn parent(&self) -> Option<Environment> {
+        self.scope.parent_scope().map(|scope| Environment { scope })
     }
 
     /// Get information about the environment chain depth
     pub fn chain_depth(&self) -> usize {
-        let mut depth = 1;
-        let mut current = self.parent;
-        while let Some(env) = current {
-            depth += 1;
-            current = env.parent;
-        }
-        depth
+        self.scope.chain().count()
     }
 
     /// Find the environment level where an identifier is bound
     pub fn find_binding_level(&self, identifier: &Symbol) -> Option<usize> {
-        let mut level = 0;
-        let mut current = Some(self);
-
-        while let Some(env) = current {
-            if env.bindings.contains_key(identifier) {
-                return Some(level);
-            }
-            level += 1;
-            current = env.parent;
-        }
-
-        None
+        self.scope
+            .chain()
+            .position(|scope| scope.read().contains_key(identifier))
     }
 
-    /// Flatten the environment chain into a single 'static environment
+    /// Flatten the environment chain into a single standalone environment
     ///
     /// Creates a new environment with all bindings from this environment
-    /// and its parent chain. This is useful for lambda closures where
-    /// we need to capture the environment without lifetime constraints.
-    pub fn flatten(&self) -> Environment<'static> {
-        let mut bindings = HashMap::new();
-        let mut current = Some(self);
-
-        // Traverse the environment chain and collect all bindings
-        // Inner scopes override outer scopes for shadowing
-        let mut levels = Vec::new();
-        while let Some(env) = current {
-            levels.push(env);
-            current = env.parent;
-        }
+    /// and its parent chain, as a snapshot of the bindings visible here.
+    /// Closures use [`Environment::new_closure`] instead, which copies only
+    /// the local bindings they refer to.
+    pub fn flatten(&self) -> Environment {
+        let mut bindings = Bindings::default();
 
         // Apply bindings from outermost to innermost to preserve shadowing
-        for env in levels.iter().rev() {
-            for (identifier, value) in &env.bindings {
+        let levels: Vec<ScopeRef<'_>> = self.scope.chain().collect();
+        for scope in levels.iter().rev() {
+            for (identifier, value) in scope.read().iter() {
                 bindings.insert(identifier.clone(), value.clone());
             }
         }
 
-        Environment {
+        Self::from_scope(
             bindings,
-            parent: None,
+            None,
+            None,
+            self.scope.builtins_visible,
+            self.scope.sandbox.clone(),
+        )
+        .with_evaluator(self.evaluator())
+    }
+
+    /// The evaluator that runs code evaluated in this environment
+    pub fn evaluator(&self) -> Evaluator {
+        self.scope.evaluator
+    }
+
+    /// A handle to the interrupt flag of evaluations in this environment
+    ///
+    /// Scopes and procedures made in a global environment share its flag.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.scope.interrupt.clone()
+    }
+
+    /// Check if unbound identifiers fall back to builtin procedures
+    pub fn builtins_visible(&self) -> bool {
+        self.scope.builtins_visible
+    }
+
+    /// The capabilities code in this environment is restricted to, if any
+    pub fn sandbox(&self) -> Option<&Sandbox> {
+        self.scope.sandbox.as_deref()
+    }
+
+    /// Limit evaluations in this global environment
+    ///
+    /// Starts a fresh budget: fuel counts from zero and the timeout runs from
+    /// now. The limits also apply to procedures already defined here.
+    pub fn set_limits(&mut self, limits: Limits) {
+        if let Some(global) = self.global_scope() {
+            let budget = Arc::new(Budget::new(limits));
+            *global
+                .budget
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = Some(budget);
+        }
+    }
+
+    /// Remove the resource limits of this global environment
+    pub fn clear_limits(&mut self) {
+        if let Some(global) = self.global_scope() {
+            *global
+                .budget
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = None;
         }
     }
+
+    /// The resource limits of evaluations in this environment, if any
+    pub fn limits(&self) -> Option<Limits> {
+        self.budget().map(|budget| budget.limits())
+    }
+
+    /// The budget evaluations in this environment draw on
+    pub(crate) fn budget(&self) -> Option<Arc<Budget>> {
+        self.global_scope().and_then(|global| {
+            global
+                .budget
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+    }
+
+    /// Check if code in this environment may use a builtin procedure
+    pub fn permits_builtin(&self, builtin: Builtin) -> bool {
+        self.sandbox()
+            .is_none_or(|sandbox| sandbox.permits_builtin(builtin))
+    }
+
+    /// Check if code in this environment may use a special form
+    pub fn permits_special_form(&self, form: SpecialForm) -> bool {
+        self.sandbox()
+            .is_none_or(|sandbox| sandbox.permits_special_form(form))
+    }
 }
 
-impl<'a> Default for Environment<'a> {
+impl Default for Environment {
     fn default() -> Self {
         Self::new()
     }
 }
 
-/// Helper function to check if two identifiers are similar
-fn is_similar_identifier(target: &Symbol, candidate: &Symbol) -> bool {
-    if target == candidate {
-        return false; // Exact match is not a "similar" suggestion
-    }
-
-    let target_str = target.as_str();
-    let candidate_str = candidate.as_str();
-    let target_len = target_str.len();
-    let candidate_len = candidate_str.len();
-
-    // Same length - check for single character differences
-    if target_len == candidate_len {
-        let differences = target_str
-            .chars()
-            .zip(candidate_str.chars())
-            .filter(|(a, b)| a != b)
-            .count();
-        return differences <= 2;
-    }
-
-    // Length difference of 1 - check for insertion/deletion
-    if (target_len as i32 - candidate_len as i32).abs() == 1 {
-        let (shorter, longer) = if target_len < candidate_len {
-            (target_str, candidate_str)
-        } else {
-            (candidate_str, target_str)
-        };
-
-        // Check if longer string contains shorter as subsequence with one extra char
-        return edit_distance_one(shorter, longer);
+/// Take a value out of a binding, to use it or copy it into a closure
+///
+/// A recursive procedure refers to itself through a WeakLambda so that it
+/// does not keep itself alive. Whatever reads that reference, such as a
+/// closure copying it or the body of a named let's loop returning it, can
+/// outlive every other reference to the procedure, so it holds the
+/// procedure strongly. A ForwardLambda that has been set is replaced by its
+/// procedure too. Placeholders that are not initialized yet stay as they
+/// are, to be set when their procedures are defined.
+pub(crate) fn strengthen(value: Value) -> Value {
+    match &value {
+        Value::Procedure(procedure @ (Procedure::WeakLambda(_) | Procedure::ForwardLambda(_))) => {
+            match procedure.resolve_weak_lambda() {
+                Ok(lambda) => Value::Procedure(Procedure::Lambda(lambda)),
+                Err(_) => value,
+            }
+        }
+        _ => value,
     }
-
-    false
 }
 
-/// Check if two strings have edit distance of at most 1
-fn edit_distance_one(shorter: &str, longer: &str) -> bool {
-    let mut i = 0;
-    let mut j = 0;
-    let mut found_difference = false;
-
-    let shorter_chars: Vec<char> = shorter.chars().collect();
-    let longer_chars: Vec<char> = longer.chars().collect();
-
-    while i < shorter_chars.len() && j < longer_chars.len() {
-        if shorter_chars[i] == longer_chars[j] {
-            i += 1;
-            j += 1;
-        } else if !found_difference {
-            found_difference = true;
-            if shorter.len() == longer.len() {
-                // Substitution
-                i += 1;
-                j += 1;
-            } else {
-                // Insertion in longer string
-                j += 1;
+/// Compute the edit distance between two identifiers
+///
+/// Counts insertions, deletions, substitutions and transpositions of
+/// adjacent characters, so `lenght` is one edit away from `length`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Three rows of the distance matrix: two back, previous and current
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
             }
-        } else {
-            return false; // More than one difference
         }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
     }
 
-    true
+    previous[b.len()]
 }
 
 #[cfg(test)]
@@ -334,7 +803,8 @@ mod tests {
 
     #[test]
     fn test_closure_creation() {
-        let mut parent = Environment::new();
+        let global = Environment::new();
+        let mut parent = Environment::new_scope(&global);
         parent.define_str("x", Value::number(42.0));
         parent.define_str("y", Value::string("hello"));
         parent.define_str("z", Value::boolean(true));
@@ -344,7 +814,7 @@ mod tests {
         let closure_env = Environment::new_closure(&parent, &identifiers);
 
         assert_eq!(closure_env.len(), 2);
-        assert!(closure_env.parent().is_none());
+        assert!(closure_env.parent().is_some()); // The global environment
         assert!(closure_env.contains_str("x"));
         assert!(closure_env.contains_str("y"));
         assert!(!closure_env.contains_str("z")); // z not captured
@@ -383,7 +853,8 @@ mod tests {
 
         let closure_env = Environment::new_closure(&child, &identifiers);
 
-        assert_eq!(closure_env.len(), 3); // Only found identifiers
+        // Only found local identifiers are copied; globals are shared
+        assert_eq!(closure_env.len(), 2);
         assert_eq!(
             closure_env.lookup_str("a").unwrap().as_number().unwrap(),
             1.0
@@ -418,6 +889,104 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_new_closure_with_captured_values() {
+        let global = Environment::new();
+        let mut env = Environment::new_scope(&global);
+        env.define_str("x", Value::number(1.0));
+        env.define_str("y", Value::number(2.0));
+
+        let captured = vec![(Symbol::new("x"), Value::number(10.0))];
+        let identifiers = [Symbol::new("y"), Symbol::new("x"), Symbol::new("z")];
+        let closure_env = Environment::new_closure_with(captured, &env, &identifiers, &[]);
+
+        // Captured values come first and take precedence; unbound ones are skipped
+        assert_eq!(closure_env.len(), 2);
+        assert_eq!(closure_env.lookup_index(0), Value::number(10.0));
+        assert_eq!(closure_env.lookup_index(1), Value::number(2.0));
+        assert_eq!(closure_env.lookup_str("x").unwrap(), Value::number(10.0));
+    }
+
+    #[test]
+    fn test_dropping_global_environment_frees_its_procedures() {
+        use crate::parser::Parser;
+
+        let mut env = Environment::new();
+        let mut parser = Parser::new("(define (f) (g)) (define (g) 1)".to_string()).unwrap();
+        while !parser.is_at_end() {
+            crate::runtime::eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+        }
+        let lambda = match env.lookup_str("f").unwrap() {
+            Value::Procedure(Procedure::Lambda(lambda)) => Arc::downgrade(&lambda),
+            other => panic!("Expected a lambda, got {other:?}"),
+        };
+        let scope = Arc::downgrade(&env.scope);
+
+        // Other handles keep the scope alive
+        let shared = env.share();
+        drop(env);
+        assert!(lambda.upgrade().is_some());
+
+        // The procedures refer to the scope weakly, so it is freed with them
+        drop(shared);
+        assert!(lambda.upgrade().is_none());
+        assert_eq!(scope.strong_count(), 0);
+    }
+
+    #[test]
+    fn test_procedures_outlive_their_global_environment() {
+        use crate::parser::Parser;
+
+        let eval_str = |source: &str, env: &mut Environment| {
+            let mut parser = Parser::new(source.to_string()).unwrap();
+            let mut result = Ok(Value::Nil);
+            while !parser.is_at_end() {
+                result = crate::runtime::eval(parser.parse_expression().unwrap().expr, env);
+            }
+            result
+        };
+        let mut env = Environment::new();
+        eval_str(
+            "(define (f) (g)) (define (g) 1) (define (double x) (* x 2))",
+            &mut env,
+        )
+        .unwrap();
+        let f = env.lookup_str("f").unwrap();
+        let double = env.lookup_str("double").unwrap();
+        drop(env);
+
+        let mut other = Environment::new();
+        other.define_str("f", f);
+        other.define_str("double", double);
+
+        // Local bindings and builtins are still found
+        assert_eq!(
+            eval_str("(double 21)", &mut other).unwrap(),
+            Value::number(42.0)
+        );
+
+        // Global bindings are gone, which is reported as such
+        let error = eval_str("(f)", &mut other).unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "cannot look up 'g': the environment the procedure was defined in has been dropped"
+            ),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_redefinition_keeps_binding_index() {
+        let mut env = Environment::new();
+        env.define_str("a", Value::number(1.0));
+        env.define_str("b", Value::number(2.0));
+        env.define_str("a", Value::number(3.0));
+
+        assert_eq!(env.len(), 2);
+        assert_eq!(env.lookup_index(0), Value::number(3.0));
+        assert_eq!(env.keys().collect::<Vec<_>>(), ["a", "b"].map(Symbol::new));
+    }
+
     #[test]
     fn test_closure_creation_with_direct_lookups() {
         // Create a deep environment chain to test batch optimization
@@ -444,9 +1013,9 @@ mod tests {
 
         let closure_env = Environment::new_closure(&child, &identifiers);
 
-        // Verify closure captured only the existing identifiers
-        assert_eq!(closure_env.len(), 3); // Should have 3 identifiers (nonexistent ignored)
-        assert!(closure_env.parent().is_none()); // No parent - standalone
+        // Verify closure captured only the existing local identifiers
+        assert_eq!(closure_env.len(), 2); // global1 is shared, nonexistent ignored
+        assert!(closure_env.parent().is_some()); // The global environment
 
         // Verify captured bindings are correct
         assert_eq!(
@@ -474,12 +1043,19 @@ mod tests {
             30.0
         );
 
-        // Verify non-captured identifiers are not accessible
-        assert!(closure_env.lookup_str("global2").is_err());
+        // Verify non-captured local identifiers are not accessible
         assert!(closure_env.lookup_str("parent2").is_err());
         assert!(closure_env.lookup_str("local2").is_err());
-        assert!(closure_env.lookup_str("unused").is_err());
         assert!(closure_env.lookup_str("nonexistent").is_err());
+
+        // Global identifiers are, including those defined later
+        assert!(closure_env.lookup_str("global2").is_ok());
+        assert!(closure_env.lookup_str("unused").is_ok());
+        grandparent.define_str("later", Value::number(40.0));
+        assert_eq!(
+            closure_env.lookup_str("later").unwrap(),
+            Value::number(40.0)
+        );
     }
 
     #[test]
@@ -633,8 +1209,7 @@ mod tests {
         assert!(result.is_err());
         let error = result.unwrap_err();
         assert!(error.to_string().contains("Unbound identifier"));
-        assert!(error.to_string().contains("Did you mean"));
-        assert!(error.to_string().contains("'identifier'"));
+        assert_eq!(error.help(), ["did you mean 'identifier'?"]);
     }
 
     #[test]
@@ -688,17 +1263,70 @@ mod tests {
         for (typo, should_suggest) in test_cases {
             let result = env.lookup_str(typo);
             assert!(result.is_err());
-            let error_msg = result.unwrap_err().to_string();
-
-            if should_suggest {
-                assert!(
-                    error_msg.contains("Did you mean"),
-                    "Should suggest for '{typo}': {error_msg}"
-                );
-            }
+            let error = result.unwrap_err();
+
+            assert_eq!(
+                !error.help().is_empty(),
+                should_suggest,
+                "Suggestions for '{typo}': {:?}",
+                error.help()
+            );
         }
     }
 
+    #[test]
+    fn test_suggestions_include_builtins_and_special_forms() {
+        let mut env = Environment::new();
+        env.define_str("lengths", Value::number(1.0));
+
+        // Transposed letters are a single edit; closest names come first
+        let error = env.lookup_str("lenght").unwrap_err();
+        assert_eq!(error.help(), ["did you mean one of: 'length', 'lengths'?"]);
+
+        let error = env.lookup_str("lamda").unwrap_err();
+        assert_eq!(error.help(), ["did you mean 'lambda'?"]);
+
+        // Builtins are not suggested where they are not visible, but using
+        // one points to the library to import
+        let library_env = Environment::new_without_builtins();
+        let error = library_env.lookup_str("dispaly").unwrap_err();
+        assert!(error.help().is_empty());
+        let error = library_env.lookup_str("display").unwrap_err();
+        assert_eq!(
+            error.help(),
+            ["'display' is exported by (scheme write): add (import (scheme write))"]
+        );
+    }
+
+    #[test]
+    fn test_suggestions_prefer_enclosing_locals() {
+        let mut global = Environment::new();
+        global.define_str("counter", Value::number(0.0));
+        let mut local = Environment::new_scope(&global);
+        local.define_str("a", Value::number(1.0));
+        local.define_str("counts", Value::number(2.0));
+
+        // Operators are each one edit from any one-character identifier
+        let error = local.lookup_str("b").unwrap_err();
+        assert_eq!(error.help(), ["did you mean 'a'?"]);
+
+        // A local comes before an equally similar global
+        let error = local.lookup_str("countr").unwrap_err();
+        assert_eq!(error.help(), ["did you mean one of: 'counts', 'counter'?"]);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("car", "car"), 0);
+        assert_eq!(edit_distance("car", "cdr"), 1);
+        assert_eq!(edit_distance("lamda", "lambda"), 1);
+        assert_eq!(edit_distance("lenght", "length"), 1);
+        assert_eq!(edit_distance("display", "dsiplya"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
     #[test]
     fn test_error_message_quality() {
         let env = Environment::new();
@@ -730,8 +1358,8 @@ mod tests {
         // Test 2: Detailed unbound identifier error with suggestions
         let error = child.lookup_str("cout").unwrap_err(); // typo for "count"
         let error_msg = error.to_string();
-        assert!(error_msg.contains("Unbound identifier: 'cout'"));
-        assert!(error_msg.contains("Did you mean one of: 'count'"));
+        assert_eq!(error_msg, "Unbound identifier: 'cout'");
+        assert_eq!(error.help(), ["did you mean 'count'?"]);
 
         // Test 3: Error without suggestions for completely different identifier
         let error = child.lookup_str("xyz").unwrap_err();
@@ -863,7 +1491,7 @@ mod tests {
         let keys = vec![Symbol::new("captured_var"), Symbol::new("another_var")];
         let closure_env = Environment::new_closure(&let_env, &keys);
 
-        assert!(closure_env.parent().is_none()); // No parent - standalone
+        assert!(closure_env.parent().is_some()); // The global environment
         assert_eq!(closure_env.len(), 2); // Only captured identifiers
         assert_eq!(
             closure_env
@@ -882,8 +1510,8 @@ mod tests {
             "test"
         );
 
-        // Test that closure doesn't have access to non-captured identifiers
-        assert!(closure_env.lookup_str("global_var").is_err());
+        // Test that closure doesn't have access to non-captured local identifiers
+        assert!(closure_env.lookup_str("global_var").is_ok());
         assert!(closure_env.lookup_str("local_var").is_err());
         assert!(closure_env.lookup_str("inner_var").is_err());
     }
@@ -1046,12 +1674,12 @@ mod tests {
 
     #[test]
     fn test_environment_flatten_static_lifetime() {
-        // Test that flatten returns Environment<'static>
+        // Test that flatten returns Environment
         let mut env = Environment::new();
         env.define_str("test", Value::number(123.0));
 
         let flattened = env.flatten();
-        fn takes_static_env(_env: Environment<'static>) {}
+        fn takes_static_env(_env: Environment) {}
         takes_static_env(flattened);
     }
 
@@ -1184,6 +1812,28 @@ mod tests {
         assert_eq!(x_result.as_number().unwrap(), 42.0);
     }
 
+    #[test]
+    fn test_environment_without_builtins() {
+        let mut env = Environment::new_without_builtins();
+        assert!(!env.builtins_visible());
+        assert!(env.lookup_str("+").is_err());
+
+        // Nested scopes and closures inherit the setting
+        env.define_str("x", Value::number(1.0));
+        let scope = Environment::new_scope(&env);
+        assert!(!scope.builtins_visible());
+        assert!(scope.lookup_str("car").is_err());
+        assert!(!scope.flatten().builtins_visible());
+        assert!(!Environment::new_closure(&scope, &[Symbol::new("x")]).builtins_visible());
+
+        // Explicitly defined builtins are still visible
+        env.define_str("+", Value::builtin_procedure(Builtin::Add));
+        assert!(env.lookup_str("+").unwrap().is_procedure());
+
+        // Top-level environments see builtins implicitly
+        assert!(Environment::new().builtins_visible());
+    }
+
     #[test]
     fn test_non_builtin_identifier_still_fails() {
         let env = Environment::new();
@@ -0,0 +1,221 @@
+//! Static analysis of expressions ahead of evaluation
+//!
+//! A closure only needs the bindings its body can refer to, rather than a
+//! copy of its whole defining environment. [`free_identifiers`] finds them by
+//! walking the body once, tracking the identifiers bound by the parameter
+//! lists and `let` forms it passes through.
+//!
+//! The analysis errs on the side of capturing too much: identifiers in forms
+//! it does not understand, and names bound by internal definitions, are
+//! treated as free. Capturing an extra binding is harmless, since inner
+//! bindings shadow it when the code runs.
+
+use crate::parser::Expression;
+use crate::runtime::special_forms::SpecialForm;
+use crate::types::{Symbol, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Find the identifiers a procedure body refers to that are not its own
+/// parameters or bound within it, in order of first reference
+pub(crate) fn free_identifiers(params: &[Symbol], body: &[Arc<Expression>]) -> Vec<Symbol> {
+    let mut analysis = Analysis::default();
+    analysis.scope(params.to_vec(), body);
+    analysis.free
+}
+
+/// The state of a walk over an expression
+#[derive(Default)]
+struct Analysis {
+    /// Identifiers bound around the current expression, innermost last
+    bound: Vec<Symbol>,
+    /// Free identifiers found so far
+    free: Vec<Symbol>,
+    seen: HashSet<Symbol>,
+}
+
+impl Analysis {
+    fn reference(&mut self, name: &Symbol) {
+        if !self.bound.contains(name) && self.seen.insert(name.clone()) {
+            self.free.push(name.clone());
+        }
+    }
+
+    /// Walk `body` with `names` bound around it
+    fn scope(&mut self, names: Vec<Symbol>, body: &[Arc<Expression>]) {
+        let depth = self.bound.len();
+        self.bound.extend(names);
+        self.all(body);
+        self.bound.truncate(depth);
+    }
+
+    fn all(&mut self, exprs: &[Arc<Expression>]) {
+        for expr in exprs {
+            self.expression(expr);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        let elements = match expr {
+            Expression::Atom(Value::Symbol(name), _) => return self.reference(name),
+            Expression::Atom(..) | Expression::Quote(..) => return,
+            Expression::List(elements, _) => elements,
+        };
+
+        // Special forms take precedence over bindings, as when evaluating
+        if let Some(name) = elements.first().and_then(|first| symbol(first))
+            && let Some(form) = SpecialForm::from_name(name.as_str())
+        {
+            if !self.special_form(form, &elements[1..]) {
+                self.all(&elements[1..]);
+            }
+            return;
+        }
+        self.all(elements);
+    }
+
+    /// Walk a special form that binds identifiers
+    ///
+    /// Returns false for other forms, and malformed ones, whose
+    /// subexpressions are all walked instead.
+    fn special_form(&mut self, form: SpecialForm, args: &[Arc<Expression>]) -> bool {
+        match (form, args) {
+            // (lambda (param ...) body ...)
+            (SpecialForm::Lambda, [params, body @ ..]) => {
+                let Some(params) = symbols(params) else {
+                    return false;
+                };
+                self.scope(params, body);
+            }
+            // (define (name param ...) body ...)
+            (SpecialForm::Define, [signature, body @ ..]) => {
+                let Some(params) = signature
+                    .as_list()
+                    .and_then(|list| symbols_of(list.get(1..)?))
+                else {
+                    return false;
+                };
+                self.scope(params, body);
+            }
+            // (let name ((id init) ...) body ...)
+            (SpecialForm::Let, [name, bindings, body @ ..]) if symbol(name).is_some() => {
+                let Some((mut ids, inits)) = let_bindings(bindings) else {
+                    return false;
+                };
+                self.all(&inits);
+                ids.extend(symbol(name).cloned());
+                self.scope(ids, body);
+            }
+            // (let ((id init) ...) body ...), and letrec, whose initial values
+            // are treated as if outside the scope
+            (
+                SpecialForm::Let | SpecialForm::Letrec | SpecialForm::LetrecStar,
+                [bindings, body @ ..],
+            ) => {
+                let Some((ids, inits)) = let_bindings(bindings) else {
+                    return false;
+                };
+                self.all(&inits);
+                self.scope(ids, body);
+            }
+            // (let* ((id init) ...) body ...)
+            (SpecialForm::LetStar, [bindings, body @ ..]) => {
+                let Some((ids, inits)) = let_bindings(bindings) else {
+                    return false;
+                };
+                let depth = self.bound.len();
+                for (id, init) in ids.into_iter().zip(&inits) {
+                    self.expression(init);
+                    self.bound.push(id);
+                }
+                self.all(body);
+                self.bound.truncate(depth);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn symbol(expr: &Expression) -> Option<&Symbol> {
+    match expr {
+        Expression::Atom(Value::Symbol(name), _) => Some(name),
+        _ => None,
+    }
+}
+
+/// The identifiers in a parameter list
+fn symbols(expr: &Expression) -> Option<Vec<Symbol>> {
+    symbols_of(expr.as_list()?)
+}
+
+fn symbols_of(exprs: &[Arc<Expression>]) -> Option<Vec<Symbol>> {
+    exprs.iter().map(|expr| symbol(expr).cloned()).collect()
+}
+
+/// The identifiers and initial values of a binding list
+fn let_bindings(expr: &Expression) -> Option<(Vec<Symbol>, Vec<Arc<Expression>>)> {
+    expr.as_list()?
+        .iter()
+        .map(|binding| match binding.as_list()?.as_slice() {
+            [id, init] => Some((symbol(id)?.clone(), Arc::clone(init))),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// The free identifiers of the lambda expression `source`
+    fn free(source: &str) -> Vec<String> {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let expr = parser.parse_expression().unwrap().expr;
+        let elements = expr.as_list().unwrap();
+        let params = symbols(&elements[1]).unwrap();
+        free_identifiers(&params, &elements[2..])
+            .iter()
+            .map(|name| name.as_str().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_free_identifiers_exclude_parameters_and_quotes() {
+        assert_eq!(free("(lambda (x) (+ x y))"), ["+", "y"]);
+        assert_eq!(free("(lambda (x) (list 'y x x))"), ["list"]);
+        assert!(free("(lambda () 1)").is_empty());
+    }
+
+    #[test]
+    fn test_free_identifiers_of_nested_forms() {
+        // Nested lambdas contribute their own free identifiers
+        assert_eq!(free("(lambda (x) (lambda (y) (f x y z)))"), ["f", "z"]);
+        assert_eq!(
+            free("(lambda () (define (g a) (h a)) (g b))"),
+            ["h", "g", "b"]
+        );
+
+        // let forms bind their identifiers in the body only
+        assert_eq!(free("(lambda () (let ((a b)) a))"), ["b"]);
+        assert_eq!(free("(lambda () (let ((a 1) (b a)) b))"), ["a"]);
+        assert_eq!(free("(lambda () (let* ((a 1) (b a)) (f b)))"), ["f"]);
+        assert_eq!(
+            free("(lambda (n) (let loop ((i n)) (if (= i 0) z (loop (- i 1)))))"),
+            ["=", "z", "-"]
+        );
+        assert_eq!(
+            free("(lambda () (letrec ((f (lambda () (g)))) (f)))"),
+            ["g"]
+        );
+    }
+
+    #[test]
+    fn test_free_identifiers_of_other_forms() {
+        // Every identifier in other forms is treated as free
+        assert_eq!(free("(lambda () (parameterize ((p 1)) (q)))"), ["p", "q"]);
+        // Special forms take precedence over parameters of the same name
+        assert_eq!(free("(lambda (if) (if a b c))"), ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Bytecode for the Twine Scheme virtual machine
+//!
+//! Expressions are compiled (see `compiler.rs`) into [`Code`]: a flat
+//! sequence of [`Op`]s for a stack machine, together with the constants,
+//! names and nested procedure templates the operations refer to by index.
+//! The virtual machine in `vm.rs` executes it.
+//!
+//! Each chunk of code is the body of a procedure or a top-level expression.
+//! The bindings of its identifiers are either addressed lexically, or looked
+//! up by name in an environment at run time. A lexical address is either a
+//! *slot*, a stack position of the running procedure, or the index of a
+//! binding captured by its closure. Closures are flat: they copy the bindings
+//! they refer to when created, so no address needs to reach further out.
+
+use crate::parser::Expression;
+use crate::runtime::builtins::Builtin;
+use crate::types::{Symbol, Value};
+use std::sync::Arc;
+
+/// A compiled chunk of code
+#[derive(Debug, Default)]
+pub(crate) struct Code {
+    /// The operations, executed from the first
+    pub(super) ops: Vec<Op>,
+    /// Where each operation came from, for error reporting
+    pub(super) sources: Vec<Source>,
+    /// Constant values loaded by [`Op::Constant`]
+    pub(super) constants: Vec<Value>,
+    /// Identifiers looked up, defined or bound by name
+    pub(super) names: Vec<Symbol>,
+    /// Procedures created by [`Op::Closure`]
+    pub(super) templates: Vec<Arc<Template>>,
+    /// Slots copied into the environment of fallback forms
+    pub(super) captures: Vec<Vec<(Symbol, u32)>>,
+    /// Forms evaluated by the tree-walking evaluator, see [`Op::Eval`]
+    pub(super) forms: Vec<Arc<Expression>>,
+    /// Number of slots, including the procedure's parameters
+    pub(super) slots: u32,
+    /// The name bound in each slot, to suggest when an identifier is unbound
+    pub(super) locals: Vec<Symbol>,
+    /// Whether local bindings live in slots rather than in an environment
+    pub(super) slotted: bool,
+}
+
+/// The origin of an operation
+#[derive(Debug, Clone, Default)]
+pub(super) struct Source {
+    /// Expression errors raised by the operation are located at
+    pub(super) expr: Option<Arc<Expression>>,
+    /// Whether the operation is a call in tail position
+    pub(super) tail: bool,
+}
+
+/// A procedure body compiled ahead of the closures created from it
+#[derive(Debug)]
+pub(super) struct Template {
+    pub(super) params: Vec<Symbol>,
+    pub(super) body: Vec<Arc<Expression>>,
+    pub(super) code: Arc<Code>,
+    /// Identifiers captured by address, in the order [`Op::Captured`] refers
+    /// to them
+    pub(super) captures: Vec<(Symbol, Capture)>,
+    /// Other free identifiers, captured by name if they are bound locally;
+    /// the rest are global, and looked up when they are used
+    pub(super) named: Vec<Symbol>,
+    /// Identifiers in slots or captured where the procedure is created, to
+    /// suggest when one of its identifiers is unbound
+    pub(super) lexical: Vec<Symbol>,
+}
+
+/// Where a new closure takes the value of a captured binding from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Capture {
+    /// A slot of the procedure creating the closure
+    Local(u32),
+    /// A binding captured by the procedure creating the closure
+    Captured(u32),
+    /// The closure itself, for a procedure referring to its local name
+    Itself,
+}
+
+/// A virtual machine operation
+///
+/// Operations take their operands from the top of the value stack and push
+/// their result. Jump targets are operation indices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Op {
+    /// Push a constant
+    Constant(u32),
+    /// Push the value of a slot
+    Local(u32),
+    /// Push the value of a binding captured by the running closure
+    Captured(u32),
+    /// Push the value bound to a name in the current environment
+    Global(u32),
+    /// Push the value bound to a name called as a procedure, checking
+    /// that it is one
+    Callee(u32),
+    /// Check that the value on top of the stack is a procedure
+    CheckProcedure,
+    /// Pop a value into a slot
+    SetLocal(u32),
+    /// Pop a value and bind it to a name in the current environment
+    Define(u32),
+    /// Discard the value on top of the stack
+    Pop,
+    /// Push a placeholder for a procedure a body refers to before defining
+    Placeholder,
+    /// Pop a placeholder and point it at the procedure on top of the stack,
+    /// unless it already points at one
+    Fill,
+
+    /// Continue at an operation
+    Jump(u32),
+    /// Pop a value and jump if it is false
+    JumpIfFalse(u32),
+    /// Jump if the value on top of the stack is false, otherwise pop it
+    JumpIfFalseElsePop(u32),
+    /// Jump if the value on top of the stack is true, otherwise pop it
+    JumpIfTrueElsePop(u32),
+
+    /// Pop the given number of parameter and value pairs and bind the
+    /// parameters in the dynamic environment, saving the previous one
+    /// unless a procedure body in tail position already has
+    Parameterize(u32),
+    /// Restore the dynamic environment saved by the last [`Op::Parameterize`]
+    Unparameterize,
+
+    /// Start a nested scope of the current environment
+    EnterScope,
+    /// Return to the enclosing scope of the current environment
+    ExitScope,
+
+    /// Create a procedure from a template, capturing its free identifiers
+    /// into its environment
+    Closure {
+        template: u32,
+        name: Option<u32>,
+    },
+    /// Move the value on top of the stack below the given number of values
+    Insert(u32),
+
+    /// Call the procedure below the given number of arguments
+    Call(u32),
+    /// Call the procedure below the given number of arguments in place of
+    /// the current one
+    TailCall(u32),
+    /// Call a builtin procedure with the given number of arguments
+    Builtin(Builtin, u32),
+    /// Specialised builtin calls with two arguments
+    Add,
+    Subtract,
+    Multiply,
+    Equal,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+
+    /// Evaluate a form with the tree-walking evaluator, in an environment
+    /// with captured slots
+    Eval {
+        form: u32,
+        capture: u32,
+    },
+
+    /// Return the value on top of the stack from the current procedure
+    Return,
+}
+
+impl Op {
+    /// The specialised operation for a two-argument call to `builtin`
+    pub(super) fn binary(builtin: Builtin) -> Option<Op> {
+        Some(match builtin {
+            Builtin::Add => Op::Add,
+            Builtin::Subtract => Op::Subtract,
+            Builtin::Multiply => Op::Multiply,
+            Builtin::Equal => Op::Equal,
+            Builtin::LessThan => Op::Less,
+            Builtin::GreaterThan => Op::Greater,
+            Builtin::LessThanOrEqual => Op::LessEqual,
+            Builtin::GreaterThanOrEqual => Op::GreaterEqual,
+            _ => return None,
+        })
+    }
+
+    /// The builtin a specialised operation stands for
+    pub(super) fn builtin(self) -> Option<Builtin> {
+        Some(match self {
+            Op::Builtin(builtin, _) => builtin,
+            Op::Add => Builtin::Add,
+            Op::Subtract => Builtin::Subtract,
+            Op::Multiply => Builtin::Multiply,
+            Op::Equal => Builtin::Equal,
+            Op::Less => Builtin::LessThan,
+            Op::Greater => Builtin::GreaterThan,
+            Op::LessEqual => Builtin::LessThanOrEqual,
+            Op::GreaterEqual => Builtin::GreaterThanOrEqual,
+            _ => return None,
+        })
+    }
+}
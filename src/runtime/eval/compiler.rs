@@ -0,0 +1,886 @@
+//! Compiler from expressions to bytecode
+//!
+//! Each top-level expression and each procedure body is compiled into a
+//! chunk of [`Code`]. The body of a `lambda` is compiled together with the
+//! expression it appears in, into a [`Template`] shared by every closure
+//! created from it.
+//!
+//! Procedure bodies keep their parameters and the bindings made by `let`,
+//! `let*` and internal definitions in slots, addressed by index. A closure
+//! captures the free identifiers of its body (see `analysis.rs`), and those
+//! bound in slots or captured by the procedure creating it are addressed by
+//! their index among the captures. Bodies that add bindings to their
+//! environment in other ways, such as a `define` that is not at body level
+//! or a `define-record-type`, are compiled to look up every identifier by name
+//! instead. Top-level expressions always are.
+//!
+//! `if`, `and` and `or` compile to jumps, `parameterize` to operations
+//! installing and restoring the dynamic environment around its body, and
+//! calls to builtin procedures that cannot have been rebound compile to
+//! specialised operations. Special
+//! forms without a compiled equivalent, and malformed ones, are evaluated by
+//! the tree-walking evaluator (see [`Op::Eval`]), so syntax errors are still
+//! reported when the form is evaluated.
+
+use crate::parser::Expression;
+use crate::runtime::Environment;
+use crate::runtime::builtins::Builtin;
+use crate::runtime::special_forms::lambda::parse_lambda;
+use crate::runtime::special_forms::parameter::parse_parameterize;
+use crate::runtime::special_forms::{SpecialForm, binding};
+use crate::runtime::utils::is_lambda_expression;
+use crate::types::{Lambda, List, Symbol, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::analysis::free_identifiers;
+use super::bytecode::{Capture, Code, Op, Source, Template};
+use super::expression_to_value;
+
+/// Compile a top-level expression evaluated in `env`
+pub(super) fn compile(expr: &Arc<Expression>, env: &Environment) -> Arc<Code> {
+    let mut chunk = Chunk::new(env, HashSet::new(), false, false);
+    chunk.global = env.is_global();
+    chunk
+        .expression(expr, false, false)
+        .expect("top-level expressions need no slots");
+    chunk.emit(Op::Return, None, false);
+    Arc::new(chunk.code)
+}
+
+/// Compile the body of a lambda created by the tree-walking evaluator
+pub(super) fn compile_lambda(lambda: &Lambda) -> Arc<Code> {
+    compile_body(
+        lambda.params(),
+        lambda.body(),
+        lambda.env(),
+        &HashSet::new(),
+        false,
+        &[],
+    )
+}
+
+/// Compile a procedure body, in slots if it allows
+///
+/// `env` is the environment of the outermost chunk, and `shadowed` and
+/// `dynamic` describe the bindings added around the procedure since, as in
+/// [`Chunk`]. `captured` are the identifiers its closures capture by address.
+fn compile_body(
+    params: &[Symbol],
+    body: &[Arc<Expression>],
+    env: &Environment,
+    shadowed: &HashSet<Symbol>,
+    dynamic: bool,
+    captured: &[Symbol],
+) -> Arc<Code> {
+    let mut chunk = Chunk::new(env, shadowed.clone(), dynamic, true);
+    chunk.captured = captured.to_vec();
+    chunk.scopes.push(Vec::new());
+    for param in params {
+        chunk.bind_slot(param);
+    }
+    if chunk.sequence(body, true, true).is_ok() {
+        return Arc::new(chunk.code);
+    }
+
+    let mut chunk = Chunk::new(env, shadowed.clone(), dynamic, false);
+    chunk.shadowed.extend(params.iter().cloned());
+    chunk
+        .sequence(body, true, true)
+        .expect("procedure bodies compile without slots");
+    Arc::new(chunk.code)
+}
+
+/// A body that must keep its local bindings in an environment
+#[derive(Debug)]
+struct NeedsEnvironment;
+
+type Compiled = std::result::Result<(), NeedsEnvironment>;
+
+/// The lexical address of a binding
+#[derive(Debug, Clone, Copy)]
+enum Address {
+    /// A slot of the running procedure
+    Local(u32),
+    /// A binding captured by the running closure
+    Captured(u32),
+}
+
+/// A chunk of code being compiled
+struct Chunk<'a> {
+    code: Code,
+    /// Environment of the outermost chunk, as it is when compiling
+    env: &'a Environment,
+    /// Lexical scopes of slots, innermost last
+    scopes: Vec<Vec<(Symbol, u32)>>,
+    /// Identifiers captured by the procedure's closures, by index
+    captured: Vec<Symbol>,
+    /// Identifiers that may be bound when the code runs, here or in an
+    /// enclosing chunk, so do not refer to builtins
+    shadowed: HashSet<Symbol>,
+    /// Whether a form may have bound identifiers that cannot be known
+    dynamic: bool,
+    /// Whether definitions here bind in the global environment
+    global: bool,
+    /// Procedures bound to placeholders because their body refers to them
+    /// before defining them
+    placeholders: HashSet<Symbol>,
+}
+
+impl<'a> Chunk<'a> {
+    fn new(env: &'a Environment, shadowed: HashSet<Symbol>, dynamic: bool, slotted: bool) -> Self {
+        Self {
+            code: Code {
+                slotted,
+                ..Code::default()
+            },
+            env,
+            scopes: Vec::new(),
+            captured: Vec::new(),
+            shadowed,
+            dynamic,
+            global: false,
+            placeholders: HashSet::new(),
+        }
+    }
+
+    /// Append an operation, returning its index
+    fn emit(&mut self, op: Op, expr: Option<&Arc<Expression>>, tail: bool) -> usize {
+        self.code.ops.push(op);
+        self.code.sources.push(Source {
+            expr: expr.cloned(),
+            tail,
+        });
+        self.code.ops.len() - 1
+    }
+
+    /// Return the value just computed if it is in tail position
+    fn finish(&mut self, tail: bool) {
+        if tail {
+            self.emit(Op::Return, None, false);
+        }
+    }
+
+    /// Point the jump at `index` to the next operation
+    fn patch(&mut self, index: usize) {
+        let target = self.code.ops.len() as u32;
+        match &mut self.code.ops[index] {
+            Op::Jump(to)
+            | Op::JumpIfFalse(to)
+            | Op::JumpIfFalseElsePop(to)
+            | Op::JumpIfTrueElsePop(to) => *to = target,
+            op => unreachable!("{op:?} is not a jump"),
+        }
+    }
+
+    fn constant(&mut self, value: Value) {
+        self.code.constants.push(value);
+        let index = self.code.constants.len() as u32 - 1;
+        self.emit(Op::Constant(index), None, false);
+    }
+
+    fn name(&mut self, name: &Symbol) -> u32 {
+        let index = match self.code.names.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                self.code.names.push(name.clone());
+                self.code.names.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    /// Find the address of the binding of `name`, if it has one
+    fn resolve(&self, name: &Symbol) -> Option<Address> {
+        let slot = self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .iter()
+                .rev()
+                .find(|(bound, _)| bound == name)
+                .map(|&(_, slot)| slot)
+        });
+        match slot {
+            Some(slot) => Some(Address::Local(slot)),
+            None => {
+                let index = self.captured.iter().position(|bound| bound == name)?;
+                Some(Address::Captured(index as u32))
+            }
+        }
+    }
+
+    /// Push the value of the binding at an address
+    fn load(&mut self, address: Address) {
+        let op = match address {
+            Address::Local(slot) => Op::Local(slot),
+            Address::Captured(index) => Op::Captured(index),
+        };
+        self.emit(op, None, false);
+    }
+
+    /// Allocate a new slot for `name` in the innermost scope
+    fn bind_slot(&mut self, name: &Symbol) -> u32 {
+        let slot = self.code.slots;
+        self.code.slots += 1;
+        self.code.locals.push(name.clone());
+        self.scopes
+            .last_mut()
+            .expect("slots are bound in a scope")
+            .push((name.clone(), slot));
+        self.shadowed.insert(name.clone());
+        slot
+    }
+
+    /// Bind the value on top of the stack to `name` in the current scope
+    fn bind(&mut self, name: &Symbol) {
+        if !self.code.slotted {
+            let index = self.name(name);
+            self.emit(Op::Define(index), None, false);
+            self.shadowed.insert(name.clone());
+            return;
+        }
+
+        // Redefining an identifier of the same scope replaces its binding
+        let existing = self
+            .scopes
+            .last()
+            .and_then(|scope| scope.iter().rev().find(|(bound, _)| bound == name));
+        let slot = match existing {
+            Some(&(_, slot)) => slot,
+            None => self.bind_slot(name),
+        };
+        self.emit(Op::SetLocal(slot), None, false);
+    }
+
+    /// The builtin an identifier refers to, if it cannot have been rebound
+    fn builtin(&self, name: &Symbol) -> Option<Builtin> {
+        if self.dynamic
+            || self.shadowed.contains(name)
+            || !self.env.builtins_visible()
+            || self.env.contains(name)
+        {
+            return None;
+        }
+        // Denied builtins are looked up, which fails with the right error
+        Builtin::from_name(name.as_str()).filter(|builtin| self.env.permits_builtin(*builtin))
+    }
+
+    /// Record the slots visible here, innermost binding of each name last
+    fn capture(&mut self) -> u32 {
+        let mut visible: Vec<(Symbol, u32)> = Vec::new();
+        for &(ref name, slot) in self.scopes.iter().flatten() {
+            match visible.iter_mut().find(|(bound, _)| bound == name) {
+                Some(binding) => binding.1 = slot,
+                None => visible.push((name.clone(), slot)),
+            }
+        }
+        self.code.captures.push(visible);
+        self.code.captures.len() as u32 - 1
+    }
+
+    /// Compile an expression
+    ///
+    /// An expression in `tail` position returns its value from the
+    /// procedure. At `body` level, definitions bind in the current scope.
+    fn expression(&mut self, expr: &Arc<Expression>, tail: bool, body: bool) -> Compiled {
+        let elements = match expr.as_ref() {
+            Expression::Atom(Value::Symbol(name), _) => {
+                match self.resolve(name) {
+                    Some(address) => self.load(address),
+                    None => {
+                        let index = self.name(name);
+                        self.emit(Op::Global(index), Some(expr), false);
+                    }
+                }
+                self.finish(tail);
+                return Ok(());
+            }
+            Expression::Atom(value, _) => {
+                self.constant(value.clone());
+                self.finish(tail);
+                return Ok(());
+            }
+            Expression::Quote(quoted, _) => {
+                match expression_to_value(quoted) {
+                    Ok(value) => self.constant(value),
+                    Err(_) => self.eval(expr),
+                }
+                self.finish(tail);
+                return Ok(());
+            }
+            Expression::List(elements, _) => elements,
+        };
+
+        // Empty list evaluates to empty list
+        let Some(first) = elements.first() else {
+            self.constant(Value::List(List::new()));
+            self.finish(tail);
+            return Ok(());
+        };
+
+        // Special forms take precedence over bindings
+        if let Expression::Atom(Value::Symbol(name), _) = first.as_ref()
+            && let Some(form) = SpecialForm::from_name(name.as_str())
+        {
+            return self.special_form(form, expr, &elements[1..], tail, body);
+        }
+        self.call(expr, elements, tail)
+    }
+
+    /// Compile a sequence of expressions, the last in `tail` position
+    fn sequence(&mut self, exprs: &[Arc<Expression>], tail: bool, body: bool) -> Compiled {
+        let Some((last, init)) = exprs.split_last() else {
+            self.constant(Value::Nil);
+            self.finish(tail);
+            return Ok(());
+        };
+
+        // Procedures referred to before their definitions are bound to
+        // placeholders first, as in `letrec`
+        let forward = match body && !self.global {
+            true => binding::forward_procedure_names(exprs),
+            false => Vec::new(),
+        };
+        for name in forward.iter() {
+            self.emit(Op::Placeholder, None, false);
+            self.bind(name);
+            self.placeholders.insert(name.clone());
+        }
+
+        for expr in init {
+            self.expression(expr, false, body)?;
+            self.emit(Op::Pop, None, false);
+        }
+        self.expression(last, tail, body)
+    }
+
+    /// Compile a procedure call
+    ///
+    /// The operator is checked to be a procedure before the operands are
+    /// evaluated.
+    fn call(
+        &mut self,
+        call: &Arc<Expression>,
+        elements: &[Arc<Expression>],
+        tail: bool,
+    ) -> Compiled {
+        let (operator, operands) = (&elements[0], &elements[1..]);
+        let argc = operands.len() as u32;
+
+        let mut builtin = None;
+        match operator.as_ref() {
+            Expression::Atom(Value::Symbol(name), _) => match self.resolve(name) {
+                Some(address) => {
+                    self.load(address);
+                    self.emit(Op::CheckProcedure, Some(call), false);
+                }
+                None => {
+                    builtin = self.builtin(name);
+                    if builtin.is_none() {
+                        let index = self.name(name);
+                        self.emit(Op::Callee(index), Some(call), false);
+                    }
+                }
+            },
+            _ => {
+                self.expression(operator, false, false)?;
+                self.emit(Op::CheckProcedure, Some(call), false);
+            }
+        }
+
+        for operand in operands {
+            self.expression(operand, false, false)?;
+        }
+
+        match builtin {
+            Some(builtin) => {
+                let op = Op::binary(builtin)
+                    .filter(|_| argc == 2)
+                    .unwrap_or(Op::Builtin(builtin, argc));
+                self.emit(op, Some(call), tail);
+                self.finish(tail);
+            }
+            None => {
+                let op = if tail {
+                    Op::TailCall(argc)
+                } else {
+                    Op::Call(argc)
+                };
+                self.emit(op, Some(call), tail);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile a special form
+    fn special_form(
+        &mut self,
+        form: SpecialForm,
+        expr: &Arc<Expression>,
+        args: &[Arc<Expression>],
+        tail: bool,
+        body: bool,
+    ) -> Compiled {
+        // Evaluating a denied form reports that it is not permitted
+        if !self.env.permits_special_form(form) {
+            self.eval(expr);
+            self.finish(tail);
+            return Ok(());
+        }
+        match form {
+            SpecialForm::If if args.len() == 3 => {
+                self.expression(&args[0], false, false)?;
+                let to_alternative = self.emit(Op::JumpIfFalse(0), None, false);
+                self.expression(&args[1], tail, false)?;
+                if tail {
+                    self.patch(to_alternative);
+                    self.expression(&args[2], tail, false)
+                } else {
+                    let to_end = self.emit(Op::Jump(0), None, false);
+                    self.patch(to_alternative);
+                    self.expression(&args[2], tail, false)?;
+                    self.patch(to_end);
+                    Ok(())
+                }
+            }
+            SpecialForm::Begin => self.sequence(args, tail, body),
+            SpecialForm::And if args.is_empty() => {
+                self.constant(Value::boolean(true));
+                self.finish(tail);
+                Ok(())
+            }
+            SpecialForm::And => self.connective(args, Op::JumpIfFalseElsePop(0), tail),
+            SpecialForm::Or if args.is_empty() => {
+                self.constant(Value::boolean(false));
+                self.finish(tail);
+                Ok(())
+            }
+            SpecialForm::Or => self.connective(args, Op::JumpIfTrueElsePop(0), tail),
+            SpecialForm::Define => self.define(expr, args, tail, body),
+            SpecialForm::Lambda => match parse_lambda(args) {
+                Ok((params, lambda_body)) => {
+                    self.closure(None, false, params, lambda_body);
+                    self.finish(tail);
+                    Ok(())
+                }
+                Err(_) => self.fallback(form, expr, tail),
+            },
+            SpecialForm::Let => match args.first().map(AsRef::as_ref) {
+                Some(Expression::Atom(Value::Symbol(name), _)) => {
+                    self.named_let(expr, name, &args[1..], tail)
+                }
+                _ => self.let_form(expr, args, tail),
+            },
+            SpecialForm::LetStar => self.let_star(expr, args, tail),
+            SpecialForm::Parameterize => self.parameterize(expr, args, tail),
+            _ => self.fallback(form, expr, tail),
+        }
+    }
+
+    /// Compile `and` or `or`, whose value is the first operand for which
+    /// `jump` is taken, or else the last
+    fn connective(&mut self, operands: &[Arc<Expression>], jump: Op, tail: bool) -> Compiled {
+        let (last, init) = operands.split_last().expect("operands are not empty");
+        let mut jumps = Vec::with_capacity(init.len());
+        for operand in init {
+            self.expression(operand, false, false)?;
+            jumps.push(self.emit(jump, None, false));
+        }
+        self.expression(last, tail, false)?;
+        for index in jumps {
+            self.patch(index);
+        }
+        self.finish(tail);
+        Ok(())
+    }
+
+    /// Compile a definition, which has no value
+    fn define(
+        &mut self,
+        form: &Arc<Expression>,
+        args: &[Arc<Expression>],
+        tail: bool,
+        body: bool,
+    ) -> Compiled {
+        if self.code.slotted && !body {
+            return Err(NeedsEnvironment);
+        }
+
+        let identifier = match args.first().map(AsRef::as_ref) {
+            // (define identifier expression)
+            Some(Expression::Atom(Value::Symbol(identifier), _)) => {
+                let [_, value_expr] = args else {
+                    return self.fallback(SpecialForm::Define, form, tail);
+                };
+                if is_lambda_expression(value_expr) {
+                    let lambda_args = &value_expr.as_list().expect("lambda is a list")[1..];
+                    let Ok((params, lambda_body)) = parse_lambda(lambda_args) else {
+                        return self.fallback(SpecialForm::Define, form, tail);
+                    };
+                    self.closure(Some(identifier), !self.global, params, lambda_body);
+                } else {
+                    self.expression(value_expr, false, false)?;
+                }
+                identifier.clone()
+            }
+            // (define (identifier param...) body...)
+            Some(Expression::List(param_elements, _)) => {
+                let lambda_body = &args[1..];
+                let Ok((identifier, params)) =
+                    binding::parse_define_procedure(param_elements, lambda_body)
+                else {
+                    return self.fallback(SpecialForm::Define, form, tail);
+                };
+                self.closure(
+                    Some(&identifier),
+                    !self.global,
+                    params,
+                    lambda_body.to_vec(),
+                );
+                identifier
+            }
+            _ => return self.fallback(SpecialForm::Define, form, tail),
+        };
+
+        if self.placeholders.contains(&identifier) {
+            match self.resolve(&identifier) {
+                Some(address) => self.load(address),
+                None => {
+                    let index = self.name(&identifier);
+                    self.emit(Op::Global(index), None, false);
+                }
+            }
+            self.emit(Op::Fill, None, false);
+        }
+        self.bind(&identifier);
+        self.constant(Value::Nil);
+        self.finish(tail);
+        Ok(())
+    }
+
+    /// Compile the creation of a procedure, which is `recursive` if it can
+    /// refer to itself by a local `name`
+    ///
+    /// Free identifiers with an address here are captured by address, and
+    /// so have one in the procedure too.
+    fn closure(
+        &mut self,
+        name: Option<&Symbol>,
+        recursive: bool,
+        params: Vec<Symbol>,
+        body: Vec<Arc<Expression>>,
+    ) {
+        let mut captures = Vec::new();
+        let mut named = Vec::new();
+        for identifier in free_identifiers(&params, &body) {
+            let capture = match self.resolve(&identifier) {
+                _ if recursive && Some(&identifier) == name => Some(Capture::Itself),
+                Some(Address::Local(slot)) => Some(Capture::Local(slot)),
+                Some(Address::Captured(index)) => Some(Capture::Captured(index)),
+                None => None,
+            };
+            match capture {
+                Some(capture) => captures.push((identifier, capture)),
+                None => named.push(identifier),
+            }
+        }
+
+        let mut shadowed = self.shadowed.clone();
+        shadowed.extend(name.cloned());
+        let captured: Vec<Symbol> = captures.iter().map(|(name, _)| name.clone()).collect();
+        let code = compile_body(&params, &body, self.env, &shadowed, self.dynamic, &captured);
+        let lexical = (self.scopes.iter().flatten().map(|(name, _)| name))
+            .chain(&self.captured)
+            .chain(name.filter(|_| recursive))
+            .cloned()
+            .collect();
+
+        self.code.templates.push(Arc::new(Template {
+            params,
+            body,
+            code,
+            captures,
+            named,
+            lexical,
+        }));
+        let template = self.code.templates.len() as u32 - 1;
+        let name = name.map(|name| self.name(name));
+        self.emit(Op::Closure { template, name }, None, false);
+    }
+
+    /// Compile a `let` without a name
+    fn let_form(
+        &mut self,
+        form: &Arc<Expression>,
+        args: &[Arc<Expression>],
+        tail: bool,
+    ) -> Compiled {
+        let Ok((identifiers, inits)) = binding::parse_let(args) else {
+            return self.fallback(SpecialForm::Let, form, tail);
+        };
+        for init in &inits {
+            self.expression(init, false, false)?;
+        }
+
+        // The values are on the stack in order, so are bound last first
+        if self.code.slotted {
+            self.scopes.push(Vec::new());
+            let slots: Vec<u32> = identifiers.iter().map(|id| self.bind_slot(id)).collect();
+            for &slot in slots.iter().rev() {
+                self.emit(Op::SetLocal(slot), None, false);
+            }
+            self.sequence(&args[1..], tail, true)?;
+            self.scopes.pop();
+        } else {
+            let global = std::mem::replace(&mut self.global, false);
+            self.emit(Op::EnterScope, None, false);
+            for identifier in identifiers.iter().rev() {
+                self.bind(identifier);
+            }
+            self.sequence(&args[1..], tail, true)?;
+            if !tail {
+                self.emit(Op::ExitScope, None, false);
+            }
+            self.global = global;
+        }
+        Ok(())
+    }
+
+    /// Compile a named `let` as a call to its loop procedure
+    fn named_let(
+        &mut self,
+        form: &Arc<Expression>,
+        name: &Symbol,
+        args: &[Arc<Expression>],
+        tail: bool,
+    ) -> Compiled {
+        let Ok((identifiers, inits)) = binding::parse_named_let(args) else {
+            return self.fallback(SpecialForm::Let, form, tail);
+        };
+        for init in &inits {
+            self.expression(init, false, false)?;
+        }
+
+        // The procedure is created once the initial values are known
+        let argc = inits.len() as u32;
+        self.closure(Some(name), true, identifiers, args[1..].to_vec());
+        self.emit(Op::Insert(argc), None, false);
+        let op = if tail {
+            Op::TailCall(argc)
+        } else {
+            Op::Call(argc)
+        };
+        self.emit(op, Some(form), tail);
+        Ok(())
+    }
+
+    /// Compile a `let*`, whose bindings share one scope
+    fn let_star(
+        &mut self,
+        form: &Arc<Expression>,
+        args: &[Arc<Expression>],
+        tail: bool,
+    ) -> Compiled {
+        let Ok((identifiers, inits)) = binding::parse_let_star(args) else {
+            return self.fallback(SpecialForm::LetStar, form, tail);
+        };
+
+        let global = std::mem::replace(&mut self.global, false);
+        if self.code.slotted {
+            self.scopes.push(Vec::new());
+        } else {
+            self.emit(Op::EnterScope, None, false);
+        }
+        for (identifier, init) in identifiers.iter().zip(&inits) {
+            self.expression(init, false, false)?;
+            if self.code.slotted {
+                let slot = self.bind_slot(identifier);
+                self.emit(Op::SetLocal(slot), None, false);
+            } else {
+                self.bind(identifier);
+            }
+        }
+        self.sequence(&args[1..], tail, true)?;
+
+        if self.code.slotted {
+            self.scopes.pop();
+        } else if !tail {
+            self.emit(Op::ExitScope, None, false);
+        }
+        self.global = global;
+        Ok(())
+    }
+
+    /// Compile a `parameterize`, whose body has a scope of its own
+    fn parameterize(
+        &mut self,
+        form: &Arc<Expression>,
+        args: &[Arc<Expression>],
+        tail: bool,
+    ) -> Compiled {
+        let Ok((exprs, body)) = parse_parameterize(args) else {
+            return self.fallback(SpecialForm::Parameterize, form, tail);
+        };
+        for (parameter, value) in &exprs {
+            self.expression(parameter, false, false)?;
+            self.expression(value, false, false)?;
+        }
+        self.emit(Op::Parameterize(exprs.len() as u32), Some(form), tail);
+
+        let global = std::mem::replace(&mut self.global, false);
+        if self.code.slotted {
+            self.scopes.push(Vec::new());
+        } else {
+            self.emit(Op::EnterScope, None, false);
+        }
+        self.sequence(body, tail, true)?;
+
+        if self.code.slotted {
+            self.scopes.pop();
+        } else if !tail {
+            self.emit(Op::ExitScope, None, false);
+        }
+        if !tail {
+            self.emit(Op::Unparameterize, None, false);
+        }
+        self.global = global;
+        Ok(())
+    }
+
+    /// Compile a special form for the tree-walking evaluator
+    fn fallback(&mut self, form: SpecialForm, expr: &Arc<Expression>, tail: bool) -> Compiled {
+        // These bind identifiers in the environment they are evaluated in
+        if matches!(
+            form,
+            SpecialForm::DefineRecordType | SpecialForm::DefineLibrary | SpecialForm::Import
+        ) {
+            if self.code.slotted {
+                return Err(NeedsEnvironment);
+            }
+            self.dynamic = true;
+        }
+        self.eval(expr);
+        self.finish(tail);
+        Ok(())
+    }
+
+    /// Emit an operation evaluating `expr` with the tree-walking evaluator
+    fn eval(&mut self, expr: &Arc<Expression>) {
+        self.code.forms.push(Arc::clone(expr));
+        let form = self.code.forms.len() as u32 - 1;
+        let capture = self.capture();
+        self.emit(Op::Eval { form, capture }, Some(expr), false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    /// Compile the procedure defined by `source`, `(define (name ...) ...)`
+    fn compile_procedure(source: &str, env: &Environment) -> Arc<Code> {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let expr = parser.parse_expression().unwrap().expr;
+        let code = compile(&expr, env);
+        Arc::clone(&code.templates[0].code)
+    }
+
+    #[test]
+    fn test_compile_slots_and_specialised_builtins() {
+        let env = Environment::new();
+        let code = compile_procedure(
+            "(define (f n) (let ((m (- n 1))) (if (< m 0) m (f m))))",
+            &env,
+        );
+
+        assert!(code.slotted);
+        assert_eq!(code.slots, 2);
+        assert_eq!(
+            code.ops,
+            [
+                Op::Local(0),
+                Op::Constant(0),
+                Op::Subtract,
+                Op::SetLocal(1),
+                Op::Local(1),
+                Op::Constant(1),
+                Op::Less,
+                Op::JumpIfFalse(10),
+                Op::Local(1),
+                Op::Return,
+                Op::Callee(0),
+                Op::Local(1),
+                Op::TailCall(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_rebound_builtins_are_called() {
+        // Parameters and definitions shadow builtins
+        let env = Environment::new();
+        let code = compile_procedure("(define (f car) (car 1))", &env);
+        assert_eq!(
+            code.ops,
+            [
+                Op::Local(0),
+                Op::CheckProcedure,
+                Op::Constant(0),
+                Op::TailCall(1)
+            ]
+        );
+
+        let mut env = Environment::new();
+        env.define_str("+", Value::number(0.0));
+        let code = compile_procedure("(define (f) (+ 1 2))", &env);
+        assert!(code.ops.contains(&Op::Callee(0)));
+    }
+
+    #[test]
+    fn test_compile_environment_fallback() {
+        let env = Environment::new();
+
+        // A definition outside body level needs an environment
+        let code = compile_procedure("(define (f x) (if x (define y 1) 2) y)", &env);
+        assert!(!code.slotted);
+        assert!(code.ops.iter().any(|op| matches!(op, Op::Define(_))));
+
+        // Other forms are evaluated with the slots they can see
+        let code = compile_procedure("(define (f x) (letrec ((y x)) y))", &env);
+        assert!(code.slotted);
+        assert_eq!(code.captures[0], [(Symbol::new("x"), 0)]);
+    }
+
+    #[test]
+    fn test_compile_lexical_addresses() {
+        let env = Environment::new();
+
+        // Closures capture the slots they refer to, and address them by index
+        let code = compile_procedure("(define (adder n) (lambda (x) (+ x n)))", &env);
+        let template = &code.templates[0];
+        assert_eq!(template.captures, [(Symbol::new("n"), Capture::Local(0))]);
+        assert_eq!(
+            template.code.ops,
+            [Op::Local(0), Op::Captured(0), Op::Add, Op::Return]
+        );
+
+        // Captured bindings are captured again from the closure
+        let code = compile_procedure("(define (f a b) (lambda () (lambda () (list b a))))", &env);
+        let inner = &code.templates[0].code.templates[0];
+        assert_eq!(
+            inner.captures,
+            [
+                (Symbol::new("b"), Capture::Captured(0)),
+                (Symbol::new("a"), Capture::Captured(1))
+            ]
+        );
+        assert_eq!(inner.named, [Symbol::new("list")]);
+
+        // A local procedure refers to itself by address, a global one by name
+        let code = compile_procedure("(define (f) (define (g) (g)) (f))", &env);
+        let template = &code.templates[0];
+        assert_eq!(template.captures, [(Symbol::new("g"), Capture::Itself)]);
+        assert_eq!(template.code.ops[0], Op::Captured(0));
+        assert!(code.ops.iter().any(|op| matches!(op, Op::Callee(_))));
+    }
+}
@@ -0,0 +1,1132 @@
+//! Explicit-continuation evaluation machine
+//!
+//! Expressions are evaluated by a CEK-style machine: the *control* is the
+//! expression being evaluated (or the procedure being applied, or the value
+//! being returned), the *environment* travels with it, and the
+//! *continuation* is a stack of frames describing what to do with the value.
+//! The stack lives on the heap, so deeply nested non-tail recursion such as
+//!
+//! ```scheme
+//! (define (sum n) (if (= n 0) 0 (+ n (sum (- n 1)))))
+//! (sum 100000)
+//! ```
+//!
+//! does not overflow the Rust stack.
+//!
+//! Every procedure call pushes a [`Continuation::Procedure`] marker, which
+//! provides the backtrace frame if an error escapes the call. A call made
+//! while the marker is on top of the stack is in tail position: it replaces
+//! the marker instead of pushing a new one, so tail calls run in constant
+//! space however they are nested in `if`, `begin`, `and`, `or`, `let`,
+//! `let*` and `parameterize`. The bodies of `letrec` and `letrec*` are not in
+//! tail position, as their scope must outlive the procedures it binds.
+//!
+//! The machine handles the special forms that evaluate subexpressions in
+//! tail position itself, and the bodies of promises applied to `force`. The
+//! other special forms are called directly and may start a nested machine
+//! for their subexpressions.
+//!
+//! A `force` of a promise that another evaluation is forcing waits in
+//! [`Control::Wait`]: an asynchronous run yields to its executor before
+//! trying again, and any other run blocks until the promise is forced.
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::parser::Expression;
+use crate::runtime::Environment;
+use crate::runtime::builtins::Builtin;
+use crate::runtime::builtins::promise::{abandon_pending, next_in_chain, resolve_pending};
+use crate::runtime::interrupt::{InterruptHandle, procedure_interrupt};
+use crate::runtime::limits::{Budget, called_from, outer_depth, outer_interrupt, procedure_budget};
+use crate::runtime::special_forms::parameter::{parameter_binding, parse_parameterize};
+use crate::runtime::special_forms::{SpecialForm, binding};
+use crate::runtime::utils::is_lambda_expression;
+use crate::types::{
+    DynamicEnvironment, EvaluationId, ForceStep, Lambda, List, Procedure, Promise, PromiseKind,
+    Symbol, Value,
+};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use super::procedure::call_frame;
+use super::{eval_atom, expression_to_value, locate_error, yield_in};
+
+/// What the machine does next
+pub(super) enum Control {
+    /// Evaluate an expression in an environment
+    Eval(Arc<Expression>, Environment),
+
+    /// Apply a procedure to evaluated arguments; `call` is the call
+    /// expression, if there is one
+    Apply {
+        procedure: Procedure,
+        args: Vec<Value>,
+        call: Option<Arc<Expression>>,
+    },
+
+    /// Pass a value to the continuation
+    Return(Value),
+
+    /// Force a promise that another evaluation was forcing, for the call
+    /// to `force` on top of the stack, continuing the chain whose promises
+    /// are `pending`
+    Wait {
+        promise: Arc<Promise>,
+        pending: Vec<Arc<Promise>>,
+    },
+}
+
+/// A frame of the continuation stack
+enum Continuation {
+    /// Evaluating the operator and operands of a procedure call
+    Call {
+        call: Arc<Expression>,
+        env: Environment,
+        operator: Option<Procedure>,
+        args: Vec<Value>,
+    },
+
+    /// Evaluating the test of an `if`
+    If {
+        form: Arc<Expression>,
+        env: Environment,
+    },
+
+    /// Evaluating a body expression other than the last
+    Sequence {
+        body: Body,
+        next: usize,
+        env: Environment,
+    },
+
+    /// Evaluating an operand of `and` other than the last
+    And {
+        form: Arc<Expression>,
+        next: usize,
+        env: Environment,
+    },
+
+    /// Evaluating an operand of `or` other than the last
+    Or {
+        form: Arc<Expression>,
+        next: usize,
+        env: Environment,
+    },
+
+    /// Evaluating the value of `(define identifier expression)`
+    Define {
+        form: Arc<Expression>,
+        identifier: Symbol,
+        env: Environment,
+    },
+
+    /// Evaluating the initial value of a binding of the `let` family
+    Bindings(Bindings),
+
+    /// Evaluating a parameter or value expression of a `parameterize`
+    Parameterize(Parameterize),
+
+    /// Evaluating the body of a `parameterize` other than in tail position
+    /// of a procedure; restores the dynamic environment it was entered from
+    Dynamic(DynamicEnvironment),
+
+    /// Evaluating the body of a promise applied to `force`
+    ///
+    /// `pending` are the promises claimed along its `delay-force` chain,
+    /// which get the final value.
+    Force {
+        call: Option<Arc<Expression>>,
+        pending: Vec<Arc<Promise>>,
+        kind: PromiseKind,
+    },
+
+    /// A procedure call in progress, for backtraces and tail calls
+    ///
+    /// `lambda` keeps a running lambda alive: references to it from its own
+    /// scope, as bound by `define` or `letrec`, are weak. `dynamic` is the
+    /// dynamic environment to restore on return, if a `parameterize` body
+    /// is running in tail position.
+    Procedure {
+        call: Option<Arc<Expression>>,
+        name: Option<Symbol>,
+        tail_calls: usize,
+        lambda: Option<Arc<Lambda>>,
+        dynamic: Option<DynamicEnvironment>,
+    },
+}
+
+impl Continuation {
+    /// The expression errors raised while resuming this frame are located at
+    fn expression(&self) -> Option<&Arc<Expression>> {
+        match self {
+            Continuation::Call { call, .. } => Some(call),
+            Continuation::If { form, .. }
+            | Continuation::And { form, .. }
+            | Continuation::Or { form, .. }
+            | Continuation::Define { form, .. } => Some(form),
+            Continuation::Sequence { body, .. } => body.form(),
+            Continuation::Bindings(bindings) => Some(&bindings.form),
+            Continuation::Parameterize(parameterize) => Some(&parameterize.form),
+            Continuation::Dynamic(_) => None,
+            Continuation::Procedure { call, .. } | Continuation::Force { call, .. } => {
+                call.as_ref()
+            }
+        }
+    }
+}
+
+/// A sequence of body expressions
+enum Body {
+    /// The expressions of a special form, from the given argument index
+    Form(Arc<Expression>, usize),
+
+    /// The body of a lambda
+    Lambda(Arc<Lambda>),
+}
+
+impl Body {
+    fn exprs(&self) -> &[Arc<Expression>] {
+        match self {
+            Body::Form(form, start) => form
+                .as_list()
+                .and_then(|elements| elements.get(start + 1..))
+                .unwrap_or_default(),
+            Body::Lambda(lambda) => lambda.body(),
+        }
+    }
+
+    fn form(&self) -> Option<&Arc<Expression>> {
+        match self {
+            Body::Form(form, _) => Some(form),
+            Body::Lambda(_) => None,
+        }
+    }
+}
+
+/// Which member of the `let` family is binding
+enum BindingKind {
+    Let,
+    NamedLet(Symbol),
+    LetStar,
+    Letrec,
+    LetrecStar,
+}
+
+/// Progress through the bindings of a `let` family form
+struct Bindings {
+    form: Arc<Expression>,
+    kind: BindingKind,
+    identifiers: Vec<Symbol>,
+    inits: Vec<Arc<Expression>>,
+    /// Index of the binding being initialised
+    next: usize,
+    /// Values collected for `let` and named `let`, which bind them together
+    values: Vec<Value>,
+    /// Environment the initial values are evaluated in
+    env: Environment,
+}
+
+/// Progress through the bindings of a `parameterize`
+struct Parameterize {
+    form: Arc<Expression>,
+    /// Parameter and value expressions, alternately
+    exprs: Vec<Arc<Expression>>,
+    /// Values of the expressions evaluated so far
+    values: Vec<Value>,
+    env: Environment,
+}
+
+/// How many steps [`Machine::run_async`] takes before yielding
+const SLICE_STEPS: usize = 4096;
+
+/// The evaluation machine and its continuation stack
+pub(super) struct Machine {
+    stack: Vec<Continuation>,
+    /// Number of [`Continuation::Procedure`] frames on the stack, plus the
+    /// lambda calls in progress in the evaluations this one is nested in
+    calls: usize,
+    /// The resource limits of the code being run
+    budget: Option<Arc<Budget>>,
+    /// The interrupt flag of the evaluation this is, or is nested in
+    interrupt: Option<InterruptHandle>,
+    /// The evaluation this is, or is nested in, which owns the promises
+    /// it forces
+    evaluation: EvaluationId,
+}
+
+impl Machine {
+    pub(super) fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            calls: outer_depth(),
+            budget: None,
+            interrupt: outer_interrupt(),
+            evaluation: EvaluationId::current(),
+        }
+    }
+
+    /// Run the machine until the continuation stack is exhausted
+    pub(super) fn run(mut self, mut control: Control) -> Result<Value> {
+        self.start(&control);
+        loop {
+            if let Control::Wait { promise, .. } = &control {
+                promise.wait(self.evaluation);
+            }
+            control = match self.step(control)? {
+                ControlFlow::Continue(control) => control,
+                ControlFlow::Break(value) => return Ok(value),
+            };
+        }
+    }
+
+    /// Run the machine until the continuation stack is exhausted, yielding
+    /// to the executor after every [`SLICE_STEPS`] steps
+    pub(super) async fn run_async(mut self, mut control: Control) -> Result<Value> {
+        self.start(&control);
+        let outer = DynamicEnvironment::current();
+        let mut steps = 0;
+        loop {
+            control = match self.step(control)? {
+                ControlFlow::Continue(control) => control,
+                ControlFlow::Break(value) => return Ok(value),
+            };
+            steps += 1;
+            if steps % SLICE_STEPS == 0 || matches!(control, Control::Wait { .. }) {
+                yield_in(&outer).await;
+            }
+        }
+    }
+
+    /// Take the resource limits and, unless nested in another evaluation,
+    /// the interrupt flag of the code `control` starts running
+    fn start(&mut self, control: &Control) {
+        let interrupt;
+        (self.budget, interrupt) = match control {
+            Control::Eval(_, env) => (env.budget(), Some(env.interrupt_handle())),
+            Control::Apply { procedure, .. } => {
+                (procedure_budget(procedure), procedure_interrupt(procedure))
+            }
+            Control::Return(_) | Control::Wait { .. } => (None, None),
+        };
+        if self.interrupt.is_none() {
+            self.interrupt = interrupt;
+        }
+    }
+
+    /// Carry out one step, breaking with the final value once the
+    /// continuation stack is exhausted
+    fn step(&mut self, control: Control) -> Result<ControlFlow<Value, Control>> {
+        if let Err(error) = self.charge() {
+            if let Control::Wait { pending, .. } = &control {
+                abandon_pending(pending);
+            }
+            return Err(self.unwind(error));
+        }
+        let step = match control {
+            Control::Eval(expr, env) => self.eval(expr, env),
+            Control::Apply {
+                procedure,
+                args,
+                call,
+            } => self.apply(procedure, args, call),
+            Control::Return(value) => match self.stack.pop() {
+                None => return Ok(ControlFlow::Break(value)),
+                Some(frame) => {
+                    let expr = frame.expression().cloned();
+                    self.resume(frame, value).map_err(|error| match expr {
+                        Some(expr) => locate_error(error, &expr),
+                        None => error,
+                    })
+                }
+            },
+            Control::Wait { promise, pending } => Ok(self.force(promise, pending)),
+        };
+        match step {
+            Ok(control) => Ok(ControlFlow::Continue(control)),
+            Err(error) => Err(self.unwind(error)),
+        }
+    }
+
+    /// Discard the continuation after an error, recording where it happened
+    ///
+    /// Every procedure call in progress contributes a backtrace frame, most
+    /// recent first.
+    fn unwind(&mut self, mut error: Error) -> Error {
+        while let Some(frame) = self.stack.pop() {
+            error = match frame {
+                Continuation::Procedure {
+                    call,
+                    name,
+                    tail_calls,
+                    dynamic,
+                    ..
+                } => {
+                    if let Some(dynamic) = dynamic {
+                        dynamic.install();
+                    }
+                    error.push_frame(call_frame(
+                        call.as_ref(),
+                        name.as_ref().map(Symbol::as_str),
+                        tail_calls,
+                    ))
+                }
+                Continuation::Dynamic(dynamic) => {
+                    dynamic.install();
+                    error
+                }
+                Continuation::Force { call, pending, .. } => {
+                    abandon_pending(&pending);
+                    match call {
+                        Some(call) => locate_error(error, &call),
+                        None => error,
+                    }
+                }
+                frame => match frame.expression() {
+                    Some(expr) => locate_error(error, expr),
+                    None => error,
+                },
+            };
+        }
+        error
+    }
+
+    /// Start evaluating an expression
+    fn eval(&mut self, expr: Arc<Expression>, env: Environment) -> Result<Control> {
+        let elements = match expr.as_ref() {
+            Expression::List(elements, _) => elements,
+            Expression::Atom(value, _) => {
+                return eval_atom(value.clone(), &env)
+                    .map(Control::Return)
+                    .map_err(|error| locate_error(error, &expr));
+            }
+            Expression::Quote(quoted, _) => {
+                return expression_to_value(quoted)
+                    .map(Control::Return)
+                    .map_err(|error| locate_error(error, &expr));
+            }
+        };
+
+        // Empty list evaluates to empty list
+        let Some(first) = elements.first() else {
+            return Ok(Control::Return(Value::List(List::new())));
+        };
+
+        let operator = match first.as_ref() {
+            Expression::Atom(Value::Symbol(identifier), _) => {
+                // Special forms take precedence over bindings
+                if let Some(form) = SpecialForm::from_name(identifier.as_str()) {
+                    return self
+                        .eval_special_form(form, &expr, env)
+                        .map_err(|error| locate_error(error, &expr));
+                }
+
+                // An unbound operator is located at the identifier itself
+                let value = env
+                    .lookup(identifier)
+                    .map_err(|error| locate_error(error, first))?;
+                as_procedure(value).map_err(|error| locate_error(error, &expr))?
+            }
+            _ => {
+                self.stack.push(Continuation::Call {
+                    call: Arc::clone(&expr),
+                    env: env.share(),
+                    operator: None,
+                    args: Vec::new(),
+                });
+                return Ok(Control::Eval(Arc::clone(first), env));
+            }
+        };
+
+        let args = Vec::with_capacity(elements.len() - 1);
+        self.eval_operands(expr, env, operator, args)
+    }
+
+    /// Evaluate the remaining operands of a call, then apply the operator
+    ///
+    /// Atoms and quotations are evaluated directly; other operands suspend
+    /// the call on the stack.
+    fn eval_operands(
+        &mut self,
+        call: Arc<Expression>,
+        env: Environment,
+        operator: Procedure,
+        mut args: Vec<Value>,
+    ) -> Result<Control> {
+        let elements = call.as_list().map(Vec::as_slice).unwrap_or_default();
+        while let Some(operand) = elements.get(args.len() + 1) {
+            match eval_simple(operand, &env) {
+                Some(value) => {
+                    args.push(value.map_err(|error| locate_error(error, operand))?);
+                }
+                None => {
+                    let operand = Arc::clone(operand);
+                    self.stack.push(Continuation::Call {
+                        call,
+                        env: env.share(),
+                        operator: Some(operator),
+                        args,
+                    });
+                    return Ok(Control::Eval(operand, env));
+                }
+            }
+        }
+
+        Ok(Control::Apply {
+            procedure: operator,
+            args,
+            call: Some(call),
+        })
+    }
+
+    /// Start evaluating a special form
+    fn eval_special_form(
+        &mut self,
+        form: SpecialForm,
+        expr: &Arc<Expression>,
+        mut env: Environment,
+    ) -> Result<Control> {
+        if !env.permits_special_form(form) {
+            return Err(Error::not_permitted(form.name()));
+        }
+        let args = expr
+            .as_list()
+            .and_then(|elements| elements.get(1..))
+            .unwrap_or_default();
+
+        match form {
+            SpecialForm::If => {
+                if args.len() != 3 {
+                    return Err(Error::arity_error("if", 3, args.len()));
+                }
+                let test = Arc::clone(&args[0]);
+                self.stack.push(Continuation::If {
+                    form: Arc::clone(expr),
+                    env: env.share(),
+                });
+                Ok(Control::Eval(test, env))
+            }
+            SpecialForm::Begin => {
+                if args.is_empty() {
+                    return Ok(Control::Return(Value::Nil));
+                }
+                Ok(self.eval_body(Body::Form(Arc::clone(expr), 0), 0, env))
+            }
+            SpecialForm::And => {
+                if args.is_empty() {
+                    return Ok(Control::Return(Value::boolean(true)));
+                }
+                Ok(
+                    self.eval_operand(expr, 0, env, |form, next, env| Continuation::And {
+                        form,
+                        next,
+                        env,
+                    }),
+                )
+            }
+            SpecialForm::Or => {
+                if args.is_empty() {
+                    return Ok(Control::Return(Value::boolean(false)));
+                }
+                Ok(
+                    self.eval_operand(expr, 0, env, |form, next, env| Continuation::Or {
+                        form,
+                        next,
+                        env,
+                    }),
+                )
+            }
+            SpecialForm::Define => match binding::value_definition(args) {
+                Some((identifier, value_expr)) => {
+                    let value_expr = Arc::clone(value_expr);
+                    self.stack.push(Continuation::Define {
+                        form: Arc::clone(expr),
+                        identifier: identifier.clone(),
+                        env: env.share(),
+                    });
+                    Ok(Control::Eval(value_expr, env))
+                }
+                None => binding::eval_define(args, &mut env).map(Control::Return),
+            },
+            SpecialForm::Let => {
+                let (kind, (identifiers, inits)) = match args.first().map(AsRef::as_ref) {
+                    Some(Expression::Atom(Value::Symbol(name), _)) => (
+                        BindingKind::NamedLet(name.clone()),
+                        binding::parse_named_let(&args[1..])?,
+                    ),
+                    _ => (BindingKind::Let, binding::parse_let(args)?),
+                };
+                self.bind(expr, kind, identifiers, inits, env)
+            }
+            SpecialForm::LetStar => {
+                let (identifiers, inits) = binding::parse_let_star(args)?;
+                let env = Environment::new_scope(&env);
+                self.bind(expr, BindingKind::LetStar, identifiers, inits, env)
+            }
+            SpecialForm::Letrec => {
+                let (identifiers, inits) = binding::parse_letrec(args)?;
+                let env = Environment::new_scope(&env);
+                self.bind(expr, BindingKind::Letrec, identifiers, inits, env)
+            }
+            SpecialForm::LetrecStar => {
+                let (identifiers, inits) = binding::parse_letrec_star(args)?;
+                let mut env = Environment::new_scope(&env);
+                binding::declare_letrec_star_procedures(&identifiers, &inits, &mut env);
+                self.bind(expr, BindingKind::LetrecStar, identifiers, inits, env)
+            }
+            SpecialForm::Parameterize => {
+                let (exprs, _) = parse_parameterize(args)?;
+                let exprs = exprs
+                    .into_iter()
+                    .flat_map(|(parameter, value)| [Arc::clone(parameter), Arc::clone(value)])
+                    .collect::<Vec<_>>();
+                self.continue_parameterize(Parameterize {
+                    form: Arc::clone(expr),
+                    values: Vec::with_capacity(exprs.len()),
+                    exprs,
+                    env,
+                })
+            }
+            _ => self
+                .call_out(|| form.call(args, &mut env))
+                .map(Control::Return),
+        }
+    }
+
+    /// Evaluate the remaining parameter and value expressions of a
+    /// `parameterize`, then its body with the parameters bound
+    fn continue_parameterize(&mut self, mut parameterize: Parameterize) -> Result<Control> {
+        while let Some(expr) = parameterize.exprs.get(parameterize.values.len()) {
+            match eval_simple(expr, &parameterize.env) {
+                Some(value) => {
+                    let value = value.map_err(|error| locate_error(error, expr))?;
+                    parameterize.values.push(value);
+                }
+                None => {
+                    let expr = Arc::clone(expr);
+                    let env = parameterize.env.share();
+                    self.stack.push(Continuation::Parameterize(parameterize));
+                    return Ok(Control::Eval(expr, env));
+                }
+            }
+        }
+
+        let Parameterize {
+            form, values, env, ..
+        } = parameterize;
+        let bindings = self.call_out(|| {
+            let mut bindings = Vec::with_capacity(values.len() / 2);
+            let mut values = values.into_iter();
+            while let (Some(parameter), Some(value)) = (values.next(), values.next()) {
+                bindings.push(parameter_binding(parameter, value)?);
+            }
+            Ok::<_, Error>(bindings)
+        })?;
+        let previous = DynamicEnvironment::current().extend(bindings).install();
+
+        match self.stack.last_mut() {
+            // The body is in tail position: the procedure restores the
+            // environment it was called in when it returns
+            Some(Continuation::Procedure { dynamic, .. }) => {
+                dynamic.get_or_insert(previous);
+            }
+            // Restoring the enclosing body's environment is enough
+            Some(Continuation::Dynamic(_)) => {}
+            _ => self.stack.push(Continuation::Dynamic(previous)),
+        }
+        let body_env = Environment::new_scope(&env);
+        Ok(self.eval_body(Body::Form(form, 1), 0, body_env))
+    }
+
+    /// Force a promise for the call to `force` on top of the stack,
+    /// continuing the chain whose promises are `pending`
+    fn force(&mut self, promise: Arc<Promise>, mut pending: Vec<Arc<Promise>>) -> Control {
+        let thunk = match promise.claim(self.evaluation) {
+            ForceStep::Ready(value) => return Control::Return(resolve_pending(&pending, value)),
+            ForceStep::Evaluate(thunk) => thunk,
+            ForceStep::Busy => return Control::Wait { promise, pending },
+        };
+        pending.push(promise);
+        let call = match self.stack.last() {
+            Some(Continuation::Procedure { call, .. }) => call.clone(),
+            _ => None,
+        };
+        self.stack.push(Continuation::Force {
+            call,
+            pending,
+            kind: thunk.kind(),
+        });
+        Control::Eval(
+            Arc::clone(thunk.body()),
+            Environment::new_scope(thunk.env()),
+        )
+    }
+
+    /// Evaluate operand `index` of `and` or `or`
+    ///
+    /// The last operand is in tail position; earlier ones push the frame
+    /// made by `frame` to decide whether to continue.
+    fn eval_operand(
+        &mut self,
+        form: &Arc<Expression>,
+        index: usize,
+        env: Environment,
+        frame: impl FnOnce(Arc<Expression>, usize, Environment) -> Continuation,
+    ) -> Control {
+        let operands = form
+            .as_list()
+            .map(|elements| &elements[1..])
+            .unwrap_or_default();
+        let operand = Arc::clone(&operands[index]);
+        if index + 1 < operands.len() {
+            self.stack
+                .push(frame(Arc::clone(form), index + 1, env.share()));
+        }
+        Control::Eval(operand, env)
+    }
+
+    /// Evaluate body expressions from `index`, the last in tail position
+    ///
+    /// A body starting is first given placeholders for the procedures it
+    /// refers to before defining them.
+    fn eval_body(&mut self, body: Body, index: usize, mut env: Environment) -> Control {
+        let exprs = body.exprs();
+        if index == 0 {
+            binding::declare_body_procedures(exprs, &mut env);
+        }
+        let expr = Arc::clone(&exprs[index]);
+        if index + 1 < exprs.len() {
+            self.stack.push(Continuation::Sequence {
+                body,
+                next: index + 1,
+                env: env.share(),
+            });
+        }
+        Control::Eval(expr, env)
+    }
+
+    /// Start binding the identifiers of a `let` family form
+    fn bind(
+        &mut self,
+        form: &Arc<Expression>,
+        kind: BindingKind,
+        identifiers: Vec<Symbol>,
+        inits: Vec<Arc<Expression>>,
+        env: Environment,
+    ) -> Result<Control> {
+        let values = match kind {
+            BindingKind::Let | BindingKind::NamedLet(_) => Vec::with_capacity(inits.len()),
+            _ => Vec::new(),
+        };
+        self.continue_bindings(Bindings {
+            form: Arc::clone(form),
+            kind,
+            identifiers,
+            inits,
+            next: 0,
+            values,
+            env,
+        })
+    }
+
+    /// Initialise the remaining bindings of a `let` family form, then
+    /// evaluate its body
+    fn continue_bindings(&mut self, mut bindings: Bindings) -> Result<Control> {
+        while let Some(init) = bindings.inits.get(bindings.next) {
+            let identifier = &bindings.identifiers[bindings.next];
+            let deferred = match bindings.kind {
+                // Lambdas are bound together once the other values are known
+                BindingKind::Letrec => is_lambda_expression(init),
+                // Lambdas are bound so that they can refer to themselves
+                BindingKind::LetrecStar => {
+                    binding::bind_lambda_expression(identifier, init, &mut bindings.env)?
+                }
+                _ => false,
+            };
+            if deferred {
+                bindings.next += 1;
+                continue;
+            }
+
+            match eval_simple(init, &bindings.env) {
+                Some(value) => {
+                    let value = value.map_err(|error| locate_error(error, init))?;
+                    bindings.record(value);
+                }
+                None => {
+                    let init = Arc::clone(init);
+                    let env = bindings.env.share();
+                    self.stack.push(Continuation::Bindings(bindings));
+                    return Ok(Control::Eval(init, env));
+                }
+            }
+        }
+
+        let Bindings {
+            form,
+            kind,
+            identifiers,
+            inits,
+            values,
+            mut env,
+            ..
+        } = bindings;
+        match kind {
+            BindingKind::Let => {
+                let mut let_env = Environment::new_scope(&env);
+                for (identifier, value) in identifiers.into_iter().zip(values) {
+                    let_env.define(identifier, value);
+                }
+                Ok(self.eval_body(Body::Form(form, 1), 0, let_env))
+            }
+            BindingKind::NamedLet(name) => {
+                let body = form
+                    .as_list()
+                    .and_then(|elements| elements.get(3..))
+                    .unwrap_or_default();
+                let procedure = binding::named_let_procedure(&name, identifiers, body, &env)?;
+                Ok(Control::Apply {
+                    procedure,
+                    args: values,
+                    call: Some(form),
+                })
+            }
+            BindingKind::LetStar => Ok(self.eval_body(Body::Form(form, 1), 0, env)),
+            BindingKind::Letrec | BindingKind::LetrecStar => {
+                if let BindingKind::Letrec = kind {
+                    binding::bind_letrec_lambdas(&identifiers, &inits, &mut env)?;
+                }
+                Ok(self.eval_body(Body::Form(form, 1), 0, env))
+            }
+        }
+    }
+
+    /// Pass a value to a continuation frame
+    fn resume(&mut self, frame: Continuation, value: Value) -> Result<Control> {
+        match frame {
+            Continuation::Call {
+                call,
+                env,
+                operator,
+                mut args,
+            } => {
+                let operator = match operator {
+                    Some(operator) => {
+                        args.push(value);
+                        operator
+                    }
+                    None => as_procedure(value)?,
+                };
+                self.eval_operands(call, env, operator, args)
+            }
+            Continuation::If { form, env } => {
+                let branch = if value.is_truthy() { 2 } else { 3 };
+                Ok(Control::Eval(
+                    Arc::clone(&form.as_list().unwrap()[branch]),
+                    env,
+                ))
+            }
+            Continuation::Sequence { body, next, env } => Ok(self.eval_body(body, next, env)),
+            Continuation::And { form, next, env } => {
+                if !value.is_truthy() {
+                    return Ok(Control::Return(Value::boolean(false)));
+                }
+                Ok(
+                    self.eval_operand(&form, next, env, |form, next, env| Continuation::And {
+                        form,
+                        next,
+                        env,
+                    }),
+                )
+            }
+            Continuation::Or { form, next, env } => {
+                if value.is_truthy() {
+                    return Ok(Control::Return(value));
+                }
+                Ok(
+                    self.eval_operand(&form, next, env, |form, next, env| Continuation::Or {
+                        form,
+                        next,
+                        env,
+                    }),
+                )
+            }
+            Continuation::Define {
+                identifier,
+                mut env,
+                ..
+            } => {
+                env.define(identifier, value);
+                Ok(Control::Return(Value::Nil))
+            }
+            Continuation::Bindings(mut bindings) => {
+                bindings.record(value);
+                self.continue_bindings(bindings)
+            }
+            Continuation::Parameterize(mut parameterize) => {
+                parameterize.values.push(value);
+                self.continue_parameterize(parameterize)
+            }
+            Continuation::Force { pending, kind, .. } => match next_in_chain(kind, value) {
+                Ok(ControlFlow::Break(value)) => {
+                    Ok(Control::Return(resolve_pending(&pending, value)))
+                }
+                Ok(ControlFlow::Continue(next)) => Ok(self.force(next, pending)),
+                Err(error) => {
+                    abandon_pending(&pending);
+                    Err(error)
+                }
+            },
+            // The procedure call, scope or dynamic extent is complete
+            Continuation::Procedure { dynamic, .. } => {
+                self.calls -= 1;
+                if let Some(dynamic) = dynamic {
+                    dynamic.install();
+                }
+                Ok(Control::Return(value))
+            }
+            Continuation::Dynamic(dynamic) => {
+                dynamic.install();
+                Ok(Control::Return(value))
+            }
+        }
+    }
+
+    /// Apply a procedure to its arguments
+    fn apply(
+        &mut self,
+        procedure: Procedure,
+        args: Vec<Value>,
+        call: Option<Arc<Expression>>,
+    ) -> Result<Control> {
+        let name = procedure.defined_name();
+        match self.stack.last_mut() {
+            // A call in tail position replaces the caller's frame
+            Some(Continuation::Procedure {
+                call: current_call,
+                name: current_name,
+                tail_calls,
+                ..
+            }) => {
+                *current_call = call.clone();
+                *current_name = name;
+                *tail_calls += 1;
+            }
+            _ => {
+                self.calls += 1;
+                self.stack.push(Continuation::Procedure {
+                    call: call.clone(),
+                    name,
+                    tail_calls: 0,
+                    lambda: None,
+                    dynamic: None,
+                });
+            }
+        }
+
+        self.enter(procedure, args).map_err(|error| match &call {
+            Some(call) => locate_error(error, call),
+            None => error,
+        })
+    }
+
+    /// Run a procedure whose call frame is on the stack
+    ///
+    /// Builtins and native procedures return their value directly; lambdas continue with their
+    /// body in a new scope.
+    fn enter(&mut self, procedure: Procedure, args: Vec<Value>) -> Result<Control> {
+        let lambda = match &procedure {
+            // Promise bodies are evaluated on the stack
+            Procedure::Builtin(Builtin::Force) if matches!(args[..], [Value::Promise(_)]) => {
+                let Some(Value::Promise(promise)) = args.into_iter().next() else {
+                    unreachable!("checked to be a promise");
+                };
+                return Ok(self.force(promise, Vec::new()));
+            }
+            Procedure::Builtin(builtin) => {
+                let value = self.call_out(|| builtin.call(&args))?;
+                return self.check_allocation(value).map(Control::Return);
+            }
+            Procedure::Native(native) => {
+                let value = self.call_out(|| native.call(&args))?;
+                return self.check_allocation(value).map(Control::Return);
+            }
+            Procedure::Record(record_proc) => {
+                return record_proc.call(&args).map(Control::Return);
+            }
+            Procedure::Parameter(parameter) => {
+                return parameter.call(&args).map(Control::Return);
+            }
+            Procedure::Lambda(_) | Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => {
+                procedure.resolve_weak_lambda()?
+            }
+        };
+
+        // Check arity
+        let expected_arity = lambda.arity();
+        if args.len() != expected_arity {
+            let name = lambda.name().map_or("<lambda>", Symbol::as_str);
+            return Err(Error::arity_error(name, expected_arity, args.len()));
+        }
+
+        // Bind parameters in a new scope extending the lambda's closure
+        let mut call_env = Environment::new_scope(lambda.env());
+        for (param, arg) in lambda.params().iter().zip(args) {
+            call_env.define(param.clone(), arg);
+        }
+
+        if lambda.body().is_empty() {
+            return Err(Error::runtime_error("Lambda body cannot be empty"));
+        }
+        if let Some(budget) = &self.budget {
+            budget.check_depth(self.calls)?;
+        }
+        if let Some(Continuation::Procedure {
+            lambda: running, ..
+        }) = self.stack.last_mut()
+        {
+            *running = Some(Arc::clone(&lambda));
+        }
+        Ok(self.eval_body(Body::Lambda(lambda), 0, call_env))
+    }
+
+    /// Check for an interrupt and count a step against the budget
+    fn charge(&self) -> Result<()> {
+        if let Some(interrupt) = &self.interrupt {
+            interrupt.check()?;
+        }
+        match &self.budget {
+            Some(budget) => budget.step(),
+            None => Ok(()),
+        }
+    }
+
+    /// Run a builtin, native procedure or special form, which may nest
+    /// evaluations within the calls in progress here
+    fn call_out<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.evaluation
+            .run(|| called_from(self.budget.as_ref(), self.interrupt.as_ref(), self.calls, f))
+    }
+
+    /// Check the size of a value returned by a builtin or native procedure
+    fn check_allocation(&self, value: Value) -> Result<Value> {
+        if let Some(budget) = &self.budget {
+            budget.check_allocation(&value)?;
+        }
+        Ok(value)
+    }
+}
+
+impl Bindings {
+    /// Record the value of the binding being initialised
+    fn record(&mut self, value: Value) {
+        match self.kind {
+            BindingKind::Let | BindingKind::NamedLet(_) => self.values.push(value),
+            _ => self.env.define(self.identifiers[self.next].clone(), value),
+        }
+        self.next += 1;
+    }
+}
+
+/// Evaluate an expression that needs no continuation: an atom or quotation
+///
+/// Returns None for lists, which must be evaluated by the machine. Errors
+/// are not yet located.
+fn eval_simple(expr: &Expression, env: &Environment) -> Option<Result<Value>> {
+    match expr {
+        Expression::Atom(value, _) => Some(eval_atom(value.clone(), env)),
+        Expression::Quote(quoted, _) => Some(expression_to_value(quoted)),
+        Expression::List(..) => None,
+    }
+}
+
+/// Check that an operator evaluated to a procedure
+fn as_procedure(value: Value) -> Result<Procedure> {
+    match value {
+        Value::Procedure(procedure) => Ok(procedure),
+        _ => {
+            let error_msg = format!("'{}' is not a procedure, got {}", value, value.type_name());
+            Err(Error::runtime_error(&error_msg).with_code(ErrorCode::NotAProcedure))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Control, Machine};
+    use crate::parser::Parser;
+    use crate::runtime::Environment;
+    use crate::types::Value;
+
+    /// Evaluate every expression in `source` on the machine, returning the
+    /// last result
+    fn eval_all(source: &str, env: &mut Environment) -> crate::Result<Value> {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let mut result = Value::Nil;
+        while let Ok(parsed) = parser.parse_expression() {
+            result = Machine::new().run(Control::Eval(parsed.expr, env.share()))?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_deep_recursion_uses_heap_stack() {
+        let mut env = Environment::new();
+        let result = eval_all(
+            "(define (count n) (if (= n 0) 0 (+ 1 (count (- n 1)))))
+             (count 10000)",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(result, Value::number(10000.0));
+    }
+
+    #[test]
+    fn test_tail_calls_replace_frames_through_forms() {
+        let mut env = Environment::new();
+        let error = eval_all(
+            "(define (f n) (if (= n 0) (car n) (let ((m (- n 1))) (begin (f m)))))
+             (define (g) (list (f 3)))
+             (g)",
+            &mut env,
+        )
+        .unwrap_err();
+
+        // f calls itself three times, then car, each in tail position
+        let frames = error.backtrace().unwrap().frames();
+        let names: Vec<&str> = frames
+            .iter()
+            .map(|frame| frame.procedure.as_str())
+            .collect();
+        assert_eq!(names, ["car", "g"]);
+        assert_eq!(frames[0].tail_calls_elided, 4);
+    }
+
+    #[test]
+    fn test_call_operands_and_operators() {
+        let mut env = Environment::new();
+
+        // Atoms and quotations are evaluated without suspending the call
+        let result = eval_all("(list (+ 0 1) 2 (car '(3)) '(4))", &mut env).unwrap();
+        assert_eq!(result.to_string(), "(1 2 3 (4))");
+
+        // Operators may be any expression, checked before the operands
+        let result = eval_all("((if #t + -) 1 (* 2 3))", &mut env).unwrap();
+        assert_eq!(result, Value::number(7.0));
+        let error = eval_all("((car '(1)) undefined)", &mut env).unwrap_err();
+        assert_eq!(error.message(), "'1' is not a procedure, got number");
+    }
+
+    #[test]
+    fn test_letrec_scope_outlives_mutual_tail_calls() {
+        let mut env = Environment::new();
+        let result = eval_all(
+            "(define (parity n)
+               (letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1)))))
+                        (odd? (lambda (n) (if (= n 0) #f (even? (- n 1))))))
+                 (even? n)))
+             (parity 1001)",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(result, Value::boolean(false));
+    }
+}
@@ -3,18 +3,48 @@
 //! This module implements the core evaluation logic for Scheme expressions.
 //! It handles atomic values, symbol lookup, list evaluation, and provides
 //! the foundation for procedure calls and special forms.
+//!
+//! Expressions are compiled to bytecode (see `compiler.rs`) and run on a
+//! virtual machine (see `vm.rs`). The tree-walking evaluator it replaced,
+//! an explicit-continuation machine (see `machine.rs`), remains available
+//! for comparison to environments created with
+//! [`Environment::with_evaluator`], and evaluates the special forms the
+//! compiler does not handle. Either way the depth of Scheme recursion is
+//! limited by memory rather than by the Rust stack, and [`eval_async`] can
+//! run an evaluation as a future that yields to its executor.
 
 use crate::error::{Error, Result};
 use crate::parser::Expression;
-use crate::types::{List, Value};
+use crate::types::{DynamicEnvironment, List, Value};
+use smol::future::yield_now;
 use std::sync::Arc;
 
-use super::{Environment, special_forms};
+use super::Environment;
 
+pub(crate) mod analysis;
+pub(crate) mod bytecode;
+mod compiler;
+mod machine;
 pub mod procedure;
+mod vm;
+
+use machine::{Control, Machine};
 
 // Re-export public functions from procedure module
-pub use procedure::{call_procedure, eval_arguments};
+pub use procedure::{apply_procedure, call_procedure, eval_arguments};
+
+/// The evaluators that can run Scheme code
+///
+/// Both produce the same results and errors; the bytecode evaluator is the
+/// default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Evaluator {
+    /// Compile expressions to bytecode and run them on a virtual machine
+    #[default]
+    Bytecode,
+    /// Walk the expression tree with an explicit-continuation machine
+    TreeWalker,
+}
 
 /// Evaluate a Scheme expression in the given environment
 ///
@@ -29,17 +59,55 @@ pub use procedure::{call_procedure, eval_arguments};
 /// * `env` - The environment for symbol lookup
 ///
 /// # Returns
-/// The evaluated value or an error if evaluation fails
+/// The evaluated value or an error if evaluation fails. Errors are annotated
+/// with the source span of the innermost expression that failed.
 pub fn eval(expr: Arc<Expression>, env: &mut Environment) -> Result<Value> {
-    match expr.as_ref() {
-        // Atoms are handled based on their value type
-        Expression::Atom(value) => eval_atom(value.clone(), env),
+    match env.evaluator() {
+        Evaluator::Bytecode => vm::eval(&expr, env),
+        Evaluator::TreeWalker => Machine::new().run(Control::Eval(expr, env.share())),
+    }
+}
 
-        // Lists represent procedure calls or special forms
-        Expression::List(elements) => eval_list(elements, env),
+/// Evaluate a Scheme expression in the given environment as a future
+///
+/// The evaluation runs when the future is polled, with the evaluator of
+/// `env`. It yields to the executor periodically,
+/// so a long evaluation shares its thread with other tasks; each slice of
+/// work still runs synchronously, including nested evaluations such as a
+/// builtin procedure calling back into Scheme code. Results and errors are
+/// the same as those of [`eval`].
+///
+/// The future does not borrow `env`: definitions it makes are visible
+/// through `env` and every other handle to the same scope.
+pub fn eval_async(
+    expr: Arc<Expression>,
+    env: &mut Environment,
+) -> impl Future<Output = Result<Value>> + Send + use<> {
+    let env = env.share();
+    async move {
+        match env.evaluator() {
+            Evaluator::Bytecode => vm::eval_async(expr, env).await,
+            Evaluator::TreeWalker => Machine::new().run_async(Control::Eval(expr, env)).await,
+        }
+    }
+}
 
-        // Quoted expressions prevent evaluation
-        Expression::Quote(quoted_expr) => eval_quote(Arc::clone(quoted_expr)),
+/// Yield to the executor from an evaluation that started in the dynamic
+/// environment `outer`
+///
+/// Other tasks run in `outer` meanwhile rather than in the bindings of a
+/// `parameterize` body the evaluation is suspended in.
+async fn yield_in(outer: &DynamicEnvironment) {
+    let inner = outer.clone().install();
+    yield_now().await;
+    inner.install();
+}
+
+/// Attach an expression's span to an error that does not have one yet
+pub(crate) fn locate_error(error: Error, expr: &Expression) -> Error {
+    match expr.span() {
+        Some(span) => error.with_span(span),
+        None => error,
     }
 }
 
@@ -48,14 +116,16 @@ pub fn eval(expr: Arc<Expression>, env: &mut Environment) -> Result<Value> {
 /// Atoms are evaluated based on their type:
 /// - Numbers, booleans, strings, and lists are self-evaluating
 /// - Symbols are looked up as identifiers in the environment
-fn eval_atom(value: Value, env: &Environment) -> Result<Value> {
+pub(super) fn eval_atom(value: Value, env: &Environment) -> Result<Value> {
     match value {
         // Self-evaluating values
         Value::Number(_)
         | Value::Boolean(_)
         | Value::String(_)
         | Value::List(_)
-        | Value::Procedure(_) => Ok(value),
+        | Value::Procedure(_)
+        | Value::Record(_)
+        | Value::Promise(_) => Ok(value),
 
         // Symbols need environment lookup
         Value::Symbol(identifier) => env.lookup(&identifier),
@@ -65,66 +135,15 @@ fn eval_atom(value: Value, env: &Environment) -> Result<Value> {
     }
 }
 
-/// Evaluate a list expression
-///
-/// Lists represent compound expressions in Scheme:
-/// - Empty lists evaluate to themselves
-/// - Non-empty lists represent special forms or procedure calls: (form/procedure arg1 arg2 ...)
-fn eval_list(elements: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
-    // Empty list evaluates to empty list
-    if elements.is_empty() {
-        return Ok(Value::List(List::new()));
-    }
-
-    // Non-empty lists can be special forms or procedure calls
-    let first_expr = Arc::clone(&elements[0]);
-    let rest_exprs = &elements[1..];
-
-    let procedure_value = match first_expr.as_ref() {
-        Expression::Atom(Value::Symbol(identifier)) => {
-            // Handle special forms first (these have special evaluation rules)
-            if let Some(special_form) = special_forms::SpecialForm::from_name(identifier.as_str()) {
-                return special_form.call(rest_exprs, env);
-            }
-
-            // Not a special form, try to look up identifier in environment
-            env.lookup(identifier)?
-        }
-        _ => eval(Arc::clone(&first_expr), env)?,
-    };
-
-    // Check if the value is a procedure and call it
-    match procedure_value {
-        Value::Procedure(procedure) => call_procedure(procedure, rest_exprs, env),
-        _ => {
-            let error_msg = format!(
-                "'{}' is not a procedure, got {}",
-                procedure_value,
-                procedure_value.type_name()
-            );
-            Err(Error::runtime_error(&error_msg))
-        }
-    }
-}
-
-/// Evaluate a quoted expression
-///
-/// Quoted expressions prevent evaluation and return the quoted expression
-/// as a value without evaluating it.
-fn eval_quote(expr: Arc<Expression>) -> Result<Value> {
-    // Convert the quoted expression back to a Value
-    expression_to_value(expr.as_ref())
-}
-
 /// Convert an Expression back to a Value
 ///
 /// This is used for quote evaluation where we need to return
 /// the quoted expression as a value without evaluating it.
-fn expression_to_value(expr: &Expression) -> Result<Value> {
+pub(super) fn expression_to_value(expr: &Expression) -> Result<Value> {
     match expr {
-        Expression::Atom(value) => Ok(value.clone()),
+        Expression::Atom(value, _) => Ok(value.clone()),
 
-        Expression::List(elements) => {
+        Expression::List(elements, _) => {
             let mut values = Vec::with_capacity(elements.len());
             for element in elements {
                 values.push(expression_to_value(element.as_ref())?);
@@ -132,7 +151,7 @@ fn expression_to_value(expr: &Expression) -> Result<Value> {
             Ok(Value::List(List::from(values)))
         }
 
-        Expression::Quote(quoted_expr) => {
+        Expression::Quote(quoted_expr, _) => {
             // Nested quotes - convert the inner expression
             expression_to_value(quoted_expr.as_ref())
         }
@@ -140,6 +159,7 @@ fn expression_to_value(expr: &Expression) -> Result<Value> {
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
@@ -251,7 +271,7 @@ mod tests {
     #[test]
     fn test_eval_non_symbol_procedure() {
         match eval_source("(42 1 2)") {
-            Err(Error::RuntimeError(message)) => {
+            Err(Error::RuntimeError { message, .. }) => {
                 assert!(message.contains("is not a procedure"));
                 assert!(message.contains("42"));
             }
@@ -260,6 +280,102 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_error_located_at_innermost_form() {
+        use crate::lexer::Position;
+
+        let mut env = Environment::new();
+        let source = "(define f (lambda (x)\n  (+ 1 (car x))))";
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+
+        let mut parser = Parser::new("(f 5)".to_string())
+            .unwrap()
+            .with_source_name("call.scm");
+        let error = eval(parser.parse_expression().unwrap().expr, &mut env).unwrap_err();
+
+        // The span points into the lambda body, not the call site
+        let span = error.span().unwrap();
+        assert_eq!(span.start, Position::new(2, 8));
+        assert_eq!(span.source, None);
+
+        // Unbound identifiers are located at the identifier itself
+        let mut parser = Parser::new("(+ 1\n   missing)".to_string()).unwrap();
+        let error = eval(parser.parse_expression().unwrap().expr, &mut env).unwrap_err();
+        assert_eq!(error.span().unwrap().start, Position::new(2, 4));
+    }
+
+    #[test]
+    fn test_eval_error_located_at_tail_call() {
+        use crate::lexer::Position;
+
+        let mut env = Environment::new();
+        let source = "(define f (lambda (x)\n  (car x)))\n(f 5)";
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+        let error = eval(parser.parse_expression().unwrap().expr, &mut env).unwrap_err();
+
+        assert_eq!(error.span().unwrap().start, Position::new(2, 3));
+    }
+
+    #[test]
+    fn test_eval_error_backtrace() {
+        use crate::lexer::Position;
+
+        let mut env = Environment::new();
+        let source = "(define inner (lambda (x) (+ 1 (car x))))
+(define outer (lambda (x) (* 2 (inner x))))
+(outer 5)";
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+        eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+        let error = eval(parser.parse_expression().unwrap().expr, &mut env).unwrap_err();
+
+        // Frames are listed from the failing builtin outwards, named as called
+        let frames = error.backtrace().unwrap().frames();
+        let names: Vec<&str> = frames
+            .iter()
+            .map(|frame| frame.procedure.as_str())
+            .collect();
+        assert_eq!(names, ["car", "inner", "outer"]);
+        assert_eq!(
+            frames[1].call_site.as_ref().unwrap().start,
+            Position::new(2, 32)
+        );
+        assert!(frames.iter().all(|frame| frame.tail_calls_elided == 0));
+
+        // Errors outside any procedure call have no backtrace
+        assert!(
+            eval_source("undefined-identifier")
+                .unwrap_err()
+                .backtrace()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_eval_backtrace_elides_tail_calls() {
+        let mut env = Environment::new();
+        let source = "(define c (lambda (x) (car x)))
+(define b (lambda (x) (c x)))
+(define a (lambda (x) (b x)))
+(define top (lambda () (list (a 1))))
+(top)";
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        for _ in 0..4 {
+            eval(parser.parse_expression().unwrap().expr, &mut env).unwrap();
+        }
+        let error = eval(parser.parse_expression().unwrap().expr, &mut env).unwrap_err();
+
+        // a, b and c were each replaced by their tail call
+        let frames = error.backtrace().unwrap().frames();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].procedure, "car");
+        assert_eq!(frames[0].tail_calls_elided, 3);
+        // Arguments are evaluated before list is called, so top is the caller
+        assert_eq!(frames[1].procedure, "top");
+    }
+
     #[test]
     fn test_eval_nested_expressions() {
         // Test deeply nested expressions
@@ -370,7 +486,7 @@ mod tests {
             Value::Number(Number::new(2.0)),
             Value::Number(Number::new(3.0)),
         ]);
-        let expr = Arc::new(Expression::Atom(Value::List(list.clone())));
+        let expr = Expression::arc_atom(Value::List(list.clone()));
 
         assert_eq!(eval(expr, &mut env).unwrap(), Value::List(list));
     }
@@ -475,6 +591,7 @@ mod tests {
                 procedure,
                 expected,
                 actual,
+                ..
             }) => {
                 assert_eq!(procedure, "if");
                 assert_eq!(expected, 3);
@@ -489,6 +606,7 @@ mod tests {
                 procedure,
                 expected,
                 actual,
+                ..
             }) => {
                 assert_eq!(procedure, "if");
                 assert_eq!(expected, 3);
@@ -567,7 +685,8 @@ mod tests {
             where factorial = (lambda (n acc)
               (if (= n 0)
                   acc
-                  (factorial (- n 1) (* acc n))))".to_string();
+                  (factorial (- n 1) (* acc n))))"
+            .to_string();
 
         // For now, test a simpler tail call scenario
         let simple_tail_call = "
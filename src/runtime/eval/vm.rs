@@ -0,0 +1,1299 @@
+//! Virtual machine executing compiled bytecode
+//!
+//! The machine keeps a stack of values and a stack of call frames, both on
+//! the heap, so like the tree-walking evaluator its recursion depth is
+//! limited only by memory. A frame's slots are the stack positions just
+//! above the procedure being called: the arguments become the first slots
+//! in place. The body of a promise applied to `force` runs in a frame of
+//! its own, and a `parameterize` body saves the dynamic environment in the
+//! frame running it, so neither nests an evaluation on the Rust stack.
+//!
+//! A tail call reuses the caller's frame, counting the calls it replaced for
+//! backtraces, so tail calls run in constant space. Errors are located and
+//! recorded in backtraces as the tree-walking evaluator does.
+//!
+//! The machine can also run in slices of procedure calls, so that an
+//! asynchronous evaluation can yield to its executor between them. A slice
+//! also ends when a `force` finds another evaluation forcing the promise:
+//! an asynchronous evaluation yields and tries again, so that fibers on the
+//! same thread can finish forcing it, and any other blocks until it is
+//! forced.
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::parser::Expression;
+use crate::runtime::Environment;
+use crate::runtime::builtins::Builtin;
+use crate::runtime::builtins::promise::{abandon_pending, next_in_chain, resolve_pending};
+use crate::runtime::environment::strengthen;
+use crate::runtime::interrupt::{InterruptHandle, procedure_interrupt};
+use crate::runtime::limits::{Budget, called_from, outer_depth, outer_interrupt, procedure_budget};
+use crate::runtime::special_forms::parameter::parameter_binding;
+use crate::types::{
+    DynamicEnvironment, EvaluationId, ForceStep, Lambda, Procedure, Promise, PromiseKind, Symbol,
+    Value,
+};
+use std::cmp::Ordering;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use super::bytecode::{Capture, Code, Op};
+use super::compiler::{compile, compile_lambda};
+use super::machine::{Control, Machine};
+use super::procedure::call_frame;
+use super::{locate_error, yield_in};
+
+/// Compile an expression and run it in `env`
+pub(super) fn eval(expr: &Arc<Expression>, env: &Environment) -> Result<Value> {
+    let mut vm = Vm::evaluating(expr, env);
+    vm.execute().map_err(|error| vm.unwind(error))
+}
+
+/// Compile an expression and run it in `env`, yielding to the executor
+/// after every [`SLICE_CALLS`] procedure calls
+///
+/// Forms the compiler leaves to the tree-walking evaluator, and nested
+/// evaluations by builtin procedures, run to completion within a slice.
+pub(super) async fn eval_async(expr: Arc<Expression>, env: Environment) -> Result<Value> {
+    let mut vm = Vm::evaluating(&expr, &env);
+    let outer = DynamicEnvironment::current();
+    loop {
+        match vm.execute_slice(SLICE_CALLS) {
+            Ok(Some(value)) => return Ok(value),
+            Ok(None) => yield_in(&outer).await,
+            Err(error) => return Err(vm.unwind(error)),
+        }
+    }
+}
+
+/// Apply a procedure to evaluated arguments
+///
+/// `call` is the call expression, if there is one.
+pub(super) fn apply(
+    procedure: Procedure,
+    args: Vec<Value>,
+    call: Option<Arc<Expression>>,
+) -> Result<Value> {
+    let mut vm = Vm {
+        call,
+        budget: procedure_budget(&procedure),
+        interrupt: outer_interrupt().or_else(|| procedure_interrupt(&procedure)),
+        outer_depth: outer_depth(),
+        ..Vm::default()
+    };
+    let argc = args.len();
+    vm.stack.push(Value::Procedure(procedure));
+    vm.stack.extend(args);
+
+    // Other procedures than lambdas and forced promises return at once
+    let result = vm.call(argc, false).and_then(|value| match value {
+        Some(value) => Ok(value),
+        None if vm.frames.is_empty() && vm.waiting.is_none() => {
+            Ok(vm.stack.pop().unwrap_or(Value::Nil))
+        }
+        None => vm.execute(),
+    });
+    result.map_err(|error| vm.unwind(error))
+}
+
+/// How many procedure calls [`eval_async`] makes before yielding
+const SLICE_CALLS: usize = 1024;
+
+/// The value and call stacks of the virtual machine
+#[derive(Default)]
+struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// The call expression of a procedure applied by [`apply`]
+    call: Option<Arc<Expression>>,
+    /// The resource limits of the code being run
+    budget: Option<Arc<Budget>>,
+    /// The interrupt flag of the evaluation this is, or is nested in
+    interrupt: Option<InterruptHandle>,
+    /// Number of frames evaluating the body of a promise
+    forcing: usize,
+    /// Lambda calls in progress in the evaluations this one is nested in
+    outer_depth: usize,
+    /// The evaluation this is, or is nested in, which owns the promises
+    /// it forces
+    evaluation: EvaluationId,
+    /// A `force` to retry when the machine next runs
+    waiting: Option<Waiting>,
+}
+
+/// A procedure call in progress, the top-level expression, or the body of
+/// a promise being forced
+struct Frame {
+    code: Arc<Code>,
+    /// Index of the next operation
+    ip: usize,
+    /// Stack index of the procedure called, where its result goes
+    start: usize,
+    /// Stack index of the first slot
+    base: usize,
+    /// Environment bindings are looked up by name in
+    env: Environment,
+    /// Nested scopes of `env`, innermost last, when locals are not slotted
+    scopes: Vec<Environment>,
+    /// The call, for backtraces; None for the top-level expression
+    call: Option<CallInfo>,
+    /// The running lambda
+    ///
+    /// Keeps it alive: references to it from its own scope, as bound by
+    /// `define` or `letrec`, are weak.
+    lambda: Option<Arc<Lambda>>,
+    /// The promise whose body this is, for a frame forcing one
+    forcing: Option<Forcing>,
+    /// Dynamic environments saved by `parameterize` bodies running here,
+    /// outermost first; the first is restored when the frame returns
+    dynamic: Vec<DynamicEnvironment>,
+}
+
+/// A promise being forced for a call to `force`
+struct Forcing {
+    /// The promises claimed along its `delay-force` chain, which get the
+    /// final value
+    pending: Vec<Arc<Promise>>,
+    kind: PromiseKind,
+    /// Whether `force` was called in tail position
+    tail: bool,
+}
+
+/// A `force` of a promise that another evaluation is forcing
+struct Waiting {
+    promise: Arc<Promise>,
+    /// The promises claimed along its `delay-force` chain so far
+    pending: Vec<Arc<Promise>>,
+    /// Whether `force` was called in tail position
+    tail: bool,
+}
+
+/// Where a frame's procedure was called from
+struct CallInfo {
+    expr: Option<Arc<Expression>>,
+    /// Name of a procedure that failed when called in place of the frame's
+    name: Option<Symbol>,
+    tail_calls: usize,
+}
+
+impl Frame {
+    /// The innermost scope of the environment
+    fn env(&self) -> &Environment {
+        self.scopes.last().unwrap_or(&self.env)
+    }
+
+    fn env_mut(&mut self) -> &mut Environment {
+        match self.scopes.last_mut() {
+            Some(scope) => scope,
+            None => &mut self.env,
+        }
+    }
+
+    /// An environment with the captured slots bound, for a form evaluated
+    /// by the tree-walking evaluator
+    ///
+    /// Without slots, it is the current environment itself.
+    fn capture(&self, capture: u32, stack: &[Value]) -> Environment {
+        if !self.code.slotted {
+            return self.env().share();
+        }
+        let mut env = Environment::new_scope(&self.env);
+        for (name, slot) in &self.code.captures[capture as usize] {
+            env.define(name.clone(), stack[self.base + *slot as usize].clone());
+        }
+        env
+    }
+
+    /// The expression the last operation was compiled from
+    fn source(&self) -> Option<&Arc<Expression>> {
+        let index = self.ip.checked_sub(1)?;
+        self.code.sources[index].expr.as_ref()
+    }
+}
+
+impl Vm {
+    /// A machine ready to run an expression compiled for `env`
+    fn evaluating(expr: &Arc<Expression>, env: &Environment) -> Self {
+        let code = compile(expr, env);
+        let mut vm = Vm {
+            budget: env.budget(),
+            interrupt: Some(outer_interrupt().unwrap_or_else(|| env.interrupt_handle())),
+            outer_depth: outer_depth(),
+            ..Vm::default()
+        };
+        vm.frames.push(Frame {
+            code,
+            ip: 0,
+            start: 0,
+            base: 0,
+            env: env.share(),
+            scopes: Vec::new(),
+            call: None,
+            lambda: None,
+            forcing: None,
+            dynamic: Vec::new(),
+        });
+        vm
+    }
+
+    /// Run until the outermost frame returns
+    ///
+    /// Blocks the thread while a `force` waits for another evaluation.
+    fn execute(&mut self) -> Result<Value> {
+        loop {
+            if let Some(value) = self.execute_slice(usize::MAX)? {
+                return Ok(value);
+            }
+            if let Some(waiting) = &self.waiting {
+                waiting.promise.wait(self.evaluation);
+            }
+        }
+    }
+
+    /// Run until the outermost frame returns, or until `calls` procedure
+    /// calls have been made
+    ///
+    /// Returns None if the calls ran out first, or if a `force` must wait
+    /// for another evaluation to force its promise; the machine can then be
+    /// run again to continue.
+    fn execute_slice(&mut self, mut calls: usize) -> Result<Option<Value>> {
+        if let Some(Waiting {
+            promise,
+            pending,
+            tail,
+        }) = self.waiting.take()
+            && let Some(value) = self.force(promise, pending, tail)?
+        {
+            return Ok(Some(value));
+        }
+        loop {
+            if self.waiting.is_some() {
+                return Ok(None);
+            }
+            if let Some(interrupt) = &self.interrupt {
+                interrupt.check()?;
+            }
+            if let Some(budget) = &self.budget {
+                budget.step()?;
+            }
+            let frame = self.frames.last_mut().expect("a frame is running");
+            let op = frame.code.ops[frame.ip];
+            frame.ip += 1;
+
+            match op {
+                Op::Constant(index) => {
+                    let value = frame.code.constants[index as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::Local(slot) => {
+                    let value = strengthen(self.stack[frame.base + slot as usize].clone());
+                    self.stack.push(value);
+                }
+                Op::Captured(index) => {
+                    let value = frame.env.lookup_index(index as usize);
+                    self.stack.push(value);
+                }
+                Op::Global(index) => {
+                    let name = &frame.code.names[index as usize];
+                    let value = frame.env().lookup_near(name, &frame.code.locals)?;
+                    self.stack.push(value);
+                }
+                Op::Callee(index) => {
+                    // An unbound operator is located at the identifier itself
+                    let name = &frame.code.names[index as usize];
+                    let value =
+                        frame
+                            .env()
+                            .lookup_near(name, &frame.code.locals)
+                            .map_err(|error| {
+                                match frame.source().and_then(|call| call.as_list()) {
+                                    Some(elements) => locate_error(error, &elements[0]),
+                                    None => error,
+                                }
+                            })?;
+                    check_procedure(&value)?;
+                    self.stack.push(value);
+                }
+                Op::CheckProcedure => check_procedure(self.stack.last().expect("operator"))?,
+                Op::SetLocal(slot) => {
+                    let value = self.stack.pop().expect("value to bind");
+                    self.stack[frame.base + slot as usize] = value;
+                }
+                Op::Define(index) => {
+                    let value = self.stack.pop().expect("value to bind");
+                    let name = frame.code.names[index as usize].clone();
+                    frame.env_mut().define(name, value);
+                }
+                Op::Pop => {
+                    self.stack.pop();
+                }
+                Op::Placeholder => {
+                    self.stack
+                        .push(Value::Procedure(Procedure::forward_lambda()));
+                }
+                Op::Fill => {
+                    let placeholder = self.stack.pop().expect("placeholder");
+                    if let (
+                        Value::Procedure(placeholder),
+                        Some(Value::Procedure(Procedure::Lambda(lambda))),
+                    ) = (placeholder, self.stack.last())
+                        && placeholder.is_placeholder()
+                    {
+                        placeholder.fill_placeholder(lambda)?;
+                    }
+                }
+
+                Op::Jump(target) => frame.ip = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !self.stack.pop().expect("test").is_truthy() {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::JumpIfFalseElsePop(target) => {
+                    if self.stack.last().expect("operand").is_truthy() {
+                        self.stack.pop();
+                    } else {
+                        frame.ip = target as usize;
+                    }
+                }
+                Op::JumpIfTrueElsePop(target) => {
+                    if self.stack.last().expect("operand").is_truthy() {
+                        frame.ip = target as usize;
+                    } else {
+                        self.stack.pop();
+                    }
+                }
+
+                Op::Parameterize(count) => {
+                    let tail = frame.code.sources[frame.ip - 1].tail;
+                    let index = self.stack.len() - 2 * count as usize;
+                    let values = self.stack.split_off(index);
+                    let bindings = self.call_out(|| {
+                        let mut bindings = Vec::with_capacity(count as usize);
+                        let mut values = values.into_iter();
+                        while let (Some(parameter), Some(value)) = (values.next(), values.next()) {
+                            bindings.push(parameter_binding(parameter, value)?);
+                        }
+                        Ok::<_, Error>(bindings)
+                    })?;
+                    let previous = DynamicEnvironment::current().extend(bindings).install();
+
+                    // In tail position, the environment is restored when the
+                    // frame returns, so an enclosing body's suffices
+                    let frame = self.frames.last_mut().expect("a frame is running");
+                    if !tail || frame.dynamic.is_empty() {
+                        frame.dynamic.push(previous);
+                    }
+                }
+                Op::Unparameterize => {
+                    let previous = frame.dynamic.pop().expect("a parameterize body is running");
+                    previous.install();
+                }
+
+                Op::EnterScope => {
+                    let scope = Environment::new_scope(frame.env());
+                    frame.scopes.push(scope);
+                }
+                Op::ExitScope => {
+                    frame.scopes.pop();
+                }
+
+                Op::Closure { template, name } => {
+                    let template = &frame.code.templates[template as usize];
+
+                    // A named procedure can refer to itself, weakly
+                    let weak_lambda = template
+                        .captures
+                        .iter()
+                        .any(|(_, capture)| *capture == Capture::Itself)
+                        .then(Procedure::weak_lambda);
+                    let captured = template
+                        .captures
+                        .iter()
+                        .map(|(identifier, capture)| {
+                            let value = match *capture {
+                                Capture::Local(slot) => {
+                                    self.stack[frame.base + slot as usize].clone()
+                                }
+                                Capture::Captured(index) => frame.env.lookup_index(index as usize),
+                                Capture::Itself => {
+                                    Value::Procedure(weak_lambda.clone().expect("weak lambda"))
+                                }
+                            };
+                            (identifier.clone(), value)
+                        })
+                        .collect();
+                    let env = Environment::new_closure_with(
+                        captured,
+                        frame.env(),
+                        &template.named,
+                        &template.lexical,
+                    );
+
+                    let name = name.map(|index| frame.code.names[index as usize].clone());
+                    let lambda = Lambda::compiled(
+                        name,
+                        template.params.clone(),
+                        template.body.clone(),
+                        env,
+                        Arc::clone(&template.code),
+                    );
+                    if let Some(weak_lambda) = weak_lambda {
+                        weak_lambda.set_weak_lambda(&lambda)?;
+                    }
+                    self.stack.push(Value::Procedure(Procedure::Lambda(lambda)));
+                }
+                Op::Insert(count) => {
+                    let value = self.stack.pop().expect("value to insert");
+                    let index = self.stack.len() - count as usize;
+                    self.stack.insert(index, value);
+                }
+
+                Op::Call(argc) => {
+                    self.call(argc as usize, false)?;
+                    calls -= 1;
+                    if calls == 0 {
+                        return Ok(None);
+                    }
+                }
+                Op::TailCall(argc) => {
+                    if let Some(value) = self.call(argc as usize, true)? {
+                        return Ok(Some(value));
+                    }
+                    calls -= 1;
+                    if calls == 0 {
+                        return Ok(None);
+                    }
+                }
+                Op::Builtin(builtin, argc) => {
+                    if let Some(procedure) = self.rebound(builtin)? {
+                        match self.call_instead(procedure, argc as usize)? {
+                            Some(value) => return Ok(Some(value)),
+                            None => continue,
+                        }
+                    }
+                    if builtin == Builtin::Force
+                        && argc == 1
+                        && let Some(Value::Promise(promise)) = self.stack.last()
+                    {
+                        let promise = Arc::clone(promise);
+                        self.stack.pop();
+                        let frame = self.frames.last().expect("a frame is running");
+                        let tail = frame.code.sources[frame.ip - 1].tail;
+                        match self.force(promise, Vec::new(), tail)? {
+                            Some(value) => return Ok(Some(value)),
+                            None => continue,
+                        }
+                    }
+                    let index = self.stack.len() - argc as usize;
+                    let result = self.call_out(|| builtin.call(&self.stack[index..]));
+                    let result = result.and_then(|value| {
+                        self.check_allocation(&value)?;
+                        Ok(value)
+                    });
+                    self.stack.truncate(index);
+                    let value = result.map_err(|error| self.builtin_failed(error, op))?;
+                    self.stack.push(value);
+                }
+                Op::Add
+                | Op::Subtract
+                | Op::Multiply
+                | Op::Equal
+                | Op::Less
+                | Op::Greater
+                | Op::LessEqual
+                | Op::GreaterEqual => {
+                    let builtin = op.builtin().expect("specialised operation");
+                    if let Some(procedure) = self.rebound(builtin)? {
+                        match self.call_instead(procedure, 2)? {
+                            Some(value) => return Ok(Some(value)),
+                            None => continue,
+                        }
+                    }
+                    let index = self.stack.len() - 2;
+                    let result = binary(op, &self.stack[index..]);
+                    self.stack.truncate(index);
+                    let value = result.map_err(|error| self.builtin_failed(error, op))?;
+                    self.stack.push(value);
+                }
+
+                Op::Eval { form, capture } => {
+                    let env = frame.capture(capture, &self.stack);
+                    let form = Arc::clone(&frame.code.forms[form as usize]);
+                    let value = self.call_out(|| Machine::new().run(Control::Eval(form, env)))?;
+                    self.stack.push(value);
+                }
+
+                Op::Return => {
+                    let value = self.stack.pop().expect("value to return");
+                    if let Some(value) = self.return_value(value)? {
+                        return Ok(Some(value));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return a value from the current frame
+    ///
+    /// Returns the value back if the frame was the outermost.
+    fn return_value(&mut self, value: Value) -> Result<Option<Value>> {
+        let frame = self.frames.pop().expect("a frame is running");
+        self.stack.truncate(frame.start);
+        if let Some(dynamic) = frame.dynamic.into_iter().next() {
+            dynamic.install();
+        }
+        if let Some(forcing) = frame.forcing {
+            self.forcing -= 1;
+            return self.forced(forcing, value);
+        }
+        self.deliver(value, false)
+    }
+
+    /// Pass on the value of a call made by the current frame, returning it
+    /// from the frame for a call in tail position
+    ///
+    /// Returns the value back if there is no frame left to pass it to.
+    fn deliver(&mut self, value: Value, tail: bool) -> Result<Option<Value>> {
+        if tail {
+            return self.return_value(value);
+        }
+        if self.frames.is_empty() {
+            return Ok(Some(value));
+        }
+        self.stack.push(value);
+        Ok(None)
+    }
+
+    /// Force a promise for a call to `force`, evaluating its body in a
+    /// frame of its own if it has no value yet
+    ///
+    /// `pending` are the promises claimed along its chain so far. Returns
+    /// the value back if there is no frame left to pass it to. If another
+    /// evaluation is forcing the promise, the force waits to be retried.
+    fn force(
+        &mut self,
+        promise: Arc<Promise>,
+        mut pending: Vec<Arc<Promise>>,
+        tail: bool,
+    ) -> Result<Option<Value>> {
+        let thunk = match promise.claim(self.evaluation) {
+            ForceStep::Ready(value) => return self.deliver(resolve_pending(&pending, value), tail),
+            ForceStep::Evaluate(thunk) => thunk,
+            ForceStep::Busy => {
+                self.waiting = Some(Waiting {
+                    promise,
+                    pending,
+                    tail,
+                });
+                return Ok(None);
+            }
+        };
+        pending.push(promise);
+
+        let env = Environment::new_scope(thunk.env());
+        let start = self.stack.len();
+        self.frames.push(Frame {
+            code: compile(thunk.body(), &env),
+            ip: 0,
+            start,
+            base: start,
+            env,
+            scopes: Vec::new(),
+            call: None,
+            lambda: None,
+            forcing: Some(Forcing {
+                pending,
+                kind: thunk.kind(),
+                tail,
+            }),
+            dynamic: Vec::new(),
+        });
+        self.forcing += 1;
+        Ok(None)
+    }
+
+    /// Continue forcing a promise with the value of its body
+    fn forced(&mut self, forcing: Forcing, value: Value) -> Result<Option<Value>> {
+        let Forcing {
+            pending,
+            kind,
+            tail,
+        } = forcing;
+        match next_in_chain(kind, value) {
+            Ok(ControlFlow::Break(value)) => self.deliver(resolve_pending(&pending, value), tail),
+            Ok(ControlFlow::Continue(next)) => self.force(next, pending, tail),
+            Err(error) => {
+                abandon_pending(&pending);
+                Err(self.call_failed(error, Some(Symbol::new(Builtin::Force.name())), tail))
+            }
+        }
+    }
+
+    /// Call the procedure below the top `argc` values of the stack
+    ///
+    /// Lambdas get a new frame, or the current one for a tail call; other
+    /// procedures are called at once. Returns the value of a tail call that
+    /// returned from the outermost frame.
+    fn call(&mut self, argc: usize, tail: bool) -> Result<Option<Value>> {
+        let index = self.stack.len() - argc - 1;
+        let procedure = match &self.stack[index] {
+            Value::Procedure(procedure) => procedure.clone(),
+            other => return Err(not_a_procedure(other)),
+        };
+        if let Procedure::Builtin(Builtin::Force) = procedure
+            && let [Value::Promise(promise)] = &self.stack[index + 1..]
+        {
+            let promise = Arc::clone(promise);
+            self.stack.truncate(index);
+            return self.force(promise, Vec::new(), tail);
+        }
+
+        let result = match &procedure {
+            Procedure::Builtin(builtin) => self
+                .call_out(|| builtin.call(&self.stack[index + 1..]))
+                .and_then(|value| self.check_allocation(&value).map(|()| value)),
+            Procedure::Native(native) => self
+                .call_out(|| native.call(&self.stack[index + 1..]))
+                .and_then(|value| self.check_allocation(&value).map(|()| value)),
+            Procedure::Record(record_proc) => record_proc.call(&self.stack[index + 1..]),
+            Procedure::Parameter(parameter) => parameter.call(&self.stack[index + 1..]),
+            Procedure::Lambda(_) | Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => {
+                return self.enter(&procedure, argc, tail).map(|_| None);
+            }
+        };
+        self.stack.truncate(index);
+        let value =
+            result.map_err(|error| self.call_failed(error, procedure.defined_name(), tail))?;
+        self.deliver(value, tail)
+    }
+
+    /// Start running a lambda whose arguments are on the stack
+    fn enter(&mut self, procedure: &Procedure, argc: usize, tail: bool) -> Result<()> {
+        let lambda = procedure
+            .resolve_weak_lambda()
+            .map_err(|error| self.call_failed(error, procedure.defined_name(), tail))?;
+
+        // Check arity
+        if argc != lambda.arity() {
+            let name = lambda.name().map_or("<lambda>", Symbol::as_str);
+            let error = Error::arity_error(name, lambda.arity(), argc);
+            return Err(self.call_failed(error, lambda.name().cloned(), tail));
+        }
+        if lambda.body().is_empty() {
+            let error = Error::runtime_error("Lambda body cannot be empty");
+            return Err(self.call_failed(error, lambda.name().cloned(), tail));
+        }
+        if let Some(budget) = &self.budget
+            && let Err(error) = budget.check_depth(self.depth(tail))
+        {
+            return Err(self.call_failed(error, lambda.name().cloned(), tail));
+        }
+
+        let code = Arc::clone(lambda.code(compile_lambda));
+        let call = self.call_site();
+
+        // A call in tail position replaces the caller's frame
+        let callee = self.stack.len() - argc - 1;
+        let start = match self.frames.last() {
+            Some(frame) if tail => {
+                self.stack.drain(frame.start..callee);
+                frame.start
+            }
+            _ => callee,
+        };
+
+        // Bind the arguments in slots or in a new scope of the closure
+        let base = start + 1;
+        let env = if code.slotted {
+            self.stack.resize(base + code.slots as usize, Value::Nil);
+            lambda.env().share()
+        } else {
+            let mut env = Environment::new_scope(lambda.env());
+            for (param, arg) in lambda.params().iter().zip(self.stack.drain(base..)) {
+                env.define(param.clone(), arg);
+            }
+            env
+        };
+
+        let tail_calls = match self.frames.last() {
+            Some(Frame {
+                call: Some(current),
+                ..
+            }) if tail => current.tail_calls + 1,
+            _ => 0,
+        };
+        let mut frame = Frame {
+            code,
+            ip: 0,
+            start,
+            base,
+            env,
+            scopes: Vec::new(),
+            call: Some(CallInfo {
+                expr: call,
+                name: None,
+                tail_calls,
+            }),
+            lambda: Some(lambda),
+            forcing: None,
+            dynamic: Vec::new(),
+        };
+        match self.frames.last_mut() {
+            Some(current) if tail => {
+                // The bodies running in tail position run on in the callee
+                frame.dynamic = std::mem::take(&mut current.dynamic);
+                *current = frame;
+            }
+            _ => self.frames.push(frame),
+        }
+        Ok(())
+    }
+
+    /// How many lambda calls are in progress, including those of the
+    /// evaluations this one is nested in
+    fn calls(&self) -> usize {
+        // Only the outermost frame can be a top-level expression
+        let top_level = self
+            .frames
+            .first()
+            .is_some_and(|frame| frame.call.is_none() && frame.forcing.is_none());
+        self.outer_depth + self.frames.len() - usize::from(top_level) - self.forcing
+    }
+
+    /// How many lambda calls will be in progress once one is entered
+    fn depth(&self, tail: bool) -> usize {
+        let replaced = tail && self.frames.last().is_some_and(|frame| frame.call.is_some());
+        self.calls() + 1 - usize::from(replaced)
+    }
+
+    /// Run a builtin, native procedure or special form, which may nest
+    /// evaluations within the calls in progress here
+    fn call_out<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.evaluation.run(|| {
+            called_from(
+                self.budget.as_ref(),
+                self.interrupt.as_ref(),
+                self.calls(),
+                f,
+            )
+        })
+    }
+
+    /// Check the size of a value returned by a builtin or native procedure
+    fn check_allocation(&self, value: &Value) -> Result<()> {
+        match &self.budget {
+            Some(budget) => budget.check_allocation(value),
+            None => Ok(()),
+        }
+    }
+
+    /// What the name of a builtin called by a specialised operation is
+    /// bound to, if it has been redefined since the code was compiled
+    fn rebound(&self, builtin: Builtin) -> Result<Option<Value>> {
+        let frame = self.frames.last().expect("a frame is running");
+        if !frame.env.builtins_rebound() {
+            return Ok(None);
+        }
+        let value = frame.env().lookup(&Symbol::new(builtin.name()))?;
+        Ok(match &value {
+            Value::Procedure(Procedure::Builtin(bound)) if *bound == builtin => None,
+            _ => Some(value),
+        })
+    }
+
+    /// Call what a builtin has been redefined as, with the arguments of a
+    /// specialised operation on the stack
+    fn call_instead(&mut self, procedure: Value, argc: usize) -> Result<Option<Value>> {
+        check_procedure(&procedure)?;
+        let frame = self.frames.last().expect("a frame is running");
+        let tail = frame.code.sources[frame.ip - 1].tail;
+        let index = self.stack.len() - argc;
+        self.stack.insert(index, procedure);
+        self.call(argc, tail)
+    }
+
+    /// The expression of the call being made
+    fn call_site(&self) -> Option<Arc<Expression>> {
+        match self.frames.last() {
+            Some(frame) => frame.source().cloned(),
+            None => self.call.clone(),
+        }
+    }
+
+    /// Record a failed call to a builtin made by a specialised operation
+    fn builtin_failed(&mut self, error: Error, op: Op) -> Error {
+        let frame = self.frames.last().expect("a frame is running");
+        let tail = frame.code.sources[frame.ip - 1].tail;
+        let name = op.builtin().map(|builtin| Symbol::new(builtin.name()));
+        self.call_failed(error, name, tail)
+    }
+
+    /// Record a failed call to the procedure called `name`
+    ///
+    /// The error is located at the call and gets a backtrace frame for it,
+    /// which replaces the caller's for a call in tail position.
+    fn call_failed(&mut self, error: Error, name: Option<Symbol>, tail: bool) -> Error {
+        let call = self.call_site();
+        let error = match &call {
+            Some(call) => locate_error(error, call),
+            None => error,
+        };
+
+        match self.frames.last_mut() {
+            Some(Frame {
+                call: Some(current),
+                lambda,
+                ..
+            }) if tail => {
+                current.expr = call;
+                current.name = name;
+                current.tail_calls += 1;
+                *lambda = None;
+                error
+            }
+            _ => error.push_frame(call_frame(
+                call.as_ref(),
+                name.as_ref().map(Symbol::as_str),
+                0,
+            )),
+        }
+    }
+
+    /// Discard the frames after an error, recording where it happened
+    ///
+    /// Every procedure call in progress contributes a backtrace frame, most
+    /// recent first.
+    fn unwind(&mut self, mut error: Error) -> Error {
+        if let Some(waiting) = self.waiting.take() {
+            abandon_pending(&waiting.pending);
+        }
+        while let Some(frame) = self.frames.pop() {
+            if let Some(expr) = frame.source() {
+                error = locate_error(error, expr);
+            }
+            if let Some(dynamic) = frame.dynamic.first() {
+                dynamic.clone().install();
+            }
+            if let Some(forcing) = &frame.forcing {
+                abandon_pending(&forcing.pending);
+                let force = Symbol::new(Builtin::Force.name());
+                error = self.call_failed(error, Some(force), forcing.tail);
+            }
+            if let Some(call) = frame.call {
+                let name = call
+                    .name
+                    .or_else(|| frame.lambda.and_then(|lambda| lambda.name().cloned()));
+                error = error.push_frame(call_frame(
+                    call.expr.as_ref(),
+                    name.as_ref().map(Symbol::as_str),
+                    call.tail_calls,
+                ));
+            }
+        }
+        self.stack.clear();
+        self.forcing = 0;
+        error
+    }
+}
+
+/// Apply a specialised arithmetic or comparison operation
+///
+/// Numbers are handled directly, with the same results as the builtins;
+/// other arguments are passed to the builtin for it to report.
+fn binary(op: Op, args: &[Value]) -> Result<Value> {
+    let (Value::Number(a), Value::Number(b)) = (&args[0], &args[1]) else {
+        return op.builtin().expect("specialised operation").call(args);
+    };
+    let (a, b) = (a.value(), b.value());
+    let ordering = a.partial_cmp(&b);
+    Ok(match op {
+        Op::Add => Value::number(0.0 + a + b),
+        Op::Subtract => Value::number(a - b),
+        Op::Multiply => Value::number(1.0 * a * b),
+        Op::Equal => Value::boolean(a == b),
+        // Unordered comparisons succeed, as they do in the builtins
+        Op::Less => Value::boolean(!matches!(
+            ordering,
+            Some(Ordering::Greater | Ordering::Equal)
+        )),
+        Op::Greater => Value::boolean(!matches!(ordering, Some(Ordering::Less | Ordering::Equal))),
+        Op::LessEqual => Value::boolean(ordering != Some(Ordering::Greater)),
+        Op::GreaterEqual => Value::boolean(ordering != Some(Ordering::Less)),
+        _ => unreachable!("{op:?} is not a binary operation"),
+    })
+}
+
+/// Check that an operator evaluated to a procedure
+fn check_procedure(value: &Value) -> Result<()> {
+    match value {
+        Value::Procedure(_) => Ok(()),
+        _ => Err(not_a_procedure(value)),
+    }
+}
+
+fn not_a_procedure(value: &Value) -> Error {
+    let error_msg = format!("'{}' is not a procedure, got {}", value, value.type_name());
+    Error::runtime_error(&error_msg).with_code(ErrorCode::NotAProcedure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::runtime::Limits;
+
+    type Evaluate = fn(Arc<Expression>, &Environment) -> Result<Value>;
+
+    /// Evaluate every expression in `source` in `env` with the given
+    /// evaluator, describing the last result or the first error
+    fn run_in(source: &str, env: &Environment, evaluate: Evaluate) -> String {
+        let mut parser = Parser::new(source.to_string()).unwrap();
+        let mut result = Value::Nil;
+        while let Ok(parsed) = parser.parse_expression() {
+            match evaluate(parsed.expr, env) {
+                Ok(value) => result = value,
+                Err(error) => return format!("{error:?}"),
+            }
+        }
+        result.to_string()
+    }
+
+    fn vm(expr: Arc<Expression>, env: &Environment) -> Result<Value> {
+        eval(&expr, env)
+    }
+
+    fn machine(expr: Arc<Expression>, env: &Environment) -> Result<Value> {
+        Machine::new().run(Control::Eval(expr, env.share()))
+    }
+
+    fn run_vm(source: &str) -> String {
+        run_in(source, &Environment::new(), vm)
+    }
+
+    fn run_machine(source: &str) -> String {
+        run_in(source, &Environment::new(), machine)
+    }
+
+    #[test]
+    fn test_vm_matches_tree_walker() {
+        let programs = [
+            // Slots, closures and loops
+            "(define (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))) (fib 15)",
+            "(define (adder n) (lambda (x) (+ x n))) ((adder 3) 4)",
+            "(define (f x) (define y (* x 2)) (define (g z) (+ y z)) (g x)) (f 5)",
+            "(define (f x) (define x 10) x) (f 1)",
+            "(define (f) (let loop ((i 0) (acc '())) (if (= i 3) acc (loop (+ i 1) (cons i acc))))) (f)",
+            "(define (f a) (let* ((a (+ a 1)) (b (* a 2))) (list a b))) (f 1)",
+            "(define (f a) (let ((a 1) (b a)) (list a b))) (f 2)",
+            // Closures capture bindings of every enclosing procedure
+            "(define (f a) (lambda (b) (lambda (c) (list a b c)))) (((f 1) 2) 3)",
+            "(define (f n) (let loop ((i 0) (acc '())) (if (= i n) acc (loop (+ i 1) (cons (lambda () (* i n)) acc))))) (map (lambda (g) (g)) (f 3))",
+            "(define (f) (define (g) g) (eq? (g) g)) (f)",
+            "(define (f g) (define (g) 1) (g)) (f 2)",
+            "(define (f x) (list (and) (or) (and x 1) (or #f x) (and 1 #f 2) (or #f #f))) (f 7)",
+            "(define (f . args) args)",
+            // Local procedures see the procedures defined after them
+            "(define (f) (define (g) (h)) (define (h) 1) (g)) (f)",
+            // Global definitions are looked up when they are used
+            "(define (f x) (g x)) (define (g x) (* x 2)) (f 21)",
+            "(define (f n) (if (= n 0) 'old (f (- n 1)))) (define g f) (define (f n) 'new) (g 3)",
+            "(define (f) (car '(1))) (define car cdr) (list (f) (car '(1 2)))",
+            "(begin (define + -) (+ 5 3))",
+            "(define (f a b) (if (< a b) (+ a b) a)) (define (+ a b) (list a b)) (f 1 2)",
+            "(define (f a b) (+ a b)) (define (g) (list (f 1 2))) (define + list) (g)",
+            "(define (f) (car '(1 2))) (define car 5) (f)",
+            "(define (f + x) (+ x x)) (f * 3)",
+            // Forms evaluated by the tree-walking evaluator
+            "(define (f n) (letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1)))))
+                                    (odd? (lambda (n) (if (= n 0) #f (even? (- n 1))))))
+                             (even? n)))
+             (f 11)",
+            "(define (f x) (define-record-type point (make-point x y) point? (x point-x))
+                           (point-x (make-point x 2)))
+             (f 1)",
+            "(define (f x) (if x (define y 1) (define y 2)) y) (f #f)",
+            "(define (f x) (force (delay (+ x 1)))) (f 1)",
+            "(define d (make-parameter 0))
+             (define p (delay (if (= (d) 0) (parameterize ((d 1)) (list (force p))) 'inner)))
+             (list (force p) (force p))",
+            "(define p (make-parameter 1)) (define (f) (parameterize ((p 2)) (p))) (list (f) (p))",
+            "(define p (make-parameter 1 (lambda (x) (* x 10))))
+             (define (f x) (parameterize ((p x)) (define y (p)) (list y (parameterize ((p 3)) (p)))))
+             (list (f 2) (p))",
+            "(define p (make-parameter 1)) (define (f) (parameterize ((p 2)) (p)) (p)) (f)",
+            "(define (f) (force (delay-force (delay-force (delay 5))))) (f)",
+            // Errors and backtraces
+            "(define (f x) (+ 1 (car x))) (define (g x) (* 2 (f x))) (g 5)",
+            "(define (f x) (car x)) (define (g x) (f x)) (list (g 1))",
+            "(define (f x) (if (= x 0) (car x) (f (- x 1)))) (list (f 3))",
+            "(define (f x) x) (f 1 2)",
+            "((lambda (x) x))",
+            "(define (f) (undefined 1)) (f)",
+            "(define (f) (+ 1 undefined)) (f)",
+            "(define (f) ((car '(1)) undefined)) (f)",
+            "(define (f) (if 1 2)) (f)",
+            "(define (f) (let ((x)) x)) (f)",
+            "(define (f) (define)) (f)",
+            "(define (f) (lambda x)) (f)",
+            "(define (f x) (< x 'a)) (f 1)",
+            "(define (f x) (list (- x))) (f \"s\")",
+            "(define (f n) (let loop ((i n)) (if (= i 0) (vector-ref i) (loop (- i 1))))) (f 2)",
+            "(define (f) (force (delay (car '())))) (list (f))",
+            "(define (f) (force (delay-force 1))) (list (f))",
+            "(define (f) (force (delay-force 1))) (f)",
+            "(define (f) (parameterize ((car 1)) 2)) (f)",
+            "(define (f) (parameterize ((f (car '()))) 2)) (f)",
+            "(define (f) (parameterize 1 2)) (f)",
+        ];
+
+        for program in programs {
+            assert_eq!(run_vm(program), run_machine(program), "{program}");
+        }
+    }
+
+    #[test]
+    fn test_internal_procedures_call_later_ones() {
+        // A local procedure sees the procedures defined before and after it
+        let programs = [
+            (
+                "(define (f) (define (g n) 7) (define (h) (g 0)) h) ((f))",
+                "7",
+            ),
+            (
+                "(define (f) (define (h) (g 0)) (define (g n) 7) (h)) (f)",
+                "7",
+            ),
+            (
+                "(define (f n)
+                   (define (ev? n) (if (= n 0) #t (od? (- n 1))))
+                   (define (od? n) (if (= n 0) #f (ev? (- n 1))))
+                   (ev? n))
+                 (list (f 10) (f 7))",
+                "(#t #f)",
+            ),
+            (
+                "(define (f) (define ev? (lambda (n) (if (= n 0) #t (od? (- n 1)))))
+                             (define od? (lambda (n) (if (= n 0) #f (ev? (- n 1)))))
+                             (let () (define (g) (h)) (define (h) (ev? 4)) (g)))
+                 (f)",
+                "#t",
+            ),
+            // Other definitions still bind in order
+            ("(define (f) (define a 1) (define (g) a) (g)) (f)", "1"),
+        ];
+        for (program, expected) in programs {
+            assert_eq!(run_vm(program), expected, "{program}");
+            assert_eq!(run_machine(program), expected, "{program}");
+        }
+
+        // A procedure called before its definition is an error
+        let program = "(define (f) (define x (g)) (define (g) 1) x) (f)";
+        for result in [run_vm(program), run_machine(program)] {
+            assert!(
+                result.contains("Procedure called before its definition"),
+                "{result}"
+            );
+        }
+
+        // A loop through such a body still runs in constant native stack,
+        // and its last expression is a tail call
+        let program = "(define (f n) (define (g) (h)) (define (h) n)
+                                     (if (= n 0) (g) (f (- n 1))))
+                       (f 100000)";
+        for evaluate in [vm as Evaluate, machine] {
+            let mut env = Environment::new();
+            env.set_limits(Limits::new().with_max_depth(10));
+            assert_eq!(run_in(program, &env, evaluate), "0");
+        }
+    }
+
+    #[test]
+    fn test_escaping_closures_keep_later_procedures_alive() {
+        // Only the returned closure refers to the procedure defined after it
+        let programs = [
+            (
+                "(define (mk) (define (a) (b)) (define (b) 7) a) ((mk))",
+                "7",
+            ),
+            (
+                "(define (mk) (letrec ((a (lambda () (b))) (b (lambda () 7))) a)) ((mk))",
+                "7",
+            ),
+            (
+                "(define (mk) (letrec* ((a (lambda () (b))) (b (lambda () 7))) a)) ((mk))",
+                "7",
+            ),
+            (
+                "(define (mk) (define (ev? n) (if (= n 0) #t (od? (- n 1))))
+                              (define (od? n) (if (= n 0) #f (ev? (- n 1))))
+                              ev?)
+                 (list ((mk) 10) ((mk) 7))",
+                "(#t #f)",
+            ),
+        ];
+        for (program, expected) in programs {
+            assert_eq!(run_vm(program), expected, "{program}");
+            assert_eq!(run_machine(program), expected, "{program}");
+        }
+    }
+
+    #[test]
+    fn test_internal_values_defined_later_are_unbound_in_earlier_closures() {
+        // Only procedures get placeholders; closures copy other locals
+        let programs = [
+            "(define (f) (define (get) later) (define later 42) (get)) (f)",
+            "(define (f) (define g (lambda () h)) (define h 7) g) ((f))",
+        ];
+        for program in programs {
+            for result in [run_vm(program), run_machine(program)] {
+                assert!(result.contains("UnboundIdentifier"), "{result}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_escaping_closures_keep_recursive_procedures_alive() {
+        // Only the returned closure refers to the loop procedure
+        let programs = [
+            "(define (mk) (let loop ((i 0)) (if (> i 2) i (lambda () (loop 5))))) ((mk))",
+            "(define (mk) (define (g n) (if (> n 2) n (lambda () (g 5)))) (g 0)) ((mk))",
+        ];
+        for program in programs {
+            assert_eq!(run_vm(program), "5", "{program}");
+            assert_eq!(run_machine(program), "5", "{program}");
+        }
+    }
+
+    #[test]
+    fn test_returned_recursive_procedures_stay_alive() {
+        // A named let's loop procedure is returned from its own body
+        let programs = [
+            (
+                "(define k (let loop ((i 0)) (if (= i 0) loop 1))) (k 5)",
+                "1",
+            ),
+            // Or from the body of its own internal definition or letrec
+            (
+                "(define (mk) (define (inner n) (if (= n 0) inner n)) (inner 0)) ((mk) 4)",
+                "4",
+            ),
+            (
+                "(define (mk) (letrec ((inner (lambda (n) (if (= n 0) inner n)))) (inner 0)))
+                 ((mk) 4)",
+                "4",
+            ),
+            (
+                "(define (counter) (let loop ((n 0)) (if (< n 3) (loop (+ n 1)) (list n loop))))
+                 ((car (cdr (counter))) 10)",
+                "(10 #<procedure:loop>)",
+            ),
+        ];
+        for (program, expected) in programs {
+            assert_eq!(run_vm(program), expected, "{program}");
+            assert_eq!(run_machine(program), expected, "{program}");
+        }
+    }
+
+    #[test]
+    fn test_unbound_identifier_suggests_enclosing_locals() {
+        let program = "(define (f) (define a 1) (define (g) (+ a b)) (define b 2) (g)) (f)";
+        for result in [run_vm(program), run_machine(program)] {
+            assert!(result.contains("\"did you mean one of: 'a', "), "{result}");
+        }
+    }
+
+    #[test]
+    fn test_duplicate_let_identifiers_rejected() {
+        // let binds simultaneously, so neither binding of `a` could win
+        for source in [
+            "(let ((a 1) (a 2)) a)",
+            "(define (f) (let ((a 1) (a 2)) a)) (f)",
+            "(define (f) (let loop ((a 1) (a 2)) a)) (f)",
+        ] {
+            for result in [run_vm(source), run_machine(source)] {
+                assert!(result.contains("DuplicateIdentifier"), "{source}: {result}");
+            }
+        }
+        // let* binds in sequence, so the later binding shadows the earlier
+        assert_eq!(run_vm("(let* ((a 1) (a 2)) a)"), "2");
+        assert_eq!(run_machine("(let* ((a 1) (a 2)) a)"), "2");
+    }
+
+    #[test]
+    fn test_force_and_parameterize_run_on_the_heap() {
+        let programs = [
+            (
+                "(define (f n) (if (= n 0) 0 (+ 1 (force (delay (f (- n 1)))))))
+                 (f 100000)",
+                "100000",
+            ),
+            (
+                "(define p (make-parameter 0))
+                 (define (f n) (if (= n 0) (p) (+ 1 (parameterize ((p n)) (f (- n 1))))))
+                 (list (f 100000) (p))",
+                "(100001 0)",
+            ),
+        ];
+        for (program, expected) in programs {
+            assert_eq!(run_vm(program), expected, "{program}");
+            assert_eq!(run_machine(program), expected, "{program}");
+        }
+
+        // Calls in tail position of a parameterize body replace the frame
+        let program = "(define p (make-parameter 0))
+                       (define (loop n) (if (= n 0) (p) (parameterize ((p n)) (loop (- n 1)))))
+                       (list (loop 100000) (p))";
+        for evaluate in [vm as Evaluate, machine] {
+            let mut env = Environment::new();
+            env.set_limits(Limits::new().with_max_depth(10));
+            assert_eq!(run_in(program, &env, evaluate), "(1 0)");
+        }
+
+        // The dynamic environment is restored when a body fails
+        for evaluate in [vm as Evaluate, machine] {
+            let env = Environment::new();
+            let program = "(define p (make-parameter 0))
+                           (define (f) (parameterize ((p 1)) (car (p))))
+                           (f)";
+            assert!(run_in(program, &env, evaluate).contains("TypeError"));
+            assert_eq!(run_in("(p)", &env, evaluate), "0");
+        }
+    }
+
+    #[test]
+    fn test_vm_specialised_comparisons_match_builtins() {
+        // Comparisons with NaN succeed as the builtins' pairwise checks do
+        let nan = "(- (* 1e308 10) (* 1e308 10))";
+        for operator in ["+", "-", "*", "=", "<", ">", "<=", ">="] {
+            for (a, b) in [("1", "2"), ("2", "1"), ("2", "2"), (nan, "1"), ("1", nan)] {
+                let call = format!("(define (f a b) ({operator} a b)) (f {a} {b})");
+                let result = run_vm(&call);
+                assert!(!result.contains("Error"), "{call}: {result}");
+                assert_eq!(result, run_machine(&call), "{call}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_vm_apply() {
+        let env = Environment::new();
+        let mut parser = Parser::new("(lambda (x) (car x))".to_string()).unwrap();
+        let procedure = match eval(&parser.parse_expression().unwrap().expr, &env).unwrap() {
+            Value::Procedure(procedure) => procedure,
+            other => panic!("Expected procedure, got {other}"),
+        };
+
+        let list = Value::List(crate::types::List::from(vec![Value::number(1.0)]));
+        assert_eq!(
+            apply(procedure.clone(), vec![list], None).unwrap(),
+            Value::number(1.0)
+        );
+
+        // The lambda is replaced by its tail call to car
+        let error = apply(procedure, vec![Value::number(1.0)], None).unwrap_err();
+        let frames = error.backtrace().unwrap().frames();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].procedure, "car");
+        assert_eq!(frames[0].tail_calls_elided, 1);
+    }
+}
@@ -0,0 +1,111 @@
+//! Interrupting a running evaluation
+//!
+//! Each global environment has an interrupt flag, shared by the scopes and
+//! procedures made in it, and setting it asks the evaluations running there
+//! to stop. Both evaluators check it on every evaluation step, builtins that
+//! loop, such as `stream->list`, on every iteration, and the fiber scheduler
+//! between fiber steps, so even a loop such as `(define (f) (f)) (f)`
+//! notices it promptly. An evaluation nested in another, such as forcing a
+//! promise, checks the flag of the one it is nested in. The first check that
+//! finds the flag set clears it and fails with an "interrupted" error, which
+//! unwinds the evaluation and leaves its environment intact.
+//!
+//! Interrupting one environment leaves the others in the process running.
+//! The REPL sets its environment's flag from a SIGINT handler, so Ctrl+C
+//! interrupts the expression being evaluated. Embedders can call
+//! [`InterruptHandle::interrupt`] from another thread instead.
+
+use crate::error::{Error, Result};
+use crate::types::Procedure;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A handle to the interrupt flag of a global environment
+///
+/// Clones share the flag, so a handle can be sent to another thread to
+/// interrupt evaluations from there.
+#[derive(Debug, Clone, Default)]
+pub struct InterruptHandle {
+    /// Whether an interrupt has been requested and not yet delivered
+    ///
+    /// Shared through an `Arc` so it can be registered with a signal handler.
+    flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Create a handle to a new flag, not set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the running evaluation to stop
+    ///
+    /// Evaluations started later are interrupted instead if none is running;
+    /// call [`InterruptHandle::clear`] before starting one to discard stale
+    /// requests.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Discard an interrupt that has not been delivered
+    pub fn clear(&self) {
+        self.flag.store(false, Ordering::Relaxed);
+    }
+
+    /// Check if an interrupt has been requested and not yet delivered
+    pub fn is_interrupted(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+
+    /// The interrupt flag, for signal handlers that set it
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.flag)
+    }
+
+    /// Fail with an "interrupted" error if an interrupt has been requested,
+    /// clearing the request
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.flag.load(Ordering::Relaxed) && self.flag.swap(false, Ordering::Relaxed) {
+            return Err(Error::interrupted());
+        }
+        Ok(())
+    }
+}
+
+/// The interrupt flag of the environment a procedure was defined in, if it
+/// is a Scheme procedure
+pub(crate) fn procedure_interrupt(procedure: &Procedure) -> Option<InterruptHandle> {
+    let lambda = match procedure {
+        Procedure::Lambda(_) | Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => {
+            procedure.resolve_weak_lambda().ok()?
+        }
+        _ => return None,
+    };
+    Some(lambda.env().interrupt_handle())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_interrupt_is_delivered_once() {
+        let handle = InterruptHandle::new();
+        assert!(handle.check().is_ok());
+
+        // Clones share the flag
+        handle.clone().interrupt();
+        assert!(handle.is_interrupted());
+        assert_eq!(handle.check().unwrap_err().code(), ErrorCode::Interrupted);
+        assert!(!handle.is_interrupted());
+        assert!(handle.check().is_ok());
+
+        // Other handles are unaffected
+        let other = InterruptHandle::new();
+        handle.interrupt();
+        assert!(!other.is_interrupted());
+        handle.clear();
+        assert!(handle.check().is_ok());
+    }
+}
@@ -0,0 +1,689 @@
+//! Library system for the Twine Scheme runtime
+//!
+//! This module implements the runtime side of R7RS libraries:
+//! - Library names such as `(scheme base)` or `(app util strings)`
+//! - Built-in libraries exposing the builtin procedures
+//! - A process-wide registry caching every loaded library
+//! - A search path used to find library source files
+//! - Import set resolution (`only`, `except`, `prefix`, `rename`)
+//!
+//! The `define-library` and `import` special forms live in
+//! `special_forms::library` and build on the functions here.
+//!
+//! ## Loading
+//! A library that is not built in and not yet registered is looked up on the
+//! search path: `(app util strings)` is loaded from `app/util/strings.sld`
+//! (or `.scm`) relative to each search directory in turn. The file must
+//! contain a `define-library` form for that name. Each library is loaded and
+//! evaluated at most once per process; concurrent imports of a library that
+//! is still loading wait for it, and circular imports are reported as errors.
+//!
+//! ## Built-in Libraries
+//! - `(scheme base)`: arithmetic, comparison, list operations, type
+//!   predicates, `newline`, `make-parameter`, `symbol->string` and
+//!   `string->symbol`
+//! - `(scheme write)`: `display`
+//! - `(scheme lazy)`: `force`, `make-promise`, `promise?`
+//! - `(scheme process-context)`: `command-line`
+//! - `(twine procedure)`: `procedure-name`
+//! - `(twine symbol)`: `gensym`, `generate-uninterned-symbol`
+//! - `(twine json)`: `json->scheme`, `scheme->json`
+//! - `(twine stream)`: the lazy stream procedures
+//!
+//! Special forms such as `define` and `lambda` are syntax and are always
+//! available. Library bodies, and programs run by the `twine-scheme` binary,
+//! start empty and see only what they import; an environment made with
+//! `Environment::new()` implicitly imports every builtin.
+
+use crate::error::{Error, Result};
+use crate::parser::{Expression, Parser};
+use crate::runtime::builtins::Builtin;
+use crate::runtime::environment::Environment;
+use crate::runtime::eval::eval;
+use crate::types::{SmolStr, Symbol, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread::{self, ThreadId};
+
+/// Environment variable holding additional library search directories
+///
+/// Directories are separated by the platform's path separator (`:` on Unix).
+pub const LIBRARY_PATH_VAR: &str = "TWINE_LIBRARY_PATH";
+
+/// File extensions tried, in order, when loading a library from disk
+const LIBRARY_EXTENSIONS: [&str; 2] = ["sld", "scm"];
+
+/// Name of a library, e.g. `(scheme base)`
+///
+/// Each part is an identifier or an exact non-negative integer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LibraryName(Vec<SmolStr>);
+
+impl LibraryName {
+    /// Create a library name from its parts
+    pub fn new<I, S>(parts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<SmolStr>,
+    {
+        LibraryName(parts.into_iter().map(Into::into).collect())
+    }
+
+    /// Parse a library name from its list expression
+    pub fn from_expression(expr: &Expression, form_name: &str) -> Result<Self> {
+        let elements = match expr {
+            Expression::List(elements, _) if !elements.is_empty() => elements,
+            other => {
+                return Err(Error::parse_error(&format!(
+                    "{form_name}: library name must be a non-empty list, got {}",
+                    other.type_name()
+                )));
+            }
+        };
+
+        let parts = elements
+            .iter()
+            .map(|element| match element.as_ref() {
+                Expression::Atom(Value::Symbol(symbol), _) => Ok(SmolStr::new(symbol.as_str())),
+                Expression::Atom(Value::Number(n), _)
+                    if n.value() >= 0.0 && n.value().fract() == 0.0 =>
+                {
+                    Ok(SmolStr::new(n.to_string()))
+                }
+                other => Err(Error::parse_error(&format!(
+                    "{form_name}: library name parts must be identifiers or non-negative integers, got {}",
+                    other.type_name()
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(LibraryName(parts))
+    }
+
+    /// Get the parts of the name
+    pub fn parts(&self) -> &[SmolStr] {
+        &self.0
+    }
+
+    /// Relative path of the library's source file, without extension
+    ///
+    /// `(app util strings)` maps to `app/util/strings`.
+    pub fn relative_path(&self) -> PathBuf {
+        self.0.iter().map(SmolStr::as_str).collect()
+    }
+}
+
+impl fmt::Display for LibraryName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({})", self.0.join(" "))
+    }
+}
+
+/// An instantiated library and its exported bindings
+#[derive(Debug)]
+pub struct Library {
+    name: LibraryName,
+    exports: Vec<(Symbol, Value)>,
+    /// The environment the library body was evaluated in, held so that its
+    /// exported procedures can go on looking up their global bindings
+    _env: Option<Environment>,
+}
+
+impl Library {
+    /// Create a library from its name and exported bindings
+    pub fn new(name: LibraryName, exports: Vec<(Symbol, Value)>) -> Arc<Self> {
+        Arc::new(Library {
+            name,
+            exports,
+            _env: None,
+        })
+    }
+
+    /// Create a library whose exported bindings were defined in `env`,
+    /// keeping it alive for as long as the library is
+    pub(crate) fn defined_in(
+        name: LibraryName,
+        exports: Vec<(Symbol, Value)>,
+        env: Environment,
+    ) -> Arc<Self> {
+        Arc::new(Library {
+            name,
+            exports,
+            _env: Some(env),
+        })
+    }
+
+    /// Get the library name
+    pub fn name(&self) -> &LibraryName {
+        &self.name
+    }
+
+    /// Get the exported bindings as (external name, value) pairs
+    pub fn exports(&self) -> &[(Symbol, Value)] {
+        &self.exports
+    }
+
+    /// Look up a single export by its external name
+    pub fn export(&self, name: &Symbol) -> Option<&Value> {
+        self.exports
+            .iter()
+            .find(|(export, _)| export == name)
+            .map(|(_, value)| value)
+    }
+}
+
+/// The names of the built-in libraries
+const BUILTIN_LIBRARIES: [[&str; 2]; 8] = [
+    ["scheme", "base"],
+    ["scheme", "write"],
+    ["scheme", "lazy"],
+    ["scheme", "process-context"],
+    ["twine", "procedure"],
+    ["twine", "symbol"],
+    ["twine", "json"],
+    ["twine", "stream"],
+];
+
+/// The built-in library that exports a builtin procedure
+pub(crate) fn library_exporting(builtin: Builtin) -> Option<LibraryName> {
+    BUILTIN_LIBRARIES
+        .iter()
+        .map(|name| LibraryName::new(*name))
+        .find(|name| {
+            builtin_library_contents(name).is_some_and(|contents| contents.contains(&builtin))
+        })
+}
+
+/// Get the builtins exported by a built-in library, if the name is one
+fn builtin_library_contents(name: &LibraryName) -> Option<&'static [Builtin]> {
+    use Builtin::*;
+
+    let parts: Vec<&str> = name.parts().iter().map(SmolStr::as_str).collect();
+    let contents: &'static [Builtin] = match parts.as_slice() {
+        ["scheme", "base"] => &[
+            Add,
+            Subtract,
+            Multiply,
+            Divide,
+            Equal,
+            LessThan,
+            GreaterThan,
+            LessThanOrEqual,
+            GreaterThanOrEqual,
+            Car,
+            Cdr,
+            Cons,
+            List,
+            NullP,
+            Length,
+            NumberP,
+            StringP,
+            BooleanP,
+            SymbolP,
+            ListP,
+            ProcedureP,
+            EqP,
+            Newline,
+            MakeParameter,
+            SymbolToString,
+            StringToSymbol,
+        ],
+        ["scheme", "write"] => &[Display],
+        ["scheme", "lazy"] => &[Force, MakePromise, PromiseP],
+        ["scheme", "process-context"] => &[CommandLine],
+        ["twine", "procedure"] => &[ProcedureName],
+        ["twine", "symbol"] => &[Gensym, GenerateUninternedSymbol],
+        ["twine", "json"] => &[JsonToScheme, SchemeToJson],
+        ["twine", "stream"] => &[
+            Stream,
+            StreamCar,
+            StreamCdr,
+            StreamNullP,
+            StreamPairP,
+            StreamToList,
+        ],
+        _ => return None,
+    };
+    Some(contents)
+}
+
+/// Build a built-in library, if the name is one
+fn builtin_library(name: &LibraryName) -> Option<Arc<Library>> {
+    let exports = builtin_library_contents(name)?
+        .iter()
+        .map(|&builtin| {
+            (
+                Symbol::new(builtin.name()),
+                Value::builtin_procedure(builtin),
+            )
+        })
+        .collect();
+    Some(Library::new(name.clone(), exports))
+}
+
+/// Process-wide cache of loaded libraries and the library search path
+#[derive(Debug, Default)]
+struct RegistryState {
+    /// Libraries that have finished loading
+    loaded: HashMap<LibraryName, Arc<Library>>,
+    /// Libraries currently being loaded, and by which thread
+    loading: HashMap<LibraryName, ThreadId>,
+    /// Directories searched for library files, in order
+    search_path: Vec<PathBuf>,
+}
+
+/// The library registry shared by all environments in the process
+#[derive(Debug)]
+struct Registry {
+    state: Mutex<RegistryState>,
+    /// Signalled whenever a library finishes (or fails) loading
+    loaded: Condvar,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut search_path = vec![PathBuf::from(".")];
+        if let Some(paths) = std::env::var_os(LIBRARY_PATH_VAR) {
+            search_path.extend(std::env::split_paths(&paths));
+        }
+        Registry {
+            state: Mutex::new(RegistryState {
+                search_path,
+                ..RegistryState::default()
+            }),
+            loaded: Condvar::new(),
+        }
+    })
+}
+
+impl Registry {
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Add a directory to the end of the library search path
+pub fn add_search_path(path: impl Into<PathBuf>) {
+    let path = path.into();
+    let mut state = registry().lock();
+    if !state.search_path.contains(&path) {
+        state.search_path.push(path);
+    }
+}
+
+/// Get the current library search path
+pub fn search_path() -> Vec<PathBuf> {
+    registry().lock().search_path.clone()
+}
+
+/// Register a library defined with `define-library`
+///
+/// Redefining a library (e.g. at the REPL) replaces the cached version for
+/// future imports; existing importers keep the bindings they already have.
+pub fn register_library(library: Arc<Library>) {
+    let registry = registry();
+    let mut state = registry.lock();
+    state.loaded.insert(library.name().clone(), library);
+    registry.loaded.notify_all();
+}
+
+/// Check if a library is built in or has already been loaded
+pub fn is_library_loaded(name: &LibraryName) -> bool {
+    builtin_library_contents(name).is_some() || registry().lock().loaded.contains_key(name)
+}
+
+/// Find a library by name, loading it from the search path if necessary
+pub fn find_library(name: &LibraryName) -> Result<Arc<Library>> {
+    if let Some(library) = builtin_library(name) {
+        return Ok(library);
+    }
+
+    let registry = registry();
+    let current = thread::current().id();
+    let search_path = {
+        let mut state = registry.lock();
+        loop {
+            if let Some(library) = state.loaded.get(name) {
+                return Ok(Arc::clone(library));
+            }
+            match state.loading.get(name) {
+                Some(&owner) if owner == current => {
+                    return Err(Error::runtime_error(&format!(
+                        "import: circular dependency while loading library {name}"
+                    )));
+                }
+                Some(_) => {
+                    state = registry
+                        .loaded
+                        .wait(state)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                }
+                None => break,
+            }
+        }
+        state.loading.insert(name.clone(), current);
+        state.search_path.clone()
+    };
+
+    // Load without holding the lock, since the library may import others
+    let result = load_library_file(name, &search_path);
+
+    let mut state = registry.lock();
+    state.loading.remove(name);
+    registry.loaded.notify_all();
+    result?;
+
+    state.loaded.get(name).cloned().ok_or_else(|| {
+        Error::runtime_error(&format!(
+            "import: library file for {name} does not define library {name}"
+        ))
+    })
+}
+
+/// Locate a library's source file on the search path
+fn locate_library_file(name: &LibraryName, search_path: &[PathBuf]) -> Option<PathBuf> {
+    let relative = name.relative_path();
+    search_path.iter().find_map(|directory| {
+        LIBRARY_EXTENSIONS.iter().find_map(|extension| {
+            let candidate = directory.join(&relative).with_extension(extension);
+            candidate.is_file().then_some(candidate)
+        })
+    })
+}
+
+/// Load and evaluate the file defining a library
+fn load_library_file(name: &LibraryName, search_path: &[PathBuf]) -> Result<()> {
+    let path = locate_library_file(name, search_path).ok_or_else(|| {
+        Error::runtime_error(&format!(
+            "import: library {name} not found (searched for {} in {})",
+            name.relative_path().display(),
+            search_path
+                .iter()
+                .map(|directory| directory.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    })?;
+    load_library_source(&path)
+}
+
+/// Evaluate every `define-library` form in a library source file
+fn load_library_source(path: &Path) -> Result<()> {
+    let source = std::fs::read_to_string(path).map_err(|error| {
+        Error::runtime_error(&format!(
+            "import: cannot read library file {}: {error}",
+            path.display()
+        ))
+    })?;
+
+    let source_name = path.display().to_string();
+    let mut parser = Parser::new(source)
+        .map_err(|error| error.in_source(&source_name))?
+        .with_source_name(&source_name);
+    let mut env = Environment::new_without_builtins();
+    while !parser.is_at_end() {
+        let expr = parser
+            .parse_expression()
+            .map_err(|error| error.in_source(&source_name))?
+            .expr;
+        eval(expr, &mut env)?;
+    }
+    Ok(())
+}
+
+/// Resolve an import set into the bindings it provides
+///
+/// Import sets are a library name or one of:
+/// - `(only <import-set> <identifier> ...)`
+/// - `(except <import-set> <identifier> ...)`
+/// - `(prefix <import-set> <prefix>)`
+/// - `(rename <import-set> (<old> <new>) ...)`
+pub fn resolve_import_set(import_set: &Expression) -> Result<Vec<(Symbol, Value)>> {
+    let elements = match import_set {
+        Expression::List(elements, _) if !elements.is_empty() => elements,
+        other => {
+            return Err(Error::parse_error(&format!(
+                "import: import set must be a non-empty list, got {}",
+                other.type_name()
+            )));
+        }
+    };
+
+    let modifier = match elements[0].as_ref() {
+        Expression::Atom(Value::Symbol(symbol), _) if elements.len() >= 2 => {
+            match (symbol.as_str(), elements[1].as_ref()) {
+                // A modifier is always followed by a nested import set
+                (keyword @ ("only" | "except" | "prefix" | "rename"), Expression::List(_, _)) => {
+                    Some(keyword)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    let Some(modifier) = modifier else {
+        let name = LibraryName::from_expression(import_set, "import")?;
+        return Ok(find_library(&name)?.exports().to_vec());
+    };
+
+    let bindings = resolve_import_set(&elements[1])?;
+    let arguments = &elements[2..];
+
+    match modifier {
+        "only" => {
+            let identifiers = import_identifiers(arguments, "only")?;
+            check_identifiers_available(&identifiers, &bindings, "only")?;
+            Ok(bindings
+                .into_iter()
+                .filter(|(name, _)| identifiers.contains(name))
+                .collect())
+        }
+        "except" => {
+            let identifiers = import_identifiers(arguments, "except")?;
+            check_identifiers_available(&identifiers, &bindings, "except")?;
+            Ok(bindings
+                .into_iter()
+                .filter(|(name, _)| !identifiers.contains(name))
+                .collect())
+        }
+        "prefix" => {
+            let [prefix] = &import_identifiers(arguments, "prefix")?[..] else {
+                return Err(Error::parse_error(
+                    "import: prefix requires exactly one prefix identifier",
+                ));
+            };
+            Ok(bindings
+                .into_iter()
+                .map(|(name, value)| (Symbol::new(&format!("{prefix}{name}")), value))
+                .collect())
+        }
+        "rename" => {
+            let renames = import_renames(arguments)?;
+            let old_names: Vec<Symbol> = renames.keys().cloned().collect();
+            check_identifiers_available(&old_names, &bindings, "rename")?;
+            Ok(bindings
+                .into_iter()
+                .map(|(name, value)| match renames.get(&name) {
+                    Some(new_name) => (new_name.clone(), value),
+                    None => (name, value),
+                })
+                .collect())
+        }
+        _ => unreachable!("modifier is one of only, except, prefix or rename"),
+    }
+}
+
+/// Parse the identifiers of an `only`, `except` or `prefix` import set
+fn import_identifiers(arguments: &[Arc<Expression>], modifier: &str) -> Result<Vec<Symbol>> {
+    arguments
+        .iter()
+        .map(|argument| match argument.as_ref() {
+            Expression::Atom(Value::Symbol(symbol), _) => Ok(symbol.clone()),
+            other => Err(Error::identifier_must_be_symbol_error(
+                &format!("import {modifier}"),
+                other.type_name(),
+            )),
+        })
+        .collect()
+}
+
+/// Parse the `(old new)` pairs of a `rename` import set
+fn import_renames(arguments: &[Arc<Expression>]) -> Result<HashMap<Symbol, Symbol>> {
+    let mut renames = HashMap::with_capacity(arguments.len());
+    for argument in arguments {
+        let pair = match argument.as_ref() {
+            Expression::List(pair, _) if pair.len() == 2 => pair,
+            _ => return Err(Error::binding_elements_wrong_arity_error("import rename")),
+        };
+        let names = import_identifiers(pair, "rename")?;
+        renames.insert(names[0].clone(), names[1].clone());
+    }
+    Ok(renames)
+}
+
+/// Ensure every identifier named by an import set modifier is provided
+fn check_identifiers_available(
+    identifiers: &[Symbol],
+    bindings: &[(Symbol, Value)],
+    modifier: &str,
+) -> Result<()> {
+    let available: HashSet<&Symbol> = bindings.iter().map(|(name, _)| name).collect();
+    match identifiers.iter().find(|name| !available.contains(name)) {
+        Some(missing) => Err(Error::runtime_error(&format!(
+            "import {modifier}: '{missing}' is not exported by the import set"
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Arc<Expression> {
+        Parser::new(source.to_string())
+            .unwrap()
+            .parse_expression()
+            .unwrap()
+            .expr
+    }
+
+    fn names(bindings: &[(Symbol, Value)]) -> Vec<&str> {
+        let mut names: Vec<&str> = bindings.iter().map(|(name, _)| name.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_library_name() {
+        let name = LibraryName::from_expression(&parse("(srfi 41 streams)"), "import").unwrap();
+        assert_eq!(name, LibraryName::new(["srfi", "41", "streams"]));
+        assert_eq!(name.to_string(), "(srfi 41 streams)");
+        assert_eq!(
+            name.relative_path(),
+            PathBuf::from("srfi").join("41").join("streams")
+        );
+
+        assert!(LibraryName::from_expression(&parse("()"), "import").is_err());
+        assert!(LibraryName::from_expression(&parse("foo"), "import").is_err());
+        assert!(LibraryName::from_expression(&parse("(foo \"bar\")"), "import").is_err());
+        assert!(LibraryName::from_expression(&parse("(foo -1)"), "import").is_err());
+    }
+
+    #[test]
+    fn test_builtin_libraries() {
+        let base = find_library(&LibraryName::new(["scheme", "base"])).unwrap();
+        assert!(base.export(&Symbol::new("car")).is_some());
+        assert!(base.export(&Symbol::new("display")).is_none());
+        assert!(base.export(&Symbol::new("string->symbol")).is_some());
+
+        let symbol = find_library(&LibraryName::new(["twine", "symbol"])).unwrap();
+        assert_eq!(
+            names(symbol.exports()),
+            vec!["generate-uninterned-symbol", "gensym"]
+        );
+
+        let write = find_library(&LibraryName::new(["scheme", "write"])).unwrap();
+        assert_eq!(names(write.exports()), vec!["display"]);
+
+        let json = find_library(&LibraryName::new(["twine", "json"])).unwrap();
+        assert_eq!(names(json.exports()), vec!["json->scheme", "scheme->json"]);
+
+        assert!(is_library_loaded(&LibraryName::new(["scheme", "lazy"])));
+        assert!(is_library_loaded(&LibraryName::new(["twine", "stream"])));
+        assert!(find_library(&LibraryName::new(["twine", "fiber"])).is_err());
+    }
+
+    #[test]
+    fn test_every_builtin_in_a_library() {
+        for builtin in Builtin::ALL {
+            assert!(
+                library_exporting(*builtin).is_some(),
+                "{} is not exported",
+                builtin.name()
+            );
+        }
+        assert_eq!(
+            library_exporting(Builtin::Display),
+            Some(LibraryName::new(["scheme", "write"]))
+        );
+    }
+
+    #[test]
+    fn test_resolve_import_set_modifiers() {
+        let only = resolve_import_set(&parse("(only (scheme base) car cdr)")).unwrap();
+        assert_eq!(names(&only), vec!["car", "cdr"]);
+
+        let except = resolve_import_set(&parse("(except (scheme lazy) force)")).unwrap();
+        assert_eq!(names(&except), vec!["make-promise", "promise?"]);
+
+        let prefix = resolve_import_set(&parse("(prefix (scheme write) io:)")).unwrap();
+        assert_eq!(names(&prefix), vec!["io:display"]);
+
+        let rename = resolve_import_set(&parse("(rename (scheme write) (display show))")).unwrap();
+        assert_eq!(names(&rename), vec!["show"]);
+
+        // Modifiers nest
+        let nested = resolve_import_set(&parse("(prefix (only (scheme base) car) list:)")).unwrap();
+        assert_eq!(names(&nested), vec!["list:car"]);
+    }
+
+    #[test]
+    fn test_resolve_import_set_errors() {
+        // Unknown identifiers
+        assert!(resolve_import_set(&parse("(only (scheme base) frobnicate)")).is_err());
+        assert!(resolve_import_set(&parse("(except (scheme base) frobnicate)")).is_err());
+        assert!(resolve_import_set(&parse("(rename (scheme base) (frob x))")).is_err());
+
+        // Malformed modifiers
+        assert!(resolve_import_set(&parse("(prefix (scheme base))")).is_err());
+        assert!(resolve_import_set(&parse("(rename (scheme base) (car))")).is_err());
+        assert!(resolve_import_set(&parse("(only (scheme base) 42)")).is_err());
+
+        // Missing library
+        let err = resolve_import_set(&parse("(no such library)")).unwrap_err();
+        assert!(err.to_string().contains("(no such library) not found"));
+    }
+
+    #[test]
+    fn test_register_library() {
+        let name = LibraryName::new(["library-tests", "registered"]);
+        register_library(Library::new(
+            name.clone(),
+            vec![(Symbol::new("answer"), Value::number(42.0))],
+        ));
+
+        assert!(is_library_loaded(&name));
+        let library = find_library(&name).unwrap();
+        assert_eq!(
+            library.export(&Symbol::new("answer")),
+            Some(&Value::number(42.0))
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Resource limits for evaluating untrusted code
+//!
+//! [`Limits`] bound the work an evaluation may do:
+//! - *fuel*: the number of evaluation steps, which stops loops such as
+//!   `(define (f) (f)) (f)`; a step is an operation of the bytecode VM or a
+//!   transition of the tree-walking machine, so the two evaluators use
+//!   different amounts of fuel for the same code
+//! - *depth*: how deeply lambda calls may be nested
+//! - *allocation*: the length of any list or string a builtin or native
+//!   procedure returns, in elements or bytes
+//! - *timeout*: how long the evaluation may run
+//!
+//! Limits are set on a global environment with
+//! [`Environment::set_limits`](crate::runtime::Environment::set_limits) and
+//! apply to all code evaluated in it and to the procedures it defines.
+//! Setting them starts a fresh budget of fuel and time, which evaluations
+//! share until it is set again. An evaluation that exceeds a limit stops
+//! with the error for that limit: [`Error::FuelExhausted`],
+//! [`Error::DepthExceeded`], [`Error::AllocationExceeded`] or
+//! [`Error::DeadlineExceeded`].
+//!
+//! An evaluation nested in a builtin, native procedure or special form, such
+//! as forcing a promise for `stream->list`, counts its depth on from the
+//! calls in progress in the evaluation that called it, and shares its fuel
+//! and time. Builtins that loop, such as `stream->list`, draw on the same
+//! budget with [`builtin_step`], and hold what they build to the allocation
+//! limit as it grows. It also checks the interrupt flag of that evaluation,
+//! which stops such a loop even without limits.
+
+use crate::error::{Error, Result};
+use crate::runtime::interrupt::InterruptHandle;
+use crate::types::{Procedure, Value};
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// How many steps are taken between checks of the deadline
+///
+/// Reading the clock on every step would slow tight loops noticeably.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// The resource limits of an evaluation; no limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    fuel: Option<u64>,
+    max_depth: Option<usize>,
+    max_allocation: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl Limits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow at most `fuel` evaluation steps
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Allow at most `depth` nested calls to Scheme procedures
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Allow procedures to return lists of at most `size` elements and
+    /// strings of at most `size` bytes
+    pub fn with_max_allocation(mut self, size: usize) -> Self {
+        self.max_allocation = Some(size);
+        self
+    }
+
+    /// Stop evaluating `timeout` after the limits are set
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum number of evaluation steps
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// The maximum depth of nested calls to Scheme procedures
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// The maximum length of a list or string returned by a procedure
+    pub fn max_allocation(&self) -> Option<usize> {
+        self.max_allocation
+    }
+
+    /// How long evaluation may run
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// The resources an environment's evaluations have used
+#[derive(Debug)]
+pub(crate) struct Budget {
+    limits: Limits,
+    deadline: Option<Instant>,
+    /// Evaluation steps taken so far
+    steps: AtomicU64,
+}
+
+impl Budget {
+    /// Start a budget whose timeout runs from now
+    pub(crate) fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            steps: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Count an evaluation step, checking the fuel and the deadline
+    pub(crate) fn step(&self) -> Result<()> {
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(fuel) = self.limits.fuel
+            && steps > fuel
+        {
+            return Err(Error::fuel_exhausted(fuel));
+        }
+        if let Some(deadline) = self.deadline
+            && steps % DEADLINE_CHECK_INTERVAL == 1
+            && Instant::now() >= deadline
+        {
+            return Err(Error::deadline_exceeded());
+        }
+        Ok(())
+    }
+
+    /// Check a call to a Scheme procedure nested `depth` calls deep against
+    /// the depth limit
+    pub(crate) fn check_depth(&self, depth: usize) -> Result<()> {
+        match self.limits.max_depth {
+            Some(max_depth) if depth > max_depth => Err(Error::depth_exceeded(max_depth)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the size of a value returned by a builtin or native procedure
+    pub(crate) fn check_allocation(&self, value: &Value) -> Result<()> {
+        match value {
+            Value::List(list) => self.check_size(list.len()),
+            Value::String(string) => self.check_size(string.len()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the length of a list or string against the allocation limit
+    fn check_size(&self, size: usize) -> Result<()> {
+        let Some(max_allocation) = self.limits.max_allocation else {
+            return Ok(());
+        };
+        match size > max_allocation {
+            true => Err(Error::allocation_exceeded(max_allocation)),
+            false => Ok(()),
+        }
+    }
+}
+
+/// The evaluation a builtin, native procedure or special form was called
+/// from
+struct Caller {
+    budget: Option<Arc<Budget>>,
+    interrupt: Option<InterruptHandle>,
+    /// Lambda calls in progress, including those of the evaluations it is
+    /// nested in
+    depth: usize,
+}
+
+thread_local! {
+    /// The evaluation that the builtin, native procedure or special form
+    /// running on this thread was called from, if it has a budget or an
+    /// interrupt flag
+    static CALLER: RefCell<Option<Caller>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, a builtin, native procedure or special form called by an
+/// evaluation with `budget`, the interrupt flag `interrupt` and `depth`
+/// lambda calls in progress
+///
+/// Evaluations nested in `f` count their depth on from `depth` and check
+/// `interrupt`, and loops in `f` draw on `budget` and check `interrupt`.
+/// Without either nothing is limited, and `f` is simply called.
+pub(crate) fn called_from<R>(
+    budget: Option<&Arc<Budget>>,
+    interrupt: Option<&InterruptHandle>,
+    depth: usize,
+    f: impl FnOnce() -> R,
+) -> R {
+    if budget.is_none() && interrupt.is_none() {
+        return f();
+    }
+    /// Restores the outer caller, even if `f` panics
+    struct Restore(Option<Caller>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CALLER.set(self.0.take());
+        }
+    }
+    let caller = Caller {
+        budget: budget.cloned(),
+        interrupt: interrupt.cloned(),
+        depth,
+    };
+    let _restore = Restore(CALLER.replace(Some(caller)));
+    f()
+}
+
+/// How many lambda calls are in progress in the evaluations a new one is
+/// nested in
+pub(crate) fn outer_depth() -> usize {
+    CALLER.with_borrow(|caller| caller.as_ref().map_or(0, |caller| caller.depth))
+}
+
+/// The interrupt flag of the evaluations a new one is nested in, if any
+pub(crate) fn outer_interrupt() -> Option<InterruptHandle> {
+    CALLER.with_borrow(|caller| caller.as_ref()?.interrupt.clone())
+}
+
+/// Count a step of a loop in a builtin against the budget of the evaluation
+/// that called it, and check that evaluation's interrupt flag
+///
+/// `size` is the length of the list or string the loop has built so far,
+/// which is checked against the allocation limit.
+pub(crate) fn builtin_step(size: usize) -> Result<()> {
+    CALLER.with_borrow(|caller| {
+        let Some(caller) = caller else {
+            return Ok(());
+        };
+        if let Some(interrupt) = &caller.interrupt {
+            interrupt.check()?;
+        }
+        match &caller.budget {
+            Some(budget) => {
+                budget.step()?;
+                budget.check_size(size)
+            }
+            None => Ok(()),
+        }
+    })
+}
+
+/// The budget of the environment a procedure was defined in, if it is a
+/// Scheme procedure and has one
+pub(crate) fn procedure_budget(procedure: &Procedure) -> Option<Arc<Budget>> {
+    let lambda = match procedure {
+        Procedure::Lambda(_) | Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => {
+            procedure.resolve_weak_lambda().ok()?
+        }
+        _ => return None,
+    };
+    lambda.env().budget()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    #[test]
+    fn test_fuel_and_depth() {
+        let budget = Budget::new(Limits::new().with_fuel(2).with_max_depth(3));
+        assert!(budget.step().is_ok());
+        assert!(budget.check_depth(3).is_ok());
+        let error = budget.check_depth(4).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::DepthExceeded);
+        assert!(matches!(error, Error::DepthExceeded { max_depth: 3, .. }));
+        assert_eq!(
+            error.to_string(),
+            "Depth limit exceeded: more than 3 nested calls"
+        );
+
+        assert!(budget.step().is_ok());
+        let error = budget.step().unwrap_err();
+        assert_eq!(error.code(), ErrorCode::FuelExhausted);
+        assert!(matches!(error, Error::FuelExhausted { fuel: 2, .. }));
+        assert_eq!(
+            error.to_string(),
+            "Fuel exhausted: more than 2 evaluation steps"
+        );
+    }
+
+    #[test]
+    fn test_deadline() {
+        let budget = Budget::new(Limits::new().with_timeout(Duration::ZERO));
+        let error = budget.step().unwrap_err();
+        assert_eq!(error.code(), ErrorCode::DeadlineExceeded);
+        assert!(matches!(error, Error::DeadlineExceeded { .. }));
+
+        // Without limits, nothing is checked
+        let budget = Budget::new(Limits::new());
+        for _ in 0..1000 {
+            budget.step().unwrap();
+            budget.check_depth(usize::MAX).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_nested_depth() {
+        let budget = Arc::new(Budget::new(Limits::new()));
+        assert_eq!(outer_depth(), 0);
+        called_from(Some(&budget), None, 3, || {
+            assert_eq!(outer_depth(), 3);
+            called_from(Some(&budget), None, 5, || assert_eq!(outer_depth(), 5));
+            // Without a budget the depth is left as it is
+            called_from(None, None, 7, || assert_eq!(outer_depth(), 3));
+        });
+        assert_eq!(outer_depth(), 0);
+    }
+
+    #[test]
+    fn test_builtin_step() {
+        // Outside an evaluation with a budget, builtins loop freely
+        assert!(builtin_step(usize::MAX).is_ok());
+
+        let budget = Arc::new(Budget::new(
+            Limits::new().with_fuel(3).with_max_allocation(10),
+        ));
+        called_from(Some(&budget), None, 0, || {
+            assert!(builtin_step(10).is_ok());
+            let error = builtin_step(11).unwrap_err();
+            assert_eq!(error.code(), ErrorCode::AllocationExceeded);
+            assert!(builtin_step(1).is_ok());
+            let error = builtin_step(1).unwrap_err();
+            assert_eq!(error.code(), ErrorCode::FuelExhausted);
+        });
+    }
+
+    #[test]
+    fn test_allocation() {
+        let budget = Budget::new(Limits::new().with_max_allocation(2));
+        let short = Value::list(vec![Value::number(1.0), Value::number(2.0)]);
+        assert!(budget.check_allocation(&short).is_ok());
+        assert!(budget.check_allocation(&Value::number(1e9)).is_ok());
+
+        let error = budget.check_allocation(&Value::string("abc")).unwrap_err();
+        assert_eq!(error.code(), ErrorCode::AllocationExceeded);
+        assert!(matches!(
+            error,
+            Error::AllocationExceeded {
+                max_allocation: 2,
+                ..
+            }
+        ));
+    }
+}
@@ -5,6 +5,10 @@
 //! - `environment`: Identifier binding and scope management
 //! - `eval`: Core evaluation engine and expression dispatch
 //! - `special_forms`: Language constructs with special evaluation rules
+//! - `library`: Library registry, search path and import set resolution
+//! - `sandbox`: Capability restrictions for evaluating untrusted code
+//! - `limits`: Resource limits for evaluating untrusted code
+//! - `interrupt`: Stopping a running evaluation, as with Ctrl+C
 //! - `builtins`: Standard library procedures organized by category
 //!
 //! ## Special Forms
@@ -41,9 +45,15 @@
 pub mod builtins;
 pub mod environment;
 pub mod eval;
+pub mod interrupt;
+pub mod library;
+pub mod limits;
+pub mod sandbox;
 pub mod special_forms;
 pub mod utils;
 
 // Re-export key types for convenience
 pub use environment::Environment;
-pub use eval::eval;
+pub use eval::{eval, eval_async};
+pub use limits::Limits;
+pub use sandbox::Sandbox;
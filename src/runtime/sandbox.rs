@@ -0,0 +1,204 @@
+//! Capability-restricted environments for untrusted code
+//!
+//! A [`Sandbox`] lists the builtin procedures and special forms that code
+//! evaluated in an environment may use. Everything else is refused with a
+//! "not permitted in this sandbox" error rather than reported as unbound, so
+//! a denied capability is easy to tell apart from a typo:
+//!
+//! ```
+//! use twine_scheme::runtime::{Environment, Sandbox};
+//! use twine_scheme::runtime::builtins::Builtin;
+//!
+//! let env = Environment::new_sandboxed(Sandbox::safe());
+//! assert!(env.lookup_str("car").is_ok());
+//! assert!(env.lookup_str("display").is_err());
+//!
+//! let env = Environment::new_sandboxed(Sandbox::safe().with_builtin(Builtin::Display));
+//! assert!(env.lookup_str("display").is_ok());
+//! ```
+//!
+//! Identifiers defined by the sandboxed code itself are unaffected, so it
+//! may define its own `display`. Library bodies are not sandboxed: allowing
+//! `import` or `define-library` grants whatever the imported libraries do,
+//! except for builtin procedures the sandbox denies.
+
+use crate::runtime::builtins::Builtin;
+use crate::runtime::special_forms::SpecialForm;
+use std::collections::HashSet;
+
+/// Builtin procedures that cannot reach outside the interpreter
+const SAFE_BUILTINS: &[Builtin] = &[
+    Builtin::Add,
+    Builtin::Subtract,
+    Builtin::Multiply,
+    Builtin::Divide,
+    Builtin::Equal,
+    Builtin::LessThan,
+    Builtin::GreaterThan,
+    Builtin::LessThanOrEqual,
+    Builtin::GreaterThanOrEqual,
+    Builtin::Car,
+    Builtin::Cdr,
+    Builtin::Cons,
+    Builtin::List,
+    Builtin::NullP,
+    Builtin::Length,
+    Builtin::NumberP,
+    Builtin::StringP,
+    Builtin::BooleanP,
+    Builtin::SymbolP,
+    Builtin::ListP,
+    Builtin::ProcedureP,
+    Builtin::EqP,
+    Builtin::MakeParameter,
+    Builtin::SymbolToString,
+    Builtin::StringToSymbol,
+    Builtin::Gensym,
+    Builtin::GenerateUninternedSymbol,
+    Builtin::JsonToScheme,
+    Builtin::SchemeToJson,
+    Builtin::ProcedureName,
+    Builtin::Force,
+    Builtin::MakePromise,
+    Builtin::PromiseP,
+    Builtin::Stream,
+    Builtin::StreamCar,
+    Builtin::StreamCdr,
+    Builtin::StreamNullP,
+    Builtin::StreamPairP,
+    Builtin::StreamToList,
+];
+
+/// Special forms that neither load code nor start concurrent tasks
+const SAFE_SPECIAL_FORMS: &[SpecialForm] = &[
+    SpecialForm::If,
+    SpecialForm::Begin,
+    SpecialForm::And,
+    SpecialForm::Or,
+    SpecialForm::Define,
+    SpecialForm::Let,
+    SpecialForm::LetStar,
+    SpecialForm::Letrec,
+    SpecialForm::LetrecStar,
+    SpecialForm::Lambda,
+    SpecialForm::DefineRecordType,
+    SpecialForm::Parameterize,
+    SpecialForm::Delay,
+    SpecialForm::DelayForce,
+    SpecialForm::StreamCons,
+];
+
+/// The builtin procedures and special forms permitted in an environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sandbox {
+    builtins: HashSet<Builtin>,
+    special_forms: HashSet<SpecialForm>,
+}
+
+impl Sandbox {
+    /// A sandbox that permits nothing
+    ///
+    /// Capabilities are added with [`Sandbox::with_builtin`] and
+    /// [`Sandbox::with_special_form`].
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// A sandbox for pure computation
+    ///
+    /// Permits every builtin procedure and special form except those that
+    /// perform I/O (`display`, `newline`), inspect the process
+    /// (`command-line`), load code (`import`, `define-library`) or start
+    /// concurrent tasks (`async`).
+    pub fn safe() -> Self {
+        Self {
+            builtins: SAFE_BUILTINS.iter().copied().collect(),
+            special_forms: SAFE_SPECIAL_FORMS.iter().copied().collect(),
+        }
+    }
+
+    /// Permit a builtin procedure
+    pub fn with_builtin(mut self, builtin: Builtin) -> Self {
+        self.builtins.insert(builtin);
+        self
+    }
+
+    /// Deny a builtin procedure
+    pub fn without_builtin(mut self, builtin: Builtin) -> Self {
+        self.builtins.remove(&builtin);
+        self
+    }
+
+    /// Permit a special form
+    pub fn with_special_form(mut self, form: SpecialForm) -> Self {
+        self.special_forms.insert(form);
+        self
+    }
+
+    /// Deny a special form
+    pub fn without_special_form(mut self, form: SpecialForm) -> Self {
+        self.special_forms.remove(&form);
+        self
+    }
+
+    /// Check if a builtin procedure is permitted
+    pub fn permits_builtin(&self, builtin: Builtin) -> bool {
+        self.builtins.contains(&builtin)
+    }
+
+    /// Check if a special form is permitted
+    pub fn permits_special_form(&self, form: SpecialForm) -> bool {
+        self.special_forms.contains(&form)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_sandbox() {
+        let sandbox = Sandbox::safe();
+        assert!(sandbox.permits_builtin(Builtin::Car));
+        assert!(sandbox.permits_special_form(SpecialForm::Lambda));
+        for builtin in [Builtin::Display, Builtin::Newline, Builtin::CommandLine] {
+            assert!(!sandbox.permits_builtin(builtin), "{}", builtin.name());
+        }
+        for form in [
+            SpecialForm::Import,
+            SpecialForm::DefineLibrary,
+            SpecialForm::Async,
+        ] {
+            assert!(!sandbox.permits_special_form(form), "{}", form.name());
+        }
+
+        // Every builtin and special form is either safe or deliberately denied
+        let denied = Builtin::ALL
+            .iter()
+            .filter(|builtin| !sandbox.permits_builtin(**builtin))
+            .count();
+        assert_eq!(denied, 3);
+        let denied = SpecialForm::ALL
+            .iter()
+            .filter(|form| !sandbox.permits_special_form(**form))
+            .count();
+        assert_eq!(denied, 3);
+    }
+
+    #[test]
+    fn test_sandbox_builder() {
+        let sandbox = Sandbox::empty()
+            .with_builtin(Builtin::Add)
+            .with_special_form(SpecialForm::If);
+        assert!(sandbox.permits_builtin(Builtin::Add));
+        assert!(!sandbox.permits_builtin(Builtin::Subtract));
+        assert!(sandbox.permits_special_form(SpecialForm::If));
+        assert!(!sandbox.permits_special_form(SpecialForm::Define));
+
+        let sandbox = Sandbox::safe()
+            .without_builtin(Builtin::Car)
+            .without_special_form(SpecialForm::Define);
+        assert!(!sandbox.permits_builtin(Builtin::Car));
+        assert!(!sandbox.permits_special_form(SpecialForm::Define));
+    }
+}
@@ -11,10 +11,12 @@
 use crate::Error;
 use crate::error::Result;
 use crate::parser::Expression;
-use crate::runtime::special_forms::lambda::create_lambda_procedure;
+use crate::runtime::eval::locate_error;
+use crate::runtime::special_forms::lambda::{create_lambda_procedure, eval_named_lambda};
+use crate::runtime::special_forms::{SpecialForm, eval_form};
 use crate::runtime::utils::{
-    eval_sequence, is_lambda_expression, parse_parameters, validate_unique_binding_identifiers,
-    validate_unique_parameters,
+    is_lambda_expression, parse_parameters, validate_unique_binding_exprs,
+    validate_unique_parameter_exprs,
 };
 use crate::runtime::{environment::Environment, eval::eval};
 use crate::types::{Procedure, Symbol, Value};
@@ -37,6 +39,17 @@ use std::sync::Arc;
 /// For procedure definition (syntactic sugar):
 /// - (define (name param1 param2) body1 body2...)
 /// - Equivalent to: (define name (lambda (param1 param2) body1 body2...))
+///
+/// Internal definitions bind in order. The procedures a body defines can
+/// call each other whatever their order, as R7RS `letrec*` allows: those
+/// referred to before their definitions are bound to placeholders when the
+/// body starts (see [`declare_body_procedures`]), which closures created
+/// earlier hold on to, so they may outlive the body.
+///
+/// Unlike `letrec*`, other values are only bound when their definitions are
+/// reached. A closure copies the local bindings it refers to when it is
+/// created, so one created before such a definition does not see it: in
+/// `(define (get) later) (define later 42)`, `later` is unbound in `get`.
 pub fn eval_define(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
     if args.is_empty() {
         return Err(Error::arity_error("define", 2, 0));
@@ -45,12 +58,14 @@ pub fn eval_define(args: &[Arc<Expression>], env: &mut Environment) -> Result<Va
     let first_arg = Arc::clone(&args[0]);
     match first_arg.as_ref() {
         // Binding definition: (define identifier expression)
-        Expression::Atom(Value::Symbol(identifier)) => {
+        Expression::Atom(Value::Symbol(identifier), _) => {
             eval_define_binding(identifier, &args[1..], env)
         }
 
         // Procedure definition: (define (name param...) body...)
-        Expression::List(param_elements) => eval_define_procedure(param_elements, &args[1..], env),
+        Expression::List(param_elements, _) => {
+            eval_define_procedure(param_elements, &args[1..], env)
+        }
 
         _ => Err(Error::runtime_error(
             "define: first argument must be a symbol or parameter list",
@@ -68,71 +83,250 @@ pub fn eval_define(args: &[Arc<Expression>], env: &mut Environment) -> Result<Va
 /// 3. Bind identifiers (id1, id2, ...) to their evaluated values simultaneously
 /// 4. Evaluate body expressions sequentially in the new environment
 /// 5. Return the value of the last body expression
+///
+/// Named let: (let name ((id1 expr1) ...) body1 body2 ...)
+///
+/// Binds `name` within the body to a procedure taking `id1 ...` whose body is
+/// `body1 body2 ...`, then calls it with the values of `expr1 ...`. Calls to
+/// `name` in tail position are iterative, so named let expresses loops.
 pub fn eval_let(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    eval_form(SpecialForm::Let, args, env)
+}
+
+/// Evaluate a letrec special form
+///
+/// Syntax: (letrec ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
+///
+/// Letrec enables recursive and mutually recursive bindings by:
+/// 1. Evaluating non-lambda expressions in the new environment
+/// 2. Creating ForwardLambda placeholders for the lambda identifiers
+/// 3. Evaluating lambda expressions in an environment containing all placeholders
+/// 4. Pointing the placeholders at the actual lambdas and binding them
+/// 5. Evaluating body expressions in the final environment
+///
+/// Returns the value of the last body expression.
+pub fn eval_letrec(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    eval_form(SpecialForm::Letrec, args, env)
+}
+
+/// Evaluate a let* special form
+///
+/// Syntax: (let* ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
+///
+/// Semantics:
+/// 1. Create a new environment with current environment as parent
+/// 2. Bind identifiers sequentially: each binding can see previous bindings
+/// 3. Evaluate body expressions sequentially in the final environment
+pub fn eval_let_star(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    eval_form(SpecialForm::LetStar, args, env)
+}
+
+/// Evaluate a letrec* special form
+///
+/// Syntax: (letrec* ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
+///
+/// Semantics:
+/// 1. Create a new environment with current environment as parent
+/// 2. Bind identifiers sequentially with recursive capability
+/// 3. Each binding can refer to earlier bindings and can be recursive
+/// 4. Evaluate body expressions sequentially in the final environment
+pub fn eval_letrec_star(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    eval_form(SpecialForm::LetrecStar, args, env)
+}
+
+// ============================================================================
+// SYNTAX CHECKING
+// ============================================================================
+
+/// Check a (non-named) let form and split its bindings
+///
+/// Returns the identifiers and the expressions that initialise them.
+pub(crate) fn parse_let(args: &[Arc<Expression>]) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     if args.is_empty() {
         return Err(Error::arity_error("let", 1, 0));
     }
 
-    // First argument must be the binding list
-    let bindings_expr = Arc::clone(&args[0]);
-    let body_exprs = &args[1..];
-
-    if body_exprs.is_empty() {
+    if args.len() < 2 {
         return Err(Error::runtime_error(
             "let: requires at least one body expression",
         ));
     }
 
-    // Parse binding list using shared helper
-    let (identifiers, expressions) = parse_bindings(bindings_expr.as_ref(), "let")?;
+    let bindings = parse_bindings(args[0].as_ref(), "let")?;
+    validate_unique_let_identifiers(args[0].as_ref())?;
+    Ok(bindings)
+}
 
-    // Evaluate all expressions in the current environment BEFORE creating bindings
-    let mut values = Vec::with_capacity(expressions.len());
-    for expr in expressions {
-        let value = eval(expr, env)?;
-        values.push(value);
+/// Check a named let form, whose arguments follow the name
+///
+/// Returns the loop identifiers and the expressions for their initial values.
+pub(crate) fn parse_named_let(
+    args: &[Arc<Expression>],
+) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
+    if args.len() < 2 {
+        return Err(Error::runtime_error(
+            "let: named let requires a binding list and at least one body expression",
+        ));
     }
 
-    // Create new environment with current environment as parent
-    let mut let_env = Environment::new_scope(env);
+    let bindings = parse_bindings(args[0].as_ref(), "let")?;
+    validate_unique_let_identifiers(args[0].as_ref())?;
+    Ok(bindings)
+}
+
+/// Check that the identifiers of a let binding list are distinct
+///
+/// A let binds its identifiers simultaneously, so a repeated identifier
+/// could take either value; it is reported as in letrec.
+fn validate_unique_let_identifiers(bindings_expr: &Expression) -> Result<()> {
+    let Expression::List(bindings, _) = bindings_expr else {
+        return Ok(());
+    };
+    let identifier_exprs = bindings
+        .iter()
+        .filter_map(|binding| match binding.as_ref() {
+            Expression::List(elements, _) => elements.first().map(AsRef::as_ref),
+            _ => None,
+        });
+    validate_unique_binding_exprs(identifier_exprs, "let")
+}
 
-    // Bind all identifiers simultaneously in the new environment
-    for (identifier, value) in identifiers.into_iter().zip(values.into_iter()) {
-        let_env.define(identifier, value);
+/// Create the loop procedure of a named let
+///
+/// The procedure is bound to `name` in its own scope so that it can call
+/// itself, and closes over `env`.
+pub(crate) fn named_let_procedure(
+    name: &Symbol,
+    identifiers: Vec<Symbol>,
+    body_exprs: &[Arc<Expression>],
+    env: &Environment,
+) -> Result<Procedure> {
+    let mut loop_env = Environment::new_scope(env);
+    let body_exprs = body_exprs.iter().map(Arc::clone).collect();
+    let procedure = bind_recursive_lambda(name, &mut loop_env, |recursive_env| {
+        Ok(create_lambda_procedure(
+            identifiers,
+            body_exprs,
+            Some(name.clone()),
+            recursive_env,
+        ))
+    })?;
+
+    match procedure {
+        Value::Procedure(procedure) => Ok(procedure),
+        _ => unreachable!("named let always binds a procedure"),
     }
+}
 
-    // Evaluate body expressions sequentially in new environment using shared helper
-    eval_sequence(body_exprs, &mut let_env)
+/// The procedures a body defines that it refers to before their definitions
+///
+/// So that a body's procedures can call each other whatever their order,
+/// these need placeholders bound before the body runs; a procedure referring
+/// only to itself or to earlier definitions needs none. Other values defined
+/// later get no placeholder, so code before their definitions cannot see
+/// them.
+pub(crate) fn forward_procedure_names(body: &[Arc<Expression>]) -> Vec<Symbol> {
+    let mut names = Vec::new();
+    for (index, expr) in body.iter().enumerate() {
+        if let Some(name) = procedure_definition_name(expr)
+            && body[..index].iter().any(|earlier| refers_to(earlier, name))
+        {
+            names.push(name.clone());
+        }
+    }
+    names
 }
 
-/// Evaluate a letrec special form
+/// The identifier of a procedure definition, either
+/// `(define (name param...) body...)` or `(define name (lambda ...))`
+fn procedure_definition_name(expr: &Expression) -> Option<&Symbol> {
+    let [keyword, target, rest @ ..] = expr.as_list()?.as_slice() else {
+        return None;
+    };
+    match keyword.as_ref() {
+        Expression::Atom(Value::Symbol(keyword), _)
+            if SpecialForm::from_name(keyword.as_str()) == Some(SpecialForm::Define) => {}
+        _ => return None,
+    }
+    match (target.as_ref(), rest) {
+        (Expression::Atom(Value::Symbol(name), _), [value_expr])
+            if is_lambda_expression(value_expr) =>
+        {
+            Some(name)
+        }
+        (Expression::List(signature, _), [_, ..]) => match signature.first()?.as_ref() {
+            Expression::Atom(Value::Symbol(name), _) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Check if `name` occurs anywhere in an expression
+fn refers_to(expr: &Expression, name: &Symbol) -> bool {
+    match expr {
+        Expression::Atom(Value::Symbol(symbol), _) => symbol == name,
+        Expression::List(elements, _) => elements.iter().any(|element| refers_to(element, name)),
+        _ => false,
+    }
+}
+
+/// Bind a placeholder for each procedure a body refers to before defining
+/// it
 ///
-/// Syntax: (letrec ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
+/// Each definition then points its placeholder at the procedure, as
+/// `letrec` does. Closures created before the definition refer to the
+/// procedure through the placeholder, which keeps it alive.
 ///
-/// Letrec enables recursive and mutually recursive bindings by:
-/// 1. Creating WeakLambda placeholders for all identifiers first
-/// 2. Evaluating expressions in an environment containing all placeholders
-/// 3. Initializing WeakLambdas with actual lambdas
-/// 4. Replacing WeakLambdas with actual Lambda procedures
-/// 5. Evaluating body expressions in the final environment
+/// Procedures calling each other this way refer to each other strongly, so
+/// they are only freed with the program.
+pub(crate) fn declare_body_procedures(body: &[Arc<Expression>], env: &mut Environment) {
+    if env.is_global() {
+        return;
+    }
+    for name in forward_procedure_names(body) {
+        env.define(name, Value::Procedure(Procedure::forward_lambda()));
+    }
+}
+
+/// Bind a placeholder for each lambda of a `letrec*` that the initial value
+/// of an earlier binding refers to, as for the procedures of a body
+pub(crate) fn declare_letrec_star_procedures(
+    identifiers: &[Symbol],
+    inits: &[Arc<Expression>],
+    env: &mut Environment,
+) {
+    for (index, (identifier, init)) in identifiers.iter().zip(inits).enumerate() {
+        if is_lambda_expression(init)
+            && inits[..index]
+                .iter()
+                .any(|earlier| refers_to(earlier, identifier))
+        {
+            env.define(
+                identifier.clone(),
+                Value::Procedure(Procedure::forward_lambda()),
+            );
+        }
+    }
+}
+
+/// Check a letrec form and split its bindings
 ///
-/// Returns the value of the last body expression.
-pub fn eval_letrec(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+/// Returns the identifiers and the expressions that initialise them.
+pub(crate) fn parse_letrec(
+    args: &[Arc<Expression>],
+) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     if args.is_empty() {
         return Err(Error::arity_error("letrec", 1, 0));
     }
 
-    // First argument must be the binding list
-    let bindings_expr = Arc::clone(&args[0]);
-    let body_exprs = &args[1..];
-
-    if body_exprs.is_empty() {
+    if args.len() < 2 {
         return Err(Error::arity_error("letrec", 2, args.len()));
     }
 
     // Parse binding list
-    let binding_elements = match bindings_expr.as_ref() {
-        Expression::List(elements) => elements,
+    let binding_elements = match args[0].as_ref() {
+        Expression::List(elements, _) => elements,
         other => {
             return Err(Error::binding_list_must_be_list_error(
                 "letrec",
@@ -143,11 +337,12 @@ pub fn eval_letrec(args: &[Arc<Expression>], env: &mut Environment) -> Result<Va
 
     // Parse and validate individual bindings
     let mut identifiers = Vec::with_capacity(binding_elements.len());
+    let mut identifier_exprs = Vec::with_capacity(binding_elements.len());
     let mut value_exprs = Vec::with_capacity(binding_elements.len());
 
     for binding_expr in binding_elements {
         let binding_elements = match binding_expr.as_ref() {
-            Expression::List(elements) => elements,
+            Expression::List(elements, _) => elements,
             other => {
                 return Err(Error::binding_must_be_list_error(
                     "letrec",
@@ -166,7 +361,7 @@ pub fn eval_letrec(args: &[Arc<Expression>], env: &mut Environment) -> Result<Va
 
         // Extract identifier
         let identifier = match binding_elements[0].as_ref() {
-            Expression::Atom(Value::Symbol(id)) => id.clone(),
+            Expression::Atom(Value::Symbol(id), _) => id.clone(),
             other => {
                 return Err(Error::identifier_must_be_symbol_error(
                     "letrec",
@@ -175,183 +370,132 @@ pub fn eval_letrec(args: &[Arc<Expression>], env: &mut Environment) -> Result<Va
             }
         };
 
-        // Extract value expression
-        let value_expr = Arc::clone(&binding_elements[1]);
-
         identifiers.push(identifier);
-        value_exprs.push(value_expr);
+        identifier_exprs.push(binding_elements[0].as_ref());
+        value_exprs.push(Arc::clone(&binding_elements[1]));
     }
 
     // Check for duplicate identifiers using shared utility
-    validate_unique_binding_identifiers(&identifiers, "letrec")?;
-
-    // Create new environment extending the current one
-    let mut letrec_env = Environment::new_scope(env);
+    validate_unique_binding_exprs(identifier_exprs, "letrec")?;
 
-    // Phase 1: Identify lambda and non-lambda expressions
-    let mut is_lambda_expr = Vec::with_capacity(value_exprs.len());
-    let mut lambda_indices = Vec::new();
-    let mut non_lambda_indices = Vec::new();
-
-    for (i, value_expr) in value_exprs.iter().enumerate() {
-        let is_lambda = is_lambda_expression(value_expr);
-        is_lambda_expr.push(is_lambda);
-
-        if is_lambda {
-            lambda_indices.push(i);
-        } else {
-            non_lambda_indices.push(i);
-        }
-    }
+    Ok((identifiers, value_exprs))
+}
 
-    // Phase 2: Evaluate non-lambda expressions first and add them to environment
-    for &i in &non_lambda_indices {
-        let value = eval(Arc::clone(&value_exprs[i]), &mut letrec_env)?;
-        letrec_env.define(identifiers[i].clone(), value);
-    }
+/// Bind the lambda expressions of a letrec once its other values are bound
+///
+/// Every lambda identifier is first bound to a ForwardLambda placeholder,
+/// so the lambdas can refer to each other; each is then defined as a
+/// recursive procedure, which points its placeholder at it.
+pub(crate) fn bind_letrec_lambdas(
+    identifiers: &[Symbol],
+    value_exprs: &[Arc<Expression>],
+    letrec_env: &mut Environment,
+) -> Result<()> {
+    let lambda_indices: Vec<usize> = (0..value_exprs.len())
+        .filter(|&i| is_lambda_expression(&value_exprs[i]))
+        .collect();
 
-    // Phase 3: Create WeakLambda placeholders for lambda expressions
-    let mut weak_lambdas = Vec::new();
+    // Create placeholders for lambda expressions
     for &i in &lambda_indices {
-        let weak_lambda = Procedure::weak_lambda();
-        weak_lambdas.push(weak_lambda.clone());
-        letrec_env.define(identifiers[i].clone(), Value::Procedure(weak_lambda));
+        letrec_env.define(
+            identifiers[i].clone(),
+            Value::Procedure(Procedure::forward_lambda()),
+        );
     }
 
-    // Phase 4: Evaluate lambda expressions (they can now see all non-lambda values and other WeakLambdas)
-    let mut lambda_values = Vec::new();
+    // Evaluate lambda expressions (they can see all non-lambda values, the
+    // lambdas bound so far and the placeholders of the rest)
     for &i in &lambda_indices {
-        let lambda_value = eval(Arc::clone(&value_exprs[i]), &mut letrec_env)?;
-        lambda_values.push(lambda_value);
-    }
-
-    // Phase 5: Initialize WeakLambdas with actual lambdas
-    for (weak_lambda, lambda_value) in weak_lambdas.iter().zip(lambda_values.iter()) {
-        if let Value::Procedure(Procedure::Lambda(actual_lambda)) = lambda_value {
-            weak_lambda
-                .set_weak_lambda(actual_lambda)
-                .map_err(|_| Error::runtime_error("Failed to initialize WeakLambda"))?;
-        }
-    }
-
-    // Phase 6: Replace WeakLambdas with actual lambdas in the environment
-    for (&i, lambda_value) in lambda_indices.iter().zip(lambda_values.iter()) {
-        letrec_env.define(identifiers[i].clone(), lambda_value.clone());
+        let lambda_args = lambda_arguments(&value_exprs[i]).unwrap_or_default();
+        bind_recursive_lambda(&identifiers[i], letrec_env, |recursive_env| {
+            eval_named_lambda(lambda_args, &identifiers[i], recursive_env)
+        })
+        .map_err(|error| locate_error(error, &value_exprs[i]))?;
     }
 
-    // Evaluate body expressions sequentially in the final environment
-    eval_sequence(body_exprs, &mut letrec_env)
+    Ok(())
 }
 
-/// Evaluate a let* special form
-///
-/// Syntax: (let* ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
-///
-/// Semantics:
-/// 1. Create a new environment with current environment as parent
-/// 2. Bind identifiers sequentially: each binding can see previous bindings
-/// 3. Evaluate body expressions sequentially in the final environment
-pub fn eval_let_star(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+/// Check a let* form and split its bindings
+pub(crate) fn parse_let_star(
+    args: &[Arc<Expression>],
+) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     if args.is_empty() {
         return Err(Error::arity_error("let*", 1, 0));
     }
 
-    // First argument must be the binding list
-    let bindings_expr = Arc::clone(&args[0]);
-    let body_exprs = &args[1..];
-
-    if body_exprs.is_empty() {
+    if args.len() < 2 {
         return Err(Error::runtime_error(
             "let*: requires at least one body expression",
         ));
     }
 
-    // Parse binding list
-    let (identifiers, expressions) = parse_bindings(bindings_expr.as_ref(), "let*")?;
-
-    // Create new environment with current environment as parent
-    let mut letstar_env = Environment::new_scope(env);
-
-    // Bind identifiers sequentially - each can see previous bindings
-    for (identifier, expression) in identifiers.into_iter().zip(expressions.into_iter()) {
-        let value = eval(expression, &mut letstar_env)?;
-        letstar_env.define(identifier, value);
-    }
-
-    // Evaluate body expressions sequentially in new environment
-    eval_sequence(body_exprs, &mut letstar_env)
+    parse_bindings(args[0].as_ref(), "let*")
 }
 
-/// Evaluate a letrec* special form
-///
-/// Syntax: (letrec* ((id1 expr1) (id2 expr2) ...) body1 body2 ...)
-///
-/// Semantics:
-/// 1. Create a new environment with current environment as parent
-/// 2. Bind identifiers sequentially with recursive capability
-/// 3. Each binding can refer to earlier bindings and can be recursive
-/// 4. Evaluate body expressions sequentially in the final environment
-pub fn eval_letrec_star(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+/// Check a letrec* form and split its bindings
+pub(crate) fn parse_letrec_star(
+    args: &[Arc<Expression>],
+) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     if args.is_empty() {
         return Err(Error::arity_error("letrec*", 1, 0));
     }
 
-    // First argument must be the binding list
-    let bindings_expr = Arc::clone(&args[0]);
-    let body_exprs = &args[1..];
-
-    if body_exprs.is_empty() {
+    if args.len() < 2 {
         return Err(Error::arity_error("letrec*", 2, args.len()));
     }
 
-    // Parse binding list
-    let (identifiers, expressions) = parse_bindings(bindings_expr.as_ref(), "letrec*")?;
-
-    // Create new environment with current environment as parent
-    let mut letrecstar_env = Environment::new_scope(env);
+    parse_bindings(args[0].as_ref(), "letrec*")
+}
 
-    // Bind identifiers sequentially with recursive capability
-    for (identifier, expression) in identifiers.into_iter().zip(expressions.into_iter()) {
-        // Use recursive binding helper for each binding
-        eval_recursive_binding(&identifier, expression, &mut letrecstar_env)?;
+/// Get the identifier and value expression of a `define` whose value must
+/// be evaluated, such as `(define x (+ 1 2))`
+///
+/// Other definitions, including procedure definitions and malformed forms,
+/// return None and are handled by [`eval_define`].
+pub(crate) fn value_definition(args: &[Arc<Expression>]) -> Option<(&Symbol, &Arc<Expression>)> {
+    match args {
+        [identifier, value_expr] if !is_lambda_expression(value_expr) => {
+            match identifier.as_ref() {
+                Expression::Atom(Value::Symbol(identifier), _) => Some((identifier, value_expr)),
+                _ => None,
+            }
+        }
+        _ => None,
     }
-
-    // Evaluate body expressions sequentially in new environment
-    eval_sequence(body_exprs, &mut letrecstar_env)
 }
 
 // ============================================================================
 // HELPER FUNCTIONS
 // ============================================================================
 
+/// Bind a lambda expression to an identifier it can call itself by
+///
+/// Returns false, binding nothing, if `value_expr` is not a lambda expression.
+pub(crate) fn bind_lambda_expression(
+    identifier: &Symbol,
+    value_expr: &Arc<Expression>,
+    env: &mut Environment,
+) -> Result<bool> {
+    let Some(lambda_args) = lambda_arguments(value_expr) else {
+        return Ok(false);
+    };
+
+    // Create a named lambda that can refer to itself
+    bind_recursive_lambda(identifier, env, |recursive_env| {
+        eval_named_lambda(lambda_args, identifier, recursive_env)
+            .map_err(|error| locate_error(error, value_expr))
+    })?;
+    Ok(true)
+}
+
 /// Handles recursive binding for lambda expressions using WeakLambda approach
 fn eval_recursive_binding(
     identifier: &Symbol,
     value_expr: Arc<Expression>,
     env: &mut Environment,
 ) -> Result<Value> {
-    if is_lambda_expression(&value_expr) {
-        // For lambda expressions, use WeakLambda approach for potential recursion
-        // 1. Create WeakLambda placeholder
-        let weak_lambda = Procedure::weak_lambda();
-
-        // 2. Create environment with the WeakLambda placeholder for recursive reference
-        let mut recursive_env = env.flatten();
-        recursive_env.define(identifier.clone(), Value::Procedure(weak_lambda.clone()));
-
-        // 3. Evaluate the lambda in the environment with WeakLambda
-        let lambda_value = eval(value_expr, &mut recursive_env)?;
-
-        // 4. Initialize WeakLambda with actual lambda
-        if let Value::Procedure(Procedure::Lambda(actual_lambda)) = &lambda_value {
-            weak_lambda
-                .set_weak_lambda(actual_lambda)
-                .map_err(|_| Error::runtime_error("Failed to initialize WeakLambda"))?;
-        }
-
-        // 5. Add the final lambda to the environment
-        env.define(identifier.clone(), lambda_value);
-    } else {
+    if !bind_lambda_expression(identifier, &value_expr, env)? {
         // For non-lambda expressions, use standard evaluation
         let value = eval(value_expr, env)?;
         env.define(identifier.clone(), value);
@@ -360,6 +504,61 @@ fn eval_recursive_binding(
     Ok(Value::Nil)
 }
 
+/// Bind a procedure that may call itself by name, using the WeakLambda approach
+///
+/// `create` builds the procedure in an environment where `identifier` is
+/// bound to a WeakLambda placeholder, which is then pointed at the result,
+/// as is the ForwardLambda placeholder bound for it in `env`, if any.
+/// A global definition needs no placeholder: the procedure finds itself in
+/// the global environment when it runs, as it finds any later redefinition.
+fn bind_recursive_lambda(
+    identifier: &Symbol,
+    env: &mut Environment,
+    create: impl FnOnce(&Environment) -> Result<Value>,
+) -> Result<Value> {
+    if env.is_global() {
+        let lambda_value = create(env)?;
+        env.define(identifier.clone(), lambda_value.clone());
+        return Ok(lambda_value);
+    }
+
+    // 1. Create WeakLambda placeholder, so the procedure does not keep
+    // itself alive
+    let weak_lambda = Procedure::weak_lambda();
+
+    // 2. Create environment with the WeakLambda placeholder for recursive reference
+    let mut recursive_env = Environment::new_scope(env);
+    recursive_env.define(identifier.clone(), Value::Procedure(weak_lambda.clone()));
+
+    // 3. Create the lambda in the environment with WeakLambda
+    let lambda_value = create(&recursive_env)?;
+
+    // 4. Initialize WeakLambda with actual lambda, and the placeholder that
+    // closures created before the definition refer to
+    if let Value::Procedure(Procedure::Lambda(actual_lambda)) = &lambda_value {
+        weak_lambda
+            .set_weak_lambda(actual_lambda)
+            .map_err(|_| Error::runtime_error("Failed to initialize WeakLambda"))?;
+        if let Some(Value::Procedure(placeholder)) = env.lookup_own(identifier)
+            && placeholder.is_placeholder()
+        {
+            placeholder.fill_placeholder(actual_lambda)?;
+        }
+    }
+
+    // 5. Add the final lambda to the environment
+    env.define(identifier.clone(), lambda_value.clone());
+    Ok(lambda_value)
+}
+
+/// Get the arguments of a lambda expression: its parameter list and body
+fn lambda_arguments(expr: &Expression) -> Option<&[Arc<Expression>]> {
+    match expr {
+        Expression::List(elements, _) if is_lambda_expression(expr) => Some(&elements[1..]),
+        _ => None,
+    }
+}
+
 fn eval_define_binding(
     identifier: &Symbol,
     value_exprs: &[Arc<Expression>],
@@ -378,6 +577,29 @@ fn eval_define_procedure(
     args: &[Arc<Expression>],
     env: &mut Environment,
 ) -> Result<Value> {
+    let (identifier, params) = parse_define_procedure(param_elements, args)?;
+
+    // Create the named lambda where it can see its own name
+    let body_exprs = args.iter().map(Arc::clone).collect();
+    bind_recursive_lambda(&identifier, env, |recursive_env| {
+        Ok(create_lambda_procedure(
+            params,
+            body_exprs,
+            Some(identifier.clone()),
+            recursive_env,
+        ))
+    })?;
+    Ok(Value::Nil)
+}
+
+/// Check a procedure definition `(define (name param...) body...)`
+///
+/// `param_elements` is the `(name param...)` list and `body` the body
+/// expressions. Returns the procedure name and its parameters.
+pub(crate) fn parse_define_procedure(
+    param_elements: &[Arc<Expression>],
+    body: &[Arc<Expression>],
+) -> Result<(Symbol, Vec<Symbol>)> {
     if param_elements.is_empty() {
         return Err(Error::runtime_error(
             "define: procedure definition requires non-empty parameter list",
@@ -386,7 +608,7 @@ fn eval_define_procedure(
 
     // Extract procedure name
     let identifier = match param_elements[0].as_ref() {
-        Expression::Atom(Value::Symbol(name)) => name.clone(),
+        Expression::Atom(Value::Symbol(name), _) => name.clone(),
         other => {
             return Err(Error::procedure_name_must_be_symbol_error(
                 "define",
@@ -397,30 +619,25 @@ fn eval_define_procedure(
 
     // Extract and validate parameters
     let params = parse_parameters(&param_elements[1..], "define")?;
-    validate_unique_parameters(&params, "define")?;
+    validate_unique_parameter_exprs(&param_elements[1..], "define")?;
 
     // Validate procedure body
-    if args.is_empty() {
+    if body.is_empty() {
         return Err(Error::runtime_error(
             "define: procedure definition requires at least one body expression",
         ));
     }
 
-    // Create lambda procedure directly using shared logic
-    let body_exprs = args.iter().map(Arc::clone).collect();
-    let lambda_value = create_lambda_procedure(params, body_exprs, env);
-
-    // Use the shared recursive binding logic
-    eval_recursive_binding(&identifier, Arc::new(Expression::Atom(lambda_value)), env)
+    Ok((identifier, params))
 }
 
 /// Helper function to parse binding list into identifiers and expressions
-fn parse_bindings(
+pub(crate) fn parse_bindings(
     bindings_expr: &Expression,
     form_name: &str,
 ) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     let binding_pairs = match bindings_expr {
-        Expression::List(pairs) => pairs,
+        Expression::List(pairs, _) => pairs,
         other => {
             return Err(Error::first_argument_must_be_list_of_bindings_error(
                 form_name,
@@ -434,15 +651,15 @@ fn parse_bindings(
 
     for pair in binding_pairs {
         match pair.as_ref() {
-            Expression::List(elements) => {
+            Expression::List(elements, _) => {
                 if elements.len() != 2 {
                     return Err(Error::binding_elements_wrong_arity_error(form_name));
                 }
 
                 // First element must be a symbol (identifier name)
                 let identifier = match elements[0].as_ref() {
-                    Expression::Atom(Value::Symbol(sym)) => sym.clone(),
-                    Expression::Atom(atom) => {
+                    Expression::Atom(Value::Symbol(sym), _) => sym.clone(),
+                    Expression::Atom(atom, _) => {
                         return Err(Error::identifier_must_be_symbol_error(
                             form_name,
                             atom.type_name(),
@@ -16,8 +16,8 @@
 
 use crate::error::Result;
 use crate::parser::Expression;
-use crate::runtime::utils::eval_sequence;
-use crate::runtime::{environment::Environment, eval::eval};
+use crate::runtime::environment::Environment;
+use crate::runtime::special_forms::{SpecialForm, eval_form};
 use crate::types::Value;
 use std::sync::Arc;
 
@@ -28,24 +28,7 @@ use std::sync::Arc;
 /// - If the result is truthy (anything except #f), evaluates and returns `consequent`
 /// - If the result is falsy (#f), evaluates and returns `alternative`
 pub fn eval_if(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
-    // if requires exactly 3 arguments: test, consequent, alternative
-    if args.len() != 3 {
-        return Err(crate::Error::arity_error("if", 3, args.len()));
-    }
-
-    let test_expr = Arc::clone(&args[0]);
-    let consequent_expr = Arc::clone(&args[1]);
-    let alternative_expr = Arc::clone(&args[2]);
-
-    // Evaluate the test expression
-    let test_result = eval(test_expr, env)?;
-
-    // In Scheme, only #f is false, everything else is true
-    if test_result.is_truthy() {
-        eval(consequent_expr, env)
-    } else {
-        eval(alternative_expr, env)
-    }
+    eval_form(SpecialForm::If, args, env)
 }
 
 /// Evaluate a begin special form
@@ -55,7 +38,7 @@ pub fn eval_if(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value>
 /// - Returns the value of the last expression
 /// - If no expressions are provided, returns Nil
 pub fn eval_begin(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
-    eval_sequence(args, env)
+    eval_form(SpecialForm::Begin, args, env)
 }
 
 /// Evaluate an and special form
@@ -66,22 +49,7 @@ pub fn eval_begin(args: &[Arc<Expression>], env: &mut Environment) -> Result<Val
 /// - If all expressions are truthy, returns the value of the last expression
 /// - If no expressions are provided, returns #t
 pub fn eval_and(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
-    if args.is_empty() {
-        return Ok(Value::boolean(true));
-    }
-
-    let mut result = Value::boolean(true);
-
-    // Evaluate expressions left to right, short-circuiting on #f
-    for expr in args {
-        result = eval(Arc::clone(expr), env)?;
-        if !result.is_truthy() {
-            return Ok(Value::boolean(false));
-        }
-    }
-
-    // Return the last result if all were truthy
-    Ok(result)
+    eval_form(SpecialForm::And, args, env)
 }
 
 /// Evaluate an or special form
@@ -92,20 +60,7 @@ pub fn eval_and(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value
 /// - If all expressions are falsy (#f), returns #f
 /// - If no expressions are provided, returns #f
 pub fn eval_or(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
-    if args.is_empty() {
-        return Ok(Value::boolean(false));
-    }
-
-    // Evaluate expressions left to right, short-circuiting on truthy values
-    for expr in args {
-        let result = eval(Arc::clone(expr), env)?;
-        if result.is_truthy() {
-            return Ok(result);
-        }
-    }
-
-    // All expressions were falsy
-    Ok(Value::boolean(false))
+    eval_form(SpecialForm::Or, args, env)
 }
 
 #[cfg(test)]
@@ -10,8 +10,9 @@
 use crate::error::{Error, Result};
 use crate::parser::Expression;
 use crate::runtime::Environment;
-use crate::runtime::utils::{parse_parameters, validate_unique_parameters};
-use crate::types::{Procedure, Symbol, Value};
+use crate::runtime::eval::analysis::free_identifiers;
+use crate::runtime::utils::{parse_parameters, validate_unique_parameter_exprs};
+use crate::types::{Lambda, Procedure, Symbol, Value};
 use std::sync::Arc;
 
 /// Evaluate a lambda expression
@@ -19,9 +20,10 @@ use std::sync::Arc;
 /// Lambda syntax: `(lambda (param1 param2 ...) body1 body2 ... bodyn)`
 ///
 /// Creates a new procedure with the specified parameters and body expressions.
-/// The procedure captures the current environment as a closure, implementing
-/// lexical scoping as required by FR-13. Multiple body expressions are evaluated
-/// in sequence, with only the last expression in tail position.
+/// The procedure captures the bindings of the current environment its body
+/// refers to as a closure, implementing lexical scoping as required by
+/// FR-13. Multiple body expressions are evaluated in sequence, with only the
+/// last expression in tail position.
 ///
 /// # Arguments
 /// * `args` - The lambda arguments: parameter list followed by one or more body expressions
@@ -38,6 +40,35 @@ use std::sync::Arc;
 /// // (lambda (x) (display x) (+ x 1))  ; Multi-expression body
 /// ```
 pub fn eval_lambda(args: &[Arc<Expression>], env: &Environment) -> Result<Value> {
+    let (params, body_exprs) = parse_lambda(args)?;
+
+    // Create lambda procedure using shared logic
+    Ok(create_lambda_procedure(params, body_exprs, None, env))
+}
+
+/// Evaluate a lambda expression that is being bound to an identifier
+///
+/// Behaves like [`eval_lambda`], but the resulting procedure remembers `name`
+/// so that it is displayed and reported in errors under that name. Used by
+/// `define`, `letrec` and `letrec*` when the bound expression is a lambda.
+pub fn eval_named_lambda(
+    args: &[Arc<Expression>],
+    name: &Symbol,
+    env: &Environment,
+) -> Result<Value> {
+    let (params, body_exprs) = parse_lambda(args)?;
+    Ok(create_lambda_procedure(
+        params,
+        body_exprs,
+        Some(name.clone()),
+        env,
+    ))
+}
+
+/// Validate lambda arguments and split them into parameters and body
+pub(crate) fn parse_lambda(
+    args: &[Arc<Expression>],
+) -> Result<(Vec<Symbol>, Vec<Arc<Expression>>)> {
     // Lambda requires at least 2 arguments: parameter list and one or more body expressions
     if args.len() < 2 {
         return Err(Error::arity_error("lambda", 2, args.len()));
@@ -46,8 +77,8 @@ pub fn eval_lambda(args: &[Arc<Expression>], env: &Environment) -> Result<Value>
     // Extract and validate parameters
     let params_expr = Arc::clone(&args[0]);
     let param_elements = match params_expr.as_ref() {
-        Expression::List(elements) => elements,
-        Expression::Atom(Value::Symbol(_)) => {
+        Expression::List(elements, _) => elements,
+        Expression::Atom(Value::Symbol(_), _) => {
             return Err(Error::parse_error(
                 "lambda: parameters must be enclosed in parentheses",
             ));
@@ -60,13 +91,11 @@ pub fn eval_lambda(args: &[Arc<Expression>], env: &Environment) -> Result<Value>
         }
     };
     let params = parse_parameters(param_elements, "lambda")?;
-    validate_unique_parameters(&params, "lambda")?;
+    validate_unique_parameter_exprs(param_elements, "lambda")?;
 
     // Collect all body expressions (everything after the parameter list)
     let body_exprs = args[1..].iter().map(Arc::clone).collect();
-
-    // Create lambda procedure using shared logic
-    Ok(create_lambda_procedure(params, body_exprs, env))
+    Ok((params, body_exprs))
 }
 
 /// Create a lambda procedure from validated parameters and body expressions
@@ -77,22 +106,32 @@ pub fn eval_lambda(args: &[Arc<Expression>], env: &Environment) -> Result<Value>
 /// # Arguments
 /// * `params` - Already validated parameter symbols
 /// * `body_exprs` - The body expressions for the lambda (one or more)
+/// * `name` - Identifier the procedure is being bound to, if any
 /// * `env` - Environment to capture for closure
 ///
+/// Only the free identifiers of the body are captured, so creating a closure
+/// costs time in proportion to its body rather than to the environment.
+///
 /// # Returns
 /// A new lambda procedure value
 pub fn create_lambda_procedure(
     params: Vec<Symbol>,
     body_exprs: Vec<Arc<Expression>>,
+    name: Option<Symbol>,
     env: &Environment,
 ) -> Value {
-    let lambda_proc = Procedure::lambda(params, body_exprs, env.flatten());
-    Value::Procedure(lambda_proc)
+    let env = Environment::new_closure(env, &free_identifiers(&params, &body_exprs));
+    let lambda = match name {
+        Some(name) => Lambda::named(name, params, body_exprs, env),
+        None => Lambda::new(params, body_exprs, env),
+    };
+    Value::Procedure(Procedure::Lambda(lambda))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::runtime::utils::validate_unique_parameters;
     use crate::types::Value;
 
     #[test]
@@ -472,7 +511,7 @@ mod tests {
             assert_eq!(body_exprs[0], Expression::arc_atom(Value::number(1.0)));
             assert_eq!(body_exprs[1], Expression::arc_atom(Value::number(2.0)));
             // The third expression should be the addition
-            if let Expression::List(elements) = body_exprs[2].as_ref() {
+            if let Expression::List(elements, _) = body_exprs[2].as_ref() {
                 assert_eq!(elements.len(), 3);
             } else {
                 panic!("Expected list expression");
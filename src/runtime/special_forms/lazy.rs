@@ -0,0 +1,231 @@
+//! Lazy evaluation special forms
+//!
+//! This module implements the special forms that create unforced promises:
+//! - `delay`: Promise whose value is the value of an expression
+//! - `delay-force`: Promise that iteratively forces the promise an expression
+//!   evaluates to (R7RS replacement for SRFI-45 `lazy`)
+//! - `stream-cons`: Stream whose head and tail are both evaluated lazily
+//!
+//! Like `lambda`, these capture the bindings the expression refers to as a
+//! closure so it can be evaluated after the defining scope has returned.
+
+use crate::error::{Error, Result};
+use crate::parser::Expression;
+use crate::runtime::builtins::stream::make_stream_pair;
+use crate::runtime::environment::Environment;
+use crate::runtime::eval::analysis::free_identifiers;
+use crate::types::{Promise, PromiseKind, Value};
+use std::sync::Arc;
+
+/// Evaluate a delay special form
+///
+/// Syntax: (delay <expression>)
+///
+/// Returns a promise which, when forced, evaluates the expression in the
+/// current environment and memoises the result.
+pub fn eval_delay(args: &[Arc<Expression>], env: &Environment) -> Result<Value> {
+    delayed_promise("delay", args, env, PromiseKind::Delay)
+}
+
+/// Evaluate a delay-force special form
+///
+/// Syntax: (delay-force <expression>)
+///
+/// The expression must evaluate to a promise. Forcing the result forces that
+/// promise in the same loop, so tail-recursive lazy algorithms run in
+/// constant stack space.
+pub fn eval_delay_force(args: &[Arc<Expression>], env: &Environment) -> Result<Value> {
+    delayed_promise("delay-force", args, env, PromiseKind::DelayForce)
+}
+
+/// Evaluate a stream-cons special form
+///
+/// Syntax: (stream-cons <head> <tail>)
+///
+/// Returns a non-empty stream without evaluating either expression. The tail
+/// expression must evaluate to a stream when the tail is demanded.
+///
+/// # Examples
+/// ```text
+/// (define integers-from
+///   (lambda (n) (stream-cons n (integers-from (+ n 1)))))
+/// (stream->list (integers-from 0) 3)   ; => (0 1 2)
+/// ```
+pub fn eval_stream_cons(args: &[Arc<Expression>], env: &Environment) -> Result<Value> {
+    if args.len() != 2 {
+        return Err(Error::arity_error("stream-cons", 2, args.len()));
+    }
+
+    let head = Promise::delayed(
+        Arc::clone(&args[0]),
+        capture(&args[0], env),
+        PromiseKind::Delay,
+    );
+    let tail = Promise::delayed(
+        Arc::clone(&args[1]),
+        capture(&args[1], env),
+        PromiseKind::DelayForce,
+    );
+    Ok(make_stream_pair(head, tail))
+}
+
+/// Create a promise for a single delayed expression
+fn delayed_promise(
+    form_name: &str,
+    args: &[Arc<Expression>],
+    env: &Environment,
+    kind: PromiseKind,
+) -> Result<Value> {
+    if args.len() != 1 {
+        return Err(Error::arity_error(form_name, 1, args.len()));
+    }
+
+    Ok(Value::promise(Promise::delayed(
+        Arc::clone(&args[0]),
+        capture(&args[0], env),
+        kind,
+    )))
+}
+
+/// Capture the bindings a delayed expression refers to
+fn capture(expr: &Arc<Expression>, env: &Environment) -> Environment {
+    let free = free_identifiers(&[], std::slice::from_ref(expr));
+    Environment::new_closure(env, &free)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::runtime::eval::eval;
+
+    fn eval_all(source: &str, env: &mut Environment) -> Result<Value> {
+        let mut parser = Parser::new(source.to_string())?;
+        let mut result = Value::Nil;
+        while !parser.is_at_end() {
+            result = eval(parser.parse_expression()?.expr, env)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_delay_is_lazy_and_memoised() {
+        let mut env = Environment::new();
+        eval_all("(define p (delay (car '())))", &mut env).unwrap();
+
+        // Creating the promise does not evaluate the expression
+        assert!(eval_all("p", &mut env).unwrap().is_promise());
+
+        // Errors surface when forced, and the promise stays unforced
+        assert!(eval_all("(force p)", &mut env).is_err());
+        assert!(eval_all("(force p)", &mut env).is_err());
+
+        eval_all("(define q (delay (+ 1 2)))", &mut env).unwrap();
+        assert_eq!(eval_all("(force q)", &mut env).unwrap(), Value::number(3.0));
+        assert_eq!(eval_all("(force q)", &mut env).unwrap(), Value::number(3.0));
+        assert_eq!(
+            format!("{}", eval_all("q", &mut env).unwrap()),
+            "#<promise:forced>"
+        );
+    }
+
+    #[test]
+    fn test_delay_captures_environment() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define make (lambda (x) (delay (* x 2))))
+             (define p (make 21))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_all("(force p)", &mut env).unwrap(),
+            Value::number(42.0)
+        );
+    }
+
+    #[test]
+    fn test_delay_force_iterative() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define loop
+               (lambda (n)
+                 (if (= n 0)
+                     (make-promise 'done)
+                     (delay-force (loop (- n 1))))))",
+            &mut env,
+        )
+        .unwrap();
+
+        // Long delay-force chains must not grow the stack
+        assert_eq!(
+            eval_all("(force (loop 100000))", &mut env).unwrap(),
+            Value::symbol("done")
+        );
+    }
+
+    #[test]
+    fn test_stream_cons_infinite() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define integers-from
+               (lambda (n) (stream-cons n (integers-from (+ n 1)))))
+             (define naturals (integers-from 0))",
+            &mut env,
+        )
+        .unwrap();
+
+        assert_eq!(
+            eval_all("(stream->list naturals 5)", &mut env).unwrap(),
+            Value::list((0..5).map(|n| Value::number(n as f64)).collect())
+        );
+        assert_eq!(
+            eval_all("(stream-car (stream-cdr (stream-cdr naturals)))", &mut env).unwrap(),
+            Value::number(2.0)
+        );
+    }
+
+    #[test]
+    fn test_stream_cons_head_is_lazy() {
+        let mut env = Environment::new();
+        eval_all("(define s (stream-cons (car '()) (stream)))", &mut env).unwrap();
+
+        assert_eq!(
+            eval_all("(stream-pair? s)", &mut env).unwrap(),
+            Value::boolean(true)
+        );
+        assert_eq!(
+            eval_all("(stream-null? (stream-cdr s))", &mut env).unwrap(),
+            Value::boolean(true)
+        );
+        assert!(eval_all("(stream-car s)", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_lazy_form_errors() {
+        let mut env = Environment::new();
+        assert!(eval_all("(delay)", &mut env).is_err());
+        assert!(eval_all("(delay 1 2)", &mut env).is_err());
+        assert!(eval_all("(delay-force)", &mut env).is_err());
+        assert!(eval_all("(stream-cons 1)", &mut env).is_err());
+
+        // delay-force must produce a promise
+        let err = eval_all("(force (delay-force 5))", &mut env).unwrap_err();
+        assert!(matches!(err, Error::TypeError { .. }));
+
+        // A promise forced from its own body evaluates it again, and the
+        // nested force, finishing first, provides the value
+        eval_all(
+            "(define depth (make-parameter 0))
+             (define p (delay (if (= (depth) 0)
+                                  (parameterize ((depth 1)) (list 'outer (force p)))
+                                  'inner)))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_all("(force p)", &mut env).unwrap(),
+            Value::symbol("inner")
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Library special forms
+//!
+//! This module implements the R7RS library forms:
+//! - `define-library`: Define a named library with explicit exports
+//! - `import`: Bring the exports of one or more libraries into scope
+//!
+//! A library body is evaluated in a fresh environment that starts out empty:
+//! it only sees the special forms and whatever its `import` declarations bring
+//! in. Only the identifiers listed in `export` declarations are visible to
+//! importers. Library lookup, loading and caching live in `runtime::library`.
+
+use crate::error::{Error, Result};
+use crate::parser::Expression;
+use crate::runtime::environment::Environment;
+use crate::runtime::eval::eval;
+use crate::runtime::library::{Library, LibraryName, register_library, resolve_import_set};
+use crate::types::{Procedure, Symbol, Value};
+use std::sync::Arc;
+
+/// Evaluate a define-library special form
+///
+/// Syntax:
+/// ```text
+/// (define-library <library-name>
+///   <library-declaration> ...)
+/// ```
+///
+/// where each declaration is one of:
+/// - `(export <export-spec> ...)`, with `<export-spec>` an identifier or
+///   `(rename <internal> <external>)`
+/// - `(import <import-set> ...)`
+/// - `(begin <body> ...)`
+///
+/// Declarations are processed in order. Once the body has been evaluated, the
+/// library is registered so later `import` forms can find it.
+///
+/// # Examples
+/// ```text
+/// (define-library (math square)
+///   (export square)
+///   (import (scheme base))
+///   (begin (define square (lambda (x) (* x x)))))
+///
+/// (import (math square))
+/// (square 4)   ; => 16
+/// ```
+///
+/// Returns Nil, like `define`.
+pub fn eval_define_library(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    if args.is_empty() {
+        return Err(Error::arity_error("define-library", 1, 0));
+    }
+
+    let name = LibraryName::from_expression(&args[0], "define-library")?;
+    let mut library_env = Environment::new_without_builtins().with_evaluator(env.evaluator());
+    let mut export_specs = Vec::new();
+
+    for declaration in &args[1..] {
+        let (keyword, body) = parse_declaration(declaration)?;
+        match keyword.as_str() {
+            "export" => {
+                for spec in body {
+                    export_specs.push(parse_export_spec(spec)?);
+                }
+            }
+            "import" => import_all(body, &mut library_env)?,
+            "begin" => {
+                for expr in body {
+                    eval(Arc::clone(expr), &mut library_env)?;
+                }
+            }
+            other => {
+                return Err(Error::parse_error(&format!(
+                    "define-library: unsupported library declaration '{other}'"
+                )));
+            }
+        }
+    }
+
+    let exports = export_specs
+        .into_iter()
+        .map(|(internal, external)| {
+            let value = library_env.lookup(&internal).map_err(|_| {
+                Error::runtime_error(&format!(
+                    "define-library: exported identifier '{internal}' is not defined in library {name}"
+                ))
+            })?;
+            Ok((external, value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Procedures the library exports go on using its environment
+    register_library(Library::defined_in(name, exports, library_env));
+    Ok(Value::Nil)
+}
+
+/// Evaluate an import special form
+///
+/// Syntax: (import <import-set> ...)
+///
+/// Each import set is a library name, optionally wrapped in `only`, `except`,
+/// `prefix` or `rename`. The resulting bindings are defined in the current
+/// environment. Libraries that have not been loaded yet are found on the
+/// library search path and evaluated once.
+///
+/// # Examples
+/// ```text
+/// (import (scheme base) (prefix (scheme write) io:))
+/// (io:display (car '(1 2)))   ; prints 1
+/// ```
+///
+/// Returns Nil, like `define`.
+pub fn eval_import(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    if args.is_empty() {
+        return Err(Error::arity_error("import", 1, 0));
+    }
+
+    import_all(args, env)?;
+    Ok(Value::Nil)
+}
+
+/// Resolve every import set and define the resulting bindings
+///
+/// All import sets are resolved before anything is defined, so a failing
+/// import leaves the environment unchanged.
+fn import_all(import_sets: &[Arc<Expression>], env: &mut Environment) -> Result<()> {
+    let mut bindings = Vec::new();
+    for import_set in import_sets {
+        bindings.extend(resolve_import_set(import_set)?);
+    }
+
+    for (name, value) in bindings {
+        // A sandbox's denied builtins cannot be imported either
+        if let Value::Procedure(Procedure::Builtin(builtin)) = &value
+            && !env.permits_builtin(*builtin)
+        {
+            continue;
+        }
+        env.define(name, value);
+    }
+    Ok(())
+}
+
+/// Split a library declaration into its keyword and body
+fn parse_declaration(declaration: &Expression) -> Result<(Symbol, &[Arc<Expression>])> {
+    match declaration {
+        Expression::List(elements, _) if !elements.is_empty() => match elements[0].as_ref() {
+            Expression::Atom(Value::Symbol(keyword), _) => Ok((keyword.clone(), &elements[1..])),
+            other => Err(Error::identifier_must_be_symbol_error(
+                "define-library",
+                other.type_name(),
+            )),
+        },
+        other => Err(Error::parse_error(&format!(
+            "define-library: library declaration must be a non-empty list, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Parse an export spec into its (internal, external) names
+fn parse_export_spec(spec: &Expression) -> Result<(Symbol, Symbol)> {
+    match spec {
+        Expression::Atom(Value::Symbol(name), _) => Ok((name.clone(), name.clone())),
+        Expression::List(elements, _) if elements.len() == 3 => {
+            match (
+                elements[0].as_ref(),
+                elements[1].as_ref(),
+                elements[2].as_ref(),
+            ) {
+                (
+                    Expression::Atom(Value::Symbol(keyword), _),
+                    Expression::Atom(Value::Symbol(internal), _),
+                    Expression::Atom(Value::Symbol(external), _),
+                ) if keyword.as_str() == "rename" => Ok((internal.clone(), external.clone())),
+                _ => Err(Error::parse_error(
+                    "export: rename spec must be (rename <internal> <external>)",
+                )),
+            }
+        }
+        other => Err(Error::parse_error(&format!(
+            "export: expected identifier or (rename <internal> <external>), got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn eval_all(source: &str, env: &mut Environment) -> Result<Value> {
+        let mut parser = Parser::new(source.to_string())?;
+        let mut result = Value::Nil;
+        while !parser.is_at_end() {
+            result = eval(parser.parse_expression()?.expr, env)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_define_library_and_import() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define-library (define-library-tests square)
+               (export square (rename cube-impl cube))
+               (import (scheme base))
+               (begin
+                 (define square (lambda (x) (* x x)))
+                 (define cube-impl (lambda (x) (* x (square x))))
+                 (define hidden 1)))
+             (import (define-library-tests square))",
+            &mut env,
+        )
+        .unwrap();
+
+        assert_eq!(
+            eval_all("(square 4)", &mut env).unwrap(),
+            Value::number(16.0)
+        );
+        assert_eq!(eval_all("(cube 3)", &mut env).unwrap(), Value::number(27.0));
+
+        // Unexported and internal names stay private
+        assert!(eval_all("hidden", &mut env).is_err());
+        assert!(eval_all("cube-impl", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_library_body_sees_only_imports() {
+        let mut env = Environment::new();
+
+        // Without an import, builtins are not visible in a library body
+        let err = eval_all(
+            "(define-library (define-library-tests no-imports)
+               (export x)
+               (begin (define x (+ 1 2))))",
+            &mut env,
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::EnvironmentError { .. }));
+
+        // Special forms remain available
+        eval_all(
+            "(define-library (define-library-tests syntax-only)
+               (export choose)
+               (begin (define choose (lambda (a b) (if a a b)))))
+             (import (define-library-tests syntax-only))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_all("(choose #f 2)", &mut env).unwrap(),
+            Value::number(2.0)
+        );
+    }
+
+    #[test]
+    fn test_import_modifiers() {
+        let mut env = Environment::new_without_builtins();
+        eval_all(
+            "(import (only (scheme base) + car)
+                     (prefix (rename (scheme write) (display show)) io:))",
+            &mut env,
+        )
+        .unwrap();
+
+        assert_eq!(eval_all("(+ 1 2)", &mut env).unwrap(), Value::number(3.0));
+        assert!(env.contains_str("io:show"));
+        assert!(eval_all("(cdr '(1 2))", &mut env).is_err());
+    }
+
+    #[test]
+    fn test_failed_import_defines_nothing() {
+        let mut env = Environment::new_without_builtins();
+        assert!(eval_all("(import (scheme base) (no such library))", &mut env).is_err());
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_library_form_errors() {
+        let mut env = Environment::new();
+        assert!(eval_all("(define-library)", &mut env).is_err());
+        assert!(eval_all("(define-library foo)", &mut env).is_err());
+        assert!(eval_all("(import)", &mut env).is_err());
+        assert!(eval_all("(import foo)", &mut env).is_err());
+
+        // Unknown declarations and malformed exports
+        assert!(eval_all("(define-library (bad decl) (include \"x.scm\"))", &mut env).is_err());
+        assert!(eval_all("(define-library (bad export) (export 42))", &mut env).is_err());
+        assert!(
+            eval_all(
+                "(define-library (bad rename) (export (rename a)))",
+                &mut env
+            )
+            .is_err()
+        );
+
+        // Exported identifiers must be defined
+        let err =
+            eval_all("(define-library (bad missing) (export missing))", &mut env).unwrap_err();
+        assert!(err.to_string().contains("'missing' is not defined"));
+    }
+}
@@ -7,7 +7,8 @@
 use crate::error::Result;
 use crate::parser::Expression;
 use crate::runtime::environment::Environment;
-use crate::types::Value;
+use crate::runtime::eval::eval;
+use crate::types::{Symbol, Value};
 use std::sync::Arc;
 
 /// Enumeration of all special forms
@@ -35,11 +36,48 @@ pub enum SpecialForm {
     // Function creation
     Lambda,
 
+    // Record definition
+    DefineRecordType,
+
+    // Dynamic binding
+    Parameterize,
+
+    // Libraries
+    DefineLibrary,
+    Import,
+
+    // Lazy evaluation
+    Delay,
+    DelayForce,
+    StreamCons,
+
     // Concurrency forms
     Async,
 }
 
 impl SpecialForm {
+    /// Every special form, in declaration order
+    pub const ALL: &'static [SpecialForm] = &[
+        SpecialForm::If,
+        SpecialForm::Begin,
+        SpecialForm::And,
+        SpecialForm::Or,
+        SpecialForm::Define,
+        SpecialForm::Let,
+        SpecialForm::LetStar,
+        SpecialForm::Letrec,
+        SpecialForm::LetrecStar,
+        SpecialForm::Lambda,
+        SpecialForm::DefineRecordType,
+        SpecialForm::Parameterize,
+        SpecialForm::DefineLibrary,
+        SpecialForm::Import,
+        SpecialForm::Delay,
+        SpecialForm::DelayForce,
+        SpecialForm::StreamCons,
+        SpecialForm::Async,
+    ];
+
     /// Get the display name for this special form
     pub fn name(self) -> &'static str {
         match self {
@@ -53,6 +91,13 @@ impl SpecialForm {
             SpecialForm::Letrec => "letrec",
             SpecialForm::LetrecStar => "letrec*",
             SpecialForm::Lambda => "lambda",
+            SpecialForm::DefineRecordType => "define-record-type",
+            SpecialForm::Parameterize => "parameterize",
+            SpecialForm::DefineLibrary => "define-library",
+            SpecialForm::Import => "import",
+            SpecialForm::Delay => "delay",
+            SpecialForm::DelayForce => "delay-force",
+            SpecialForm::StreamCons => "stream-cons",
             SpecialForm::Async => "async",
         }
     }
@@ -70,6 +115,13 @@ impl SpecialForm {
             SpecialForm::LetStar => binding::eval_let_star(args, env),
             SpecialForm::Letrec => binding::eval_letrec(args, env),
             SpecialForm::LetrecStar => binding::eval_letrec_star(args, env),
+            SpecialForm::DefineRecordType => record::eval_define_record_type(args, env),
+            SpecialForm::Parameterize => parameter::eval_parameterize(args, env),
+            SpecialForm::DefineLibrary => library::eval_define_library(args, env),
+            SpecialForm::Import => library::eval_import(args, env),
+            SpecialForm::Delay => lazy::eval_delay(args, env),
+            SpecialForm::DelayForce => lazy::eval_delay_force(args, env),
+            SpecialForm::StreamCons => lazy::eval_stream_cons(args, env),
             SpecialForm::Async => concurrency::eval_async(args, env),
         }
     }
@@ -87,16 +139,46 @@ impl SpecialForm {
             "letrec" => Some(SpecialForm::Letrec),
             "letrec*" => Some(SpecialForm::LetrecStar),
             "lambda" => Some(SpecialForm::Lambda),
+            "define-record-type" => Some(SpecialForm::DefineRecordType),
+            "parameterize" => Some(SpecialForm::Parameterize),
+            "define-library" => Some(SpecialForm::DefineLibrary),
+            "import" => Some(SpecialForm::Import),
+            "delay" => Some(SpecialForm::Delay),
+            "delay-force" => Some(SpecialForm::DelayForce),
+            "stream-cons" => Some(SpecialForm::StreamCons),
             "async" => Some(SpecialForm::Async),
             _ => None,
         }
     }
 }
 
+/// Evaluate a special form handled directly by the evaluation machine
+///
+/// Rebuilds the form from its arguments and evaluates it, so that forms such
+/// as `if` and `let` have a single implementation that keeps their bodies in
+/// tail position. Must not be used for forms the machine delegates to
+/// [`SpecialForm::call`], which would recurse forever.
+pub(crate) fn eval_form(
+    form: SpecialForm,
+    args: &[Arc<Expression>],
+    env: &mut Environment,
+) -> Result<Value> {
+    let mut elements = Vec::with_capacity(args.len() + 1);
+    elements.push(Expression::arc_atom(Value::Symbol(Symbol::new(
+        form.name(),
+    ))));
+    elements.extend(args.iter().map(Arc::clone));
+    eval(Expression::arc_list(elements), env)
+}
+
 pub mod binding;
 pub mod concurrency;
 pub mod control_flow;
 pub mod lambda;
+pub mod lazy;
+pub mod library;
+pub mod parameter;
+pub mod record;
 
 #[cfg(test)]
 mod tests {
@@ -257,9 +339,24 @@ mod tests {
         assert_eq!(SpecialForm::Letrec.name(), "letrec");
         assert_eq!(SpecialForm::LetrecStar.name(), "letrec*");
         assert_eq!(SpecialForm::Lambda.name(), "lambda");
+        assert_eq!(SpecialForm::DefineRecordType.name(), "define-record-type");
+        assert_eq!(SpecialForm::Parameterize.name(), "parameterize");
+        assert_eq!(SpecialForm::Delay.name(), "delay");
+        assert_eq!(SpecialForm::DelayForce.name(), "delay-force");
+        assert_eq!(SpecialForm::StreamCons.name(), "stream-cons");
         assert_eq!(SpecialForm::Async.name(), "async");
     }
 
+    #[test]
+    fn test_special_form_all_round_trips() {
+        for special_form in SpecialForm::ALL {
+            assert_eq!(
+                SpecialForm::from_name(special_form.name()),
+                Some(*special_form)
+            );
+        }
+    }
+
     #[test]
     fn test_special_form_from_name() {
         assert_eq!(SpecialForm::from_name("if"), Some(SpecialForm::If));
@@ -275,6 +372,23 @@ mod tests {
             Some(SpecialForm::LetrecStar)
         );
         assert_eq!(SpecialForm::from_name("lambda"), Some(SpecialForm::Lambda));
+        assert_eq!(
+            SpecialForm::from_name("define-record-type"),
+            Some(SpecialForm::DefineRecordType)
+        );
+        assert_eq!(
+            SpecialForm::from_name("parameterize"),
+            Some(SpecialForm::Parameterize)
+        );
+        assert_eq!(SpecialForm::from_name("delay"), Some(SpecialForm::Delay));
+        assert_eq!(
+            SpecialForm::from_name("delay-force"),
+            Some(SpecialForm::DelayForce)
+        );
+        assert_eq!(
+            SpecialForm::from_name("stream-cons"),
+            Some(SpecialForm::StreamCons)
+        );
         assert_eq!(SpecialForm::from_name("async"), Some(SpecialForm::Async));
 
         // Test unknown names
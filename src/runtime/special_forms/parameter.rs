@@ -0,0 +1,212 @@
+//! Dynamic binding special form
+//!
+//! This module implements R7RS `parameterize`, which rebinds parameter
+//! objects created by `make-parameter` for the dynamic extent of its body.
+//!
+//! Bindings live in the thread's current `DynamicEnvironment` rather than the
+//! lexical `Environment`, so they are visible to every procedure called from
+//! the body and to fibers spawned inside it, but not to sibling fibers.
+
+use crate::error::{Error, Result};
+use crate::parser::Expression;
+use crate::runtime::builtins::parameter::convert_parameter_value;
+use crate::runtime::environment::Environment;
+use crate::runtime::eval::eval;
+use crate::runtime::utils::eval_sequence;
+use crate::types::{DynamicEnvironment, Parameter, Procedure, Value};
+use std::sync::Arc;
+
+/// Evaluate a parameterize special form
+///
+/// Syntax: (parameterize ((param1 value1) (param2 value2) ...) body1 body2 ...)
+///
+/// Semantics:
+/// 1. Evaluate every parameter and value expression in the current environment
+/// 2. Pass each value through its parameter's converter
+/// 3. Extend the current dynamic environment with the converted values
+/// 4. Evaluate body expressions sequentially in a new lexical scope
+/// 5. Restore the previous dynamic environment, even if the body fails
+///
+/// Returns the value of the last body expression. Both evaluators evaluate
+/// the form on their own stacks instead, with the body in tail position.
+pub fn eval_parameterize(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    let (exprs, body_exprs) = parse_parameterize(args)?;
+
+    // Evaluate all parameters and values BEFORE installing any binding
+    let mut bindings = Vec::with_capacity(exprs.len());
+    for (parameter, value) in exprs {
+        let parameter = eval(Arc::clone(parameter), env)?;
+        let value = eval(Arc::clone(value), env)?;
+        bindings.push(parameter_binding(parameter, value)?);
+    }
+
+    // The scope guard restores the previous dynamic environment on exit
+    let _scope = DynamicEnvironment::current().extend(bindings).enter();
+
+    let mut body_env = Environment::new_scope(env);
+    eval_sequence(body_exprs, &mut body_env)
+}
+
+/// The parameter and value expressions of a parameterize form, and its body
+pub(crate) type ParameterizeParts<'a> = (
+    Vec<(&'a Arc<Expression>, &'a Arc<Expression>)>,
+    &'a [Arc<Expression>],
+);
+
+/// Parse the arguments of a parameterize form
+pub(crate) fn parse_parameterize(args: &[Arc<Expression>]) -> Result<ParameterizeParts<'_>> {
+    if args.is_empty() {
+        return Err(Error::arity_error("parameterize", 1, 0));
+    }
+
+    let body_exprs = &args[1..];
+    if body_exprs.is_empty() {
+        return Err(Error::runtime_error(
+            "parameterize: requires at least one body expression",
+        ));
+    }
+
+    let binding_pairs = match args[0].as_ref() {
+        Expression::List(pairs, _) => pairs,
+        other => {
+            return Err(Error::first_argument_must_be_list_of_bindings_error(
+                "parameterize",
+                other.type_name(),
+            ));
+        }
+    };
+
+    let mut exprs = Vec::with_capacity(binding_pairs.len());
+    for pair in binding_pairs {
+        let elements = match pair.as_ref() {
+            Expression::List(elements, _) => elements,
+            _ => return Err(Error::each_binding_must_be_list_error("parameterize")),
+        };
+        if elements.len() != 2 {
+            return Err(Error::binding_elements_wrong_arity_error("parameterize"));
+        }
+        exprs.push((&elements[0], &elements[1]));
+    }
+    Ok((exprs, body_exprs))
+}
+
+/// Check that a parameterize binding is of a parameter object, and pass
+/// its value through the parameter's converter
+pub(crate) fn parameter_binding(parameter: Value, value: Value) -> Result<(Arc<Parameter>, Value)> {
+    let parameter = match parameter {
+        Value::Procedure(Procedure::Parameter(parameter)) => parameter,
+        other => {
+            return Err(Error::type_error(
+                "parameterize",
+                "parameter",
+                &other.type_description(),
+                None,
+            ));
+        }
+    };
+    let value = convert_parameter_value(parameter.converter(), value)?;
+    Ok((parameter, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn eval_all(source: &str, env: &mut Environment) -> Result<Value> {
+        let mut parser = Parser::new(source.to_string())?;
+        let mut result = Value::Nil;
+        while !parser.is_at_end() {
+            result = eval(parser.parse_expression()?.expr, env)?;
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn test_parameterize_basic() {
+        let mut env = Environment::new();
+        eval_all("(define p (make-parameter 10))", &mut env).unwrap();
+
+        assert_eq!(eval_all("(p)", &mut env).unwrap(), Value::number(10.0));
+        assert_eq!(
+            eval_all("(parameterize ((p 20)) (p))", &mut env).unwrap(),
+            Value::number(20.0)
+        );
+
+        // The previous value is restored after the body
+        assert_eq!(eval_all("(p)", &mut env).unwrap(), Value::number(10.0));
+    }
+
+    #[test]
+    fn test_parameterize_dynamic_scope() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define level (make-parameter 'info))
+             (define (current-level) (level))",
+            &mut env,
+        )
+        .unwrap();
+
+        // Procedures called from the body see the dynamic binding
+        assert_eq!(
+            eval_all("(parameterize ((level 'debug)) (current-level))", &mut env).unwrap(),
+            Value::symbol("debug")
+        );
+
+        // Nested parameterize shadows and then restores the outer binding
+        assert_eq!(
+            eval_all(
+                "(parameterize ((level 'debug))
+                   (list (parameterize ((level 'trace)) (current-level))
+                         (current-level)))",
+                &mut env
+            )
+            .unwrap(),
+            Value::list(vec![Value::symbol("trace"), Value::symbol("debug")])
+        );
+    }
+
+    #[test]
+    fn test_parameterize_converter() {
+        let mut env = Environment::new();
+        eval_all(
+            "(define p (make-parameter 1 (lambda (x) (* x 10))))",
+            &mut env,
+        )
+        .unwrap();
+
+        assert_eq!(eval_all("(p)", &mut env).unwrap(), Value::number(10.0));
+        assert_eq!(
+            eval_all("(parameterize ((p 5)) (p))", &mut env).unwrap(),
+            Value::number(50.0)
+        );
+    }
+
+    #[test]
+    fn test_parameterize_restores_on_error() {
+        let mut env = Environment::new();
+        eval_all("(define p (make-parameter 1))", &mut env).unwrap();
+
+        assert!(eval_all("(parameterize ((p 2)) (car '()))", &mut env).is_err());
+        assert_eq!(eval_all("(p)", &mut env).unwrap(), Value::number(1.0));
+    }
+
+    #[test]
+    fn test_parameterize_errors() {
+        let mut env = Environment::new();
+        eval_all("(define p (make-parameter 1))", &mut env).unwrap();
+
+        // Missing body
+        assert!(eval_all("(parameterize ((p 2)))", &mut env).is_err());
+        assert!(eval_all("(parameterize)", &mut env).is_err());
+
+        // Malformed bindings
+        assert!(eval_all("(parameterize p (p))", &mut env).is_err());
+        assert!(eval_all("(parameterize (p) (p))", &mut env).is_err());
+        assert!(eval_all("(parameterize ((p)) (p))", &mut env).is_err());
+
+        // Only parameter objects can be parameterized
+        let err = eval_all("(parameterize ((car 2)) 1)", &mut env).unwrap_err();
+        assert!(matches!(err, Error::TypeError { .. }));
+    }
+}
@@ -0,0 +1,406 @@
+//! Record type definition special form
+//!
+//! This module implements R7RS `define-record-type`, which defines a new
+//! record type together with a constructor, a type predicate and one accessor
+//! per field.
+//!
+//! ## Immutability
+//! Twine does not allow mutation, so the optional third element of a field
+//! specification (the R7RS "modifier") defines a *functional updater* instead:
+//! a procedure taking a record and a new field value and returning a modified
+//! copy, leaving the original record untouched.
+
+use crate::error::{Error, Result};
+use crate::parser::Expression;
+use crate::runtime::environment::Environment;
+use crate::runtime::utils::validate_unique_binding_identifiers;
+use crate::types::{Procedure, RecordProcedure, RecordProcedureKind, RecordType, Symbol, Value};
+use std::sync::Arc;
+
+/// Evaluate a define-record-type special form
+///
+/// Syntax:
+/// ```text
+/// (define-record-type <type-name>
+///   (<constructor> <field> ...)
+///   <predicate>
+///   (<field> <accessor> [<updater>]) ...)
+/// ```
+///
+/// The constructor may also be a bare identifier (taking every field in
+/// declaration order) or `#f` (no constructor is defined).
+///
+/// # Examples
+/// ```text
+/// (define-record-type <point>
+///   (make-point x y)
+///   point?
+///   (x point-x point-with-x)
+///   (y point-y))
+///
+/// (define p (make-point 1 2))   ; p => #<point x: 1 y: 2>
+/// (point-x (point-with-x p 5))  ; => 5, p is unchanged
+/// ```
+///
+/// Returns Nil, like `define`.
+pub fn eval_define_record_type(args: &[Arc<Expression>], env: &mut Environment) -> Result<Value> {
+    if args.len() < 2 {
+        return Err(Error::arity_error("define-record-type", 2, args.len()));
+    }
+
+    let type_name = match args[0].as_ref() {
+        Expression::Atom(Value::Symbol(name), _) => name.clone(),
+        other => {
+            return Err(Error::identifier_must_be_symbol_error(
+                "define-record-type",
+                other.type_name(),
+            ));
+        }
+    };
+
+    // Field specifications come first so the constructor can refer to them
+    let field_specs = args[3.min(args.len())..]
+        .iter()
+        .map(|spec| parse_field_spec(spec.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let field_names: Vec<Symbol> = field_specs.iter().map(|spec| spec.name.clone()).collect();
+    validate_unique_binding_identifiers(&field_names, "define-record-type")?;
+
+    let record_type = RecordType::new(type_name, field_names);
+
+    // Constructor: (make-point x y), make-point, or #f
+    if let Some((name, kind)) = parse_constructor(&args[1], &record_type)? {
+        env.define(name.clone(), record_procedure(name, &record_type, kind));
+    }
+
+    // Predicate
+    if let Some(predicate_expr) = args.get(2) {
+        match predicate_expr.as_ref() {
+            Expression::Atom(Value::Symbol(name), _) => env.define(
+                name.clone(),
+                record_procedure(name.clone(), &record_type, RecordProcedureKind::Predicate),
+            ),
+            Expression::Atom(Value::Boolean(false), _) => {}
+            other => {
+                return Err(Error::identifier_must_be_symbol_error(
+                    "define-record-type",
+                    other.type_name(),
+                ));
+            }
+        }
+    }
+
+    // Accessors and functional updaters
+    for (index, spec) in field_specs.into_iter().enumerate() {
+        env.define(
+            spec.accessor.clone(),
+            record_procedure(
+                spec.accessor,
+                &record_type,
+                RecordProcedureKind::Accessor(index),
+            ),
+        );
+
+        if let Some(updater) = spec.updater {
+            env.define(
+                updater.clone(),
+                record_procedure(updater, &record_type, RecordProcedureKind::Updater(index)),
+            );
+        }
+    }
+
+    Ok(Value::Nil)
+}
+
+/// Parsed field specification: `(field accessor [updater])`
+struct FieldSpec {
+    name: Symbol,
+    accessor: Symbol,
+    updater: Option<Symbol>,
+}
+
+/// Parse a single field specification
+fn parse_field_spec(spec: &Expression) -> Result<FieldSpec> {
+    let elements = match spec {
+        Expression::List(elements, _) => elements,
+        other => {
+            return Err(Error::binding_must_be_list_error(
+                "define-record-type",
+                other.type_name(),
+            ));
+        }
+    };
+
+    if elements.len() != 2 && elements.len() != 3 {
+        return Err(Error::field_spec_wrong_arity_error(
+            "define-record-type",
+            elements.len(),
+        ));
+    }
+
+    let symbols = elements
+        .iter()
+        .map(|element| match element.as_ref() {
+            Expression::Atom(Value::Symbol(symbol), _) => Ok(symbol.clone()),
+            other => Err(Error::identifier_must_be_symbol_error(
+                "define-record-type",
+                other.type_name(),
+            )),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(FieldSpec {
+        name: symbols[0].clone(),
+        accessor: symbols[1].clone(),
+        updater: symbols.get(2).cloned(),
+    })
+}
+
+/// Parse the constructor specification into its name and field indices
+///
+/// Returns `None` when the constructor is `#f`.
+fn parse_constructor(
+    spec: &Expression,
+    record_type: &Arc<RecordType>,
+) -> Result<Option<(Symbol, RecordProcedureKind)>> {
+    match spec {
+        // Bare identifier: constructor takes every field in order
+        Expression::Atom(Value::Symbol(name), _) => Ok(Some((
+            name.clone(),
+            RecordProcedureKind::Constructor((0..record_type.fields().len()).collect()),
+        ))),
+
+        // No constructor
+        Expression::Atom(Value::Boolean(false), _) => Ok(None),
+
+        // (make-point x y)
+        Expression::List(elements, _) if !elements.is_empty() => {
+            let name = match elements[0].as_ref() {
+                Expression::Atom(Value::Symbol(name), _) => name.clone(),
+                other => {
+                    return Err(Error::procedure_name_must_be_symbol_error(
+                        "define-record-type",
+                        other.type_name(),
+                    ));
+                }
+            };
+
+            let mut fields = Vec::with_capacity(elements.len() - 1);
+            for element in &elements[1..] {
+                match element.as_ref() {
+                    Expression::Atom(Value::Symbol(field), _) => fields.push(field.clone()),
+                    other => {
+                        return Err(Error::parameter_must_be_symbol_error(
+                            "define-record-type",
+                            other.type_name(),
+                        ));
+                    }
+                }
+            }
+            validate_unique_binding_identifiers(&fields, "define-record-type")?;
+
+            let indices = fields
+                .iter()
+                .map(|field| {
+                    record_type.field_index(field).ok_or_else(|| {
+                        Error::unknown_constructor_field_error(
+                            "define-record-type",
+                            field.as_str(),
+                            record_type.name().as_str(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Ok(Some((name, RecordProcedureKind::Constructor(indices))))
+        }
+
+        other => Err(Error::constructor_spec_error(
+            "define-record-type",
+            other.type_name(),
+        )),
+    }
+}
+
+/// Wrap a record procedure as a procedure value
+fn record_procedure(
+    name: Symbol,
+    record_type: &Arc<RecordType>,
+    kind: RecordProcedureKind,
+) -> Value {
+    Value::Procedure(Procedure::Record(RecordProcedure::new(
+        name,
+        Arc::clone(record_type),
+        kind,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::parser::Parser;
+    use crate::runtime::eval::eval;
+
+    fn eval_all(source: &str, env: &mut Environment) -> Result<Value> {
+        let mut parser = Parser::new(source.to_string())?;
+        let mut result = Value::Nil;
+        while !parser.is_at_end() {
+            result = eval(parser.parse_expression()?.expr, env)?;
+        }
+        Ok(result)
+    }
+
+    const POINT: &str = "(define-record-type <point>
+                           (make-point x y)
+                           point?
+                           (x point-x point-with-x)
+                           (y point-y))";
+
+    #[test]
+    fn test_define_record_type_bindings() {
+        let mut env = Environment::new();
+        assert_eq!(eval_all(POINT, &mut env).unwrap(), Value::Nil);
+
+        for name in ["make-point", "point?", "point-x", "point-y", "point-with-x"] {
+            assert!(env.contains_str(name), "{name} should be defined");
+        }
+        assert!(!env.contains_str("point-with-y"));
+    }
+
+    #[test]
+    fn test_define_record_type_usage() {
+        let mut env = Environment::new();
+        eval_all(POINT, &mut env).unwrap();
+
+        let point = eval_all("(make-point 1 2)", &mut env).unwrap();
+        assert_eq!(format!("{point}"), "#<point x: 1 y: 2>");
+
+        assert_eq!(
+            eval_all("(point-y (make-point 1 2))", &mut env).unwrap(),
+            Value::number(2.0)
+        );
+        assert_eq!(
+            eval_all("(point? (make-point 1 2))", &mut env).unwrap(),
+            Value::boolean(true)
+        );
+        assert_eq!(
+            eval_all("(point? 42)", &mut env).unwrap(),
+            Value::boolean(false)
+        );
+    }
+
+    #[test]
+    fn test_define_record_type_functional_update() {
+        let mut env = Environment::new();
+        eval_all(POINT, &mut env).unwrap();
+        eval_all("(define p (make-point 1 2))", &mut env).unwrap();
+        eval_all("(define q (point-with-x p 10))", &mut env).unwrap();
+
+        assert_eq!(
+            eval_all("(point-x p)", &mut env).unwrap(),
+            Value::number(1.0)
+        );
+        assert_eq!(
+            eval_all("(point-x q)", &mut env).unwrap(),
+            Value::number(10.0)
+        );
+        assert_eq!(
+            eval_all("(point-y q)", &mut env).unwrap(),
+            Value::number(2.0)
+        );
+    }
+
+    #[test]
+    fn test_define_record_type_constructor_variants() {
+        let mut env = Environment::new();
+
+        // Bare identifier constructor takes all fields in order
+        eval_all(
+            "(define-record-type pair make-pair pair? (a pair-a) (b pair-b))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", eval_all("(make-pair 1 2)", &mut env).unwrap()),
+            "#<pair a: 1 b: 2>"
+        );
+
+        // #f means no constructor
+        eval_all(
+            "(define-record-type <opaque> #f opaque? (v opaque-v))",
+            &mut env,
+        )
+        .unwrap();
+        assert!(env.contains_str("opaque?"));
+        assert!(!env.contains_str("make-opaque"));
+
+        // Constructor can take fields in a different order
+        eval_all(
+            "(define-record-type <range> (make-range hi lo) range? (lo range-lo) (hi range-hi))",
+            &mut env,
+        )
+        .unwrap();
+        assert_eq!(
+            eval_all("(range-lo (make-range 10 1))", &mut env).unwrap(),
+            Value::number(1.0)
+        );
+    }
+
+    #[test]
+    fn test_define_record_type_errors() {
+        let mut env = Environment::new();
+
+        // Missing arguments
+        assert!(eval_all("(define-record-type <p>)", &mut env).is_err());
+
+        // Type name must be a symbol
+        assert!(eval_all("(define-record-type 42 (make-p) p?)", &mut env).is_err());
+
+        // Constructor field must be declared
+        let err =
+            eval_all("(define-record-type <p> (make-p x z) p? (x p-x))", &mut env).unwrap_err();
+        assert!(err.to_string().contains("'z' is not a field"));
+
+        // Duplicate fields
+        let err = eval_all(
+            "(define-record-type <p> (make-p x) p? (x p-x) (x p-x2))",
+            &mut env,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("duplicate identifier 'x'"));
+
+        // Malformed specifications are form errors
+        for source in [
+            "(define-record-type <p> (make-p) p? (x))",
+            "(define-record-type <p> (make-p) p? x)",
+            "(define-record-type <p> 42 p? (x p-x))",
+            "(define-record-type <p> (make-p z) p? (x p-x))",
+        ] {
+            let err = eval_all(source, &mut env).unwrap_err();
+            assert_eq!(err.code(), ErrorCode::Parse, "{source}");
+        }
+
+        // Accessor applied to the wrong type
+        eval_all(POINT, &mut env).unwrap();
+        let err = eval_all("(point-x 5)", &mut env).unwrap_err();
+        assert!(matches!(err, Error::TypeError { .. }));
+        let err = eval_all("(make-point 1)", &mut env).unwrap_err();
+        assert!(matches!(err, Error::ArityError { .. }));
+    }
+
+    #[test]
+    fn test_record_types_are_distinct() {
+        let mut env = Environment::new();
+        eval_all(POINT, &mut env).unwrap();
+        eval_all("(define old (make-point 1 2))", &mut env).unwrap();
+
+        // Redefining the record type creates a new, incompatible type
+        eval_all(POINT, &mut env).unwrap();
+        assert_eq!(
+            eval_all("(point? old)", &mut env).unwrap(),
+            Value::boolean(false)
+        );
+    }
+}
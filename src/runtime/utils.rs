@@ -14,7 +14,7 @@ use crate::error::{Error, Result};
 use crate::parser::Expression;
 use crate::runtime::{environment::Environment, eval::eval};
 use crate::types::{Symbol, Value};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 // ============================================================================
@@ -65,9 +65,9 @@ pub fn eval_sequence(exprs: &[Arc<Expression>], env: &mut Environment) -> Result
 pub fn is_lambda_expression(expr: &Expression) -> bool {
     matches!(
         expr,
-        Expression::List(elements) if !elements.is_empty() && matches!(
+        Expression::List(elements, _) if !elements.is_empty() && matches!(
             elements[0].as_ref(),
-            Expression::Atom(Value::Symbol(sym)) if sym.as_str() == "lambda"
+            Expression::Atom(Value::Symbol(sym), _) if sym.as_str() == "lambda"
         )
     )
 }
@@ -98,7 +98,7 @@ pub fn parse_parameters(
 
     for element in param_elements {
         match element.as_ref() {
-            Expression::Atom(Value::Symbol(symbol)) => {
+            Expression::Atom(Value::Symbol(symbol), _) => {
                 params.push(symbol.clone());
             }
             other => {
@@ -187,6 +187,80 @@ pub fn validate_unique_binding_identifiers(identifiers: &[Symbol], form_name: &s
     )
 }
 
+/// Check identifier expressions for duplicates, locating any duplicate found
+///
+/// Like [`validate_unique_identifiers`], but works on the identifier
+/// expressions themselves so that the error points at the repeated
+/// identifier, with a secondary label on its first occurrence. Expressions
+/// that are not symbols are ignored.
+///
+/// # Arguments
+/// * `identifier_exprs` - Identifier expressions to check for duplicates
+/// * `form_name` - Name of the form (for error messages)
+/// * `error_fn` - Function to create the appropriate error for duplicates
+/// * `first_label` - Label for the first occurrence, e.g. "first defined here"
+pub fn validate_unique_identifier_exprs<'a, F>(
+    identifier_exprs: impl IntoIterator<Item = &'a Expression>,
+    form_name: &str,
+    error_fn: F,
+    first_label: &str,
+) -> Result<()>
+where
+    F: Fn(&str, &str) -> crate::Error,
+{
+    let mut seen = HashMap::new();
+
+    for expr in identifier_exprs {
+        let Expression::Atom(Value::Symbol(identifier), _) = expr else {
+            continue;
+        };
+        if let Some(first) = seen.insert(identifier, expr) {
+            let mut error = error_fn(form_name, identifier.as_str());
+            if let Some(span) = expr.span() {
+                error = error.with_span(span);
+            }
+            if let Some(span) = first.span() {
+                error = error.with_label(span, first_label);
+            }
+            return Err(error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Located version of [`validate_unique_parameters`]
+///
+/// The error points at the repeated parameter and labels where it was first
+/// declared.
+pub fn validate_unique_parameter_exprs(
+    param_exprs: &[Arc<Expression>],
+    form_name: &str,
+) -> Result<()> {
+    validate_unique_identifier_exprs(
+        param_exprs.iter().map(AsRef::as_ref),
+        form_name,
+        crate::Error::duplicate_parameter_error,
+        "parameter first declared here",
+    )
+}
+
+/// Located version of [`validate_unique_binding_identifiers`]
+///
+/// The error points at the repeated identifier and labels where the binding
+/// was first defined.
+pub fn validate_unique_binding_exprs<'a>(
+    identifier_exprs: impl IntoIterator<Item = &'a Expression>,
+    form_name: &str,
+) -> Result<()> {
+    validate_unique_identifier_exprs(
+        identifier_exprs,
+        form_name,
+        crate::Error::duplicate_identifier_error,
+        "binding first defined here",
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,6 +331,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_validate_unique_exprs_locates_duplicate() {
+        use crate::parser::Parser;
+
+        let expr = Parser::new("(a b\n a)".to_string())
+            .unwrap()
+            .parse_expression()
+            .unwrap()
+            .expr;
+        let Expression::List(params, _) = expr.as_ref() else {
+            panic!("expected a list");
+        };
+
+        let error = validate_unique_parameter_exprs(params, "lambda").unwrap_err();
+        assert_eq!(error.code(), crate::error::ErrorCode::DuplicateParameter);
+        let span = error.span().unwrap();
+        assert_eq!((span.start.line, span.start.column), (2, 2));
+
+        let label = &error.labels()[0];
+        assert_eq!((label.span.start.line, label.span.start.column), (1, 2));
+        assert_eq!(label.message, "parameter first declared here");
+
+        let error =
+            validate_unique_binding_exprs(params.iter().map(Arc::as_ref), "letrec").unwrap_err();
+        assert_eq!(error.labels()[0].message, "binding first defined here");
+    }
+
     #[test]
     fn test_parse_parameters_empty() {
         let params = vec![];
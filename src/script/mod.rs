@@ -0,0 +1,302 @@
+//! Non-interactive script execution for the Twine Scheme interpreter.
+//!
+//! Evaluates a whole source text (a file, standard input or a `-e` one-liner)
+//! expression by expression in a single environment. Failures are reported as
+//! a [`ScriptError`] naming the source and the position of the innermost
+//! form that failed. Its `Display` output is the familiar one-line
+//! `file:line:column: message` form; [`ScriptError::render`] produces a full
+//! diagnostic with the offending source line.
+
+use std::fmt;
+use std::io::Read;
+use std::path::Path;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+use crate::{
+    Error,
+    diagnostics::{Renderer, SourceMap},
+    lexer::{Position, Span},
+    parser::{Expression, Parser},
+    runtime::{Environment, eval, eval_async},
+    types::Value,
+};
+
+/// Source name used for programs read from standard input
+pub const STDIN_SOURCE_NAME: &str = "<stdin>";
+
+/// Source name used for `-e` expressions
+pub const EXPRESSION_SOURCE_NAME: &str = "<expression>";
+
+/// An error raised while running a script
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    /// File name or pseudo-name (such as `<stdin>`) of the source
+    pub source_name: String,
+    /// Position of the top-level expression that was being evaluated, if known
+    ///
+    /// The error's own span, when present, pinpoints the failing form more
+    /// precisely and takes priority when displaying the error.
+    pub position: Option<Position>,
+    /// The underlying interpreter error
+    pub error: Box<Error>,
+    /// Text of the source, if it was read, for rendering snippets
+    pub source: Option<Arc<str>>,
+}
+
+impl ScriptError {
+    fn new(source_name: &str, position: Option<Position>, error: Error) -> Self {
+        Self {
+            source_name: source_name.to_string(),
+            position,
+            error: Box::new(error),
+            source: None,
+        }
+    }
+
+    fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(Arc::from(source));
+        self
+    }
+
+    /// Render the error as a diagnostic showing the failing source line
+    ///
+    /// Errors without a span of their own are located at the top-level
+    /// expression that was being evaluated.
+    pub fn render(&self, renderer: &Renderer) -> String {
+        let mut sources = SourceMap::new();
+        if let Some(source) = &self.source {
+            sources.add(&self.source_name, source);
+        }
+
+        match (self.error.span(), &self.position) {
+            (None, Some(position)) => {
+                let span = Span::new(
+                    position.clone(),
+                    Position::new(position.line, position.column + 1),
+                )
+                .with_source(Some(Arc::from(self.source_name.as_str())));
+                let error = self.error.as_ref().clone().with_span(&span);
+                renderer.render(&error, &sources)
+            }
+            _ => renderer.render(&self.error, &sources),
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Error::SyntaxError {
+            message,
+            line,
+            column,
+            ..
+        } = self.error.as_ref()
+        {
+            // Syntax errors already carry the exact position of the bad token
+            return write!(
+                f,
+                "{}:{line}:{column}: Syntax error: {message}",
+                self.source_name
+            );
+        }
+
+        match (self.error.span(), &self.position) {
+            // The span of the failing form, which may be in another file
+            (Some(span), _) if span.source.is_some() => write!(f, "{span}: {}", self.error),
+            (Some(span), _) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.source_name, span.start.line, span.start.column, self.error
+            ),
+            (None, Some(position)) => write!(
+                f,
+                "{}:{}:{}: {}",
+                self.source_name, position.line, position.column, self.error
+            ),
+            (None, None) => write!(f, "{}: {}", self.source_name, self.error),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// Evaluate every expression in a source text, in order
+///
+/// Returns the value of the last expression, or Nil for an empty source.
+/// Evaluation stops at the first error.
+pub fn run_source(
+    source_name: &str,
+    source: &str,
+    env: &mut Environment,
+) -> Result<Value, ScriptError> {
+    let run = run_with(source_name, source, env, |expr, env| {
+        std::future::ready(eval(expr, env))
+    });
+    // Each evaluation is ready at once, so the script runs to the end on the
+    // first poll
+    match pin!(run).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(result) => result,
+        Poll::Pending => unreachable!("synchronous evaluation never waits"),
+    }
+}
+
+/// Evaluate every expression in a source text, in order, as a future
+///
+/// Like [`run_source`], but each expression is evaluated with
+/// [`eval_async`], so the script yields to the executor as it runs.
+pub fn run_source_async(
+    source_name: &str,
+    source: &str,
+    env: &mut Environment,
+) -> impl Future<Output = Result<Value, ScriptError>> + Send + use<> {
+    run_with(source_name, source, env, eval_async)
+}
+
+/// Parse and evaluate every expression in a source text with `evaluate`
+fn run_with<F, Fut>(
+    source_name: &str,
+    source: &str,
+    env: &mut Environment,
+    mut evaluate: F,
+) -> impl Future<Output = Result<Value, ScriptError>> + use<F, Fut>
+where
+    F: FnMut(Arc<Expression>, &mut Environment) -> Fut,
+    Fut: Future<Output = crate::Result<Value>>,
+{
+    let source_name = source_name.to_string();
+    let source = source.to_string();
+    let mut env = env.share();
+    async move {
+        let fail = |position: Option<Position>, error: Error| {
+            ScriptError::new(&source_name, position, error.in_source(&source_name))
+                .with_source(&source)
+        };
+        let mut parser = Parser::new(source.clone())
+            .map_err(|error| fail(None, error))?
+            .with_source_name(&source_name);
+        let mut last_value = Value::nil();
+
+        while !parser.is_at_end() {
+            let start = parser.current_position();
+            let positioned = parser
+                .parse_expression()
+                .map_err(|error| fail(Some(start), error))?;
+            last_value = evaluate(positioned.expr, &mut env)
+                .await
+                .map_err(|error| fail(Some(positioned.position), error))?;
+        }
+
+        Ok(last_value)
+    }
+}
+
+/// Read and evaluate a source file
+///
+/// The file's path, as given, is used as the source name in errors.
+pub fn run_file(path: &Path, env: &mut Environment) -> Result<Value, ScriptError> {
+    let source_name = path.display().to_string();
+    let source = std::fs::read_to_string(path).map_err(|error| {
+        ScriptError::new(
+            &source_name,
+            None,
+            Error::runtime_error(&format!("cannot read file {source_name}: {error}")),
+        )
+    })?;
+    run_source(&source_name, &source, env)
+}
+
+/// Read a program from standard input and evaluate it
+pub fn run_stdin(env: &mut Environment) -> Result<Value, ScriptError> {
+    let mut source = String::new();
+    std::io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|error| {
+            ScriptError::new(
+                STDIN_SOURCE_NAME,
+                None,
+                Error::runtime_error(&format!("cannot read standard input: {error}")),
+            )
+        })?;
+    run_source(STDIN_SOURCE_NAME, &source, env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_source_returns_last_value() {
+        let mut env = Environment::new();
+        let result = run_source("test.scm", "(define x 2)\n(* x 21)", &mut env).unwrap();
+        assert_eq!(result, Value::number(42.0));
+
+        // Definitions persist in the environment
+        assert_eq!(env.lookup_str("x").unwrap(), Value::number(2.0));
+
+        assert_eq!(run_source("empty.scm", "", &mut env).unwrap(), Value::nil());
+    }
+
+    #[test]
+    fn test_run_source_reports_failing_expression_position() {
+        let mut env = Environment::new();
+        let error =
+            run_source("test.scm", "(define x 1)\n\n  (car x)\n(oops)", &mut env).unwrap_err();
+
+        assert_eq!(error.source_name, "test.scm");
+        assert_eq!(error.position, Some(Position::new(3, 3)));
+        assert!(matches!(*error.error, Error::TypeError { .. }));
+        assert!(error.to_string().starts_with("test.scm:3:3: car:"));
+    }
+
+    #[test]
+    fn test_run_source_syntax_error() {
+        let mut env = Environment::new();
+        let error = run_source("bad.scm", "(+ 1 2)\n(display \"oops)", &mut env).unwrap_err();
+        assert!(matches!(*error.error, Error::SyntaxError { .. }));
+        assert!(error.to_string().starts_with("bad.scm:2:"));
+        assert!(error.to_string().contains("Syntax error:"));
+    }
+
+    #[test]
+    fn test_render_shows_source_line() {
+        let mut env = Environment::new();
+        let error = run_source("test.scm", "(define x 1)\n(car x)", &mut env).unwrap_err();
+        let rendered = error.render(&Renderer::new());
+        assert!(rendered.starts_with(
+            "error[E0005]: car: expected list, got number\n --> test.scm:2:1\n  |\n2 | (car x)\n  | ^^^^^^^\n"
+        ));
+
+        // Errors without a span fall back to the top-level expression
+        let error = ScriptError::new(
+            "test.scm",
+            Some(Position::new(1, 1)),
+            Error::runtime_error("boom"),
+        )
+        .with_source("(boom)");
+        assert_eq!(
+            error.render(&Renderer::new()),
+            "error[E0003]: boom\n --> test.scm:1:1\n  |\n1 | (boom)\n  | ^\n  |\n"
+        );
+    }
+
+    #[test]
+    fn test_run_file_missing() {
+        let mut env = Environment::new();
+        let error = run_file(Path::new("/nonexistent/script.scm"), &mut env).unwrap_err();
+        assert_eq!(error.position, None);
+        assert!(
+            error
+                .to_string()
+                .starts_with("/nonexistent/script.scm: Runtime error: cannot read file")
+        );
+
+        // The rendered diagnostic has no source location, so the message names the file
+        let rendered = error.render(&Renderer::with_color(false));
+        assert!(
+            rendered.starts_with("error[E0003]: cannot read file /nonexistent/script.scm: "),
+            "{rendered}"
+        );
+    }
+}
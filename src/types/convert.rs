@@ -0,0 +1,481 @@
+//! Conversions between Rust types and Scheme values
+//!
+//! [`FromValue`] extracts a Rust value from a Scheme value, failing with a
+//! type error that names the expected type, and [`IntoValue`] builds a Scheme
+//! value from a Rust one. They are implemented for:
+//!
+//! | Rust | Scheme |
+//! |------|--------|
+//! | `f64`, `i64` | number; `i64` requires an integer in range |
+//! | `bool` | boolean |
+//! | `String`, `ArcString`, `&str` (into only) | string |
+//! | `Vec<T>` | list |
+//! | `Option<T>` | `#f` for `None`, otherwise the value; `Some(false)` reads back as `None` |
+//! | `HashMap<String, T>` | association list of `(key value)` lists; keys may be strings or symbols |
+//! | tuples of up to four elements | list of that length |
+//! | `()` (into only) | nil |
+//! | `Value` | any value, unchanged |
+//!
+//! Conversions from a value return owned Rust values, so there is no
+//! `FromValue` for `&str`. A closure that only reads a string argument can
+//! take an [`ArcString`], which shares the string instead of copying it, and
+//! borrow it with [`ArcString::as_str`].
+//!
+//! [`NativeFunction`] uses them to turn typed Rust closures, such as
+//! `|a: f64, b: f64| a + b`, into native procedures that check their
+//! arguments and report which one had the wrong type.
+
+use crate::error::{Error, Result};
+use crate::types::{ArcString, Value};
+use std::collections::HashMap;
+
+/// Procedure name used in type errors from conversions outside a procedure
+const CONVERSION: &str = "conversion";
+
+/// A Rust type that can be extracted from a Scheme value
+pub trait FromValue: Sized {
+    /// Convert `value`, or fail with a type error if it has the wrong type
+    fn from_value(value: &Value) -> Result<Self>;
+}
+
+/// A Rust type that can be converted into a Scheme value
+pub trait IntoValue {
+    /// Convert into a Scheme value
+    fn into_value(self) -> Value;
+}
+
+fn mismatch(expected: &str, value: &Value) -> Error {
+    Error::type_error(CONVERSION, expected, &value.type_description(), None)
+}
+
+/// Describe a failure to convert part of a compound value
+///
+/// The expected type is prefixed with the compound's, e.g. `list of number`,
+/// and the actual type says which part was wrong.
+fn nested(error: Error, compound: &str, part: &str) -> Error {
+    match error {
+        Error::TypeError {
+            expected, actual, ..
+        } => Error::type_error(
+            CONVERSION,
+            &format!("{compound} of {expected}"),
+            &format!("{actual} at {part}"),
+            None,
+        ),
+        other => other,
+    }
+}
+
+/// The elements of a list value, treating nil as the empty list
+fn elements<'a>(value: &'a Value, expected: &str) -> Result<&'a [Value]> {
+    match value {
+        Value::List(list) => Ok(list.as_slice()),
+        Value::Nil => Ok(&[]),
+        other => Err(mismatch(expected, other)),
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: &Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Nil
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: &Value) -> Result<Self> {
+        value.as_number().ok_or_else(|| mismatch("number", value))
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::number(self)
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value.as_number() {
+            // i64::MAX rounds up to 2^63 as a float, which is out of range
+            Some(n) if n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64 => {
+                Ok(n as i64)
+            }
+            _ => Err(mismatch("integer", value)),
+        }
+    }
+}
+
+/// Integers beyond 2^53 lose precision, as numbers are stored as `f64`
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::number(self as f64)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: &Value) -> Result<Self> {
+        value.as_boolean().ok_or_else(|| mismatch("boolean", value))
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::boolean(self)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(string) => Ok(string.as_str().to_string()),
+            other => Err(mismatch("string", other)),
+        }
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::String(ArcString::from_string(self))
+    }
+}
+
+impl FromValue for ArcString {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(string) => Ok(string.clone()),
+            other => Err(mismatch("string", other)),
+        }
+    }
+}
+
+impl IntoValue for ArcString {
+    fn into_value(self) -> Value {
+        Value::String(self)
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::string(self)
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        elements(value, "list")?
+            .iter()
+            .enumerate()
+            .map(|(index, element)| {
+                T::from_value(element)
+                    .map_err(|error| nested(error, "list", &format!("index {index}")))
+            })
+            .collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::list(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Boolean(false) => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::boolean(false), IntoValue::into_value)
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: &Value) -> Result<Self> {
+        let expected = "association list";
+        elements(value, expected)?
+            .iter()
+            .map(|entry| {
+                let (key, value) = match entry {
+                    Value::List(list) if list.len() == 2 => {
+                        (&list.as_slice()[0], &list.as_slice()[1])
+                    }
+                    other => {
+                        return Err(nested(
+                            mismatch("(key value) list", other),
+                            expected,
+                            "an entry",
+                        ));
+                    }
+                };
+                let key = match key {
+                    Value::String(key) => key.as_str(),
+                    Value::Symbol(key) => key.as_str(),
+                    other => {
+                        return Err(nested(
+                            mismatch("string or symbol", other),
+                            expected,
+                            "a key",
+                        ));
+                    }
+                };
+                let value = T::from_value(value)
+                    .map_err(|error| nested(error, expected, &format!("key '{key}'")))?;
+                Ok((key.to_string(), value))
+            })
+            .collect()
+    }
+}
+
+/// Entries are sorted by key, so the result does not depend on the map's
+/// iteration order
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        let mut entries: Vec<_> = self.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Value::list(
+            entries
+                .into_iter()
+                .map(|(key, value)| Value::list(vec![key.into_value(), value.into_value()]))
+                .collect(),
+        )
+    }
+}
+
+macro_rules! impl_tuple_conversions {
+    ($len:literal; $($element:ident $index:tt),+) => {
+        impl<$($element: FromValue),+> FromValue for ($($element,)+) {
+            fn from_value(value: &Value) -> Result<Self> {
+                let expected = concat!("list of length ", $len);
+                let elements = elements(value, expected)?;
+                if elements.len() != $len {
+                    return Err(mismatch(expected, value));
+                }
+                Ok(($(
+                    $element::from_value(&elements[$index])
+                        .map_err(|error| nested(error, "list", &format!("index {}", $index)))?,
+                )+))
+            }
+        }
+
+        impl<$($element: IntoValue),+> IntoValue for ($($element,)+) {
+            fn into_value(self) -> Value {
+                Value::list(vec![$(self.$index.into_value()),+])
+            }
+        }
+    };
+}
+
+impl_tuple_conversions!(1; A 0);
+impl_tuple_conversions!(2; A 0, B 1);
+impl_tuple_conversions!(3; A 0, B 1, C 2);
+impl_tuple_conversions!(4; A 0, B 1, C 2, D 3);
+
+/// The result of a typed native procedure: a value, or a `Result` of one
+pub trait NativeResult {
+    /// Convert into the procedure's result
+    fn into_result(self) -> Result<Value>;
+}
+
+impl<T: IntoValue> NativeResult for T {
+    fn into_result(self) -> Result<Value> {
+        Ok(self.into_value())
+    }
+}
+
+impl<T: IntoValue> NativeResult for Result<T> {
+    fn into_result(self) -> Result<Value> {
+        self.map(IntoValue::into_value)
+    }
+}
+
+/// A typed Rust closure that can be called as a native procedure
+///
+/// Implemented for closures of up to six arguments whose argument types
+/// implement [`FromValue`] and whose return type is a [`NativeResult`]. The
+/// `Args` parameter is the tuple of argument types, which only serves to
+/// tell the implementations apart.
+pub trait NativeFunction<Args>: Send + Sync + 'static {
+    /// The number of arguments the closure takes
+    const ARITY: usize;
+
+    /// Convert the arguments and call the closure
+    ///
+    /// `args` must have [`ARITY`](Self::ARITY) elements. Type errors name
+    /// the procedure `name` and the position of the offending argument.
+    fn call(&self, name: &str, args: &[Value]) -> Result<Value>;
+}
+
+/// Convert the argument at `index`, naming it in the error if it fails
+fn argument<T: FromValue>(name: &str, args: &[Value], index: usize) -> Result<T> {
+    T::from_value(&args[index]).map_err(|error| match error {
+        Error::TypeError {
+            expected, actual, ..
+        } => Error::type_error(name, &expected, &actual, Some(index + 1)),
+        other => other,
+    })
+}
+
+macro_rules! impl_native_function {
+    ($arity:literal; $($arg:ident $index:tt),*) => {
+        impl<F, R, $($arg),*> NativeFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + Send + Sync + 'static,
+            R: NativeResult,
+            $($arg: FromValue,)*
+        {
+            const ARITY: usize = $arity;
+
+            #[allow(unused_variables)]
+            fn call(&self, name: &str, args: &[Value]) -> Result<Value> {
+                self($(argument::<$arg>(name, args, $index)?),*).into_result()
+            }
+        }
+    };
+}
+
+impl_native_function!(0;);
+impl_native_function!(1; A 0);
+impl_native_function!(2; A 0, B 1);
+impl_native_function!(3; A 0, B 1, C 2);
+impl_native_function!(4; A 0, B 1, C 2, D 3);
+impl_native_function!(5; A 0, B 1, C 2, D 3, E 4);
+impl_native_function!(6; A 0, B 1, C 2, D 3, E 4, G 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: FromValue + IntoValue + Clone + PartialEq + std::fmt::Debug>(value: T) {
+        assert_eq!(T::from_value(&value.clone().into_value()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_scalar_conversions() {
+        round_trip(2.5);
+        round_trip(-42_i64);
+        round_trip(true);
+        round_trip(String::from("text"));
+        round_trip(ArcString::new("text"));
+        assert_eq!("text".into_value(), Value::string("text"));
+        assert_eq!(().into_value(), Value::Nil);
+
+        assert!(i64::from_value(&Value::number(1.5)).is_err());
+        assert!(i64::from_value(&Value::number(1e300)).is_err());
+        assert!(String::from_value(&Value::symbol("text")).is_err());
+        assert!(ArcString::from_value(&Value::symbol("text")).is_err());
+
+        let error = bool::from_value(&Value::number(1.0)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "conversion: expected boolean, got number"
+        );
+    }
+
+    #[test]
+    fn test_compound_conversions() {
+        round_trip(vec![1_i64, 2, 3]);
+        round_trip(Vec::<f64>::new());
+        round_trip(Some(String::from("x")));
+        round_trip(None::<f64>);
+
+        // #f is None, so Option<bool> cannot tell it from Some(false)
+        assert_eq!(Some(true).into_value(), Value::boolean(true));
+        assert_eq!(Some(false).into_value(), Value::boolean(false));
+        assert_eq!(
+            Option::<bool>::from_value(&Value::boolean(false)).unwrap(),
+            None
+        );
+        round_trip((1_i64, String::from("two"), false));
+        round_trip(HashMap::from([
+            (String::from("a"), 1_i64),
+            (String::from("b"), 2),
+        ]));
+
+        // Nil is the empty list, and maps may have symbol keys
+        assert_eq!(
+            Vec::<i64>::from_value(&Value::Nil).unwrap(),
+            Vec::<i64>::new()
+        );
+        let alist = Value::list(vec![Value::list(vec![
+            Value::symbol("port"),
+            Value::number(80.0),
+        ])]);
+        assert_eq!(
+            HashMap::<String, i64>::from_value(&alist).unwrap(),
+            HashMap::from([(String::from("port"), 80)])
+        );
+
+        // Errors say which part of the value was wrong
+        let list = Value::list(vec![Value::number(1.0), Value::string("x")]);
+        let error = Vec::<f64>::from_value(&list).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "conversion: expected list of number, got string at index 1"
+        );
+        let error = <(f64, f64, f64)>::from_value(&list).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "conversion: expected list of length 3, got list"
+        );
+    }
+
+    #[test]
+    fn test_native_function_arguments() {
+        fn call<Args, F: NativeFunction<Args>>(function: F, args: &[Value]) -> Result<Value> {
+            assert_eq!(F::ARITY, args.len());
+            function.call("f", args)
+        }
+
+        let add = |a: f64, b: f64| a + b;
+        assert_eq!(
+            call(add, &[Value::number(1.0), Value::number(2.0)]).unwrap(),
+            Value::number(3.0)
+        );
+
+        let error = call(add, &[Value::number(1.0), Value::string("2")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "f: expected number for argument 2, got string"
+        );
+
+        // Closures may fail with their own errors
+        let checked = |n: i64| -> Result<i64> {
+            n.checked_mul(2)
+                .ok_or_else(|| Error::runtime_error("overflow"))
+        };
+        assert_eq!(
+            call(checked, &[Value::number(4.0)]).unwrap(),
+            Value::number(8.0)
+        );
+        assert_eq!(call(|| "constant", &[]).unwrap(), Value::string("constant"));
+
+        // String arguments round-trip, owned or shared
+        let text = Value::string("text");
+        let shout = |s: String| s.to_uppercase();
+        assert_eq!(
+            call(shout, std::slice::from_ref(&text)).unwrap(),
+            Value::string("TEXT")
+        );
+        let same = |s: ArcString| s;
+        assert_eq!(call(same, std::slice::from_ref(&text)).unwrap(), text);
+        let length = |s: ArcString| s.as_str().len() as f64;
+        assert_eq!(call(length, &[text]).unwrap(), Value::number(4.0));
+    }
+}
@@ -6,21 +6,34 @@
 //!
 //! ## Performance Optimizations
 //!
-//! - **Symbols**: Use `SmolStr` for stack allocation of short identifiers (≤23 bytes)
+//! - **Symbols**: Interned process-wide, so comparing and hashing them is O(1)
 //! - **Strings/Lists**: Use `Arc` for efficient sharing across threads
+//! - **Records**: Share the type descriptor and field values via `Arc`
+//! - **Promises**: Memoise forced values in a `OnceLock`, written at most once
+//! - **Parameters**: Dynamic bindings form an `Arc`-linked chain that fibers capture in O(1)
 //! - **Numbers**: Use primitive `f64` with `Copy` semantics
 
+pub mod convert;
 pub mod list;
 pub mod number;
+pub mod parameter;
 pub mod procedure;
+pub mod promise;
+pub mod record;
+#[cfg(feature = "serde")]
+pub mod serde;
 pub mod string;
 pub mod symbol;
 pub mod value;
 
 // Re-export core types for convenience
+pub use convert::{FromValue, IntoValue, NativeFunction, NativeResult};
 pub use list::List;
 pub use number::Number;
-pub use procedure::{Lambda, Procedure};
+pub use parameter::{DynamicEnvironment, DynamicScope, Parameter};
+pub use procedure::{Arity, Lambda, Native, NativeFn, Procedure};
+pub use promise::{EvaluationId, ForceStep, Promise, PromiseKind, PromiseThunk};
+pub use record::{Record, RecordProcedure, RecordProcedureKind, RecordType};
 pub use string::ArcString;
 pub use symbol::Symbol;
 pub use value::Value;
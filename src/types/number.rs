@@ -119,6 +119,7 @@ impl From<Number> for f64 {
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
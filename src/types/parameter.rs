@@ -0,0 +1,319 @@
+//! Parameter objects and the dynamic environment
+//!
+//! Implements the runtime representation behind R7RS `make-parameter` and
+//! `parameterize`. A parameter holds a default value and an optional
+//! converter procedure; `parameterize` rebinds parameters for the dynamic
+//! extent of its body by extending the current [`DynamicEnvironment`].
+//!
+//! ## Fibers
+//! The dynamic environment is an immutable, Arc-linked list of frames, so
+//! capturing it is O(1). Each fiber captures the spawning context's dynamic
+//! environment and installs it while the fiber runs, which means fibers
+//! spawned inside a `parameterize` body inherit its bindings while sibling
+//! fibers remain unaffected.
+
+use crate::error::{Error, Result};
+use crate::types::{Procedure, Value};
+use std::cell::RefCell;
+use std::sync::Arc;
+
+/// Parameter object created by `make-parameter`
+///
+/// Parameters are compared by identity: two parameters are the same only if
+/// they are the same Arc instance.
+#[derive(Debug)]
+pub struct Parameter {
+    /// Value returned when the parameter is not rebound by `parameterize`
+    ///
+    /// The converter has already been applied to this value.
+    default: Value,
+    /// Converter applied to values supplied by `parameterize`
+    converter: Option<Procedure>,
+}
+
+impl Parameter {
+    /// Create a new parameter wrapped in Arc
+    ///
+    /// The `default` value must already have been passed through the
+    /// converter, as R7RS requires.
+    pub fn new(default: Value, converter: Option<Procedure>) -> Arc<Self> {
+        Arc::new(Parameter { default, converter })
+    }
+
+    /// Get the default (unparameterized) value
+    pub fn default_value(&self) -> &Value {
+        &self.default
+    }
+
+    /// Get the converter procedure, if any
+    pub fn converter(&self) -> Option<&Procedure> {
+        self.converter.as_ref()
+    }
+
+    /// Get the current value of this parameter
+    ///
+    /// Looks the parameter up in the current thread's dynamic environment and
+    /// falls back to the default value when it is not rebound.
+    pub fn value(self: &Arc<Self>) -> Value {
+        DynamicEnvironment::with_current(|dynamic_env| dynamic_env.lookup(self))
+            .unwrap_or_else(|| self.default.clone())
+    }
+
+    /// Call the parameter as a procedure
+    ///
+    /// Parameters take no arguments and return their current value.
+    pub fn call(self: &Arc<Self>, args: &[Value]) -> Result<Value> {
+        if !args.is_empty() {
+            return Err(Error::arity_error("parameter", 0, args.len()));
+        }
+        Ok(self.value())
+    }
+}
+
+impl std::fmt::Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<parameter>")
+    }
+}
+
+/// Single parameter binding in the dynamic environment
+#[derive(Debug)]
+struct DynamicFrame {
+    parameter: Arc<Parameter>,
+    value: Value,
+    parent: Option<Arc<DynamicFrame>>,
+}
+
+/// Immutable chain of parameter bindings
+///
+/// Extending a dynamic environment never modifies it; a new environment
+/// sharing the existing frames is returned instead. This makes it cheap to
+/// capture for fibers and safe to share across threads.
+#[derive(Debug, Clone, Default)]
+pub struct DynamicEnvironment {
+    head: Option<Arc<DynamicFrame>>,
+}
+
+thread_local! {
+    /// Dynamic environment of the code currently running on this thread
+    static CURRENT: RefCell<DynamicEnvironment> = RefCell::new(DynamicEnvironment::new());
+}
+
+impl DynamicEnvironment {
+    /// Create an empty dynamic environment (every parameter has its default)
+    pub fn new() -> Self {
+        DynamicEnvironment { head: None }
+    }
+
+    /// Get a copy of the current thread's dynamic environment
+    pub fn current() -> Self {
+        Self::with_current(Clone::clone)
+    }
+
+    /// Run a closure with a reference to the current dynamic environment
+    fn with_current<R>(f: impl FnOnce(&DynamicEnvironment) -> R) -> R {
+        CURRENT.with(|current| f(&current.borrow()))
+    }
+
+    /// Make this the current thread's dynamic environment
+    ///
+    /// Returns the previously installed environment so that callers can
+    /// restore it, as the fiber scheduler does around each fiber step.
+    pub fn install(self) -> DynamicEnvironment {
+        CURRENT.with(|current| current.replace(self))
+    }
+
+    /// Install this environment until the returned guard is dropped
+    ///
+    /// The previous environment is restored even if evaluation fails or
+    /// panics, which is what gives `parameterize` its dynamic extent.
+    pub fn enter(self) -> DynamicScope {
+        DynamicScope {
+            previous: Some(self.install()),
+        }
+    }
+
+    /// Look up the innermost binding for a parameter
+    pub fn lookup(&self, parameter: &Arc<Parameter>) -> Option<Value> {
+        let mut frame = self.head.as_ref();
+        while let Some(current) = frame {
+            if Arc::ptr_eq(&current.parameter, parameter) {
+                return Some(current.value.clone());
+            }
+            frame = current.parent.as_ref();
+        }
+        None
+    }
+
+    /// Create a new environment with additional bindings
+    ///
+    /// Later bindings shadow earlier ones and all existing bindings. A
+    /// shadowed binding of the same parameter is left out, so that a loop
+    /// rebinding a parameter keeps an environment of bounded size.
+    pub fn extend(&self, bindings: impl IntoIterator<Item = (Arc<Parameter>, Value)>) -> Self {
+        bindings
+            .into_iter()
+            .fold(self.clone(), |env, (parameter, value)| {
+                env.bind(parameter, value)
+            })
+    }
+
+    /// Create a new environment binding one more parameter
+    fn bind(&self, parameter: Arc<Parameter>, value: Value) -> Self {
+        // Each parameter is bound at most once, so this walks no further
+        // than the number of parameters bound
+        let mut above = Vec::new();
+        let mut frame = self.head.as_ref();
+        let parent = loop {
+            match frame {
+                Some(current) if Arc::ptr_eq(&current.parameter, &parameter) => {
+                    break current.parent.clone();
+                }
+                Some(current) => {
+                    above.push(current);
+                    frame = current.parent.as_ref();
+                }
+                None => {
+                    above.clear();
+                    break self.head.clone();
+                }
+            }
+        };
+
+        // The frames above the shadowed binding are copied onto its parent
+        let parent = above.into_iter().rev().fold(parent, |parent, frame| {
+            Some(Arc::new(DynamicFrame {
+                parameter: Arc::clone(&frame.parameter),
+                value: frame.value.clone(),
+                parent,
+            }))
+        });
+        DynamicEnvironment {
+            head: Some(Arc::new(DynamicFrame {
+                parameter,
+                value,
+                parent,
+            })),
+        }
+    }
+
+    /// Check if the environment contains no bindings
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+}
+
+/// Guard restoring the previous dynamic environment on drop
+///
+/// Created by [`DynamicEnvironment::enter`].
+#[derive(Debug)]
+pub struct DynamicScope {
+    previous: Option<DynamicEnvironment>,
+}
+
+impl Drop for DynamicScope {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            previous.install();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parameter_default_value() {
+        let parameter = Parameter::new(Value::number(10.0), None);
+        assert_eq!(parameter.value(), Value::number(10.0));
+        assert_eq!(parameter.call(&[]).unwrap(), Value::number(10.0));
+        assert!(parameter.converter().is_none());
+        assert_eq!(format!("{parameter}"), "#<parameter>");
+    }
+
+    #[test]
+    fn test_parameter_call_arity() {
+        let parameter = Parameter::new(Value::number(10.0), None);
+        let err = parameter.call(&[Value::number(1.0)]).unwrap_err();
+        assert!(matches!(err, Error::ArityError { .. }));
+    }
+
+    #[test]
+    fn test_dynamic_environment_lookup_and_shadowing() {
+        let p = Parameter::new(Value::number(1.0), None);
+        let q = Parameter::new(Value::number(2.0), None);
+
+        let empty = DynamicEnvironment::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.lookup(&p), None);
+
+        let outer = empty.extend([(Arc::clone(&p), Value::number(10.0))]);
+        let inner = outer.extend([
+            (Arc::clone(&p), Value::number(100.0)),
+            (Arc::clone(&q), Value::number(200.0)),
+        ]);
+
+        assert_eq!(outer.lookup(&p), Some(Value::number(10.0)));
+        assert_eq!(outer.lookup(&q), None);
+        assert_eq!(inner.lookup(&p), Some(Value::number(100.0)));
+        assert_eq!(inner.lookup(&q), Some(Value::number(200.0)));
+
+        // Extending never modifies the original environment
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_scope_restores_previous() {
+        let p = Parameter::new(Value::number(1.0), None);
+
+        {
+            let _scope = DynamicEnvironment::current()
+                .extend([(Arc::clone(&p), Value::number(2.0))])
+                .enter();
+            assert_eq!(p.value(), Value::number(2.0));
+
+            {
+                let _inner = DynamicEnvironment::current()
+                    .extend([(Arc::clone(&p), Value::number(3.0))])
+                    .enter();
+                assert_eq!(p.value(), Value::number(3.0));
+            }
+
+            assert_eq!(p.value(), Value::number(2.0));
+        }
+
+        assert_eq!(p.value(), Value::number(1.0));
+    }
+
+    #[test]
+    fn test_dynamic_environment_is_per_thread() {
+        let p = Parameter::new(Value::number(1.0), None);
+        let _scope = DynamicEnvironment::new()
+            .extend([(Arc::clone(&p), Value::number(2.0))])
+            .enter();
+
+        // Another thread starts with an empty dynamic environment...
+        let other = Arc::clone(&p);
+        let value = std::thread::spawn(move || other.value()).join().unwrap();
+        assert_eq!(value, Value::number(1.0));
+
+        // ...unless the captured environment is installed explicitly
+        let captured = DynamicEnvironment::current();
+        let other = Arc::clone(&p);
+        let value = std::thread::spawn(move || {
+            let _scope = captured.enter();
+            other.value()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(value, Value::number(2.0));
+    }
+
+    #[test]
+    fn test_parameter_thread_safety() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Parameter>();
+        assert_send_sync::<DynamicEnvironment>();
+    }
+}
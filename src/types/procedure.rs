@@ -1,11 +1,13 @@
 //! Function and procedure types for Scheme
 //!
 //! This module implements the procedure system for Scheme functions,
-//! including built-in procedures and user-defined lambdas with closures.
+//! including built-in procedures, native procedures registered by the host
+//! application and user-defined lambdas with closures.
 
 use crate::parser::Expression;
+use crate::runtime::eval::bytecode::Code;
 use crate::runtime::{Environment, builtins::Builtin};
-use crate::types::Symbol;
+use crate::types::{NativeFunction, Parameter, RecordProcedure, Symbol, Value};
 use std::sync::{Arc, OnceLock, Weak};
 
 /// Lambda procedure definition
@@ -13,14 +15,131 @@ use std::sync::{Arc, OnceLock, Weak};
 /// Lambda procedures are created by the `lambda` special form and
 /// capture their defining environment as a closure. This struct
 /// enables efficient sharing via Arc.
+///
+/// Lambdas bound by `define`, `letrec`, `letrec*` or named `let` remember the
+/// name they were bound to, for display and error reporting.
 #[derive(Debug)]
 pub struct Lambda {
+    /// Name the procedure was defined with, if any
+    name: Option<Symbol>,
     /// Parameter identifiers for the procedure
     params: Vec<Symbol>,
     /// Expressions that form the procedure body
     body: Vec<Arc<Expression>>,
     /// Captured environment (closure) from procedure definition
-    env: Environment<'static>,
+    env: Environment,
+    /// Body compiled for the virtual machine, once it has been
+    code: OnceLock<Arc<Code>>,
+}
+
+/// The Rust function behind a native procedure
+pub type NativeFn = dyn Fn(&[Value]) -> crate::Result<Value> + Send + Sync;
+
+/// The number of arguments a native procedure accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    /// The fewest arguments accepted
+    pub min: usize,
+    /// The most arguments accepted, or None for no limit
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    /// Accept exactly `count` arguments
+    pub fn exactly(count: usize) -> Self {
+        Arity {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    /// Accept `min` or more arguments
+    pub fn at_least(min: usize) -> Self {
+        Arity { min, max: None }
+    }
+
+    /// Accept between `min` and `max` arguments, inclusive
+    pub fn between(min: usize, max: usize) -> Self {
+        Arity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    /// Check if `count` arguments are accepted
+    pub fn accepts(self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+/// Procedure implemented by a Rust closure supplied by the host application
+///
+/// Unlike [`Builtin`], which is fixed when the crate is built, native
+/// procedures can be created at run time, so embedders can expose their own
+/// functions to Scheme. The arity is checked before the closure is called.
+#[derive(Clone)]
+pub struct Native {
+    name: Symbol,
+    arity: Arity,
+    function: Arc<NativeFn>,
+}
+
+impl Native {
+    /// Create a new native procedure
+    pub fn new(
+        name: &str,
+        arity: Arity,
+        function: impl Fn(&[Value]) -> crate::Result<Value> + Send + Sync + 'static,
+    ) -> Self {
+        Native {
+            name: Symbol::new(name),
+            arity,
+            function: Arc::new(function),
+        }
+    }
+
+    /// Create a native procedure from a typed Rust closure
+    ///
+    /// The arity is the closure's number of arguments, and each argument is
+    /// converted with [`FromValue`](crate::types::FromValue), so a type
+    /// error names the argument that could not be converted.
+    pub fn from_fn<Args, F: NativeFunction<Args>>(name: &str, function: F) -> Self {
+        let procedure = name.to_string();
+        Self::new(name, Arity::exactly(F::ARITY), move |args| {
+            function.call(&procedure, args)
+        })
+    }
+
+    /// Get the name the procedure was registered with
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Get the number of arguments the procedure accepts
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    /// Call the procedure after checking the number of arguments
+    pub fn call(&self, args: &[Value]) -> crate::Result<Value> {
+        if !self.arity.accepts(args.len()) {
+            let expected = match self.arity.max {
+                Some(max) if args.len() > max => max,
+                _ => self.arity.min,
+            };
+            return Err(crate::Error::arity_error(self.name(), expected, args.len()));
+        }
+        (self.function)(args)
+    }
+}
+
+impl std::fmt::Debug for Native {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
 }
 
 /// Procedure types in Scheme
@@ -35,6 +154,12 @@ pub enum Procedure {
     /// eliminating the need to store redundant name and function pointer data.
     Builtin(Builtin),
 
+    /// Procedure implemented by a Rust closure registered at run time
+    ///
+    /// Called like a builtin; native procedures are equal only if they share
+    /// the same closure.
+    Native(Native),
+
     /// User-defined lambda procedure with closure
     ///
     /// Lambda procedures use Arc for efficient sharing and cloning.
@@ -48,6 +173,26 @@ pub enum Procedure {
     /// This enables recursive and mutually recursive procedure definitions without
     /// circular reference issues.
     WeakLambda(Arc<OnceLock<Weak<Lambda>>>),
+
+    /// Placeholder for a procedure referred to before its definition
+    ///
+    /// Bound while a body or `letrec` defines its procedures, for the
+    /// closures created before a definition to copy. Once set it refers to
+    /// the procedure strongly, so those closures go on calling it after the
+    /// body returns.
+    ForwardLambda(Arc<OnceLock<Arc<Lambda>>>),
+
+    /// Procedure generated by `define-record-type`
+    ///
+    /// Constructors, predicates, accessors and functional updaters all close
+    /// over the same Arc'd record type descriptor.
+    Record(RecordProcedure),
+
+    /// Parameter object created by `make-parameter`
+    ///
+    /// Calling a parameter with no arguments returns its value in the
+    /// current dynamic environment.
+    Parameter(Arc<Parameter>),
 }
 
 impl Lambda {
@@ -60,12 +205,58 @@ impl Lambda {
     ///
     /// # Returns
     /// A new `Arc<Lambda>` for efficient sharing
-    pub fn new(
+    pub fn new(params: Vec<Symbol>, body: Vec<Arc<Expression>>, env: Environment) -> Arc<Self> {
+        Arc::new(Lambda {
+            name: None,
+            params,
+            body,
+            env,
+            code: OnceLock::new(),
+        })
+    }
+
+    /// Create a new Lambda instance that remembers its defining name
+    ///
+    /// # Arguments
+    /// * `name` - Identifier the procedure is bound to
+    /// * `params` - Parameter identifiers for the procedure
+    /// * `body` - Expressions that form the procedure body
+    /// * `env` - Captured environment from procedure definition
+    pub fn named(
+        name: Symbol,
         params: Vec<Symbol>,
         body: Vec<Arc<Expression>>,
-        env: Environment<'static>,
+        env: Environment,
     ) -> Arc<Self> {
-        Arc::new(Lambda { params, body, env })
+        Arc::new(Lambda {
+            name: Some(name),
+            params,
+            body,
+            env,
+            code: OnceLock::new(),
+        })
+    }
+
+    /// Create a Lambda whose body has already been compiled
+    pub(crate) fn compiled(
+        name: Option<Symbol>,
+        params: Vec<Symbol>,
+        body: Vec<Arc<Expression>>,
+        env: Environment,
+        code: Arc<Code>,
+    ) -> Arc<Self> {
+        Arc::new(Lambda {
+            name,
+            params,
+            body,
+            env,
+            code: OnceLock::from(code),
+        })
+    }
+
+    /// Get the name the procedure was defined with, if any
+    pub fn name(&self) -> Option<&Symbol> {
+        self.name.as_ref()
     }
 
     /// Get a reference to the parameter identifiers
@@ -79,7 +270,7 @@ impl Lambda {
     }
 
     /// Get a reference to the captured environment
-    pub fn env(&self) -> &Environment<'static> {
+    pub fn env(&self) -> &Environment {
         &self.env
     }
 
@@ -87,6 +278,11 @@ impl Lambda {
     pub fn arity(&self) -> usize {
         self.params.len()
     }
+
+    /// Get the compiled body, compiling it with `compile` on first use
+    pub(crate) fn code(&self, compile: impl FnOnce(&Lambda) -> Arc<Code>) -> &Arc<Code> {
+        self.code.get_or_init(|| compile(self))
+    }
 }
 
 impl Procedure {
@@ -101,6 +297,20 @@ impl Procedure {
         Procedure::Builtin(builtin)
     }
 
+    /// Create a new native procedure from a Rust closure
+    ///
+    /// # Arguments
+    /// * `name` - Name for display and error messages
+    /// * `arity` - The number of arguments the closure accepts
+    /// * `function` - The closure to call with the evaluated arguments
+    pub fn native(
+        name: &str,
+        arity: Arity,
+        function: impl Fn(&[Value]) -> crate::Result<Value> + Send + Sync + 'static,
+    ) -> Self {
+        Procedure::Native(Native::new(name, arity, function))
+    }
+
     /// Create a new lambda procedure
     ///
     /// # Arguments
@@ -110,23 +320,46 @@ impl Procedure {
     ///
     /// # Returns
     /// A new `Procedure::Lambda` instance with Arc sharing
-    pub fn lambda(
-        params: Vec<Symbol>,
-        body: Vec<Arc<Expression>>,
-        env: Environment<'static>,
-    ) -> Self {
+    pub fn lambda(params: Vec<Symbol>, body: Vec<Arc<Expression>>, env: Environment) -> Self {
         Procedure::Lambda(Lambda::new(params, body, env))
     }
 
     /// Get the display name of the procedure
     ///
     /// For built-in procedures, returns the stored name.
-    /// For lambda procedures, returns a generic lambda description.
+    /// For lambda procedures, returns the defining name, or a generic lambda
+    /// description for anonymous lambdas.
     pub fn name(&self) -> &str {
         match self {
             Procedure::Builtin(builtin) => builtin.name(),
-            Procedure::Lambda(_) => "<lambda>",
-            Procedure::WeakLambda(_) => "<lambda>",
+            Procedure::Native(native) => native.name(),
+            Procedure::Lambda(lambda) => lambda.name().map_or("<lambda>", Symbol::as_str),
+            Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => "<lambda>",
+            Procedure::Record(record_proc) => record_proc.name(),
+            Procedure::Parameter(_) => "<parameter>",
+        }
+    }
+
+    /// Get the name the procedure was defined with, if it has one
+    ///
+    /// Builtins, native and record procedures always have a name; lambdas have one
+    /// when they were bound by `define`, `letrec`, `letrec*` or named `let`,
+    /// including when referred to recursively from their own body. Anonymous
+    /// lambdas and parameter objects have none.
+    pub fn defined_name(&self) -> Option<Symbol> {
+        match self {
+            Procedure::Builtin(builtin) => Some(Symbol::new(builtin.name())),
+            Procedure::Native(native) => Some(native.name.clone()),
+            Procedure::Lambda(lambda) => lambda.name().cloned(),
+            Procedure::WeakLambda(once_lock) => once_lock
+                .get()
+                .and_then(|weak| weak.upgrade())
+                .and_then(|lambda| lambda.name().cloned()),
+            Procedure::ForwardLambda(once_lock) => {
+                once_lock.get().and_then(|lambda| lambda.name().cloned())
+            }
+            Procedure::Record(record_proc) => Some(Symbol::new(record_proc.name())),
+            Procedure::Parameter(_) => None,
         }
     }
 
@@ -135,61 +368,81 @@ impl Procedure {
         matches!(self, Procedure::Builtin(_))
     }
 
-    /// Check if this is a lambda procedure (including weak lambda)
+    /// Check if this is a lambda procedure (including weak and forward
+    /// lambdas)
     pub fn is_lambda(&self) -> bool {
-        matches!(self, Procedure::Lambda(_) | Procedure::WeakLambda(_))
+        matches!(
+            self,
+            Procedure::Lambda(_) | Procedure::WeakLambda(_) | Procedure::ForwardLambda(_)
+        )
     }
 
     /// Get the parameter count for the procedure
     ///
     /// Returns the number of parameters this procedure expects.
     /// For built-in procedures, this is not directly available, so None is returned.
+    /// For native procedures, returns the count if it is fixed.
     /// For lambda procedures, returns the parameter count.
     /// For weak lambda procedures, returns None if not yet initialized.
     pub fn arity(&self) -> Option<usize> {
         match self {
             Procedure::Builtin(_) => None, // Arity varies for built-ins
+            Procedure::Native(native) => match native.arity() {
+                Arity {
+                    min,
+                    max: Some(max),
+                } if min == max => Some(min),
+                _ => None,
+            },
             Procedure::Lambda(lambda) => Some(lambda.arity()),
+            Procedure::Record(record_proc) => Some(record_proc.arity()),
+            Procedure::Parameter(_) => Some(0),
             Procedure::WeakLambda(once_lock) => once_lock
                 .get()
                 .and_then(|weak| weak.upgrade())
                 .map(|lambda| lambda.arity()),
+            Procedure::ForwardLambda(once_lock) => once_lock.get().map(|lambda| lambda.arity()),
         }
     }
 
     /// Get a reference to the parameters (lambda procedures only)
     pub fn params(&self) -> Option<&[Symbol]> {
         match self {
-            Procedure::Builtin(_) => None,
+            Procedure::Builtin(_) | Procedure::Native(_) => None,
             Procedure::Lambda(lambda) => Some(lambda.params()),
-            Procedure::WeakLambda(_) => None, // Cannot access params through weak reference
+            // Placeholders are resolved before their procedures are used
+            Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => None,
+            Procedure::Record(_) | Procedure::Parameter(_) => None,
         }
     }
 
     /// Get a reference to the body expressions (lambda procedures only)
     pub fn body(&self) -> Option<&[Arc<Expression>]> {
         match self {
-            Procedure::Builtin(_) => None,
+            Procedure::Builtin(_) | Procedure::Native(_) => None,
             Procedure::Lambda(lambda) => Some(lambda.body()),
-            Procedure::WeakLambda(_) => None, // Cannot access body through weak reference
+            Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => None,
+            Procedure::Record(_) | Procedure::Parameter(_) => None,
         }
     }
 
     /// Get a reference to the captured environment (lambda procedures only)
-    pub fn env(&self) -> Option<&Environment<'static>> {
+    pub fn env(&self) -> Option<&Environment> {
         match self {
-            Procedure::Builtin(_) => None,
+            Procedure::Builtin(_) | Procedure::Native(_) => None,
             Procedure::Lambda(lambda) => Some(lambda.env()),
-            Procedure::WeakLambda(_) => None, // Cannot access env through weak reference
+            Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => None,
+            Procedure::Record(_) | Procedure::Parameter(_) => None,
         }
     }
 
     /// Get a reference to the Lambda struct (lambda procedures only)
     pub fn as_lambda(&self) -> Option<&Arc<Lambda>> {
         match self {
-            Procedure::Builtin(_) => None,
+            Procedure::Builtin(_) | Procedure::Native(_) => None,
             Procedure::Lambda(lambda) => Some(lambda),
-            Procedure::WeakLambda(_) => None, // Cannot return Arc through weak reference
+            Procedure::WeakLambda(_) | Procedure::ForwardLambda(_) => None,
+            Procedure::Record(_) | Procedure::Parameter(_) => None,
         }
     }
 
@@ -201,6 +454,29 @@ impl Procedure {
         Procedure::WeakLambda(Arc::new(OnceLock::new()))
     }
 
+    /// Create a placeholder for a procedure referred to before its
+    /// definition
+    pub(crate) fn forward_lambda() -> Self {
+        Procedure::ForwardLambda(Arc::new(OnceLock::new()))
+    }
+
+    /// Check if this is a ForwardLambda that is not initialized yet
+    pub(crate) fn is_placeholder(&self) -> bool {
+        matches!(self, Procedure::ForwardLambda(once_lock) if once_lock.get().is_none())
+    }
+
+    /// Point a ForwardLambda placeholder at the procedure it stands for
+    pub(crate) fn fill_placeholder(&self, lambda: &Arc<Lambda>) -> Result<(), crate::Error> {
+        match self {
+            Procedure::ForwardLambda(once_lock) => once_lock
+                .set(Arc::clone(lambda))
+                .map_err(|_| crate::Error::runtime_error("ForwardLambda already initialized")),
+            _ => Err(crate::Error::runtime_error(
+                "Cannot fill a placeholder on non-ForwardLambda procedure",
+            )),
+        }
+    }
+
     /// Initialize a WeakLambda with the actual lambda
     ///
     /// This can only be called once per WeakLambda instance.
@@ -216,23 +492,32 @@ impl Procedure {
         }
     }
 
-    /// Get the actual lambda from a WeakLambda
+    /// Get the actual lambda from a WeakLambda or ForwardLambda
     ///
-    /// Returns the upgraded `Arc<Lambda>` if the WeakLambda is initialized
+    /// Returns the upgraded `Arc<Lambda>` if the placeholder is initialized
     /// and the lambda hasn't been dropped. Used during procedure application.
     pub fn resolve_weak_lambda(&self) -> Result<Arc<Lambda>, crate::Error> {
         match self {
             Procedure::WeakLambda(once_lock) => {
-                let weak = once_lock
-                    .get()
-                    .ok_or_else(|| crate::Error::runtime_error("WeakLambda not yet initialized"))?;
+                let weak = once_lock.get().ok_or_else(|| {
+                    crate::Error::runtime_error("Procedure called before its definition")
+                })?;
                 weak.upgrade()
                     .ok_or_else(|| crate::Error::runtime_error("Lambda was dropped"))
             }
+            Procedure::ForwardLambda(once_lock) => once_lock.get().cloned().ok_or_else(|| {
+                crate::Error::runtime_error("Procedure called before its definition")
+            }),
             Procedure::Lambda(lambda) => Ok(Arc::clone(lambda)),
-            Procedure::Builtin(_) => Err(crate::Error::runtime_error(
+            Procedure::Builtin(_) | Procedure::Native(_) => Err(crate::Error::runtime_error(
                 "Cannot resolve lambda from builtin procedure",
             )),
+            Procedure::Record(_) => Err(crate::Error::runtime_error(
+                "Cannot resolve lambda from record procedure",
+            )),
+            Procedure::Parameter(_) => Err(crate::Error::runtime_error(
+                "Cannot resolve lambda from parameter object",
+            )),
         }
     }
 }
@@ -243,6 +528,11 @@ impl PartialEq for Procedure {
             // Built-in procedures are equal if they have the same kind
             (Procedure::Builtin(kind1), Procedure::Builtin(kind2)) => kind1 == kind2,
 
+            // Native procedures are equal if they share the same closure
+            (Procedure::Native(native1), Procedure::Native(native2)) => {
+                Arc::ptr_eq(&native1.function, &native2.function)
+            }
+
             // Lambda procedures are equal if both Arc<Lambda> point to the same Lambda.
             // This uses pointer equality rather than content equality - two lambdas
             // are only equal if they're the exact same Arc instance (e.g., via cloning).
@@ -278,6 +568,23 @@ impl PartialEq for Procedure {
                 .map(|lambda1| Arc::ptr_eq(&lambda1, lambda2))
                 .unwrap_or(false),
 
+            // A ForwardLambda is equal to the lambda it points to
+            (Procedure::ForwardLambda(once_lock), other)
+            | (other, Procedure::ForwardLambda(once_lock)) => once_lock
+                .get()
+                .is_some_and(|lambda| Procedure::Lambda(Arc::clone(lambda)) == *other),
+
+            // Record procedures are equal if they were generated for the same
+            // record type with the same name and role
+            (Procedure::Record(record_proc1), Procedure::Record(record_proc2)) => {
+                record_proc1 == record_proc2
+            }
+
+            // Parameters are equal only if they are the same parameter object
+            (Procedure::Parameter(parameter1), Procedure::Parameter(parameter2)) => {
+                Arc::ptr_eq(parameter1, parameter2)
+            }
+
             // Different procedure types are never equal
             _ => false,
         }
@@ -286,6 +593,9 @@ impl PartialEq for Procedure {
 
 impl std::fmt::Display for Lambda {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            return write!(f, "#<procedure:{name}>");
+        }
         write!(f, "#<lambda:")?;
         for (i, param) in self.params.iter().enumerate() {
             if i > 0 {
@@ -301,6 +611,7 @@ impl std::fmt::Display for Procedure {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Procedure::Builtin(builtin) => write!(f, "#<builtin:{}>", builtin.name()),
+            Procedure::Native(native) => write!(f, "#<builtin:{}>", native.name()),
             Procedure::Lambda(lambda) => write!(f, "{lambda}"),
             Procedure::WeakLambda(once_lock) => {
                 match once_lock.get().and_then(|weak| weak.upgrade()) {
@@ -308,6 +619,12 @@ impl std::fmt::Display for Procedure {
                     None => write!(f, "#<weak-lambda:uninitialized>"),
                 }
             }
+            Procedure::ForwardLambda(once_lock) => match once_lock.get() {
+                Some(lambda) => write!(f, "{lambda}"),
+                None => write!(f, "#<forward-lambda:uninitialized>"),
+            },
+            Procedure::Record(record_proc) => write!(f, "{record_proc}"),
+            Procedure::Parameter(parameter) => write!(f, "{parameter}"),
         }
     }
 }
@@ -349,6 +666,29 @@ mod tests {
         assert_eq!(format!("{lambda_no_params}"), "#<lambda:>");
     }
 
+    #[test]
+    fn test_named_lambda() {
+        let params = vec![Symbol::new("n")];
+        let body = vec![Expression::arc_atom(Value::symbol("n"))];
+        let lambda = Lambda::named(Symbol::new("fact"), params, body, Environment::new());
+
+        assert_eq!(lambda.name(), Some(&Symbol::new("fact")));
+        assert_eq!(format!("{lambda}"), "#<procedure:fact>");
+
+        let procedure = Procedure::Lambda(lambda);
+        assert_eq!(procedure.name(), "fact");
+        assert_eq!(procedure.defined_name(), Some(Symbol::new("fact")));
+
+        // Anonymous lambdas and parameters have no defined name
+        let anonymous = Procedure::lambda(vec![], vec![], Environment::new());
+        assert_eq!(anonymous.name(), "<lambda>");
+        assert_eq!(anonymous.defined_name(), None);
+        assert_eq!(
+            Procedure::builtin(Builtin::Car).defined_name(),
+            Some(Symbol::new("car"))
+        );
+    }
+
     #[test]
     fn test_builtin_procedure_creation() {
         let proc = Procedure::builtin(Builtin::Add);
@@ -429,6 +769,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_native_procedure() {
+        let native = Procedure::native("sum", Arity::at_least(1), |args| {
+            let total = args.iter().filter_map(Value::as_number).sum();
+            Ok(Value::number(total))
+        });
+        assert_eq!(native.name(), "sum");
+        assert_eq!(native.defined_name(), Some(Symbol::new("sum")));
+        assert_eq!(native.arity(), None);
+        assert_eq!(format!("{native}"), "#<builtin:sum>");
+
+        let Procedure::Native(inner) = &native else {
+            panic!("Expected native procedure");
+        };
+        assert_eq!(
+            inner
+                .call(&[Value::number(1.0), Value::number(2.0)])
+                .unwrap(),
+            Value::number(3.0)
+        );
+        assert!(inner.call(&[]).is_err());
+
+        // Natives are equal only if they share a closure
+        assert_eq!(native, native.clone());
+        assert_ne!(
+            native,
+            Procedure::native("sum", Arity::exactly(0), |_| Ok(Value::nil()))
+        );
+        assert_eq!(
+            Procedure::native("pair", Arity::exactly(2), |_| Ok(Value::nil())).arity(),
+            Some(2)
+        );
+        assert!(Arity::between(1, 2).accepts(2));
+        assert!(!Arity::between(1, 2).accepts(3));
+    }
+
     #[test]
     fn test_procedure_equality() {
         // Same kind built-ins are equal
@@ -0,0 +1,417 @@
+//! Promises for lazy evaluation
+//!
+//! Implements the runtime representation behind R7RS `delay`, `delay-force`
+//! and `make-promise`. A promise starts out holding an unevaluated body and
+//! its captured environment; the first `force` evaluates the body and every
+//! later `force` returns the memoised value.
+//!
+//! ## Thread Safety
+//! Promises may be forced concurrently from several fibers. The memoised
+//! value lives in a `OnceLock`, so it is written at most once. A promise is
+//! claimed by the [`EvaluationId`] forcing it rather than by a thread, as
+//! fibers on one executor thread take turns running their evaluations.
+//! While one evaluation is evaluating the body, others forcing the same
+//! promise wait until the value is available: an evaluation running as a
+//! future yields to its executor and tries again, and any other blocks.
+//!
+//! A body may force its own promise (R7RS 4.2.5). The nested force
+//! evaluates the body again in the same evaluation, and whichever finishes
+//! first provides the value; the outer one's result is discarded.
+//!
+//! The forcing loop itself lives in `runtime::builtins::promise`, because it
+//! needs the evaluator; this module only provides the state machine.
+
+use crate::parser::Expression;
+use crate::runtime::Environment;
+use crate::types::Value;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+
+/// Identifies an evaluation, as the owner of the promises it is forcing
+///
+/// Evaluations nested in a builtin, native procedure or special form, such
+/// as forcing a promise for `stream->list`, are part of the evaluation that
+/// called it and share its identity (see [`EvaluationId::run`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvaluationId(u64);
+
+thread_local! {
+    /// The evaluation running on this thread, if any
+    static CURRENT: Cell<Option<EvaluationId>> = const { Cell::new(None) };
+}
+
+impl EvaluationId {
+    /// The evaluation running on this thread, or a new one if none is
+    pub fn current() -> Self {
+        CURRENT.get().unwrap_or_else(|| {
+            static NEXT: AtomicU64 = AtomicU64::new(0);
+            EvaluationId(NEXT.fetch_add(1, Ordering::Relaxed))
+        })
+    }
+
+    /// Run `f` as part of this evaluation, so that evaluations it starts
+    /// force promises as this one
+    pub fn run<R>(self, f: impl FnOnce() -> R) -> R {
+        /// Restores the outer evaluation, even if `f` panics
+        struct Restore(Option<EvaluationId>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.set(self.0);
+            }
+        }
+        let _restore = Restore(CURRENT.replace(Some(self)));
+        f()
+    }
+}
+
+impl Default for EvaluationId {
+    /// The evaluation running on this thread, or a new one
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+/// How a delayed promise's body should be treated once evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromiseKind {
+    /// Created by `delay`: the body's value is the promise's value
+    Delay,
+    /// Created by `delay-force`: the body must evaluate to another promise,
+    /// which is forced iteratively in place of this one
+    DelayForce,
+}
+
+/// Unevaluated body of a delayed promise
+#[derive(Debug)]
+pub struct PromiseThunk {
+    /// Expression to evaluate when the promise is forced
+    body: Arc<Expression>,
+    /// Environment captured when the promise was created
+    env: Environment,
+    /// Whether the body yields a value or another promise
+    kind: PromiseKind,
+}
+
+impl PromiseThunk {
+    /// Get the body expression
+    pub fn body(&self) -> &Arc<Expression> {
+        &self.body
+    }
+
+    /// Get the captured environment
+    pub fn env(&self) -> &Environment {
+        &self.env
+    }
+
+    /// Get the kind of promise this thunk belongs to
+    pub fn kind(&self) -> PromiseKind {
+        self.kind
+    }
+}
+
+/// Forcing state of a promise
+#[derive(Debug)]
+enum PromiseState {
+    /// Not yet forced
+    Delayed(Arc<PromiseThunk>),
+    /// Currently being forced by the given evaluation, with the number of
+    /// nested forces of the promise from its own body
+    Forcing(EvaluationId, usize, Arc<PromiseThunk>),
+    /// Value is available in the `OnceLock`
+    Done,
+}
+
+/// Result of claiming a promise for forcing
+#[derive(Debug)]
+pub enum ForceStep {
+    /// The promise already has a value
+    Ready(Value),
+    /// The caller now owns the promise and must evaluate this thunk, then
+    /// call [`Promise::resolve`] or [`Promise::abandon`]
+    Evaluate(Arc<PromiseThunk>),
+    /// Another evaluation is forcing the promise: the caller should claim
+    /// it again once that has finished (see [`Promise::wait`])
+    Busy,
+}
+
+/// Memoising promise
+///
+/// Promises are compared by identity: two promises are equal only if they
+/// are the same Arc instance.
+#[derive(Debug)]
+pub struct Promise {
+    /// Memoised value, written exactly once
+    value: OnceLock<Value>,
+    /// Forcing state machine
+    state: Mutex<PromiseState>,
+    /// Signalled when a promise being forced is resolved or abandoned
+    forced: Condvar,
+}
+
+impl Promise {
+    /// Create a promise that has not been forced yet
+    ///
+    /// The environment should be a closure environment (see
+    /// `Environment::flatten`) so that the promise owns its bindings.
+    pub fn delayed(body: Arc<Expression>, env: Environment, kind: PromiseKind) -> Arc<Self> {
+        let thunk = Arc::new(PromiseThunk { body, env, kind });
+        Arc::new(Promise {
+            value: OnceLock::new(),
+            state: Mutex::new(PromiseState::Delayed(thunk)),
+            forced: Condvar::new(),
+        })
+    }
+
+    /// Create a promise that is already forced to the given value
+    pub fn ready(value: Value) -> Arc<Self> {
+        Arc::new(Promise {
+            value: OnceLock::from(value),
+            state: Mutex::new(PromiseState::Done),
+            forced: Condvar::new(),
+        })
+    }
+
+    /// Get the memoised value, if the promise has been forced
+    pub fn value(&self) -> Option<&Value> {
+        self.value.get()
+    }
+
+    /// Check if the promise has been forced
+    pub fn is_forced(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Claim the promise for forcing by `evaluation`
+    ///
+    /// Returns the value if the promise is already forced. Otherwise marks
+    /// the promise as being forced by `evaluation` and returns its thunk,
+    /// including when `evaluation` is already forcing it. Returns
+    /// [`ForceStep::Busy`] without waiting if another evaluation is forcing
+    /// the promise.
+    pub fn claim(&self, evaluation: EvaluationId) -> ForceStep {
+        let mut state = self.lock_state();
+        match &*state {
+            PromiseState::Done => {
+                let value = self.value.get().cloned().unwrap_or(Value::Nil);
+                ForceStep::Ready(value)
+            }
+            PromiseState::Delayed(thunk) => {
+                let thunk = Arc::clone(thunk);
+                *state = PromiseState::Forcing(evaluation, 0, Arc::clone(&thunk));
+                ForceStep::Evaluate(thunk)
+            }
+            PromiseState::Forcing(owner, nested, thunk) if *owner == evaluation => {
+                let thunk = Arc::clone(thunk);
+                *state = PromiseState::Forcing(evaluation, nested + 1, Arc::clone(&thunk));
+                ForceStep::Evaluate(thunk)
+            }
+            PromiseState::Forcing(_, _, _) => ForceStep::Busy,
+        }
+    }
+
+    /// Block the thread until no evaluation other than `evaluation` is
+    /// forcing the promise
+    ///
+    /// Only for callers that cannot yield: an evaluation running as a
+    /// future should yield to its executor instead, as the evaluation it
+    /// waits for may need the same thread to finish.
+    pub fn wait(&self, evaluation: EvaluationId) {
+        let mut state = self.lock_state();
+        while matches!(&*state, PromiseState::Forcing(owner, _, _) if *owner != evaluation) {
+            state = self
+                .forced
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Store the promise's value and wake any waiting threads
+    ///
+    /// Returns the promise's value, which is an earlier one if a nested
+    /// force has already resolved the promise: the first value wins. Must
+    /// only be called by the evaluation that claimed the promise.
+    pub fn resolve(&self, value: Value) -> Value {
+        let mut state = self.lock_state();
+        let value = self.value.get_or_init(|| value).clone();
+        *state = PromiseState::Done;
+        self.forced.notify_all();
+        value
+    }
+
+    /// Give up forcing after an error so the promise can be forced again
+    ///
+    /// Must only be called by the evaluation that claimed the promise.
+    pub fn abandon(&self) {
+        let mut state = self.lock_state();
+        match &*state {
+            PromiseState::Forcing(owner, nested, thunk) if *nested > 0 => {
+                *state = PromiseState::Forcing(*owner, nested - 1, Arc::clone(thunk));
+            }
+            PromiseState::Forcing(_, _, thunk) => {
+                *state = PromiseState::Delayed(Arc::clone(thunk));
+                self.forced.notify_all();
+            }
+            _ => {}
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, PromiseState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl PartialEq for Promise {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl std::fmt::Display for Promise {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_forced() {
+            write!(f, "#<promise:forced>")
+        } else {
+            write!(f, "#<promise>")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delayed_number(n: f64) -> Arc<Promise> {
+        Promise::delayed(
+            Expression::arc_atom(Value::number(n)),
+            Environment::new(),
+            PromiseKind::Delay,
+        )
+    }
+
+    #[test]
+    fn test_ready_promise() {
+        let promise = Promise::ready(Value::number(42.0));
+        assert!(promise.is_forced());
+        assert_eq!(promise.value(), Some(&Value::number(42.0)));
+        assert!(matches!(
+            promise.claim(EvaluationId::current()),
+            ForceStep::Ready(value) if value == Value::number(42.0)
+        ));
+        assert_eq!(format!("{promise}"), "#<promise:forced>");
+    }
+
+    #[test]
+    fn test_delayed_promise_claim_and_resolve() {
+        let evaluation = EvaluationId::current();
+        let promise = delayed_number(1.0);
+        assert!(!promise.is_forced());
+        assert_eq!(format!("{promise}"), "#<promise>");
+
+        match promise.claim(evaluation) {
+            ForceStep::Evaluate(thunk) => {
+                assert_eq!(thunk.kind(), PromiseKind::Delay);
+                assert_eq!(thunk.body().as_ref(), &Expression::atom(Value::number(1.0)));
+            }
+            other => panic!("Expected thunk, got {other:?}"),
+        }
+
+        promise.resolve(Value::number(1.0));
+        assert_eq!(promise.value(), Some(&Value::number(1.0)));
+        assert!(matches!(promise.claim(evaluation), ForceStep::Ready(_)));
+    }
+
+    #[test]
+    fn test_reentrant_claim_keeps_first_value() {
+        let evaluation = EvaluationId::current();
+        let promise = delayed_number(1.0);
+        assert!(matches!(promise.claim(evaluation), ForceStep::Evaluate(_)));
+
+        // A nested force from the body evaluates it again, and a failed
+        // nested force leaves the outer one in progress
+        assert!(matches!(promise.claim(evaluation), ForceStep::Evaluate(_)));
+        promise.abandon();
+        assert!(matches!(promise.claim(evaluation), ForceStep::Evaluate(_)));
+        assert_eq!(promise.resolve(Value::number(2.0)), Value::number(2.0));
+
+        // The outer evaluation finishes last, and its value is discarded
+        assert_eq!(promise.resolve(Value::number(3.0)), Value::number(2.0));
+        assert_eq!(promise.value(), Some(&Value::number(2.0)));
+    }
+
+    #[test]
+    fn test_abandoned_promise_can_be_reclaimed() {
+        let evaluation = EvaluationId::current();
+        let promise = delayed_number(1.0);
+        assert!(matches!(promise.claim(evaluation), ForceStep::Evaluate(_)));
+        promise.abandon();
+        assert!(!promise.is_forced());
+        assert!(matches!(promise.claim(evaluation), ForceStep::Evaluate(_)));
+    }
+
+    #[test]
+    fn test_claim_by_another_evaluation_is_busy() {
+        let owner = EvaluationId::current();
+        let other = EvaluationId::current();
+        assert_ne!(owner, other);
+
+        let promise = delayed_number(1.0);
+        assert!(matches!(promise.claim(owner), ForceStep::Evaluate(_)));
+        // Evaluations on the same thread do not share the promise, unless
+        // one is nested in the other
+        assert!(matches!(promise.claim(other), ForceStep::Busy));
+        owner.run(|| {
+            assert_eq!(EvaluationId::current(), owner);
+            assert!(matches!(
+                promise.claim(EvaluationId::current()),
+                ForceStep::Evaluate(_)
+            ));
+        });
+        assert_ne!(EvaluationId::current(), owner);
+
+        promise.resolve(Value::number(1.0));
+        assert!(matches!(promise.claim(other), ForceStep::Ready(_)));
+    }
+
+    #[test]
+    fn test_concurrent_claim_waits_for_value() {
+        let promise = delayed_number(7.0);
+        assert!(matches!(
+            promise.claim(EvaluationId::current()),
+            ForceStep::Evaluate(_)
+        ));
+
+        let waiter = {
+            let promise = Arc::clone(&promise);
+            std::thread::spawn(move || {
+                let evaluation = EvaluationId::current();
+                promise.wait(evaluation);
+                match promise.claim(evaluation) {
+                    ForceStep::Ready(value) => value,
+                    _ => panic!("Promise should not be evaluated twice"),
+                }
+            })
+        };
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        promise.resolve(Value::number(7.0));
+        assert_eq!(waiter.join().unwrap(), Value::number(7.0));
+    }
+
+    #[test]
+    fn test_promise_identity_equality() {
+        let promise = delayed_number(1.0);
+        let same = Arc::clone(&promise);
+        let other = delayed_number(1.0);
+        assert_eq!(*promise, *same);
+        assert_ne!(*promise, *other);
+    }
+
+    #[test]
+    fn test_promise_thread_safety() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Promise>();
+    }
+}
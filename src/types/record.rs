@@ -0,0 +1,460 @@
+//! Record types for Scheme
+//!
+//! Implements the runtime representation behind R7RS `define-record-type`:
+//! record type descriptors, immutable record instances, and the generated
+//! constructor, predicate, accessor and functional-update procedures.
+
+use crate::error::{Error, Result};
+use crate::types::{Symbol, Value};
+use std::sync::Arc;
+
+/// Record type descriptor
+///
+/// Created once per `define-record-type` form and shared via Arc by every
+/// instance and generated procedure. Two record types are only the same type
+/// if they are the same Arc instance, so redefining a record type creates a
+/// new, incompatible type.
+#[derive(Debug)]
+pub struct RecordType {
+    /// Type name as written in the definition (e.g. `<point>` or `point`)
+    name: Symbol,
+    /// Field names in declaration order
+    fields: Vec<Symbol>,
+}
+
+impl RecordType {
+    /// Create a new record type descriptor wrapped in Arc
+    pub fn new(name: Symbol, fields: Vec<Symbol>) -> Arc<Self> {
+        Arc::new(RecordType { name, fields })
+    }
+
+    /// Get the type name as written in the definition
+    pub fn name(&self) -> &Symbol {
+        &self.name
+    }
+
+    /// Get the type name without the conventional surrounding angle brackets
+    ///
+    /// `<point>` becomes `point`; names without brackets are returned unchanged.
+    pub fn display_name(&self) -> &str {
+        let name = self.name.as_str();
+        name.strip_prefix('<')
+            .and_then(|inner| inner.strip_suffix('>'))
+            .filter(|inner| !inner.is_empty())
+            .unwrap_or(name)
+    }
+
+    /// Get the field names in declaration order
+    pub fn fields(&self) -> &[Symbol] {
+        &self.fields
+    }
+
+    /// Get the index of a field by name
+    pub fn field_index(&self, field: &Symbol) -> Option<usize> {
+        self.fields.iter().position(|candidate| candidate == field)
+    }
+}
+
+/// Immutable record instance
+///
+/// Field values are stored in an `Arc<[Value]>` so that cloning a record is
+/// O(1) and instances can be shared freely across fibers. Records are never
+/// mutated; a functional update produces a new record.
+#[derive(Debug, Clone)]
+pub struct Record {
+    record_type: Arc<RecordType>,
+    values: Arc<[Value]>,
+}
+
+impl Record {
+    /// Create a new record instance
+    ///
+    /// Returns an error if the number of values does not match the number of
+    /// fields declared by the record type.
+    pub fn new(record_type: Arc<RecordType>, values: Vec<Value>) -> Result<Self> {
+        if values.len() != record_type.fields().len() {
+            return Err(Error::arity_error(
+                record_type.display_name(),
+                record_type.fields().len(),
+                values.len(),
+            ));
+        }
+
+        Ok(Record {
+            record_type,
+            values: values.into(),
+        })
+    }
+
+    /// Get the record type descriptor
+    pub fn record_type(&self) -> &Arc<RecordType> {
+        &self.record_type
+    }
+
+    /// Check if this record is an instance of the given record type
+    pub fn is_instance_of(&self, record_type: &Arc<RecordType>) -> bool {
+        Arc::ptr_eq(&self.record_type, record_type)
+    }
+
+    /// Get a field value by index
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+
+    /// Get a field value by name
+    pub fn field(&self, field: &Symbol) -> Option<&Value> {
+        self.record_type
+            .field_index(field)
+            .and_then(|index| self.get(index))
+    }
+
+    /// Get all field values in declaration order
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Create a copy of this record with one field replaced
+    ///
+    /// This is the functional-update counterpart of an R7RS field modifier:
+    /// the original record is left untouched.
+    pub fn with_field(&self, index: usize, value: Value) -> Result<Self> {
+        if index >= self.values.len() {
+            return Err(Error::runtime_error(&format!(
+                "{}: field index {} out of range",
+                self.record_type.display_name(),
+                index
+            )));
+        }
+
+        let mut values = self.values.to_vec();
+        values[index] = value;
+        Ok(Record {
+            record_type: Arc::clone(&self.record_type),
+            values: values.into(),
+        })
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        // Records are equal if they share the same type descriptor and all
+        // field values are equal. Structural comparison is safe because
+        // records are immutable.
+        Arc::ptr_eq(&self.record_type, &other.record_type) && self.values == other.values
+    }
+}
+
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#<{}", self.record_type.display_name())?;
+        for (field, value) in self.record_type.fields().iter().zip(self.values.iter()) {
+            write!(f, " {field}: {value}")?;
+        }
+        write!(f, ">")
+    }
+}
+
+/// Kind of procedure generated by `define-record-type`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordProcedureKind {
+    /// Constructor taking the listed fields (by index) as arguments
+    Constructor(Vec<usize>),
+    /// Type predicate
+    Predicate,
+    /// Field accessor for the field at the given index
+    Accessor(usize),
+    /// Functional updater returning a copy with the field at the given index replaced
+    Updater(usize),
+}
+
+/// Procedure generated by `define-record-type`
+///
+/// Holds the record type it operates on together with the name it was bound
+/// to, so that error messages and printed representations match the
+/// definition.
+#[derive(Debug, Clone)]
+pub struct RecordProcedure {
+    name: Symbol,
+    record_type: Arc<RecordType>,
+    kind: RecordProcedureKind,
+}
+
+impl RecordProcedure {
+    /// Create a new record procedure
+    pub fn new(name: Symbol, record_type: Arc<RecordType>, kind: RecordProcedureKind) -> Self {
+        RecordProcedure {
+            name,
+            record_type,
+            kind,
+        }
+    }
+
+    /// Get the name this procedure was defined with
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Get the record type this procedure operates on
+    pub fn record_type(&self) -> &Arc<RecordType> {
+        &self.record_type
+    }
+
+    /// Get the kind of this record procedure
+    pub fn kind(&self) -> &RecordProcedureKind {
+        &self.kind
+    }
+
+    /// Get the number of arguments this procedure expects
+    pub fn arity(&self) -> usize {
+        match &self.kind {
+            RecordProcedureKind::Constructor(indices) => indices.len(),
+            RecordProcedureKind::Predicate | RecordProcedureKind::Accessor(_) => 1,
+            RecordProcedureKind::Updater(_) => 2,
+        }
+    }
+
+    /// Apply this procedure to already-evaluated arguments
+    pub fn call(&self, args: &[Value]) -> Result<Value> {
+        if args.len() != self.arity() {
+            return Err(Error::arity_error(self.name(), self.arity(), args.len()));
+        }
+
+        match &self.kind {
+            RecordProcedureKind::Constructor(indices) => {
+                // Fields not initialised by the constructor start out as nil
+                let mut values = vec![Value::Nil; self.record_type.fields().len()];
+                for (&index, arg) in indices.iter().zip(args.iter()) {
+                    values[index] = arg.clone();
+                }
+                Ok(Value::Record(Record::new(
+                    Arc::clone(&self.record_type),
+                    values,
+                )?))
+            }
+            RecordProcedureKind::Predicate => Ok(Value::boolean(matches!(
+                &args[0],
+                Value::Record(record) if record.is_instance_of(&self.record_type)
+            ))),
+            RecordProcedureKind::Accessor(index) => {
+                let record = self.expect_record(&args[0])?;
+                Ok(record.get(*index).cloned().unwrap_or(Value::Nil))
+            }
+            RecordProcedureKind::Updater(index) => {
+                let record = self.expect_record(&args[0])?;
+                Ok(Value::Record(record.with_field(*index, args[1].clone())?))
+            }
+        }
+    }
+
+    /// Check that a value is an instance of this procedure's record type
+    fn expect_record<'v>(&self, value: &'v Value) -> Result<&'v Record> {
+        match value {
+            Value::Record(record) if record.is_instance_of(&self.record_type) => Ok(record),
+            other => Err(Error::type_error(
+                self.name(),
+                self.record_type.display_name(),
+                &other.type_description(),
+                Some(1),
+            )),
+        }
+    }
+}
+
+impl PartialEq for RecordProcedure {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.kind == other.kind
+            && Arc::ptr_eq(&self.record_type, &other.record_type)
+    }
+}
+
+impl std::fmt::Display for RecordProcedure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            RecordProcedureKind::Constructor(_) => "record-constructor",
+            RecordProcedureKind::Predicate => "record-predicate",
+            RecordProcedureKind::Accessor(_) => "record-accessor",
+            RecordProcedureKind::Updater(_) => "record-updater",
+        };
+        write!(f, "#<{kind}:{}>", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_type() -> Arc<RecordType> {
+        RecordType::new(
+            Symbol::new("<point>"),
+            vec![Symbol::new("x"), Symbol::new("y")],
+        )
+    }
+
+    #[test]
+    fn test_record_type_creation() {
+        let point = point_type();
+        assert_eq!(point.name().as_str(), "<point>");
+        assert_eq!(point.display_name(), "point");
+        assert_eq!(point.fields().len(), 2);
+        assert_eq!(point.field_index(&Symbol::new("y")), Some(1));
+        assert_eq!(point.field_index(&Symbol::new("z")), None);
+
+        // Names without angle brackets are kept as-is
+        let plain = RecordType::new(Symbol::new("node"), vec![]);
+        assert_eq!(plain.display_name(), "node");
+    }
+
+    #[test]
+    fn test_record_creation_and_access() {
+        let point = point_type();
+        let record = Record::new(
+            Arc::clone(&point),
+            vec![Value::number(1.0), Value::number(2.0)],
+        )
+        .unwrap();
+
+        assert!(record.is_instance_of(&point));
+        assert_eq!(record.get(0), Some(&Value::number(1.0)));
+        assert_eq!(record.field(&Symbol::new("y")), Some(&Value::number(2.0)));
+        assert_eq!(record.field(&Symbol::new("z")), None);
+
+        // Wrong number of values is rejected
+        assert!(Record::new(point, vec![Value::number(1.0)]).is_err());
+    }
+
+    #[test]
+    fn test_record_functional_update() {
+        let point = point_type();
+        let original = Record::new(point, vec![Value::number(1.0), Value::number(2.0)]).unwrap();
+        let updated = original.with_field(0, Value::number(10.0)).unwrap();
+
+        // The original record is unchanged
+        assert_eq!(original.get(0), Some(&Value::number(1.0)));
+        assert_eq!(updated.get(0), Some(&Value::number(10.0)));
+        assert_eq!(updated.get(1), Some(&Value::number(2.0)));
+        assert!(original.with_field(5, Value::Nil).is_err());
+    }
+
+    #[test]
+    fn test_record_equality() {
+        let point = point_type();
+        let a = Record::new(
+            Arc::clone(&point),
+            vec![Value::number(1.0), Value::number(2.0)],
+        )
+        .unwrap();
+        let b = Record::new(
+            Arc::clone(&point),
+            vec![Value::number(1.0), Value::number(2.0)],
+        )
+        .unwrap();
+        let c = Record::new(point, vec![Value::number(3.0), Value::number(2.0)]).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        // Same shape but a different type descriptor is not equal
+        let other =
+            Record::new(point_type(), vec![Value::number(1.0), Value::number(2.0)]).unwrap();
+        assert_ne!(a, other);
+    }
+
+    #[test]
+    fn test_record_display() {
+        let record =
+            Record::new(point_type(), vec![Value::number(1.0), Value::number(2.0)]).unwrap();
+        assert_eq!(format!("{record}"), "#<point x: 1 y: 2>");
+
+        let empty = Record::new(RecordType::new(Symbol::new("<unit>"), vec![]), vec![]).unwrap();
+        assert_eq!(format!("{empty}"), "#<unit>");
+    }
+
+    #[test]
+    fn test_record_procedures() {
+        let point = point_type();
+        let make = RecordProcedure::new(
+            Symbol::new("make-point"),
+            Arc::clone(&point),
+            RecordProcedureKind::Constructor(vec![0, 1]),
+        );
+        let is_point = RecordProcedure::new(
+            Symbol::new("point?"),
+            Arc::clone(&point),
+            RecordProcedureKind::Predicate,
+        );
+        let point_x = RecordProcedure::new(
+            Symbol::new("point-x"),
+            Arc::clone(&point),
+            RecordProcedureKind::Accessor(0),
+        );
+        let with_x = RecordProcedure::new(
+            Symbol::new("point-with-x"),
+            Arc::clone(&point),
+            RecordProcedureKind::Updater(0),
+        );
+
+        let p = make
+            .call(&[Value::number(1.0), Value::number(2.0)])
+            .unwrap();
+        assert_eq!(
+            is_point.call(std::slice::from_ref(&p)).unwrap(),
+            Value::boolean(true)
+        );
+        assert_eq!(
+            is_point.call(&[Value::number(1.0)]).unwrap(),
+            Value::boolean(false)
+        );
+        assert_eq!(
+            point_x.call(std::slice::from_ref(&p)).unwrap(),
+            Value::number(1.0)
+        );
+
+        let moved = with_x.call(&[p.clone(), Value::number(5.0)]).unwrap();
+        assert_eq!(point_x.call(&[moved]).unwrap(), Value::number(5.0));
+        assert_eq!(point_x.call(&[p]).unwrap(), Value::number(1.0));
+
+        // Arity and type errors
+        assert!(make.call(&[Value::number(1.0)]).is_err());
+        let err = point_x.call(&[Value::number(1.0)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "point-x: expected point for argument 1, got number"
+        );
+
+        assert_eq!(format!("{make}"), "#<record-constructor:make-point>");
+        assert_eq!(format!("{point_x}"), "#<record-accessor:point-x>");
+    }
+
+    #[test]
+    fn test_record_constructor_partial_fields() {
+        let point = point_type();
+        let make = RecordProcedure::new(
+            Symbol::new("make-origin-x"),
+            point,
+            RecordProcedureKind::Constructor(vec![0]),
+        );
+        let record = make.call(&[Value::number(7.0)]).unwrap();
+        assert_eq!(format!("{record}"), "#<point x: 7 y: ()>");
+    }
+
+    #[test]
+    fn test_record_thread_safety() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<RecordType>();
+        assert_send_sync::<Record>();
+        assert_send_sync::<RecordProcedure>();
+
+        let record =
+            Record::new(point_type(), vec![Value::number(1.0), Value::number(2.0)]).unwrap();
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = record.clone();
+                std::thread::spawn(move || format!("{shared}"))
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), "#<point x: 1 y: 2>");
+        }
+    }
+}
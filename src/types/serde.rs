@@ -0,0 +1,554 @@
+//! Serde support for Scheme values, behind the `serde` feature
+//!
+//! [`Value`] implements `Serialize` and `Deserialize` with this mapping:
+//!
+//! | Scheme | Serde data model |
+//! |--------|------------------|
+//! | number | `i64` if it is an integer, otherwise `f64` |
+//! | boolean | `bool` |
+//! | string, symbol | string; deserializing always gives a string |
+//! | list | sequence |
+//! | nil | unit (`null` in JSON) |
+//! | record | map from field names to values; not deserializable |
+//! | procedure, promise | not serializable |
+//!
+//! Maps deserialize to association lists of `(key value)` lists with string
+//! keys, the same representation [`IntoValue`](super::IntoValue) gives a
+//! `HashMap`.
+//!
+//! [`from_value`] deserializes Rust types straight from Scheme data, such as
+//! configuration written in Scheme:
+//!
+//! - structs and maps from association lists, with symbol or string keys
+//! - `Option` from `#f` or nil for `None`, or the value itself for `Some`
+//! - enums from a symbol or string for unit variants, or a list starting with
+//!   the variant name and followed by its contents, e.g. `(circle 2.5)`
+//!
+//! ```
+//! use serde::Deserialize;
+//! use twine_scheme::Interpreter;
+//! use twine_scheme::types::serde::from_value;
+//!
+//! #[derive(Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let mut interpreter = Interpreter::new();
+//! let config = interpreter.eval_str("'((host \"localhost\") (port 8080))").unwrap();
+//! let server: Server = from_value(&config).unwrap();
+//! assert_eq!(server.port, 8080);
+//! ```
+
+use crate::error::Error;
+use crate::types::{ArcString, Value};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use serde::ser::{SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Deserialize a Rust value from a Scheme value
+pub fn from_value<'de, T: Deserialize<'de>>(value: &'de Value) -> crate::Result<T> {
+    T::deserialize(value)
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::runtime_error(&message.to_string())
+    }
+}
+
+/// The value of an integral number, if it fits in an `i64`
+fn integer(n: f64) -> Option<i64> {
+    (n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64).then_some(n as i64)
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Number(n) => match integer(n.value()) {
+                Some(n) => serializer.serialize_i64(n),
+                None => serializer.serialize_f64(n.value()),
+            },
+            Value::Boolean(b) => serializer.serialize_bool(*b),
+            Value::String(s) => serializer.serialize_str(s.as_str()),
+            Value::Symbol(s) => serializer.serialize_str(s.as_str()),
+            Value::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for element in list.iter() {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            Value::Record(record) => {
+                let fields = record.record_type().fields();
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (field, value) in fields.iter().zip(record.values()) {
+                    map.serialize_entry(field.as_str(), value)?;
+                }
+                map.end()
+            }
+            Value::Nil => serializer.serialize_unit(),
+            Value::Procedure(_) | Value::Promise(_) => Err(serde::ser::Error::custom(format!(
+                "cannot serialize a {}",
+                self.type_name()
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Scheme value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::boolean(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::number(n as f64))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::number(n as f64))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::number(n))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::string(s))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(ArcString::from_string(s)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(element) = seq.next_element()? {
+            elements.push(element);
+        }
+        Ok(Value::list(elements))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, value)) = map.next_entry::<String, Value>()? {
+            entries.push(Value::list(vec![
+                Value::String(ArcString::from_string(key)),
+                value,
+            ]));
+        }
+        Ok(Value::list(entries))
+    }
+}
+
+/// Describe a value for deserialization errors
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value {
+        Value::Number(n) => de::Unexpected::Float(n.value()),
+        Value::Boolean(b) => de::Unexpected::Bool(*b),
+        Value::String(s) => de::Unexpected::Str(s.as_str()),
+        Value::Symbol(s) => de::Unexpected::Other(s.as_str()),
+        Value::List(_) => de::Unexpected::Seq,
+        Value::Nil => de::Unexpected::Unit,
+        other => de::Unexpected::Other(other.type_name()),
+    }
+}
+
+/// The elements of a list, treating nil as the empty list
+fn elements(value: &Value) -> Option<&[Value]> {
+    match value {
+        Value::List(list) => Some(list.as_slice()),
+        Value::Nil => Some(&[]),
+        _ => None,
+    }
+}
+
+/// The name of a string or symbol
+fn name(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s.as_str()),
+        Value::Symbol(s) => Some(s.as_str()),
+        _ => None,
+    }
+}
+
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match self {
+            Value::Number(n) => match integer(n.value()) {
+                Some(n) => visitor.visit_i64(n),
+                None => visitor.visit_f64(n.value()),
+            },
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::String(s) => visitor.visit_borrowed_str(s.as_str()),
+            Value::Symbol(s) => visitor.visit_borrowed_str(s.as_str()),
+            Value::List(list) => visitor.visit_seq(Elements(list.iter())),
+            Value::Nil => visitor.visit_unit(),
+            other => Err(de::Error::invalid_type(unexpected(other), &visitor)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match self {
+            Value::Nil | Value::Boolean(false) => visitor.visit_none(),
+            other => visitor.visit_some(other),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match elements(self) {
+            Some([]) => visitor.visit_unit(),
+            _ => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match elements(self) {
+            Some(elements) => visitor.visit_seq(Elements(elements.iter())),
+            None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match elements(self) {
+            Some(entries) => visitor.visit_map(Entries {
+                entries: entries.iter(),
+                value: None,
+            }),
+            None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        let (variant, contents) = match elements(self) {
+            Some([variant, contents @ ..]) => (variant, contents),
+            _ => (self, &[][..]),
+        };
+        match name(variant) {
+            Some(variant) => visitor.visit_enum(Variant { variant, contents }),
+            None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> crate::Result<V::Value> {
+        match name(self) {
+            Some(name) => visitor.visit_borrowed_str(name),
+            None => Err(de::Error::invalid_type(unexpected(self), &visitor)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf ignored_any
+    }
+}
+
+/// The elements of a list, for deserializing sequences
+struct Elements<'de>(std::slice::Iter<'de, Value>);
+
+impl<'de> SeqAccess<'de> for Elements<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> crate::Result<Option<T::Value>> {
+        self.0
+            .next()
+            .map(|value| seed.deserialize(value))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+/// The entries of an association list, for deserializing maps and structs
+struct Entries<'de> {
+    entries: std::slice::Iter<'de, Value>,
+    /// The value of the entry whose key was just deserialized
+    value: Option<&'de Value>,
+}
+
+impl<'de> MapAccess<'de> for Entries<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> crate::Result<Option<K::Value>> {
+        let Some(entry) = self.entries.next() else {
+            return Ok(None);
+        };
+        match elements(entry) {
+            Some([key, value]) => {
+                self.value = Some(value);
+                match name(key) {
+                    Some(name) => seed.deserialize(name.into_deserializer()).map(Some),
+                    None => seed.deserialize(key).map(Some),
+                }
+            }
+            _ => Err(de::Error::invalid_value(
+                unexpected(entry),
+                &"a (key value) list",
+            )),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> crate::Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| <Error as de::Error>::custom("value requested before key"))?;
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// An enum variant: its name, and the rest of the list it started
+struct Variant<'de> {
+    variant: &'de str,
+    contents: &'de [Value],
+}
+
+impl<'de> EnumAccess<'de> for Variant<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> crate::Result<(V::Value, Self)> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> crate::Result<()> {
+        match self.contents {
+            [] => Ok(()),
+            _ => Err(de::Error::invalid_length(
+                self.contents.len(),
+                &"no contents",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> crate::Result<T::Value> {
+        match self.contents {
+            [contents] => seed.deserialize(contents),
+            _ => Err(de::Error::invalid_length(self.contents.len(), &"one value")),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> crate::Result<V::Value> {
+        visitor.visit_seq(Elements(self.contents.iter()))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> crate::Result<V::Value> {
+        visitor.visit_map(Entries {
+            entries: self.contents.iter(),
+            value: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use std::collections::HashMap;
+
+    /// The datum written in `source`
+    fn datum(source: &str) -> Value {
+        let mut parser = Parser::new(format!("'{source}")).unwrap();
+        let expr = parser.parse_expression().unwrap().expr;
+        let mut env = crate::runtime::Environment::new();
+        crate::runtime::eval(expr, &mut env).unwrap()
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        name: String,
+        retries: u32,
+        ratio: f64,
+        tags: Vec<String>,
+        timeout: Option<u64>,
+        mode: Mode,
+        shape: Shape,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    enum Mode {
+        FastStart,
+        Safe,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        #[serde(rename = "circle")]
+        Circle(f64),
+        #[serde(rename = "rect")]
+        Rect { width: f64, height: f64 },
+    }
+
+    #[test]
+    fn test_from_value_struct() {
+        let value = datum(
+            "((name \"api\") (retries 3) (ratio 0.5) (tags (\"a\" \"b\"))
+              (timeout #f) (mode fast-start) (shape (rect (width 2) (height 3))))",
+        );
+        let config: Config = from_value(&value).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "api".to_string(),
+                retries: 3,
+                ratio: 0.5,
+                tags: vec!["a".to_string(), "b".to_string()],
+                timeout: None,
+                mode: Mode::FastStart,
+                shape: Shape::Rect {
+                    width: 2.0,
+                    height: 3.0
+                },
+            }
+        );
+
+        let shape: Shape = from_value(&datum("(circle 1.5)")).unwrap();
+        assert_eq!(shape, Shape::Circle(1.5));
+        let mode: Mode = from_value(&datum("safe")).unwrap();
+        assert_eq!(mode, Mode::Safe);
+        let map: HashMap<String, i32> = from_value(&datum("((a 1) (\"b\" 2))")).unwrap();
+        assert_eq!(
+            map,
+            HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_from_value_errors() {
+        let error = from_value::<u32>(&datum("\"three\"")).unwrap_err();
+        assert!(error.to_string().contains("invalid type: string \"three\""));
+
+        let error = from_value::<u8>(&datum("1.5")).unwrap_err();
+        assert!(error.to_string().contains("invalid type: floating point"));
+
+        let error = from_value::<Config>(&datum("((name \"api\"))")).unwrap_err();
+        assert!(error.to_string().contains("missing field `retries`"));
+    }
+
+    #[test]
+    fn test_serialize_value() {
+        let value = datum("(1 2.5 #t \"s\" sym ())");
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"[1,2.5,true,"s","sym",[]]"#
+        );
+        assert_eq!(serde_json::to_string(&Value::Nil).unwrap(), "null");
+
+        let procedure = Value::Procedure(crate::types::Procedure::builtin(
+            crate::runtime::builtins::Builtin::Car,
+        ));
+        assert!(serde_json::to_string(&procedure).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_value() {
+        let value: Value = serde_json::from_str(r#"{"a": [1, 2.5, null], "b": true}"#).unwrap();
+        assert_eq!(value.to_string(), r#"(("a" (1 2.5 ())) ("b" #t))"#);
+
+        // Values survive a round trip through the deserializer
+        let original = datum("(1 (\"x\" #f) 2.5)");
+        assert_eq!(from_value::<Value>(&original).unwrap(), original);
+    }
+}
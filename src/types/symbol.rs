@@ -1,59 +1,167 @@
 //! Symbol type implementation for Scheme
 //!
-//! Wraps SmolStr for efficient storage and sharing of symbol names.
-//! Most symbols (≤23 bytes) are stack-allocated with O(1) clone operations.
+//! Symbols are interned in a process-wide table, so every symbol with a given
+//! name is the same object. Comparing and hashing symbols only looks at that
+//! object's address, which makes both O(1) however long the name is, and
+//! environment lookups never compare strings.
+//!
+//! Uninterned symbols, created by [`Symbol::uninterned`] and
+//! [`Symbol::generate`], are never added to the table. They are distinct from
+//! every other symbol, including interned ones with the same name, which is
+//! what macros need to introduce identifiers that cannot capture user code.
+//!
+//! Names are stored as `SmolStr`, so most names (≤23 bytes) are kept inline in
+//! the symbol's entry. The table refers to entries weakly: an interned symbol
+//! is removed from it when its last copy is dropped, so programs that make
+//! symbols from data, such as `string->symbol`, do not grow it without bound.
 
 use smol_str::SmolStr;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, PoisonError, RwLock, Weak};
+
+/// The interned symbols, by name
+static TABLE: LazyLock<RwLock<HashMap<SmolStr, Weak<Entry>>>> = LazyLock::new(Default::default);
+
+/// The number of symbols created by [`Symbol::generate`]
+static GENERATED: AtomicU64 = AtomicU64::new(0);
 
 /// Symbol type for Scheme identifiers
 ///
-/// Wraps SmolStr to enable efficient storage and sharing of symbol names.
-/// SmolStr optimizes for short strings by storing them on the stack when
-/// they fit in 23 bytes, making most symbols very efficient to clone.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Symbol(SmolStr);
+/// A shared pointer to the symbol's entry, so cloning is O(1). Two symbols
+/// are equal only if they are the same object: interned symbols are equal
+/// when their names are, and uninterned symbols only to themselves.
+#[derive(Clone)]
+pub struct Symbol(Arc<Entry>);
+
+struct Entry {
+    name: SmolStr,
+    interned: bool,
+}
 
 impl Symbol {
     /// Create a new Symbol from a string slice
     pub fn new(s: &str) -> Self {
-        Symbol(SmolStr::new(s))
+        Self::intern(SmolStr::new(s))
     }
 
     /// Create a new Symbol from an owned String
     pub fn from_string(s: String) -> Self {
-        Symbol(SmolStr::from(s))
+        Self::intern(SmolStr::from(s))
     }
 
     /// Create a new Symbol from a SmolStr for maximum efficiency
     pub fn from_smol_str(s: SmolStr) -> Self {
-        Symbol(s)
+        Self::intern(s)
+    }
+
+    /// Find the interned symbol named `name`, adding it if there is none
+    fn intern(name: SmolStr) -> Self {
+        let found = TABLE
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&name)
+            .and_then(Weak::upgrade);
+        if let Some(entry) = found {
+            return Symbol(entry);
+        }
+
+        // Another thread may have added it, or its last copy may be dropping
+        let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = table.get(&name).and_then(Weak::upgrade) {
+            return Symbol(entry);
+        }
+        let entry = Arc::new(Entry {
+            name: name.clone(),
+            interned: true,
+        });
+        table.insert(name, Arc::downgrade(&entry));
+        Symbol(entry)
+    }
+
+    /// Create a symbol that is not interned, and so is distinct from every
+    /// other symbol
+    pub fn uninterned(name: &str) -> Self {
+        Symbol(Arc::new(Entry {
+            name: SmolStr::new(name),
+            interned: false,
+        }))
+    }
+
+    /// Create an uninterned symbol with a fresh name: `prefix` followed by a
+    /// number that no other generated symbol in this process has used
+    pub fn generate(prefix: &str) -> Self {
+        let count = GENERATED.fetch_add(1, Ordering::Relaxed) + 1;
+        Self::uninterned(&format!("{prefix}{count}"))
+    }
+
+    /// Check if this symbol is in the symbol table
+    pub fn is_interned(&self) -> bool {
+        self.0.interned
     }
 
     /// Get a string slice view of the symbol name
     pub fn as_str(&self) -> &str {
-        self.0.as_str()
+        self.0.name.as_str()
     }
 
     /// Get the length of the symbol name in bytes
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.0.name.len()
     }
 
     /// Check if the symbol name is empty
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.0.name.is_empty()
     }
 
-    /// Check if this symbol is heap-allocated
-    /// Returns true for symbols longer than 23 bytes
+    /// Check if the symbol name is heap-allocated
+    /// Returns true for names longer than 23 bytes
     pub fn is_heap_allocated(&self) -> bool {
-        self.0.is_heap_allocated()
+        self.0.name.is_heap_allocated()
+    }
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        if !self.interned {
+            return;
+        }
+        // The name may have been interned again since, as a new entry
+        let mut table = TABLE.write().unwrap_or_else(PoisonError::into_inner);
+        if table
+            .get(&self.name)
+            .is_some_and(|entry| std::ptr::eq(entry.as_ptr(), self))
+        {
+            table.remove(&self.name);
+        }
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(Arc::as_ptr(&self.0), state);
+    }
+}
+
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Symbol").field(&self.0.name).finish()
     }
 }
 
 impl std::fmt::Display for Symbol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.0.name)
     }
 }
 
@@ -71,13 +179,13 @@ impl From<String> for Symbol {
 
 impl From<SmolStr> for Symbol {
     fn from(s: SmolStr) -> Self {
-        Symbol(s)
+        Symbol::from_smol_str(s)
     }
 }
 
 impl From<Symbol> for SmolStr {
     fn from(s: Symbol) -> Self {
-        s.0
+        s.0.name.clone()
     }
 }
 
@@ -130,6 +238,60 @@ mod tests {
         assert_eq!(s1, s4);
     }
 
+    #[test]
+    fn test_symbol_interning() {
+        // Symbols with the same name are the same object
+        let s1 = Symbol::new("interned");
+        let s2 = Symbol::from_string(String::from("interned"));
+        assert!(Arc::ptr_eq(&s1.0, &s2.0));
+        assert!(s1.is_interned());
+
+        // Interning is shared between threads
+        let s3 = std::thread::spawn(|| Symbol::new("interned"))
+            .join()
+            .unwrap();
+        assert!(Arc::ptr_eq(&s1.0, &s3.0));
+    }
+
+    #[test]
+    fn test_dropped_symbols_leave_the_table() {
+        let interned = |name: &str| TABLE.read().unwrap().contains_key(name);
+
+        let symbol = Symbol::new("test-dropped-symbols-leave-the-table");
+        let copy = symbol.clone();
+        drop(symbol);
+        assert!(interned("test-dropped-symbols-leave-the-table"));
+
+        // The last copy takes the name out of the table
+        drop(copy);
+        assert!(!interned("test-dropped-symbols-leave-the-table"));
+
+        // Interning the name again makes a new symbol, equal to later ones
+        let again = Symbol::new("test-dropped-symbols-leave-the-table");
+        assert!(interned("test-dropped-symbols-leave-the-table"));
+        assert_eq!(again, Symbol::new("test-dropped-symbols-leave-the-table"));
+    }
+
+    #[test]
+    fn test_uninterned_symbols() {
+        let interned = Symbol::new("temp");
+        let uninterned = Symbol::uninterned("temp");
+        assert_eq!(uninterned.as_str(), "temp");
+        assert!(!uninterned.is_interned());
+
+        // Uninterned symbols are only equal to themselves
+        assert_ne!(uninterned, interned);
+        assert_ne!(uninterned, Symbol::uninterned("temp"));
+        assert_eq!(uninterned, uninterned.clone());
+
+        // Generated symbols have fresh names
+        let g1 = Symbol::generate("g");
+        let g2 = Symbol::generate("g");
+        assert!(g1.as_str().starts_with('g'));
+        assert_ne!(g1.as_str(), g2.as_str());
+        assert_ne!(g1, g2);
+    }
+
     #[test]
     fn test_symbol_display() {
         let s = Symbol::new("my-identifier");
@@ -2,8 +2,10 @@
 //!
 //! Implements the main Value enum with construction and extraction methods.
 
-use super::{ArcString, List, Number, Procedure, Symbol};
+use super::{ArcString, List, Number, Procedure, Promise, Record, Symbol};
 use smol_str::SmolStr;
+use std::borrow::Cow;
+use std::sync::Arc;
 
 /// The core value type for all Scheme data
 ///
@@ -43,6 +45,18 @@ pub enum Value {
     /// and user-defined lambda procedures with closures.
     Procedure(Procedure),
 
+    /// Record instances created by `define-record-type` constructors
+    ///
+    /// Records hold an Arc'd type descriptor and immutable field values,
+    /// so they can be shared across fibers without copying.
+    Record(Record),
+
+    /// Promises created by `delay`, `delay-force` and `make-promise`
+    ///
+    /// The memoised value is written at most once, so a promise can be
+    /// forced from several fibers concurrently.
+    Promise(Arc<Promise>),
+
     /// The nil/null value
     ///
     /// Represents both the empty list '() and null/undefined values,
@@ -116,6 +130,16 @@ impl Value {
         Value::Procedure(Procedure::builtin(builtin))
     }
 
+    /// Create a new record value from a Record
+    pub fn record(record: Record) -> Self {
+        Value::Record(record)
+    }
+
+    /// Create a new promise value from a Promise
+    pub fn promise(promise: Arc<Promise>) -> Self {
+        Value::Promise(promise)
+    }
+
     /// Create the nil value
     pub fn nil() -> Self {
         Value::Nil
@@ -156,6 +180,16 @@ impl Value {
         matches!(self, Value::Procedure(_))
     }
 
+    /// Check if this value is a record
+    pub fn is_record(&self) -> bool {
+        matches!(self, Value::Record(_))
+    }
+
+    /// Check if this value is a promise
+    pub fn is_promise(&self) -> bool {
+        matches!(self, Value::Promise(_))
+    }
+
     /// Check if this value is truthy in Scheme semantics
     ///
     /// In Scheme, only #f is false. Everything else, including 0, empty lists,
@@ -220,6 +254,22 @@ impl Value {
         }
     }
 
+    /// Extract the record value if this is a record
+    pub fn as_record(&self) -> Option<&Record> {
+        match self {
+            Value::Record(record) => Some(record),
+            _ => None,
+        }
+    }
+
+    /// Extract the promise value if this is a promise
+    pub fn as_promise(&self) -> Option<&Arc<Promise>> {
+        match self {
+            Value::Promise(promise) => Some(promise),
+            _ => None,
+        }
+    }
+
     /// Get a string representation of the value's type
     pub fn type_name(&self) -> &'static str {
         match self {
@@ -229,9 +279,25 @@ impl Value {
             Value::Symbol(_) => "symbol",
             Value::List(_) => "list",
             Value::Procedure(_) => "procedure",
+            Value::Record(_) => "record",
+            Value::Promise(_) => "promise",
             Value::Nil => "nil",
         }
     }
+
+    /// Describe the value's type for error messages
+    ///
+    /// Like [`type_name`](Self::type_name), except that procedures with a
+    /// defined name are described by it, e.g. `procedure 'square'`.
+    pub fn type_description(&self) -> Cow<'static, str> {
+        match self {
+            Value::Procedure(procedure) => match procedure.defined_name() {
+                Some(name) => Cow::Owned(format!("procedure '{name}'")),
+                None => Cow::Borrowed("procedure"),
+            },
+            other => Cow::Borrowed(other.type_name()),
+        }
+    }
 }
 
 /// Display implementation for Value
@@ -248,12 +314,15 @@ impl std::fmt::Display for Value {
             Value::Symbol(s) => write!(f, "{s}"),
             Value::List(l) => write!(f, "{l}"),
             Value::Procedure(p) => write!(f, "{p}"),
+            Value::Record(r) => write!(f, "{r}"),
+            Value::Promise(p) => write!(f, "{p}"),
             Value::Nil => write!(f, "()"),
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
 
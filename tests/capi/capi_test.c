@@ -0,0 +1,157 @@
+/* Exercises the C API: run by tests/integration_capi.rs */
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "twine.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                                       \
+  do {                                                                         \
+    if (!(condition)) {                                                        \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__,         \
+              #condition);                                                     \
+      failures++;                                                              \
+    }                                                                          \
+  } while (0)
+
+/* Evaluate source, check the status, and compare the output */
+static void check_eval(TwineInterpreter *interpreter, const char *source,
+                       TwineStatus expected_status,
+                       const char *expected_output) {
+  char *output = NULL;
+  TwineStatus status = twine_eval(interpreter, source, &output);
+  if (status != expected_status || output == NULL ||
+      strcmp(output, expected_output) != 0) {
+    fprintf(stderr, "%s: got status %d and \"%s\", expected %d and \"%s\"\n",
+            source, status, output ? output : "(null)", expected_status,
+            expected_output);
+    failures++;
+  }
+  twine_string_free(output);
+}
+
+/* Evaluate source, check that it fails, and compare the start of the
+   diagnostic: its header and location */
+static void check_eval_error(TwineInterpreter *interpreter, const char *source,
+                             const char *expected_start) {
+  char *output = NULL;
+  TwineStatus status = twine_eval(interpreter, source, &output);
+  if (status != TWINE_STATUS_ERROR || output == NULL ||
+      strncmp(output, expected_start, strlen(expected_start)) != 0) {
+    fprintf(stderr,
+            "%s: got status %d and \"%s\", expected %d and \"%s...\"\n",
+            source, status, output ? output : "(null)", TWINE_STATUS_ERROR,
+            expected_start);
+    failures++;
+  }
+  twine_string_free(output);
+}
+
+/* (scale x): multiplies x by the double user_data points to */
+static TwineStatus scale(void *user_data, const TwineValue *const *args,
+                         size_t arg_count, TwineValue **result) {
+  double number;
+  if (arg_count != 1 || !twine_value_as_number(args[0], &number)) {
+    *result = twine_value_string("expected a number");
+    return TWINE_STATUS_ERROR;
+  }
+  *result = twine_value_number(number * *(double *)user_data);
+  return TWINE_STATUS_OK;
+}
+
+/* (greet name ...): joins "hello" and the names, as a string */
+static TwineStatus greet(void *user_data, const TwineValue *const *args,
+                         size_t arg_count, TwineValue **result) {
+  char greeting[256] = "hello";
+  (void)user_data;
+  for (size_t i = 0; i < arg_count; i++) {
+    char *name = twine_value_as_string(args[i]);
+    if (name == NULL) {
+      char *printed = twine_value_print(args[i]);
+      char message[256];
+      snprintf(message, sizeof message, "not a name: %s", printed);
+      twine_string_free(printed);
+      *result = twine_value_string(message);
+      return TWINE_STATUS_ERROR;
+    }
+    strncat(greeting, " ", sizeof greeting - strlen(greeting) - 1);
+    strncat(greeting, name, sizeof greeting - strlen(greeting) - 1);
+    twine_string_free(name);
+  }
+  *result = twine_value_string(greeting);
+  return TWINE_STATUS_OK;
+}
+
+int main(void) {
+  TwineInterpreter *interpreter = twine_interpreter_new();
+  CHECK(interpreter != NULL);
+
+  check_eval(interpreter, "(+ 1 2)", TWINE_STATUS_OK, "3");
+  check_eval(interpreter, "(define (square x) (* x x)) (square 12)",
+             TWINE_STATUS_OK, "144");
+  check_eval(interpreter, "(list \"a\" 'b #t)", TWINE_STATUS_OK,
+             "(\"a\" b #t)");
+  check_eval_error(interpreter, "(car '())",
+                   "error[E0003]: car: cannot take car of empty list\n"
+                   " --> <string>:1:1\n"
+                   "  |\n"
+                   "1 | (car '())\n");
+  check_eval_error(interpreter, "(square",
+                   "error[E0001]: Unexpected end of input, expected ')'\n"
+                   " --> <string>:1:1\n");
+
+  /* Definitions persist between evaluations */
+  check_eval(interpreter, "(square 3)", TWINE_STATUS_OK, "9");
+
+  double factor = 2.5;
+  CHECK(twine_define_native(interpreter, "scale", 1, 1, scale, &factor) ==
+        TWINE_STATUS_OK);
+  check_eval(interpreter, "(scale (square 2))", TWINE_STATUS_OK, "10");
+  check_eval_error(interpreter, "(scale \"x\")",
+                   "error[E0003]: scale: expected a number\n"
+                   " --> <string>:1:1\n");
+
+  CHECK(twine_define_native(interpreter, "greet", 0, -1, greet, NULL) ==
+        TWINE_STATUS_OK);
+  check_eval(interpreter, "(greet \"ada\" \"alan\")", TWINE_STATUS_OK,
+             "\"hello ada alan\"");
+  check_eval(interpreter, "(greet)", TWINE_STATUS_OK, "\"hello\"");
+  check_eval_error(interpreter, "(greet 'x)",
+                   "error[E0003]: greet: not a name: x\n"
+                   " --> <string>:1:1\n");
+
+  /* Invalid arguments */
+  CHECK(twine_eval(NULL, "1", NULL) == TWINE_STATUS_INVALID_ARGUMENT);
+  CHECK(twine_eval(interpreter, NULL, NULL) == TWINE_STATUS_INVALID_ARGUMENT);
+  CHECK(twine_eval(interpreter, "\xff", NULL) ==
+        TWINE_STATUS_INVALID_ARGUMENT);
+  CHECK(twine_define_native(interpreter, "bad", 2, 1, greet, NULL) ==
+        TWINE_STATUS_INVALID_ARGUMENT);
+
+  /* Values owned by the host */
+  TwineValue *value = twine_value_boolean(true);
+  bool boolean = false;
+  double number = 0;
+  CHECK(twine_value_as_boolean(value, &boolean) && boolean);
+  CHECK(!twine_value_as_number(value, &number));
+  CHECK(twine_value_as_string(value) == NULL);
+  twine_value_free(value);
+
+  value = twine_value_nil();
+  char *printed = twine_value_print(value);
+  CHECK(strcmp(printed, "()") == 0);
+  twine_string_free(printed);
+  twine_value_free(value);
+
+  twine_interpreter_free(interpreter);
+
+  if (failures > 0) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return EXIT_FAILURE;
+  }
+  printf("all checks passed\n");
+  return EXIT_SUCCESS;
+}
@@ -1,7 +1,7 @@
 //! Integration tests for advanced language features
 //!
 //! This file contains integration tests for advanced functionality:
-//! - Tail call optimization
+//! - Tail call optimization and deep non-tail recursion
 //! - Multiple lambda body expressions
 //! - Comprehensive evaluation scenarios
 //! - Mixed operations combining multiple language features
@@ -588,3 +588,67 @@ fn test_integration_mixed_operations() {
     let result = eval_source("(process (car '(5 10)) (if (> 3 2) 7 0))", &mut env).unwrap();
     assert_eq!(result.as_number().unwrap(), 17.0); // (5 * 2) + 7 = 17
 }
+
+#[test]
+fn test_integration_deep_non_tail_recursion() {
+    let mut env = Environment::new();
+
+    // Pending additions are kept on the heap, not the Rust stack
+    eval_source(
+        "(define (sum n) (if (= n 0) 0 (+ n (sum (- n 1)))))",
+        &mut env,
+    )
+    .unwrap();
+    let result = eval_source("(sum 100000)", &mut env).unwrap();
+    assert_eq!(result, Value::number(5000050000.0));
+
+    // Deep recursion through let and argument positions
+    eval_source(
+        r#"(define (depth n)
+             (let ((rest (if (= n 0) 0 (depth (- n 1)))))
+               (+ 1 rest)))"#,
+        &mut env,
+    )
+    .unwrap();
+    let result = eval_source("(depth 20000)", &mut env).unwrap();
+    assert_eq!(result, Value::number(20001.0));
+
+    // Errors deep in the recursion still unwind with a backtrace
+    eval_source(
+        "(define (fail n) (if (= n 0) (car n) (+ 1 (fail (- n 1)))))",
+        &mut env,
+    )
+    .unwrap();
+    let error = eval_source("(fail 20000)", &mut env).unwrap_err();
+    assert_eq!(error.to_string(), "car: expected list, got number");
+    assert!(error.backtrace().is_some());
+}
+
+#[test]
+fn test_integration_tail_calls_in_nested_forms() {
+    let mut env = Environment::new();
+
+    // Calls in tail position of if, begin, let, let*, and and or run in
+    // constant space
+    eval_source(
+        r#"(define (loop n)
+             (if (= n 0)
+               'done
+               (begin
+                 (let ((m (- n 1)))
+                   (let* ((k m))
+                     (or #f (and #t (loop k))))))))"#,
+        &mut env,
+    )
+    .unwrap();
+    let result = eval_source("(loop 100000)", &mut env).unwrap();
+    assert_eq!(result, Value::Symbol("done".into()));
+
+    // Named let loops are iterative
+    let result = eval_source(
+        "(let count ((i 0) (total 0)) (if (= i 100000) total (count (+ i 1) (+ total 1))))",
+        &mut env,
+    )
+    .unwrap();
+    assert_eq!(result, Value::number(100000.0));
+}
@@ -214,6 +214,7 @@ fn test_integration_arithmetic_with_expressions() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn test_integration_arithmetic_precision() {
     use twine_scheme::runtime::environment::Environment;
 
@@ -0,0 +1,154 @@
+//! Integration tests for async evaluation
+//!
+//! This file contains integration tests for evaluating scripts as futures,
+//! run with both evaluators:
+//! - Scripts spawned on a `smol::Executor` yield, so a long evaluation
+//!   does not hold up a short one spawned after it
+//! - Definitions made asynchronously are visible to the interpreter
+//! - Errors are located as for synchronous evaluation
+//! - Resource limits apply
+//! - Scripts spawned as fibers run on the interpreter's scheduler
+//! - Fibers forcing the same promise on one thread evaluate its body once
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use twine_scheme::Interpreter;
+use twine_scheme::error::ErrorCode;
+use twine_scheme::runtime::Limits;
+use twine_scheme::runtime::eval::Evaluator;
+use twine_scheme::types::{Arity, Value};
+
+const COUNT: &str = "(define (count n) (if (= n 0) 'done (count (- n 1))))";
+
+fn check_scripts_yield(evaluator: Evaluator) {
+    let interpreter = Interpreter::new().with_evaluator(evaluator);
+    let executor = smol::Executor::new();
+    let finished = Arc::new(Mutex::new(Vec::new()));
+
+    let long = format!("{COUNT} (count 200000)");
+    let scripts = [
+        ("long", long.as_str()),
+        ("short", "(define short 'done) short"),
+    ];
+    let tasks: Vec<_> = scripts
+        .into_iter()
+        .map(|(name, source)| {
+            let script = interpreter.eval_async(source);
+            let finished = Arc::clone(&finished);
+            executor.spawn(async move {
+                let value = script.await;
+                finished.lock().unwrap().push(name);
+                value
+            })
+        })
+        .collect();
+    for task in tasks {
+        let value = smol::block_on(executor.run(task)).unwrap();
+        assert_eq!(value, Value::symbol("done"));
+    }
+    assert_eq!(*finished.lock().unwrap(), ["short", "long"]);
+
+    // Definitions made asynchronously are visible to the interpreter
+    assert_eq!(interpreter.get("short").unwrap(), Value::symbol("done"));
+}
+
+#[test]
+fn test_integration_async_scripts_yield_bytecode() {
+    check_scripts_yield(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_async_scripts_yield_tree_walker() {
+    check_scripts_yield(Evaluator::TreeWalker);
+}
+
+fn check_errors_located(evaluator: Evaluator) {
+    let interpreter = Interpreter::new().with_evaluator(evaluator);
+    let error = smol::block_on(interpreter.eval_async("(define x 1)\n(car x)")).unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::Type);
+    assert!(error.to_string().starts_with("<string>:2:1: "), "{error}");
+}
+
+#[test]
+fn test_integration_async_errors_located_bytecode() {
+    check_errors_located(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_async_errors_located_tree_walker() {
+    check_errors_located(Evaluator::TreeWalker);
+}
+
+fn check_limits(evaluator: Evaluator) {
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter.set_limits(Limits::new().with_fuel(10_000));
+    let error = smol::block_on(interpreter.eval_async("(define (f) (f)) (f)")).unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::FuelExhausted);
+}
+
+#[test]
+fn test_integration_async_limits_bytecode() {
+    check_limits(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_async_limits_tree_walker() {
+    check_limits(Evaluator::TreeWalker);
+}
+
+fn check_fibers(evaluator: Evaluator) {
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter.eval_str(COUNT).unwrap();
+    let first = interpreter.spawn_fiber("(count 5000)");
+    let second = interpreter.spawn_fiber("(+ 1 2)");
+    let failed = interpreter.spawn_fiber("(car '())");
+    smol::block_on(interpreter.scheduler().lock().unwrap().run_scheduler()).unwrap();
+    assert_eq!(smol::block_on(first.wait()).unwrap(), Value::symbol("done"));
+    assert_eq!(smol::block_on(second.wait()).unwrap(), Value::number(3.0));
+    assert!(smol::block_on(failed.wait()).is_err());
+}
+
+#[test]
+fn test_integration_async_fibers_bytecode() {
+    check_fibers(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_async_fibers_tree_walker() {
+    check_fibers(Evaluator::TreeWalker);
+}
+
+fn check_shared_promise(evaluator: Evaluator) {
+    // The second script waits for the first to finish forcing the promise,
+    // yielding so that the first can run on the same thread
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    let forced = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&forced);
+    interpreter.define_native("note-forced", Arity::exactly(0), move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Ok(Value::Nil)
+    });
+    interpreter.eval_str(COUNT).unwrap();
+    interpreter
+        .eval_str("(define p (delay (begin (note-forced) (count 20000) 'forced)))")
+        .unwrap();
+    let executor = smol::Executor::new();
+    let tasks: Vec<_> = (0..2)
+        .map(|_| executor.spawn(interpreter.eval_async("(force p)")))
+        .collect();
+    for task in tasks {
+        let value = smol::block_on(executor.run(task)).unwrap();
+        assert_eq!(value, Value::symbol("forced"));
+    }
+    assert_eq!(forced.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_integration_async_shared_promise_bytecode() {
+    check_shared_promise(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_async_shared_promise_tree_walker() {
+    check_shared_promise(Evaluator::TreeWalker);
+}
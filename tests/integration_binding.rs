@@ -11,6 +11,8 @@
 //! - Procedure definitions with lambda expressions
 //! - Complex procedure definitions and calls
 //! - Error handling for procedure definitions
+//! - Recursive procedure definitions and named let
+//! - Procedure names in display, `procedure-name` and error messages
 
 mod common;
 
@@ -136,14 +138,14 @@ fn test_integration_define_procedure_closure() {
     let define_result = eval_source("(define (add-to-base x) (+ x base))", &mut env).unwrap();
     assert_eq!(define_result, Value::Nil);
 
-    // Test closure behavior - should use captured base
+    // Test closure behavior - should use global base
     let result = eval_source("(add-to-base 42)", &mut env).unwrap();
     assert_eq!(result, Value::number(142.0));
 
-    // Change base and test again - with lexical scoping, procedure should still use captured base (100)
+    // Redefine base and test again - globals are looked up when the procedure runs
     eval_source("(define base 200)", &mut env).unwrap();
     let result = eval_source("(add-to-base 42)", &mut env).unwrap();
-    assert_eq!(result, Value::number(142.0)); // Should use original captured base (100)
+    assert_eq!(result, Value::number(242.0));
 
     // Test nested closure
     eval_source("(define multiplier 5)", &mut env).unwrap();
@@ -156,6 +158,56 @@ fn test_integration_define_procedure_closure() {
     assert_eq!(result, Value::number(250.0)); // (10 * 5) + 200
 }
 
+#[test]
+fn test_integration_define_late_binding() {
+    let mut env = Environment::new();
+
+    // A procedure can call a helper defined after it
+    eval_source("(define (area r) (* (pi) r r))", &mut env).unwrap();
+    eval_source("(define (pi) 3)", &mut env).unwrap();
+    assert_eq!(
+        eval_source("(area 2)", &mut env).unwrap(),
+        Value::number(12.0)
+    );
+
+    // Mutually recursive global procedures
+    eval_source(
+        "(define (my-even? n) (if (= n 0) #t (my-odd? (- n 1))))",
+        &mut env,
+    )
+    .unwrap();
+    eval_source(
+        "(define (my-odd? n) (if (= n 0) #f (my-even? (- n 1))))",
+        &mut env,
+    )
+    .unwrap();
+    assert_eq!(
+        eval_source("(my-even? 100)", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+
+    // Redefinitions, including of builtins, affect existing procedures
+    eval_source("(define (pi) 4)", &mut env).unwrap();
+    eval_source("(define (double x) (+ x x))", &mut env).unwrap();
+    eval_source("(define (+ a b) (* a b))", &mut env).unwrap();
+    assert_eq!(
+        eval_source("(area 2)", &mut env).unwrap(),
+        Value::number(16.0)
+    );
+    assert_eq!(
+        eval_source("(double 5)", &mut env).unwrap(),
+        Value::number(25.0)
+    );
+
+    // Local bindings are still captured when the closure is created
+    eval_source("(define counter (let ((n 1)) (lambda () n)))", &mut env).unwrap();
+    eval_source("(define n 2)", &mut env).unwrap();
+    assert_eq!(
+        eval_source("(counter)", &mut env).unwrap(),
+        Value::number(1.0)
+    );
+}
+
 #[test]
 fn test_integration_define_procedure_parameter_shadowing() {
     let mut env = Environment::new();
@@ -1416,3 +1468,143 @@ fn test_integration_let_star_empty_bindings() {
     let result = eval_source("(let* () 42)", &mut env).unwrap();
     assert_eq!(result, Value::number(42.0));
 }
+
+#[test]
+fn test_integration_define_procedure_recursion() {
+    let mut env = Environment::new();
+
+    // Procedure definitions can call themselves
+    eval_source(
+        "(define (factorial n)
+           (if (= n 0)
+               1
+               (* n (factorial (- n 1)))))",
+        &mut env,
+    )
+    .unwrap();
+    assert_eq!(
+        eval_source("(factorial 5)", &mut env).unwrap(),
+        Value::number(120.0)
+    );
+
+    // Internal procedure definitions too
+    let source = "(define (count-down n)
+                    (define (step k acc)
+                      (if (= k 0) acc (step (- k 1) (cons k acc))))
+                    (step n '()))";
+    eval_source(source, &mut env).unwrap();
+    assert_eq!(
+        eval_source("(count-down 3)", &mut env).unwrap(),
+        Value::list(vec![
+            Value::number(1.0),
+            Value::number(2.0),
+            Value::number(3.0)
+        ])
+    );
+}
+
+#[test]
+fn test_integration_named_let() {
+    let mut env = Environment::new();
+
+    let source = "(let loop ((i 0) (acc '()))
+                    (if (= i 3)
+                        acc
+                        (loop (+ i 1) (cons i acc))))";
+    assert_eq!(
+        eval_source(source, &mut env).unwrap(),
+        Value::list(vec![
+            Value::number(2.0),
+            Value::number(1.0),
+            Value::number(0.0)
+        ])
+    );
+
+    // Initial values are evaluated outside the loop's scope
+    eval_source("(define loop 10)", &mut env).unwrap();
+    assert_eq!(
+        eval_source("(let loop ((n loop)) n)", &mut env).unwrap(),
+        Value::number(10.0)
+    );
+
+    assert!(eval_source("(let loop ((i 0)))", &mut env).is_err());
+    assert!(eval_source("(let loop ((i 0) (i 1)) i)", &mut env).is_err());
+}
+
+#[test]
+fn test_integration_procedure_names() {
+    let mut env = Environment::new();
+    eval_source("(define (square x) (* x x))", &mut env).unwrap();
+    eval_source("(define cube (lambda (x) (* x x x)))", &mut env).unwrap();
+    eval_source("(define alias square)", &mut env).unwrap();
+
+    let name = |source: &str, env: &mut Environment| eval_source(source, env).unwrap();
+    assert_eq!(
+        name("(procedure-name square)", &mut env),
+        Value::symbol("square")
+    );
+    assert_eq!(
+        name("(procedure-name cube)", &mut env),
+        Value::symbol("cube")
+    );
+    // Procedures keep the name they were defined with
+    assert_eq!(
+        name("(procedure-name alias)", &mut env),
+        Value::symbol("square")
+    );
+    assert_eq!(name("(procedure-name car)", &mut env), Value::symbol("car"));
+    assert_eq!(
+        name("(procedure-name (lambda (x) x))", &mut env),
+        Value::boolean(false)
+    );
+    assert_eq!(
+        name(
+            "(letrec ((even? (lambda (n) (if (= n 0) #t (odd? (- n 1)))))
+                      (odd? (lambda (n) (if (= n 0) #f (even? (- n 1))))))
+               (procedure-name odd?))",
+            &mut env
+        ),
+        Value::symbol("odd?")
+    );
+    assert_eq!(
+        name("(let walk ((n 0)) (procedure-name walk))", &mut env),
+        Value::symbol("walk")
+    );
+
+    assert_eq!(
+        eval_source("square", &mut env).unwrap().to_string(),
+        "#<procedure:square>"
+    );
+}
+
+#[test]
+fn test_integration_procedure_names_in_errors() {
+    let mut env = Environment::new();
+    eval_source("(define (square x) (* x x))", &mut env).unwrap();
+
+    let error = eval_source("(square 1 2)", &mut env).unwrap_err();
+    assert_eq!(error.to_string(), "square: expected 1 argument, got 2");
+
+    // Arity is also checked for calls in tail position
+    eval_source("(define (call-square) (square))", &mut env).unwrap();
+    let error = eval_source("(call-square)", &mut env).unwrap_err();
+    assert_eq!(error.to_string(), "square: expected 1 argument, got 0");
+
+    let error = eval_source("(car square)", &mut env).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "car: expected list, got procedure 'square'"
+    );
+
+    // Backtrace frames use the defining name, not the name at the call site
+    eval_source("(define (add-one x) (+ 1 (square x)))", &mut env).unwrap();
+    eval_source("(define alias add-one)", &mut env).unwrap();
+    let error = eval_source("(list (alias 'x))", &mut env).unwrap_err();
+    let frames = error.backtrace().unwrap().frames();
+    let names: Vec<&str> = frames
+        .iter()
+        .map(|frame| frame.procedure.as_str())
+        .collect();
+    assert_eq!(names, ["*", "add-one"]);
+    assert_eq!(frames[0].tail_calls_elided, 1);
+}
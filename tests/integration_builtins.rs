@@ -7,6 +7,7 @@
 //! - Builtins in nested environments
 //! - Error handling for builtin procedures
 //! - Error handling for builtin operations
+//! - Symbol identity for interned and uninterned symbols
 
 mod common;
 
@@ -448,3 +449,47 @@ fn test_integration_builtin_comprehensive_interaction() {
     .unwrap();
     assert_eq!(result.as_string().unwrap(), "both-true");
 }
+
+#[test]
+fn test_integration_symbol_identity() {
+    let mut env = Environment::new();
+
+    // string->symbol returns the interned symbol written in source code
+    let result = eval_source("(eq? (string->symbol \"apple\") 'apple)", &mut env).unwrap();
+    assert_eq!(result, Value::boolean(true));
+    let result = eval_source("(symbol->string 'apple)", &mut env).unwrap();
+    assert_eq!(result, Value::string("apple"));
+
+    // Generated symbols are only eq? to themselves
+    eval_source("(define g (gensym))", &mut env).unwrap();
+    let result = eval_source("(eq? g g)", &mut env).unwrap();
+    assert_eq!(result, Value::boolean(true));
+    let result = eval_source("(eq? g (string->symbol (symbol->string g)))", &mut env).unwrap();
+    assert_eq!(result, Value::boolean(false));
+    let result = eval_source("(eq? (gensym) (generate-uninterned-symbol))", &mut env).unwrap();
+    assert_eq!(result, Value::boolean(false));
+}
+
+#[test]
+fn test_integration_json_round_trip() {
+    let mut env = Environment::new();
+
+    eval_source(
+        "(define config (json->scheme \"{\\\"name\\\": \\\"twine\\\", \\\"ports\\\": [80, 443], \\\"debug\\\": null}\"))",
+        &mut env,
+    )
+    .unwrap();
+    let result = eval_source("(car (cdr (car config)))", &mut env).unwrap();
+    assert_eq!(result, Value::string("twine"));
+    let result = eval_source("(eq? (car (cdr (car (cdr (cdr config))))) 'null)", &mut env).unwrap();
+    assert_eq!(result, Value::boolean(true));
+
+    let result = eval_source("(scheme->json config)", &mut env).unwrap();
+    assert_eq!(
+        result,
+        Value::string(r#"{"name":"twine","ports":[80,443],"debug":null}"#)
+    );
+
+    let result = eval_source("(json->scheme \"[1,\\n 2\")", &mut env);
+    assert!(result.is_err());
+}
@@ -0,0 +1,83 @@
+//! Integration tests for the C API
+//!
+//! This file checks that the checked-in `include/twine.h` matches the
+//! header generated by the build script. It then builds the library with
+//! the C API, compiles
+//! `tests/capi/capi_test.c` against it and the generated header with the
+//! system C compiler (`cc`, or `$CC`), and runs it. The C program checks:
+//! - Evaluating source and reading printed results and error messages
+//! - Definitions persisting between evaluations
+//! - C callbacks registered as native procedures, including their errors
+//! - Null pointers and invalid UTF-8 being rejected
+//! - Creating, inspecting and freeing values
+
+#![cfg(all(feature = "capi", unix))]
+
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_integration_capi_header_is_current() {
+    let generated = concat!(env!("OUT_DIR"), "/twine.h");
+    let checked_in = Path::new(env!("CARGO_MANIFEST_DIR")).join("include/twine.h");
+    assert!(
+        std::fs::read_to_string(generated).unwrap()
+            == std::fs::read_to_string(&checked_in).unwrap(),
+        "include/twine.h is out of date; copy the generated header over it:\n  cp {generated} {}",
+        checked_in.display()
+    );
+}
+
+#[test]
+fn test_integration_capi() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi");
+
+    // Build the static library in a target directory of its own, so the
+    // other tests' build of the crate is left alone
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let output = Command::new(cargo)
+        .args(["rustc", "--lib", "--features", "capi", "--crate-type"])
+        .args(["staticlib", "--manifest-path"])
+        .arg(manifest_dir.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target_dir)
+        .output()
+        .expect("Failed to start cargo");
+    assert!(
+        output.status.success(),
+        "building the library failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    // Linked statically, so no older shared library can be loaded instead
+    let library = target_dir.join("debug/libtwine_scheme.a");
+    let executable = target_dir.join("capi_test");
+
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let output = Command::new(compiler)
+        .arg(manifest_dir.join("tests/capi/capi_test.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(&library)
+        // System libraries the Rust standard library needs
+        .args(["-lpthread", "-ldl", "-lm", "-Wall", "-Werror", "-o"])
+        .arg(&executable)
+        .output()
+        .expect("Failed to start the C compiler");
+    assert!(
+        output.status.success(),
+        "compiling the C test failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = Command::new(&executable).output().unwrap();
+    assert!(
+        output.status.success(),
+        "the C test failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "all checks passed\n"
+    );
+}
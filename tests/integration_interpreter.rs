@@ -0,0 +1,76 @@
+//! Integration tests for the embedding API
+//!
+//! This file contains integration tests for the `Interpreter` facade:
+//! - Evaluating files and strings in one global environment
+//! - Calling Scheme procedures from Rust with values defined from Rust
+//! - Native Rust procedures called from Scheme
+//! - Located errors for failing source
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use twine_scheme::Interpreter;
+use twine_scheme::types::{Arity, Value};
+
+#[test]
+fn test_integration_interpreter_eval_file() {
+    let path = std::env::temp_dir().join(format!("twine-interpreter-{}.scm", std::process::id()));
+    std::fs::write(
+        &path,
+        "(define (sum-to n) (if (= n 0) 0 (+ n (sum-to (- n 1)))))\n(sum-to limit)",
+    )
+    .unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.define("limit", Value::number(10.0));
+    assert_eq!(interpreter.eval_file(&path).unwrap(), Value::number(55.0));
+
+    // Definitions from the file can be called from Rust
+    let result = interpreter.call("sum-to", &[Value::number(100.0)]).unwrap();
+    assert_eq!(result, Value::number(5050.0));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_integration_interpreter_errors() {
+    let mut interpreter = Interpreter::new();
+    let error = interpreter.eval_str("(define x 1)\n(car x)").unwrap_err();
+    assert!(error.to_string().starts_with("<string>:2:1: car:"));
+
+    let error = interpreter
+        .eval_file("/nonexistent/twine/file.scm")
+        .unwrap_err();
+    assert!(error.to_string().contains("cannot read file"));
+
+    // The interpreter is still usable after an error
+    assert_eq!(interpreter.eval_str("(+ x 1)").unwrap(), Value::number(2.0));
+}
+
+#[test]
+fn test_integration_native_procedures() {
+    let mut interpreter = Interpreter::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&calls);
+    interpreter.define_native("tick!", Arity::between(0, 1), move |args| {
+        let step = args.first().and_then(Value::as_number).unwrap_or(1.0) as usize;
+        let total = counter.fetch_add(step, Ordering::Relaxed) + step;
+        Ok(Value::number(total as f64))
+    });
+
+    // Natives can be called in tail position
+    let result = interpreter
+        .eval_str(
+            "(define (loop n) (if (= n 1) (tick!) (begin (tick! 2) (loop (- n 1)))))
+             (loop 100000)",
+        )
+        .unwrap();
+    assert_eq!(result, Value::number(199999.0));
+    assert_eq!(calls.load(Ordering::Relaxed), 199999);
+
+    assert_eq!(
+        interpreter.eval_str("(procedure-name tick!)").unwrap(),
+        Value::symbol("tick!")
+    );
+    let error = interpreter.eval_str("(tick! 1 2)").unwrap_err();
+    assert!(error.to_string().contains("tick!"));
+}
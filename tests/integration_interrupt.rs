@@ -0,0 +1,132 @@
+//! Integration tests for interrupting evaluations
+//!
+//! This file contains integration tests for the interrupt flag, run with
+//! both evaluators:
+//! - An interrupt stops a runaway loop with an "interrupted" error
+//! - An interrupt stops a loop inside a builtin, such as `stream->list`
+//! - The environment keeps its definitions after an interrupt
+//! - Each interrupt is delivered once
+//! - Interrupting one interpreter leaves the others running
+
+use std::thread;
+use std::time::Duration;
+use twine_scheme::Interpreter;
+use twine_scheme::error::ErrorCode;
+use twine_scheme::runtime::eval::Evaluator;
+use twine_scheme::script::ScriptError;
+use twine_scheme::types::Value;
+
+const COUNT: &str = "(define (count n) (if (= n 0) 'done (count (- n 1))))";
+
+/// Evaluate `source`, interrupting it from another thread once it is running
+fn interrupted(
+    interpreter: &mut Interpreter,
+    source: &str,
+) -> std::result::Result<Value, ScriptError> {
+    let handle = interpreter.interrupt_handle();
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    let result = interpreter.eval_str(source);
+    interrupter.join().unwrap();
+    result
+}
+
+fn check_runaway_loop(evaluator: Evaluator) {
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter
+        .eval_str("(define kept 42) (define (spin n) (spin (+ n 1)))")
+        .unwrap();
+
+    let error = interrupted(&mut interpreter, "(spin 0)").unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::Interrupted);
+    assert_eq!(error.error.to_string(), "Runtime error: interrupted");
+    assert!(!interpreter.interrupt_handle().is_interrupted());
+
+    // Definitions survive, and later evaluations run normally
+    assert_eq!(interpreter.eval_str("kept").unwrap(), Value::number(42.0));
+    interpreter.eval_str(COUNT).unwrap();
+    assert_eq!(
+        interpreter.eval_str("(count 1000)").unwrap(),
+        Value::symbol("done")
+    );
+}
+
+#[test]
+fn test_integration_interrupt_runaway_loop_bytecode() {
+    check_runaway_loop(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_interrupt_runaway_loop_tree_walker() {
+    check_runaway_loop(Evaluator::TreeWalker);
+}
+
+fn check_builtin_loop(evaluator: Evaluator) {
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter
+        .eval_str("(define s (stream-cons 1 s))")
+        .unwrap();
+
+    let error = interrupted(&mut interpreter, "(stream->list s)").unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::Interrupted);
+    assert!(!interpreter.interrupt_handle().is_interrupted());
+    assert_eq!(
+        interpreter.eval_str("(stream->list s 3)").unwrap(),
+        interpreter.eval_str("'(1 1 1)").unwrap()
+    );
+}
+
+#[test]
+fn test_integration_interrupt_builtin_loop_bytecode() {
+    check_builtin_loop(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_interrupt_builtin_loop_tree_walker() {
+    check_builtin_loop(Evaluator::TreeWalker);
+}
+
+fn check_delivered_once(evaluator: Evaluator) {
+    // A pending interrupt stops the next evaluation, and only that one
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter.eval_str(COUNT).unwrap();
+    interpreter.interrupt_handle().interrupt();
+    let error = interpreter.eval_str("(count 10)").unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::Interrupted);
+    assert_eq!(
+        interpreter.eval_str("(count 10)").unwrap(),
+        Value::symbol("done")
+    );
+}
+
+#[test]
+fn test_integration_interrupt_delivered_once_bytecode() {
+    check_delivered_once(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_interrupt_delivered_once_tree_walker() {
+    check_delivered_once(Evaluator::TreeWalker);
+}
+
+fn check_other_interpreters_run(evaluator: Evaluator) {
+    let mut stopped = Interpreter::new().with_evaluator(evaluator);
+    let mut other = Interpreter::new().with_evaluator(evaluator);
+    stopped.interrupt_handle().interrupt();
+    assert_eq!(other.eval_str("(+ 1 2)").unwrap(), Value::number(3.0));
+    assert!(!other.interrupt_handle().is_interrupted());
+    let error = stopped.eval_str("(+ 1 2)").unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::Interrupted);
+}
+
+#[test]
+fn test_integration_interrupt_other_interpreters_run_bytecode() {
+    check_other_interpreters_run(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_interrupt_other_interpreters_run_tree_walker() {
+    check_other_interpreters_run(Evaluator::TreeWalker);
+}
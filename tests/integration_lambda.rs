@@ -478,10 +478,10 @@ fn test_integration_lambda_application_closure() {
     let result = eval_source("(addx 5)", &mut env).unwrap();
     assert_eq!(result, Value::number(15.0));
 
-    // Change x and test again - with lexical scoping, lambda should still use captured x (10)
+    // Redefine x and test again - the global x is looked up when the lambda runs
     eval_source("(define x 20)", &mut env).unwrap();
     let result = eval_source("(addx 5)", &mut env).unwrap();
-    assert_eq!(result, Value::number(15.0)); // Should use original captured x (10)
+    assert_eq!(result, Value::number(25.0));
 
     // Test closure with let
     let closure_expr = r#"
@@ -608,7 +608,7 @@ fn test_integration_lambda_application_error_cases() {
     eval_source("(define x 42)", &mut env).unwrap();
     let result = eval_source("(x 1 2 3)", &mut env);
     assert!(result.is_err());
-    if let Err(Error::RuntimeError(msg)) = result {
+    if let Err(Error::RuntimeError { message: msg, .. }) = result {
         assert!(msg.contains("is not a procedure, got number"));
     } else {
         panic!("Expected RuntimeError for calling non-procedure");
@@ -731,11 +731,7 @@ fn test_integration_lambda_application_comprehensive() {
                 (cons (+ first 5) rest)))))
     "#;
 
-    eval_source(
-        &format!("(define process-data {complex_lambda})"),
-        &mut env,
-    )
-    .unwrap();
+    eval_source(&format!("(define process-data {complex_lambda})"), &mut env).unwrap();
 
     // Test with data where first element > 10
     let result = eval_source("(process-data '(15 20 30))", &mut env).unwrap();
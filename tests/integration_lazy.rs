@@ -0,0 +1,111 @@
+//! Integration tests for lazy evaluation
+//!
+//! This file contains integration tests for promises and streams:
+//! - delay, delay-force, make-promise and force
+//! - Memoisation of forced promises, including promises forced from their
+//!   own body
+//! - Infinite streams built with stream-cons
+//! - Lazy values shared across procedures
+
+mod common;
+
+use common::{eval_source, test_io};
+use twine_scheme::runtime::Environment;
+use twine_scheme::types::Value;
+
+#[test]
+fn test_integration_promise_basic() {
+    let mut env = Environment::new();
+
+    assert_eq!(
+        eval_source("(force (delay (* 6 7)))", &mut env).unwrap(),
+        Value::number(42.0)
+    );
+    assert_eq!(
+        eval_source("(force (make-promise 'ready))", &mut env).unwrap(),
+        Value::symbol("ready")
+    );
+    assert_eq!(
+        eval_source("(promise? (delay 1))", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+    assert_eq!(
+        eval_source("(promise? (force (delay 1)))", &mut env).unwrap(),
+        Value::boolean(false)
+    );
+}
+
+#[test]
+fn test_integration_promise_memoised() {
+    // The delayed body runs exactly once, however often it is forced
+    test_io(
+        "(begin (define p (delay (begin (display \"computing \") 42)))
+                (display (force p))
+                (display \" \")
+                (display (force p)))",
+        "computing 42 42",
+    );
+}
+
+#[test]
+fn test_integration_promise_forced_from_its_own_body() {
+    // R7RS 4.2.5: the nested force finishes first, and its value is kept
+    test_io(
+        "(begin (define depth (make-parameter 0))
+                (define p (delay (if (= (depth) 0)
+                                     (parameterize ((depth 1)) (list 'outer (force p)))
+                                     'inner)))
+                (display (list (force p) (force p))))",
+        "(inner inner)",
+    );
+}
+
+#[test]
+fn test_integration_stream_infinite() {
+    let mut env = Environment::new();
+    eval_source(
+        "(define integers-from (lambda (n) (stream-cons n (integers-from (+ n 1)))))",
+        &mut env,
+    )
+    .unwrap();
+    eval_source(
+        "(define stream-scale
+           (lambda (s k)
+             (stream-cons (* k (stream-car s)) (stream-scale (stream-cdr s) k))))",
+        &mut env,
+    )
+    .unwrap();
+
+    assert_eq!(
+        eval_source(
+            "(stream->list (stream-scale (integers-from 1) 10) 4)",
+            &mut env
+        )
+        .unwrap(),
+        Value::list(vec![
+            Value::number(10.0),
+            Value::number(20.0),
+            Value::number(30.0),
+            Value::number(40.0),
+        ])
+    );
+}
+
+#[test]
+fn test_integration_stream_finite() {
+    let mut env = Environment::new();
+
+    assert_eq!(
+        eval_source("(stream->list (stream 1 2 3))", &mut env).unwrap(),
+        Value::list(vec![
+            Value::number(1.0),
+            Value::number(2.0),
+            Value::number(3.0),
+        ])
+    );
+    assert_eq!(
+        eval_source("(stream-null? (stream-cdr (stream 1)))", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+    assert!(eval_source("(stream-car (stream))", &mut env).is_err());
+}
@@ -0,0 +1,197 @@
+//! Integration tests for the library system
+//!
+//! This file contains integration tests for define-library and import:
+//! - Libraries defined inline and imported in the same program
+//! - Loading libraries from files on the search path
+//! - Import set modifiers (only, except, prefix, rename)
+//! - Libraries importing other libraries, loaded once and cached
+//! - Error reporting for missing and circular libraries
+
+mod common;
+
+use common::{eval_source, test_io};
+use std::path::PathBuf;
+use twine_scheme::runtime::Environment;
+use twine_scheme::runtime::library::add_search_path;
+use twine_scheme::types::Value;
+
+/// Create a fresh library directory populated with the given files
+fn library_dir(test_name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "twine-libraries-{test_name}-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    for (path, source) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    add_search_path(&dir);
+    dir
+}
+
+#[test]
+fn test_integration_inline_library() {
+    test_io(
+        "(define-library (shapes area)
+           (export circle-area)
+           (import (scheme base))
+           (begin
+             (define pi 3)
+             (define circle-area (lambda (r) (* pi r r)))))
+         (import (shapes area) (scheme write))
+         (display (circle-area 2))",
+        "12",
+    );
+}
+
+#[test]
+fn test_integration_symbol_libraries() {
+    test_io(
+        "(define-library (util names)
+           (export tag fresh)
+           (import (scheme base) (twine symbol))
+           (begin
+             (define tag (lambda (name) (string->symbol (symbol->string name))))
+             (define fresh (lambda () (symbol? (gensym))))))
+         (import (util names) (scheme write))
+         (display (list (tag 'point) (fresh)))",
+        "(point #t)",
+    );
+    test_io(
+        "(import (twine json))
+         (display (json->scheme \"{}\"))",
+        "empty-object",
+    );
+}
+
+#[test]
+fn test_integration_library_from_file() {
+    let dir = library_dir(
+        "from-file",
+        &[(
+            "file-tests/strings.sld",
+            "(define-library (file-tests strings)
+               (export greeting)
+               (import (scheme base))
+               (begin (define greeting (list 'hello 'world))))",
+        )],
+    );
+
+    let mut env = Environment::new();
+    eval_source("(import (file-tests strings))", &mut env).unwrap();
+    assert_eq!(
+        eval_source("greeting", &mut env).unwrap(),
+        Value::list(vec![Value::symbol("hello"), Value::symbol("world")])
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_integration_library_dependencies_loaded_once() {
+    let dir = library_dir(
+        "dependencies",
+        &[
+            (
+                "dep-tests/counter.sld",
+                "(define-library (dep-tests counter)
+                   (export instance)
+                   (begin (define instance (lambda () 'shared))))",
+            ),
+            (
+                "dep-tests/left.scm",
+                "(define-library (dep-tests left)
+                   (export left)
+                   (import (dep-tests counter))
+                   (begin (define left instance)))",
+            ),
+            (
+                "dep-tests/right.sld",
+                "(define-library (dep-tests right)
+                   (export right)
+                   (import (dep-tests counter))
+                   (begin (define right instance)))",
+            ),
+        ],
+    );
+
+    let mut env = Environment::new();
+    eval_source("(import (dep-tests left) (dep-tests right))", &mut env).unwrap();
+
+    // Both importers see the same instance of the shared library
+    assert_eq!(
+        eval_source("(eq? left right)", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+    assert_eq!(
+        eval_source("(left)", &mut env).unwrap(),
+        Value::symbol("shared")
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_integration_import_modifiers() {
+    let mut env = Environment::new_without_builtins();
+    eval_source(
+        "(import (prefix (except (scheme base) cons) base:)
+                 (rename (only (scheme lazy) force make-promise) (make-promise ready)))",
+        &mut env,
+    )
+    .unwrap();
+
+    assert_eq!(
+        eval_source("(base:car (base:list 1 2))", &mut env).unwrap(),
+        Value::number(1.0)
+    );
+    assert_eq!(
+        eval_source("(force (ready 5))", &mut env).unwrap(),
+        Value::number(5.0)
+    );
+    assert!(eval_source("(base:cons 1 '())", &mut env).is_err());
+    assert!(eval_source("(car '(1))", &mut env).is_err());
+    assert!(eval_source("(promise? 1)", &mut env).is_err());
+}
+
+#[test]
+fn test_integration_library_errors() {
+    let dir = library_dir(
+        "errors",
+        &[
+            (
+                "cycle-tests/a.sld",
+                "(define-library (cycle-tests a)
+                   (export a)
+                   (import (cycle-tests b))
+                   (begin (define a 1)))",
+            ),
+            (
+                "cycle-tests/b.sld",
+                "(define-library (cycle-tests b)
+                   (export b)
+                   (import (cycle-tests a))
+                   (begin (define b 2)))",
+            ),
+            ("cycle-tests/wrong.sld", "(define wrong 1)"),
+        ],
+    );
+
+    let mut env = Environment::new();
+
+    let err = eval_source("(import (cycle-tests a))", &mut env).unwrap_err();
+    assert!(err.to_string().contains("circular dependency"));
+
+    let err = eval_source("(import (cycle-tests wrong))", &mut env).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("does not define library (cycle-tests wrong)")
+    );
+
+    let err = eval_source("(import (cycle-tests missing))", &mut env).unwrap_err();
+    assert!(err.to_string().contains("not found"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,218 @@
+//! Integration tests for resource limits
+//!
+//! This file contains integration tests for evaluation limits, run with
+//! both evaluators:
+//! - Fuel stops runaway tail-call loops
+//! - Each evaluation of an interpreter gets a fresh budget
+//! - Fuel, the timeout and the allocation limit stop loops in builtins
+//! - The depth limit stops deep non-tail recursion, including recursion
+//!   through `force`, `parameterize` and builtins that evaluate code
+//! - The allocation limit stops oversized lists and strings
+//! - The timeout stops long evaluations
+
+use std::time::{Duration, Instant};
+use twine_scheme::Interpreter;
+use twine_scheme::error::ErrorCode;
+use twine_scheme::runtime::eval::Evaluator;
+use twine_scheme::runtime::{Limits, Sandbox};
+use twine_scheme::types::Value;
+
+const COUNT: &str = "(define (count n) (if (= n 0) 'done (count (- n 1))))";
+
+fn limited(evaluator: Evaluator, limits: Limits) -> Interpreter {
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    interpreter.set_limits(limits);
+    interpreter
+}
+
+fn exceeded(interpreter: &mut Interpreter, source: &str) -> ErrorCode {
+    interpreter.eval_str(source).unwrap_err().error.code()
+}
+
+fn check_fuel(evaluator: Evaluator) {
+    let mut interpreter = limited(evaluator, Limits::new().with_fuel(1_000_000));
+    assert_eq!(
+        exceeded(&mut interpreter, "(define (f) (f)) (f)"),
+        ErrorCode::FuelExhausted
+    );
+
+    // The next evaluation has a fresh budget
+    interpreter.eval_str(COUNT).unwrap();
+    assert_eq!(
+        interpreter.eval_str("(count 5000)").unwrap(),
+        Value::symbol("done")
+    );
+    assert_eq!(
+        interpreter
+            .call("count", &[Value::number(200_000.0)])
+            .unwrap_err()
+            .code(),
+        ErrorCode::FuelExhausted
+    );
+}
+
+#[test]
+fn test_integration_limits_fuel_bytecode() {
+    check_fuel(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_fuel_tree_walker() {
+    check_fuel(Evaluator::TreeWalker);
+}
+
+fn check_depth(evaluator: Evaluator) {
+    let mut interpreter = limited(evaluator, Limits::new().with_max_depth(100));
+    interpreter
+        .eval_str("(define (sum n) (if (= n 0) 0 (+ n (sum (- n 1)))))")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval_str("(sum 99)").unwrap(),
+        Value::number(4950.0)
+    );
+    assert_eq!(
+        exceeded(&mut interpreter, "(sum 100)"),
+        ErrorCode::DepthExceeded
+    );
+
+    // Tail calls do not nest
+    interpreter.eval_str(COUNT).unwrap();
+    assert_eq!(
+        interpreter.eval_str("(count 1000)").unwrap(),
+        Value::symbol("done")
+    );
+}
+
+#[test]
+fn test_integration_limits_depth_bytecode() {
+    check_depth(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_depth_tree_walker() {
+    check_depth(Evaluator::TreeWalker);
+}
+
+fn check_builtin_loops(evaluator: Evaluator) {
+    // Builtins that loop draw on the budget as they go
+    let endless = "(define s (stream-cons 1 s)) (stream->list s)";
+    let mut interpreter = Interpreter::sandboxed(Sandbox::safe()).with_evaluator(evaluator);
+    interpreter.set_limits(Limits::new().with_fuel(100_000));
+    assert_eq!(
+        exceeded(&mut interpreter, endless),
+        ErrorCode::FuelExhausted
+    );
+    interpreter.set_limits(Limits::new().with_max_allocation(1000));
+    assert_eq!(
+        exceeded(&mut interpreter, endless),
+        ErrorCode::AllocationExceeded
+    );
+    interpreter.set_limits(Limits::new().with_timeout(Duration::from_millis(50)));
+    let start = Instant::now();
+    assert_eq!(
+        exceeded(&mut interpreter, endless),
+        ErrorCode::DeadlineExceeded
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_integration_limits_builtin_loops_bytecode() {
+    check_builtin_loops(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_builtin_loops_tree_walker() {
+    check_builtin_loops(Evaluator::TreeWalker);
+}
+
+fn check_nested_depth(evaluator: Evaluator) {
+    // Nested evaluations count their depth on from the calls in progress
+    for source in [
+        "(define (f n) (force (delay (+ 1 (f n))))) (f 1)",
+        "(define p (make-parameter 0)) (define (f n) (parameterize ((p n)) (+ 1 (f n)))) (f 1)",
+        "(define (f n) (+ 1 (car (stream->list (stream-cons (f n) (stream)))))) (f 1)",
+    ] {
+        let mut interpreter = Interpreter::sandboxed(Sandbox::safe()).with_evaluator(evaluator);
+        interpreter.set_limits(
+            Limits::new()
+                .with_max_depth(100)
+                .with_fuel(10_000_000)
+                .with_timeout(Duration::from_secs(3)),
+        );
+        assert_eq!(
+            exceeded(&mut interpreter, source),
+            ErrorCode::DepthExceeded,
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn test_integration_limits_nested_depth_bytecode() {
+    check_nested_depth(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_nested_depth_tree_walker() {
+    check_nested_depth(Evaluator::TreeWalker);
+}
+
+fn check_allocation(evaluator: Evaluator) {
+    let mut interpreter = limited(evaluator, Limits::new().with_max_allocation(10));
+    interpreter
+        .eval_str("(define (grow l n) (if (= n 0) l (grow (cons n l) (- n 1))))")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval_str("(length (grow '() 10))").unwrap(),
+        Value::number(10.0)
+    );
+    assert_eq!(
+        exceeded(&mut interpreter, "(grow '() 11)"),
+        ErrorCode::AllocationExceeded
+    );
+    assert_eq!(
+        exceeded(&mut interpreter, "(symbol->string 'abcdefghijk)"),
+        ErrorCode::AllocationExceeded
+    );
+}
+
+#[test]
+fn test_integration_limits_allocation_bytecode() {
+    check_allocation(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_allocation_tree_walker() {
+    check_allocation(Evaluator::TreeWalker);
+}
+
+fn check_timeout(evaluator: Evaluator) {
+    let mut interpreter = limited(
+        evaluator,
+        Limits::new().with_timeout(Duration::from_millis(50)),
+    );
+    let start = Instant::now();
+    let error = interpreter.eval_str("(define (f) (f)) (f)").unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::DeadlineExceeded);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(error.error.to_string(), "Deadline exceeded");
+
+    // Without limits the interpreter is unrestricted
+    interpreter.clear_limits();
+    interpreter.eval_str(COUNT).unwrap();
+    assert_eq!(
+        interpreter.eval_str("(count 100000)").unwrap(),
+        Value::symbol("done")
+    );
+}
+
+#[test]
+fn test_integration_limits_timeout_bytecode() {
+    check_timeout(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_limits_timeout_tree_walker() {
+    check_timeout(Evaluator::TreeWalker);
+}
@@ -0,0 +1,108 @@
+//! Integration tests for parameter objects
+//!
+//! This file contains integration tests for dynamic binding:
+//! - make-parameter with and without converters
+//! - parameterize scoping across procedure calls
+//! - Nested parameterize through higher-order procedures
+
+mod common;
+
+use common::eval_source;
+use twine_scheme::runtime::Environment;
+use twine_scheme::types::Value;
+
+#[test]
+fn test_integration_parameter_basic() {
+    let mut env = Environment::new();
+    eval_source("(define indent (make-parameter 0))", &mut env).unwrap();
+
+    assert_eq!(
+        eval_source("(indent)", &mut env).unwrap(),
+        Value::number(0.0)
+    );
+    assert_eq!(
+        eval_source("(procedure? indent)", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+    assert_eq!(
+        eval_source("(parameterize ((indent 4)) (+ (indent) 1))", &mut env).unwrap(),
+        Value::number(5.0)
+    );
+    assert_eq!(
+        eval_source("(indent)", &mut env).unwrap(),
+        Value::number(0.0)
+    );
+}
+
+#[test]
+fn test_integration_parameter_dynamic_not_lexical() {
+    let mut env = Environment::new();
+    eval_source("(define request-id (make-parameter 'none))", &mut env).unwrap();
+
+    // The closure is created outside parameterize but called inside it
+    eval_source("(define (log-line msg) (list (request-id) msg))", &mut env).unwrap();
+
+    assert_eq!(
+        eval_source(
+            "(parameterize ((request-id 'req-42)) (log-line \"started\"))",
+            &mut env
+        )
+        .unwrap(),
+        Value::list(vec![Value::symbol("req-42"), Value::string("started")])
+    );
+    assert_eq!(
+        eval_source("(log-line \"idle\")", &mut env).unwrap(),
+        Value::list(vec![Value::symbol("none"), Value::string("idle")])
+    );
+}
+
+#[test]
+fn test_integration_parameter_nesting() {
+    let mut env = Environment::new();
+    eval_source("(define depth (make-parameter 0))", &mut env).unwrap();
+    eval_source(
+        "(define (deeper thunk) (parameterize ((depth (+ (depth) 1))) (thunk)))",
+        &mut env,
+    )
+    .unwrap();
+
+    assert_eq!(
+        eval_source(
+            "(deeper (lambda () (deeper (lambda () (deeper depth)))))",
+            &mut env
+        )
+        .unwrap(),
+        Value::number(3.0)
+    );
+    assert_eq!(
+        eval_source("(depth)", &mut env).unwrap(),
+        Value::number(0.0)
+    );
+}
+
+#[test]
+fn test_integration_parameter_converter() {
+    let mut env = Environment::new();
+    eval_source(
+        "(define port-number (make-parameter 80 (lambda (x) (if (number? x) x 0))))",
+        &mut env,
+    )
+    .unwrap();
+
+    assert_eq!(
+        eval_source(
+            "(parameterize ((port-number 8080)) (port-number))",
+            &mut env
+        )
+        .unwrap(),
+        Value::number(8080.0)
+    );
+    assert_eq!(
+        eval_source(
+            "(parameterize ((port-number \"bad\")) (port-number))",
+            &mut env
+        )
+        .unwrap(),
+        Value::number(0.0)
+    );
+}
@@ -0,0 +1,130 @@
+//! Integration tests for record types
+//!
+//! This file contains integration tests for `define-record-type`:
+//! - Constructor, predicate and accessor generation
+//! - Functional updaters producing modified copies
+//! - Records inside lists and procedures
+//! - Record display and error handling
+
+mod common;
+
+use common::{eval_source, test_io};
+use twine_scheme::runtime::Environment;
+use twine_scheme::types::Value;
+
+fn define_point(env: &mut Environment) {
+    eval_source(
+        "(define-record-type <point> (make-point x y) point? (x point-x point-with-x) (y point-y point-with-y))",
+        env,
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_integration_record_basic() {
+    let mut env = Environment::new();
+    define_point(&mut env);
+
+    eval_source("(define p (make-point 3 4))", &mut env).unwrap();
+    assert_eq!(
+        eval_source("(point-x p)", &mut env).unwrap(),
+        Value::number(3.0)
+    );
+    assert_eq!(
+        eval_source("(point-y p)", &mut env).unwrap(),
+        Value::number(4.0)
+    );
+    assert_eq!(
+        eval_source("(point? p)", &mut env).unwrap(),
+        Value::boolean(true)
+    );
+    assert_eq!(
+        eval_source("(point? '(3 4))", &mut env).unwrap(),
+        Value::boolean(false)
+    );
+}
+
+#[test]
+fn test_integration_record_functional_update() {
+    let mut env = Environment::new();
+    define_point(&mut env);
+
+    eval_source("(define p (make-point 3 4))", &mut env).unwrap();
+    eval_source(
+        "(define moved (point-with-y (point-with-x p 10) 20))",
+        &mut env,
+    )
+    .unwrap();
+
+    // Original record is unchanged
+    assert_eq!(
+        format!("{}", eval_source("p", &mut env).unwrap()),
+        "#<point x: 3 y: 4>"
+    );
+    assert_eq!(
+        format!("{}", eval_source("moved", &mut env).unwrap()),
+        "#<point x: 10 y: 20>"
+    );
+}
+
+#[test]
+fn test_integration_record_with_procedures() {
+    let mut env = Environment::new();
+    define_point(&mut env);
+
+    eval_source(
+        "(define (distance-squared a b)
+           (let ((dx (- (point-x a) (point-x b)))
+                 (dy (- (point-y a) (point-y b))))
+             (+ (* dx dx) (* dy dy))))",
+        &mut env,
+    )
+    .unwrap();
+    assert_eq!(
+        eval_source(
+            "(distance-squared (make-point 0 0) (make-point 3 4))",
+            &mut env
+        )
+        .unwrap(),
+        Value::number(25.0)
+    );
+
+    // Records can be stored in lists
+    assert_eq!(
+        eval_source(
+            "(point-y (car (cdr (list (make-point 1 2) (make-point 5 6)))))",
+            &mut env
+        )
+        .unwrap(),
+        Value::number(6.0)
+    );
+}
+
+#[test]
+fn test_integration_record_errors() {
+    let mut env = Environment::new();
+    define_point(&mut env);
+    eval_source(
+        "(define-record-type <circle> (make-circle r) circle? (r circle-r))",
+        &mut env,
+    )
+    .unwrap();
+
+    // Accessor applied to another record type
+    let err = eval_source("(point-x (make-circle 1))", &mut env).unwrap_err();
+    assert!(err.to_string().contains("point-x"));
+
+    // Constructor arity
+    assert!(eval_source("(make-point 1 2 3)", &mut env).is_err());
+
+    // Updater arity
+    assert!(eval_source("(point-with-x (make-point 1 2))", &mut env).is_err());
+}
+
+#[test]
+fn test_integration_record_display() {
+    test_io(
+        "(begin (define-record-type <point> (make-point x y) point? (x point-x) (y point-y)) (display (make-point 1 2)))",
+        "#<point x: 1 y: 2>",
+    );
+}
@@ -0,0 +1,167 @@
+//! Integration tests for sandboxed evaluation
+//!
+//! This file contains integration tests for capability-restricted
+//! environments, run with both evaluators:
+//! - Permitted code runs as usual
+//! - Denied builtins and special forms fail as not permitted
+//! - Denied capabilities stay denied inside procedures and local scopes
+//! - Sandboxed code may define identifiers of its own with denied names
+//! - Unrestricted interpreters are unaffected
+//! - Imports leave out denied builtins
+
+use twine_scheme::Interpreter;
+use twine_scheme::error::ErrorCode;
+use twine_scheme::runtime::Sandbox;
+use twine_scheme::runtime::builtins::Builtin;
+use twine_scheme::runtime::eval::Evaluator;
+use twine_scheme::runtime::special_forms::SpecialForm;
+use twine_scheme::types::Value;
+
+fn sandboxed(evaluator: Evaluator) -> Interpreter {
+    Interpreter::sandboxed(Sandbox::safe()).with_evaluator(evaluator)
+}
+
+fn denied(interpreter: &mut Interpreter, source: &str) -> String {
+    let error = interpreter.eval_str(source).unwrap_err();
+    assert_eq!(error.error.code(), ErrorCode::NotPermitted, "{source}");
+    error.error.message()
+}
+
+fn check_permitted_code_runs(evaluator: Evaluator) {
+    let mut interpreter = sandboxed(evaluator);
+    assert_eq!(
+        interpreter
+            .eval_str("(let loop ((i 0) (acc '())) (if (= i 3) acc (loop (+ i 1) (cons i acc))))")
+            .unwrap()
+            .to_string(),
+        "(2 1 0)"
+    );
+
+    // Symbols made from strings are freed when no longer used
+    assert_eq!(
+        interpreter
+            .eval_str("(cons (string->symbol \"fresh\") (json->scheme \"{\\\"key\\\": 1}\"))")
+            .unwrap()
+            .to_string(),
+        "(fresh (key 1))"
+    );
+}
+
+#[test]
+fn test_integration_sandbox_permitted_code_runs_bytecode() {
+    check_permitted_code_runs(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_permitted_code_runs_tree_walker() {
+    check_permitted_code_runs(Evaluator::TreeWalker);
+}
+
+fn check_denied_capabilities(evaluator: Evaluator) {
+    let mut interpreter = sandboxed(evaluator);
+    assert_eq!(
+        denied(&mut interpreter, "(display \"hi\")"),
+        "'display' is not permitted in this sandbox"
+    );
+    assert_eq!(
+        denied(&mut interpreter, "(import (scheme base))"),
+        "'import' is not permitted in this sandbox"
+    );
+    denied(&mut interpreter, "(command-line)");
+}
+
+#[test]
+fn test_integration_sandbox_denied_capabilities_bytecode() {
+    check_denied_capabilities(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_denied_capabilities_tree_walker() {
+    check_denied_capabilities(Evaluator::TreeWalker);
+}
+
+fn check_denied_inside_procedures(evaluator: Evaluator) {
+    let mut interpreter = sandboxed(evaluator);
+    interpreter
+        .eval_str("(define (greet) (let ((x 1)) (newline)))")
+        .unwrap();
+    assert_eq!(
+        denied(&mut interpreter, "(greet)"),
+        "'newline' is not permitted in this sandbox"
+    );
+    assert_eq!(
+        denied(&mut interpreter, "(define (f) (async 1)) (f)"),
+        "'async' is not permitted in this sandbox"
+    );
+}
+
+#[test]
+fn test_integration_sandbox_denied_inside_procedures_bytecode() {
+    check_denied_inside_procedures(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_denied_inside_procedures_tree_walker() {
+    check_denied_inside_procedures(Evaluator::TreeWalker);
+}
+
+fn check_defining_denied_names(evaluator: Evaluator) {
+    // Defining a denied name makes an ordinary binding
+    let mut interpreter = sandboxed(evaluator);
+    interpreter.eval_str("(define (display x) x)").unwrap();
+    assert_eq!(
+        interpreter.eval_str("(display 5)").unwrap(),
+        Value::number(5.0)
+    );
+}
+
+#[test]
+fn test_integration_sandbox_defining_denied_names_bytecode() {
+    check_defining_denied_names(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_defining_denied_names_tree_walker() {
+    check_defining_denied_names(Evaluator::TreeWalker);
+}
+
+fn check_unrestricted_interpreters(evaluator: Evaluator) {
+    // A sandboxed interpreter alongside restricts only itself
+    let _sandboxed = sandboxed(evaluator);
+    let mut interpreter = Interpreter::new().with_evaluator(evaluator);
+    assert_eq!(
+        interpreter.eval_str("(procedure? display)").unwrap(),
+        Value::boolean(true)
+    );
+}
+
+#[test]
+fn test_integration_sandbox_unrestricted_interpreters_bytecode() {
+    check_unrestricted_interpreters(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_unrestricted_interpreters_tree_walker() {
+    check_unrestricted_interpreters(Evaluator::TreeWalker);
+}
+
+fn check_imports_leave_out_denied_builtins(evaluator: Evaluator) {
+    let sandbox = Sandbox::empty()
+        .with_special_form(SpecialForm::Import)
+        .with_builtin(Builtin::Add);
+    let mut interpreter = Interpreter::sandboxed(sandbox).with_evaluator(evaluator);
+    interpreter.eval_str("(import (scheme base))").unwrap();
+    assert_eq!(interpreter.eval_str("(+ 1 2)").unwrap(), Value::number(3.0));
+    denied(&mut interpreter, "(car '(1))");
+    denied(&mut interpreter, "(if #t 1 2)");
+}
+
+#[test]
+fn test_integration_sandbox_imports_leave_out_denied_builtins_bytecode() {
+    check_imports_leave_out_denied_builtins(Evaluator::Bytecode);
+}
+
+#[test]
+fn test_integration_sandbox_imports_leave_out_denied_builtins_tree_walker() {
+    check_imports_leave_out_denied_builtins(Evaluator::TreeWalker);
+}
@@ -0,0 +1,327 @@
+//! Integration tests for running scripts with the main binary
+//!
+//! This file contains integration tests for the non-interactive entry points:
+//! - Running a script file with arguments visible through `command-line`
+//! - One-liners with `-e`
+//! - Programs read from standard input with `-`
+//! - Exit codes and positioned error messages on failure
+//! - Backtraces of the Scheme call stack for runtime errors
+//! - Imports required for builtins, unless `--implicit-builtins` is given
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Run the interpreter binary with the given arguments and standard input
+fn twine(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_twine-scheme"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start twine-scheme");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Write a script to a unique temporary file
+fn script_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("twine-script-{}-{name}", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+/// The import line the test programs start with
+const IMPORTS: &str = "(import (scheme base) (scheme write) (scheme process-context))";
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_integration_run_script_file() {
+    let path = script_file(
+        "args.scm",
+        &format!(
+            "{IMPORTS}
+         (define args (cdr (command-line)))
+         (display (length args))
+         (newline)
+         (display (car args))"
+        ),
+    );
+    let output = twine(&[path.to_str().unwrap(), "first", "second"], "");
+
+    assert!(output.status.success(), "stderr: {}", stderr(&output));
+    assert_eq!(stdout(&output), "2\nfirst");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_integration_script_error_exit_code() {
+    let path = script_file(
+        "error.scm",
+        &format!("{IMPORTS}\n(display \"before\")\n\n(car 5)\n(display \"after\")"),
+    );
+    let output = twine(&[path.to_str().unwrap()], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "before");
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "error[E0005]: car: expected list, got number
+ --> {}:4:1
+  |
+4 | (car 5)
+  | ^^^^^^^
+  |
+Backtrace (most recent call first):
+  0: car at {0}:4:1
+",
+            path.display()
+        )
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_integration_missing_script_file() {
+    let output = twine(&["/nonexistent/twine/script.scm"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("cannot read file /nonexistent/twine/script.scm"));
+}
+
+#[test]
+fn test_integration_expression_option() {
+    let output = twine(&["-e", "(import (scheme base)) (define x 20) (+ x 22)"], "");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "42\n");
+
+    // Unspecified results are not printed
+    let output = twine(&["-e", "(import (scheme write)) (display 'hi)"], "");
+    assert_eq!(stdout(&output), "hi");
+
+    let output = twine(&["-e", "(car"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error[E0001]: Unexpected end of input"));
+}
+
+#[test]
+fn test_integration_tree_walker_option() {
+    let program = &format!(
+        "{IMPORTS}
+(define (count n) (let loop ((i n) (acc 0)) (if (= i 0) acc (loop (- i 1) (+ acc i)))))
+(display (count 1000))
+(define (f x) (+ 1 (car x)))
+(f 5)"
+    );
+
+    // Both evaluators print the same output and the same error
+    let bytecode = twine(&["-"], program);
+    let tree_walker = twine(&["--tree-walker", "-"], program);
+    assert_eq!(bytecode.status.code(), Some(1));
+    assert_eq!(tree_walker.status.code(), Some(1));
+    assert_eq!(stdout(&tree_walker), "500500");
+    assert_eq!(stdout(&tree_walker), stdout(&bytecode));
+    assert_eq!(stderr(&tree_walker), stderr(&bytecode));
+}
+
+#[test]
+fn test_integration_stdin_script() {
+    let output = twine(
+        &["-", "arg"],
+        "(import (scheme write) (scheme process-context)) (display (command-line))",
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "(\"-\" \"arg\")");
+
+    let output = twine(
+        &["-"],
+        &format!("{IMPORTS}\n(display 1)\n(undefined-thing)"),
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("error[E0006]: Unbound identifier"));
+    assert!(stderr(&output).contains(" --> <stdin>:3:2\n"));
+}
+
+#[test]
+fn test_integration_unbound_identifier_suggestions() {
+    let output = twine(
+        &[
+            "-e",
+            "(import (scheme base)) (define (f xs) (lenght xs)) (f 1)",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("  = help: did you mean 'length'?\n"));
+
+    let output = twine(&["-e", "((lamda (x) x) 1)"], "");
+    assert!(stderr(&output).contains("  = help: did you mean 'lambda'?\n"));
+
+    // Local names are suggested, including those around a returned closure,
+    // and the label points at the identifier rather than the call
+    let programs = [
+        "(import (scheme base)) (define (f counter) (let ((total 1)) (+ countr total))) (f 1)",
+        "(import (scheme base)) (define (f counter) (lambda () (lambda () (+ 1 countr)))) (((f 1)))",
+        "(define (f counter) (lambda () (countr 1))) ((f 1))",
+    ];
+    for program in programs {
+        for args in [&["-e", program][..], &["--tree-walker", "-e", program]] {
+            let output = twine(args, "");
+            assert_eq!(output.status.code(), Some(1));
+            let stderr = stderr(&output);
+            assert!(
+                stderr.contains("  = help: did you mean 'counter'?\n"),
+                "{stderr}"
+            );
+            let column = program.find("countr").unwrap() + 1;
+            assert!(
+                stderr.contains(&format!(" --> <expression>:1:{column}\n")),
+                "{stderr}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_integration_usage_errors() {
+    let output = twine(&["--no-such-option"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Usage:"));
+
+    let output = twine(&["-e"], "");
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = twine(&["--help"], "");
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Usage:"));
+}
+
+#[test]
+fn test_integration_error_location_inside_procedure() {
+    let path = script_file(
+        "nested.scm",
+        &format!(
+            "{IMPORTS}
+         (define first-of
+           (lambda (xs)
+             (car xs)))
+         (display \"ok\")
+         (first-of 42)"
+        ),
+    );
+    let output = twine(&[path.to_str().unwrap()], "");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output).lines().nth(1).unwrap(),
+        format!(" --> {}:4:14", path.display())
+    );
+    assert!(stderr(&output).contains("4 |              (car xs)))\n  |              ^^^^^^^^\n"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_integration_error_location_in_library_file() {
+    // Libraries next to the script are found on the search path
+    let dir = std::env::temp_dir().join(format!("twine-script-{}-libdir", std::process::id()));
+    std::fs::create_dir_all(dir.join("located")).unwrap();
+    let library = dir.join("located").join("util.sld");
+    std::fs::write(
+        &library,
+        "(define-library (located util)
+           (export head)
+           (import (scheme base))
+           (begin
+             (define head (lambda (xs) (car xs)))))",
+    )
+    .unwrap();
+    let script = dir.join("main.scm");
+    std::fs::write(&script, "(import (located util))\n(head 1)").unwrap();
+
+    let output = twine(&[script.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    // The snippet is read from the library file
+    assert_eq!(
+        stderr(&output).lines().nth(1).unwrap(),
+        format!(" --> {}:5:40", library.display())
+    );
+    assert!(stderr(&output).contains("(define head (lambda (xs) (car xs)))))\n"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_integration_error_backtrace() {
+    let path = script_file(
+        "backtrace.scm",
+        &format!(
+            "{IMPORTS}
+(define validate (lambda (x) (car x)))
+(define process (lambda (x) (list (validate x))))
+(define main (lambda () (+ 1 (length (process 7)))))
+(main)"
+        ),
+    );
+    let output = twine(&[path.to_str().unwrap()], "");
+    let path = path.display();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "error[E0005]: car: expected list, got number
+ --> {path}:2:30
+  |
+2 | (define validate (lambda (x) (car x)))
+  |                              ^^^^^^^
+  |
+Backtrace (most recent call first):
+  0: car at {path}:2:30
+     ... 1 tail call elided
+  1: process at {path}:4:38
+  2: main at {path}:5:1
+"
+        )
+    );
+
+    std::fs::remove_file(path.to_string()).unwrap();
+}
+
+#[test]
+fn test_integration_builtins_require_imports() {
+    let output = twine(&["-e", "(display (+ 1 2))"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).contains(
+            "  = help: 'display' is exported by (scheme write): add (import (scheme write))\n"
+        ),
+        "{}",
+        stderr(&output)
+    );
+
+    // The flag binds every builtin, as programs did before imports were required
+    for args in [
+        &["--implicit-builtins"][..],
+        &["--implicit-builtins", "--tree-walker"],
+    ] {
+        let output = twine(&[args, &["-e", "(display (+ 1 2))"]].concat(), "");
+        assert!(output.status.success(), "stderr: {}", stderr(&output));
+        assert_eq!(stdout(&output), "3");
+    }
+}